@@ -0,0 +1,228 @@
+//! ZedUI clipboard utilities.
+
+#![deny(missing_docs)]
+#![doc(html_logo_url = "https://zed-ui.dev/static/logos/zed-ui.svg")]
+
+#[cfg(target_os = "macos")]
+#[macro_use]
+extern crate objc;
+
+extern crate zui_shared as shared;
+
+use std::{
+    fmt,
+    marker::PhantomData,
+};
+use shared::ZedString;
+
+mod sys;
+pub mod mime;
+pub mod os;
+
+/// A handle to a system clipboard.
+///
+/// Reading and writing are performed against the clipboard shared with every
+/// other application, so contents may change between calls.
+#[derive(Clone)]
+pub struct Clipboard {
+    sys: sys::Clipboard,
+    // !Send + !Sync
+    _marker: PhantomData<*mut ()>,
+}
+
+#[doc(hidden)]
+impl From<sys::Clipboard> for Clipboard {
+    #[inline]
+    fn from(sys: sys::Clipboard) -> Self {
+        Clipboard { sys, _marker: PhantomData }
+    }
+}
+
+impl fmt::Debug for Clipboard {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.sys.fmt(f)
+    }
+}
+
+impl Clipboard {
+    /// Returns a handle to the general clipboard, which is used by copy and
+    /// paste.
+    #[inline]
+    pub fn general() -> Self {
+        sys::Clipboard::general().into()
+    }
+
+    /// Returns a handle to the primary selection, if the platform has one.
+    ///
+    /// The primary selection holds the most recently selected text and is
+    /// usually pasted with a middle click. It is available on X11 and on
+    /// Wayland compositors that support it. macOS has no such selection, so
+    /// `None` is returned there.
+    #[inline]
+    pub fn primary_selection() -> Option<Self> {
+        sys::Clipboard::primary_selection().map(Into::into)
+    }
+
+    /// Returns the MIME types of the formats currently held by `self`.
+    #[inline]
+    pub fn formats(&self) -> Vec<String> {
+        self.sys.formats()
+    }
+
+    /// Returns whether `self` holds data of type `mime`.
+    #[inline]
+    pub fn has_format(&self, mime: &str) -> bool {
+        self.sys.has_format(mime)
+    }
+
+    /// Returns the plain text held by `self`, if any.
+    #[inline]
+    pub fn text(&self) -> Option<String> {
+        self.sys.text()
+    }
+
+    /// Returns the HTML held by `self`, if any.
+    #[inline]
+    pub fn html(&self) -> Option<String> {
+        let html = self.data(mime::TEXT_HTML)?;
+        String::from_utf8(html).ok()
+    }
+
+    /// Returns the PNG encoded image held by `self`, if any.
+    #[inline]
+    pub fn image_png(&self) -> Option<Vec<u8>> {
+        self.data(mime::IMAGE_PNG)
+    }
+
+    /// Returns the data of type `mime` held by `self`, if any.
+    #[inline]
+    pub fn data(&self, mime: &str) -> Option<Vec<u8>> {
+        self.sys.data(mime)
+    }
+
+    /// Replaces the contents of `self` with plain text.
+    #[inline]
+    pub fn set_text<S: Into<ZedString>>(&self, text: S) {
+        self.sys.set_text(text.into());
+    }
+
+    /// Replaces the contents of `self` with HTML.
+    #[inline]
+    pub fn set_html(&self, html: &str) {
+        self.write(ClipboardData::new().html(html));
+    }
+
+    /// Replaces the contents of `self` with a PNG encoded image.
+    #[inline]
+    pub fn set_image_png(&self, png: &[u8]) {
+        self.write(ClipboardData::new().image_png(png));
+    }
+
+    /// Replaces the contents of `self` with data of type `mime`.
+    #[inline]
+    pub fn set_data(&self, mime: &str, data: &[u8]) {
+        self.write(ClipboardData::new().data(mime, data));
+    }
+
+    /// Replaces the contents of `self` with every format in `data`.
+    ///
+    /// This allows for pasting into applications that only understand some
+    /// of the formats, such as writing both HTML and plain text.
+    #[inline]
+    pub fn write(&self, data: &ClipboardData) {
+        self.sys.write(data);
+    }
+
+    /// Removes all contents from `self`.
+    #[inline]
+    pub fn clear(&self) {
+        self.sys.clear();
+    }
+
+    /// Returns a counter that changes whenever the contents of `self` change.
+    #[inline]
+    pub fn change_count(&self) -> u64 {
+        self.sys.change_count()
+    }
+
+    /// Calls `f` on the main thread whenever the contents of `self` change.
+    ///
+    /// Changes are observed for as long as the returned watcher is alive. On
+    /// Linux, `f` is called on the thread that created the watcher while it
+    /// runs its main loop.
+    #[inline]
+    pub fn watch<F>(&self, f: F) -> ClipboardWatcher
+    where
+        F: FnMut(&Clipboard) + 'static,
+    {
+        ClipboardWatcher {
+            sys: self.sys.watch(self.clone(), Box::new(f)),
+            _marker: PhantomData,
+        }
+    }
+}
+
+/// Data to be written to a [`Clipboard`](struct.Clipboard.html) in one or
+/// more formats.
+#[derive(Clone, Debug, Default)]
+pub struct ClipboardData {
+    formats: Vec<(String, Vec<u8>)>,
+}
+
+impl ClipboardData {
+    /// Creates an instance with no formats.
+    #[inline]
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Adds plain text.
+    #[inline]
+    pub fn text(&mut self, text: &str) -> &mut Self {
+        self.data(mime::TEXT_PLAIN, text.as_bytes())
+    }
+
+    /// Adds HTML.
+    #[inline]
+    pub fn html(&mut self, html: &str) -> &mut Self {
+        self.data(mime::TEXT_HTML, html.as_bytes())
+    }
+
+    /// Adds a PNG encoded image.
+    #[inline]
+    pub fn image_png(&mut self, png: &[u8]) -> &mut Self {
+        self.data(mime::IMAGE_PNG, png)
+    }
+
+    /// Adds `data` of type `mime`, replacing any data previously added with
+    /// the same type, regardless of parameters such as `charset`.
+    pub fn data(&mut self, mime: &str, data: &[u8]) -> &mut Self {
+        shared::mime::set_format(&mut self.formats, mime, data);
+        self
+    }
+
+    /// Returns an iterator over each MIME type and its data.
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &[u8])> {
+        self.formats.iter().map(|(m, d)| (m.as_str(), d.as_slice()))
+    }
+}
+
+/// Observes changes to a [`Clipboard`](struct.Clipboard.html).
+///
+/// Created by [`Clipboard::watch`](struct.Clipboard.html#method.watch).
+/// Dropping the watcher stops observing changes.
+pub struct ClipboardWatcher {
+    #[allow(dead_code)]
+    sys: sys::ClipboardWatcher,
+    // !Send + !Sync
+    _marker: PhantomData<*mut ()>,
+}
+
+impl fmt::Debug for ClipboardWatcher {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ClipboardWatcher").finish()
+    }
+}
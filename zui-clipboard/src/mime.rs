@@ -0,0 +1,13 @@
+//! MIME types understood by every [`Clipboard`](../struct.Clipboard.html)
+//! backend.
+//!
+//! Any other MIME type may still be used, in which case the payload is stored
+//! and retrieved verbatim under that name.
+
+pub use shared::mime::{
+    IMAGE_PNG,
+    IMAGE_TIFF,
+    TEXT_HTML,
+    TEXT_PLAIN,
+    TEXT_RTF,
+};
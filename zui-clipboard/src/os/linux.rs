@@ -0,0 +1,21 @@
+//! Linux-specific extensions.
+
+use shared::os::linux::DisplayBackend;
+use crate::Clipboard;
+
+/// Linux-specific extensions for [`Clipboard`](../../struct.Clipboard.html).
+pub trait ClipboardExt {
+    /// Returns the display server that `self` is connected to, or `None` if
+    /// no display server with a usable clipboard could be reached.
+    ///
+    /// A Wayland compositor is only used if it supports a data control
+    /// protocol. Otherwise, the clipboard of XWayland is used if available.
+    fn display_backend(&self) -> Option<DisplayBackend>;
+}
+
+impl ClipboardExt for Clipboard {
+    #[inline]
+    fn display_backend(&self) -> Option<DisplayBackend> {
+        self.sys.display_backend()
+    }
+}
@@ -0,0 +1,30 @@
+//! macOS-specific extensions.
+
+use cocoa::base::id;
+use objc::rc::StrongPtr;
+use crate::{
+    Clipboard,
+    sys::Clipboard as SysClipboard,
+};
+
+/// macOS-specific extensions for [`Clipboard`](../../struct.Clipboard.html).
+pub trait ClipboardExt {
+    /// Creates an instance wrapped around an
+    /// [`NSPasteboard`](https://developer.apple.com/documentation/appkit/nspasteboard).
+    unsafe fn from_ns_pasteboard(ns_pasteboard: StrongPtr) -> Self;
+
+    /// Returns the `NSPasteboard` handle for `self`.
+    fn ns_pasteboard(&self) -> id;
+}
+
+impl ClipboardExt for Clipboard {
+    #[inline]
+    unsafe fn from_ns_pasteboard(ns_pasteboard: StrongPtr) -> Self {
+        SysClipboard { ns_pasteboard }.into()
+    }
+
+    #[inline]
+    fn ns_pasteboard(&self) -> id {
+        *self.sys.ns_pasteboard
+    }
+}
@@ -0,0 +1,11 @@
+//! OS-specific functionality.
+
+cfg_if::cfg_if! {
+    if #[cfg(target_os = "macos")] {
+        pub mod macos;
+    } else if #[cfg(target_os = "linux")] {
+        pub mod linux;
+    } else {
+        compile_error!("`zui-clipboard` does not compile for this platform");
+    }
+}
@@ -0,0 +1,448 @@
+//! Clipboards backed by X11 selections or the Wayland data control
+//! protocols.
+//!
+//! Each backend runs its own display connection on a background thread, which
+//! answers requests for data owned by this process and tracks changes made by
+//! other clients. Watchers are notified on the thread that created them
+//! through its main loop.
+
+mod wayland;
+mod x11;
+
+use std::{
+    cell::RefCell,
+    collections::BTreeMap,
+    fmt,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+        Mutex,
+        OnceLock,
+    },
+};
+use shared::{
+    mime::{is_text_plain, same_type},
+    os::linux::{main_loop, DisplayBackend, ZedStringExt},
+    ZedString,
+};
+use crate::{mime, ClipboardData};
+
+/// Targets that hold UTF-8 text, in the order they are read.
+const UTF8_TARGETS: &[&str] = &[mime::TEXT_PLAIN, "UTF8_STRING", "text/plain"];
+
+/// The X11 target for Latin-1 text.
+const STRING_TARGET: &str = "STRING";
+
+/// The X11 target that lets the owner choose a text encoding.
+const TEXT_TARGET: &str = "TEXT";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Selection {
+    Clipboard,
+    Primary,
+}
+
+impl Selection {
+    const ALL: [Selection; 2] = [Selection::Clipboard, Selection::Primary];
+
+    #[inline]
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+/// A connection to the display server that owns the selections.
+trait Backend: Send + Sync {
+    fn display_backend(&self) -> DisplayBackend;
+
+    /// Returns whether the primary selection can be read and written.
+    fn has_primary(&self) -> bool;
+
+    /// Returns the targets currently offered for `selection`.
+    fn targets(&self, selection: Selection) -> Vec<String>;
+
+    /// Returns the data of `selection` converted to `target`.
+    fn read(&self, selection: Selection, target: &str) -> Option<Vec<u8>>;
+
+    /// Takes ownership of `selection` with `contents`, or gives it up if
+    /// `contents` is empty.
+    fn write(&self, selection: Selection, contents: Contents);
+
+    fn changes(&self) -> &Changes;
+}
+
+fn backend() -> Option<Arc<dyn Backend>> {
+    static BACKEND: OnceLock<Option<Arc<dyn Backend>>> = OnceLock::new();
+
+    BACKEND.get_or_init(connect).clone()
+}
+
+fn connect() -> Option<Arc<dyn Backend>> {
+    let x11 = || x11::Server::connect().map(|s| s as Arc<dyn Backend>);
+    match DisplayBackend::current()? {
+        // Compositors without a data control protocol only hand the clipboard
+        // to focused surfaces, so XWayland is tried before giving up.
+        DisplayBackend::Wayland => wayland::Server::connect()
+            .map(|s| s as Arc<dyn Backend>)
+            .or_else(x11),
+        DisplayBackend::X11 => x11(),
+    }
+}
+
+#[derive(Clone)]
+pub struct Clipboard {
+    backend: Option<Arc<dyn Backend>>,
+    selection: Selection,
+}
+
+impl fmt::Debug for Clipboard {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Clipboard")
+            .field("backend", &self.display_backend())
+            .field("selection", &self.selection)
+            .finish()
+    }
+}
+
+impl Clipboard {
+    #[inline]
+    pub fn general() -> Self {
+        Clipboard { backend: backend(), selection: Selection::Clipboard }
+    }
+
+    #[inline]
+    pub fn primary_selection() -> Option<Self> {
+        let backend = backend().filter(|b| b.has_primary())?;
+        Some(Clipboard { backend: Some(backend), selection: Selection::Primary })
+    }
+
+    #[inline]
+    pub fn display_backend(&self) -> Option<DisplayBackend> {
+        self.backend.as_ref().map(|b| b.display_backend())
+    }
+
+    fn targets(&self) -> Vec<String> {
+        match &self.backend {
+            Some(backend) => backend.targets(self.selection),
+            None => Vec::new(),
+        }
+    }
+
+    pub fn formats(&self) -> Vec<String> {
+        mime_types(&self.targets())
+    }
+
+    pub fn has_format(&self, mime: &str) -> bool {
+        self.formats().iter().any(|m| same_type(m, mime))
+    }
+
+    #[inline]
+    pub fn text(&self) -> Option<String> {
+        let text = self.data(mime::TEXT_PLAIN)?;
+        Some(String::from_utf8_lossy(&text).into_owned())
+    }
+
+    pub fn data(&self, mime: &str) -> Option<Vec<u8>> {
+        let backend = self.backend.as_ref()?;
+        let target = read_target(&backend.targets(self.selection), mime)?;
+        let data = backend.read(self.selection, &target)?;
+        Some(decode(&target, data))
+    }
+
+    #[inline]
+    pub fn set_text(&self, text: ZedString) {
+        self.write(ClipboardData::new().text(text.as_str()));
+    }
+
+    pub fn write(&self, data: &ClipboardData) {
+        if let Some(backend) = &self.backend {
+            backend.write(self.selection, Contents::new(data));
+        }
+    }
+
+    #[inline]
+    pub fn clear(&self) {
+        if let Some(backend) = &self.backend {
+            backend.write(self.selection, Contents::default());
+        }
+    }
+
+    #[inline]
+    pub fn change_count(&self) -> u64 {
+        match &self.backend {
+            Some(backend) => backend.changes().count(self.selection),
+            None => 0,
+        }
+    }
+
+    pub fn watch(
+        &self,
+        clipboard: crate::Clipboard,
+        callback: Box<dyn FnMut(&crate::Clipboard)>,
+    ) -> ClipboardWatcher {
+        let backend = match &self.backend {
+            Some(backend) => backend.clone(),
+            None => return ClipboardWatcher { id: 0, backend: None },
+        };
+        let id = backend.changes().add_watcher(self.selection);
+        let watcher = Watcher {
+            change_count: self.change_count(),
+            clipboard,
+            callback,
+        };
+        WATCHERS.with(|w| w.borrow_mut().insert(id, Some(watcher)));
+        ClipboardWatcher { id, backend: Some(backend) }
+    }
+}
+
+/// Formats owned by this process, which are served to other clients.
+#[derive(Clone, Debug, Default)]
+pub struct Contents {
+    formats: Arc<Vec<(String, Vec<u8>)>>,
+}
+
+impl Contents {
+    fn new(data: &ClipboardData) -> Self {
+        let formats = data.iter().map(|(m, d)| (m.to_owned(), d.to_vec())).collect();
+        Contents { formats: Arc::new(formats) }
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.formats.is_empty()
+    }
+
+    /// Returns every target that the contents can be converted to.
+    pub fn targets(&self) -> Vec<String> {
+        let mut targets = Vec::new();
+        for (mime, _) in self.formats.iter() {
+            for target in format_targets(mime) {
+                if !targets.contains(&target) {
+                    targets.push(target);
+                }
+            }
+        }
+        targets
+    }
+
+    /// Returns the contents converted to `target`.
+    pub fn convert(&self, target: &str) -> Option<Vec<u8>> {
+        let (_, data) = self.formats.iter()
+            .find(|(mime, _)| format_targets(mime).iter().any(|t| t == target))?;
+        if target == STRING_TARGET {
+            Some(to_latin1(data))
+        } else {
+            Some(data.clone())
+        }
+    }
+}
+
+/// Returns the targets under which a format of type `mime` is offered.
+fn format_targets(mime: &str) -> Vec<String> {
+    if is_text_plain(mime) {
+        UTF8_TARGETS.iter()
+            .chain(&[TEXT_TARGET, STRING_TARGET])
+            .map(|&t| t.to_owned())
+            .collect()
+    } else {
+        vec![mime.to_owned()]
+    }
+}
+
+/// Returns the MIME type for data offered as `target`, or `None` if `target`
+/// is not a format, such as `TARGETS` or `TIMESTAMP`.
+fn mime_type(target: &str) -> Option<&str> {
+    if target == STRING_TARGET || target == TEXT_TARGET || UTF8_TARGETS.contains(&target) {
+        Some(mime::TEXT_PLAIN)
+    } else if target.contains('/') {
+        Some(target)
+    } else {
+        None
+    }
+}
+
+/// Returns the distinct MIME types offered by `targets`.
+fn mime_types(targets: &[String]) -> Vec<String> {
+    let mut types: Vec<String> = Vec::new();
+    for mime in targets.iter().filter_map(|t| mime_type(t)) {
+        if !types.iter().any(|m| same_type(m, mime)) {
+            types.push(mime.to_owned());
+        }
+    }
+    types
+}
+
+/// Returns the best of the offered `targets` to read data of type `mime`.
+fn read_target(targets: &[String], mime: &str) -> Option<String> {
+    if is_text_plain(mime) {
+        UTF8_TARGETS.iter()
+            .chain(&[STRING_TARGET])
+            .find(|&&t| targets.iter().any(|o| o == t))
+            .map(|&t| t.to_owned())
+    } else {
+        targets.iter().find(|t| same_type(t, mime)).cloned()
+    }
+}
+
+/// Converts data read as `target` to the encoding of its MIME type.
+fn decode(target: &str, data: Vec<u8>) -> Vec<u8> {
+    if target == STRING_TARGET {
+        data.iter().map(|&b| b as char).collect::<String>().into_bytes()
+    } else {
+        data
+    }
+}
+
+/// Encodes UTF-8 `text` as Latin-1, replacing characters outside of it.
+fn to_latin1(text: &[u8]) -> Vec<u8> {
+    String::from_utf8_lossy(text)
+        .chars()
+        .map(|c| if (c as u32) < 0x100 { c as u8 } else { b'?' })
+        .collect()
+}
+
+/// Change counters and watchers for both selections of a backend.
+#[derive(Default)]
+pub struct Changes {
+    counts: [AtomicU64; 2],
+    watchers: Mutex<Vec<(u64, Selection, main_loop::Sender)>>,
+}
+
+impl Changes {
+    #[inline]
+    fn count(&self, selection: Selection) -> u64 {
+        self.counts[selection.index()].load(Ordering::SeqCst)
+    }
+
+    /// Records a change to `selection` and notifies its watchers.
+    pub fn changed(&self, selection: Selection) {
+        self.counts[selection.index()].fetch_add(1, Ordering::SeqCst);
+        for (id, _, sender) in self.watchers.lock().unwrap().iter()
+            .filter(|(_, s, _)| *s == selection)
+        {
+            let id = *id;
+            let _ = sender.send(move || notify(id));
+        }
+    }
+
+    fn add_watcher(&self, selection: Selection) -> u64 {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+        let id = NEXT_ID.fetch_add(1, Ordering::SeqCst);
+        self.watchers.lock().unwrap().push((id, selection, main_loop::sender()));
+        id
+    }
+
+    fn remove_watcher(&self, id: u64) {
+        self.watchers.lock().unwrap().retain(|(i, _, _)| *i != id);
+    }
+}
+
+struct Watcher {
+    change_count: u64,
+    clipboard: crate::Clipboard,
+    callback: Box<dyn FnMut(&crate::Clipboard)>,
+}
+
+thread_local! {
+    // A watcher is taken out of its slot while its callback runs, so that the
+    // callback may create or drop watchers.
+    static WATCHERS: RefCell<BTreeMap<u64, Option<Watcher>>> = const { RefCell::new(BTreeMap::new()) };
+}
+
+fn notify(id: u64) {
+    let watcher = WATCHERS.with(|w| w.borrow_mut().get_mut(&id).and_then(Option::take));
+    let mut watcher = match watcher {
+        Some(watcher) => watcher,
+        None => return,
+    };
+
+    // Several changes may be delivered at once.
+    let change_count = watcher.clipboard.change_count();
+    if change_count != watcher.change_count {
+        watcher.change_count = change_count;
+        (watcher.callback)(&watcher.clipboard);
+    }
+
+    WATCHERS.with(|w| {
+        if let Some(slot) = w.borrow_mut().get_mut(&id) {
+            *slot = Some(watcher);
+        }
+    });
+}
+
+pub struct ClipboardWatcher {
+    id: u64,
+    backend: Option<Arc<dyn Backend>>,
+}
+
+impl Drop for ClipboardWatcher {
+    fn drop(&mut self) {
+        if let Some(backend) = &self.backend {
+            backend.changes().remove_watcher(self.id);
+            let watcher = WATCHERS.with(|w| w.borrow_mut().remove(&self.id));
+            drop(watcher);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(targets: &[&str]) -> Vec<String> {
+        targets.iter().map(|&t| t.to_owned()).collect()
+    }
+
+    #[test]
+    fn text_targets() {
+        let mut data = ClipboardData::new();
+        data.text("héllo ✓").html("<b>hi</b>");
+        let contents = Contents::new(&data);
+
+        assert_eq!(contents.targets(), strings(&[
+            "text/plain;charset=utf-8",
+            "UTF8_STRING",
+            "text/plain",
+            "TEXT",
+            "STRING",
+            "text/html",
+        ]));
+        assert_eq!(contents.convert("UTF8_STRING").unwrap(), "héllo ✓".as_bytes());
+        assert_eq!(contents.convert("STRING").unwrap(), b"h\xe9llo ?");
+        assert_eq!(contents.convert("text/html").unwrap(), b"<b>hi</b>");
+        assert_eq!(contents.convert("image/png"), None);
+        assert!(Contents::default().targets().is_empty());
+    }
+
+    #[test]
+    fn offered_formats() {
+        let targets = strings(&[
+            "TARGETS",
+            "TIMESTAMP",
+            "UTF8_STRING",
+            "STRING",
+            "text/plain",
+            "text/html",
+            "image/png",
+        ]);
+        assert_eq!(mime_types(&targets), strings(&[
+            mime::TEXT_PLAIN,
+            mime::TEXT_HTML,
+            mime::IMAGE_PNG,
+        ]));
+    }
+
+    #[test]
+    fn preferred_read_targets() {
+        let targets = strings(&["STRING", "UTF8_STRING", "text/html;charset=utf-8"]);
+        assert_eq!(read_target(&targets, "text/plain").unwrap(), "UTF8_STRING");
+        assert_eq!(read_target(&targets, mime::TEXT_HTML).unwrap(), "text/html;charset=utf-8");
+        assert_eq!(read_target(&targets, mime::IMAGE_PNG), None);
+
+        let targets = strings(&["STRING"]);
+        assert_eq!(read_target(&targets, mime::TEXT_PLAIN).unwrap(), "STRING");
+        assert_eq!(decode("STRING", b"caf\xe9".to_vec()), "café".as_bytes());
+        assert_eq!(decode("UTF8_STRING", b"caf\xe9".to_vec()), b"caf\xe9");
+    }
+}
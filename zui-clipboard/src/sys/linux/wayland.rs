@@ -0,0 +1,765 @@
+//! The selections of a Wayland seat, through a data control protocol.
+//!
+//! The core `wl_data_device` only hands the clipboard to a focused surface,
+//! so the `ext-data-control-v1` protocol is used instead, or the older
+//! `wlr-data-control-unstable-v1` protocol if the compositor lacks it. Events
+//! are dispatched on a background thread with its own event queue.
+
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{Read, Write},
+    mem,
+    os::unix::io::{AsFd, AsRawFd, BorrowedFd, FromRawFd, OwnedFd},
+    sync::{Arc, Mutex},
+    thread,
+    time::Duration,
+};
+use wayland_client::{
+    backend::ObjectId,
+    delegate_noop,
+    event_created_child,
+    globals::{registry_queue_init, GlobalListContents},
+    protocol::{wl_registry::WlRegistry, wl_seat::WlSeat},
+    Connection,
+    Dispatch,
+    Proxy,
+    QueueHandle,
+};
+use wayland_protocols::ext::data_control::v1::client::{
+    ext_data_control_device_v1 as ext_device,
+    ext_data_control_manager_v1::ExtDataControlManagerV1,
+    ext_data_control_offer_v1 as ext_offer,
+    ext_data_control_source_v1 as ext_source,
+};
+use wayland_protocols_wlr::data_control::v1::client::{
+    zwlr_data_control_device_v1 as wlr_device,
+    zwlr_data_control_manager_v1::ZwlrDataControlManagerV1,
+    zwlr_data_control_offer_v1 as wlr_offer,
+    zwlr_data_control_source_v1 as wlr_source,
+};
+use shared::os::linux::DisplayBackend;
+use super::{Backend, Changes, Contents, Selection};
+
+/// How long to wait for the owner of a selection to send more data before
+/// giving up.
+const TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Calls a method shared by both protocols on the wrapped object.
+macro_rules! either {
+    ($object:expr, $x:ident => $call:expr) => {
+        match $object {
+            Either::Ext($x) => $call,
+            Either::Wlr($x) => $call,
+        }
+    };
+}
+
+/// An object of either data control protocol.
+#[derive(Clone, Debug, PartialEq)]
+enum Either<E, W> {
+    Ext(E),
+    Wlr(W),
+}
+
+type Manager = Either<ExtDataControlManagerV1, ZwlrDataControlManagerV1>;
+type Device = Either<ext_device::ExtDataControlDeviceV1, wlr_device::ZwlrDataControlDeviceV1>;
+type Source = Either<ext_source::ExtDataControlSourceV1, wlr_source::ZwlrDataControlSourceV1>;
+type Offer = Either<ext_offer::ExtDataControlOfferV1, wlr_offer::ZwlrDataControlOfferV1>;
+
+impl<E: Proxy, W: Proxy> Either<E, W> {
+    #[inline]
+    fn id(&self) -> ObjectId {
+        either!(self, x => x.id())
+    }
+}
+
+#[derive(Default)]
+struct Shared {
+    /// The MIME types of every live offer.
+    offers: HashMap<ObjectId, Vec<String>>,
+    /// The current offer of each selection.
+    selections: [Option<Offer>; 2],
+    /// The sources owned by this process, with the contents they serve.
+    sources: [Option<(Source, Contents)>; 2],
+}
+
+/// The state of the background event queue.
+struct State {
+    shared: Arc<Mutex<Shared>>,
+    changes: Arc<Changes>,
+}
+
+impl State {
+    fn offered(&mut self, offer: &ObjectId, mime: String) {
+        let mut shared = self.shared.lock().unwrap();
+        shared.offers.entry(offer.clone()).or_default().push(mime);
+    }
+
+    fn selection(&mut self, selection: Selection, offer: Option<Offer>) {
+        {
+            let mut shared = self.shared.lock().unwrap();
+            let old = mem::replace(&mut shared.selections[selection.index()], offer);
+            if let Some(old) = old {
+                if !shared.selections.contains(&Some(old.clone())) {
+                    shared.offers.remove(&old.id());
+                    either!(&old, x => x.destroy());
+                }
+            }
+        }
+        self.changes.changed(selection);
+    }
+
+    fn finished(&mut self) {
+        for &selection in &Selection::ALL {
+            self.selection(selection, None);
+        }
+    }
+
+    fn send(&mut self, source: &ObjectId, target: String, fd: OwnedFd) {
+        let shared = self.shared.lock().unwrap();
+        let data = shared.sources.iter()
+            .flatten()
+            .find(|(s, _)| s.id() == *source)
+            .and_then(|(_, contents)| contents.convert(&target));
+
+        // The receiver may read slowly, so writing must not block the queue.
+        if let Some(data) = data {
+            let _ = thread::Builder::new()
+                .name("zui-clipboard-send".into())
+                .spawn(move || File::from(fd).write_all(&data));
+        }
+    }
+
+    fn cancelled(&mut self, source: &Source) {
+        let mut shared = self.shared.lock().unwrap();
+        for slot in shared.sources.iter_mut() {
+            if slot.as_ref().is_some_and(|(s, _)| s == source) {
+                *slot = None;
+            }
+        }
+        either!(source, x => x.destroy());
+    }
+}
+
+delegate_noop!(State: ignore WlSeat);
+delegate_noop!(State: ExtDataControlManagerV1);
+delegate_noop!(State: ZwlrDataControlManagerV1);
+
+impl Dispatch<WlRegistry, GlobalListContents> for State {
+    fn event(
+        _: &mut Self,
+        _: &WlRegistry,
+        _: <WlRegistry as Proxy>::Event,
+        _: &GlobalListContents,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {}
+}
+
+/// Implements event dispatch for the objects of a data control protocol.
+macro_rules! dispatch {
+    ($variant:ident, $device:ident::$Device:ident, $offer:ident::$Offer:ident, $source:ident::$Source:ident) => {
+        impl Dispatch<$device::$Device, ()> for State {
+            fn event(
+                state: &mut Self,
+                _: &$device::$Device,
+                event: $device::Event,
+                _: &(),
+                _: &Connection,
+                _: &QueueHandle<Self>,
+            ) {
+                match event {
+                    $device::Event::DataOffer { id } => {
+                        state.shared.lock().unwrap().offers.insert(id.id(), Vec::new());
+                    },
+                    $device::Event::Selection { id } => {
+                        state.selection(Selection::Clipboard, id.map(Either::$variant));
+                    },
+                    $device::Event::PrimarySelection { id } => {
+                        state.selection(Selection::Primary, id.map(Either::$variant));
+                    },
+                    $device::Event::Finished => state.finished(),
+                    _ => {},
+                }
+            }
+
+            event_created_child!(State, $device::$Device, [
+                $device::EVT_DATA_OFFER_OPCODE => ($offer::$Offer, ()),
+            ]);
+        }
+
+        impl Dispatch<$offer::$Offer, ()> for State {
+            fn event(
+                state: &mut Self,
+                offer: &$offer::$Offer,
+                event: $offer::Event,
+                _: &(),
+                _: &Connection,
+                _: &QueueHandle<Self>,
+            ) {
+                if let $offer::Event::Offer { mime_type } = event {
+                    state.offered(&offer.id(), mime_type);
+                }
+            }
+        }
+
+        impl Dispatch<$source::$Source, ()> for State {
+            fn event(
+                state: &mut Self,
+                source: &$source::$Source,
+                event: $source::Event,
+                _: &(),
+                _: &Connection,
+                _: &QueueHandle<Self>,
+            ) {
+                match event {
+                    $source::Event::Send { mime_type, fd } => {
+                        state.send(&source.id(), mime_type, fd);
+                    },
+                    $source::Event::Cancelled => {
+                        state.cancelled(&Either::$variant(source.clone()));
+                    },
+                    _ => {},
+                }
+            }
+        }
+    };
+}
+
+dispatch!(Ext, ext_device::ExtDataControlDeviceV1, ext_offer::ExtDataControlOfferV1, ext_source::ExtDataControlSourceV1);
+dispatch!(Wlr, wlr_device::ZwlrDataControlDeviceV1, wlr_offer::ZwlrDataControlOfferV1, wlr_source::ZwlrDataControlSourceV1);
+
+pub struct Server {
+    conn: Connection,
+    queue: QueueHandle<State>,
+    manager: Manager,
+    device: Device,
+    has_primary: bool,
+    shared: Arc<Mutex<Shared>>,
+    changes: Arc<Changes>,
+}
+
+impl Server {
+    pub fn connect() -> Option<Arc<Self>> {
+        Self::new(Connection::connect_to_env().ok()?)
+    }
+
+    fn new(conn: Connection) -> Option<Arc<Self>> {
+        let (globals, mut events) = registry_queue_init::<State>(&conn).ok()?;
+        let queue = events.handle();
+
+        let seat: WlSeat = globals.bind(&queue, 1..=1, ()).ok()?;
+        let (manager, has_primary) = match globals.bind::<ExtDataControlManagerV1, _, _>(&queue, 1..=1, ()) {
+            Ok(manager) => (Either::Ext(manager), true),
+            Err(_) => {
+                let manager: ZwlrDataControlManagerV1 = globals.bind(&queue, 1..=2, ()).ok()?;
+                let has_primary = manager.version() >= 2;
+                (Either::Wlr(manager), has_primary)
+            },
+        };
+        let device = match &manager {
+            Either::Ext(m) => Either::Ext(m.get_data_device(&seat, &queue, ())),
+            Either::Wlr(m) => Either::Wlr(m.get_data_device(&seat, &queue, ())),
+        };
+
+        let mut state = State {
+            shared: Default::default(),
+            changes: Default::default(),
+        };
+        // Receives the current selections.
+        events.roundtrip(&mut state).ok()?;
+
+        let server = Arc::new(Server {
+            conn,
+            queue,
+            manager,
+            device,
+            has_primary,
+            shared: state.shared.clone(),
+            changes: state.changes.clone(),
+        });
+
+        thread::Builder::new()
+            .name("zui-clipboard-wayland".into())
+            .spawn(move || while events.blocking_dispatch(&mut state).is_ok() {})
+            .ok()?;
+
+        Some(server)
+    }
+
+    fn owned(&self, selection: Selection) -> Option<Contents> {
+        let shared = self.shared.lock().unwrap();
+        shared.sources[selection.index()].as_ref().map(|(_, c)| c.clone())
+    }
+
+    fn create_source(&self, contents: &Contents) -> Source {
+        let source = match &self.manager {
+            Either::Ext(m) => Either::Ext(m.create_data_source(&self.queue, ())),
+            Either::Wlr(m) => Either::Wlr(m.create_data_source(&self.queue, ())),
+        };
+        for target in contents.targets() {
+            either!(&source, x => x.offer(target.clone()));
+        }
+        source
+    }
+
+    fn set_selection(&self, selection: Selection, source: Option<&Source>) {
+        match (&self.device, source) {
+            (Either::Ext(d), Some(Either::Ext(s))) => match selection {
+                Selection::Clipboard => d.set_selection(Some(s)),
+                Selection::Primary => d.set_primary_selection(Some(s)),
+            },
+            (Either::Wlr(d), Some(Either::Wlr(s))) => match selection {
+                Selection::Clipboard => d.set_selection(Some(s)),
+                Selection::Primary => d.set_primary_selection(Some(s)),
+            },
+            (Either::Ext(d), _) => match selection {
+                Selection::Clipboard => d.set_selection(None),
+                Selection::Primary => d.set_primary_selection(None),
+            },
+            (Either::Wlr(d), _) => match selection {
+                Selection::Clipboard => d.set_selection(None),
+                Selection::Primary => d.set_primary_selection(None),
+            },
+        }
+    }
+}
+
+impl Backend for Server {
+    #[inline]
+    fn display_backend(&self) -> DisplayBackend {
+        DisplayBackend::Wayland
+    }
+
+    #[inline]
+    fn has_primary(&self) -> bool {
+        self.has_primary
+    }
+
+    fn targets(&self, selection: Selection) -> Vec<String> {
+        if let Some(contents) = self.owned(selection) {
+            return contents.targets();
+        }
+        let shared = self.shared.lock().unwrap();
+        shared.selections[selection.index()].as_ref()
+            .and_then(|offer| shared.offers.get(&offer.id()).cloned())
+            .unwrap_or_default()
+    }
+
+    fn read(&self, selection: Selection, target: &str) -> Option<Vec<u8>> {
+        if let Some(contents) = self.owned(selection) {
+            return contents.convert(target);
+        }
+        let offer = self.shared.lock().unwrap().selections[selection.index()].clone()?;
+        let (reader, writer) = pipe()?;
+        either!(&offer, x => x.receive(target.to_owned(), writer.as_fd()));
+        self.conn.flush().ok()?;
+        // The owner closes its end once all data is written.
+        drop(writer);
+        read_to_end(reader)
+    }
+
+    fn write(&self, selection: Selection, contents: Contents) {
+        let source = Some(&contents)
+            .filter(|c| !c.is_empty())
+            .map(|c| self.create_source(c));
+        self.set_selection(selection, source.as_ref());
+
+        let old = {
+            let mut shared = self.shared.lock().unwrap();
+            let new = source.map(|s| (s, contents));
+            mem::replace(&mut shared.sources[selection.index()], new)
+        };
+        if let Some((old, _)) = old {
+            either!(&old, x => x.destroy());
+        }
+        let _ = self.conn.flush();
+    }
+
+    #[inline]
+    fn changes(&self) -> &Changes {
+        &self.changes
+    }
+}
+
+fn pipe() -> Option<(OwnedFd, OwnedFd)> {
+    let mut fds = [0; 2];
+    if unsafe { libc::pipe2(fds.as_mut_ptr(), libc::O_CLOEXEC) } != 0 {
+        return None;
+    }
+    unsafe { Some((OwnedFd::from_raw_fd(fds[0]), OwnedFd::from_raw_fd(fds[1]))) }
+}
+
+/// Reads `fd` until the writer closes it, unless it stalls for `TIMEOUT`.
+fn read_to_end(fd: OwnedFd) -> Option<Vec<u8>> {
+    let mut data = Vec::new();
+    let mut buffer = [0; 8192];
+    let mut file = File::from(fd);
+    loop {
+        if !wait_readable(file.as_fd(), TIMEOUT) {
+            return None;
+        }
+        match file.read(&mut buffer) {
+            Ok(0) => return Some(data),
+            Ok(n) => data.extend_from_slice(&buffer[..n]),
+            Err(ref e) if e.kind() == std::io::ErrorKind::Interrupted => {},
+            Err(_) => return None,
+        }
+    }
+}
+
+fn wait_readable(fd: BorrowedFd, timeout: Duration) -> bool {
+    let mut poll_fd = libc::pollfd { fd: fd.as_raw_fd(), events: libc::POLLIN, revents: 0 };
+    loop {
+        match unsafe { libc::poll(&mut poll_fd, 1, timeout.as_millis() as libc::c_int) } {
+            -1 if std::io::Error::last_os_error().kind() == std::io::ErrorKind::Interrupted => {},
+            n => return n > 0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        cell::Cell,
+        os::unix::net::UnixStream,
+        rc::Rc,
+        sync::atomic::{AtomicBool, Ordering},
+        thread::JoinHandle,
+        time::Instant,
+    };
+    use wayland_protocols::ext::data_control::v1::server::{
+        ext_data_control_device_v1::{self, ExtDataControlDeviceV1},
+        ext_data_control_manager_v1::{self, ExtDataControlManagerV1},
+        ext_data_control_offer_v1::{self, ExtDataControlOfferV1},
+        ext_data_control_source_v1::{self, ExtDataControlSourceV1},
+    };
+    use wayland_server::{
+        backend::ClientData,
+        protocol::wl_seat::{self, WlSeat},
+        Client,
+        DataInit,
+        Display,
+        DisplayHandle,
+        GlobalDispatch,
+        New,
+        Resource,
+    };
+    use shared::os::linux::main_loop;
+    use crate::{mime, ClipboardData};
+    use super::super::Clipboard;
+
+    /// A compositor that implements only the seat and the data control
+    /// protocol.
+    #[derive(Default)]
+    struct Compositor {
+        devices: Vec<ExtDataControlDeviceV1>,
+        selections: [Option<ExtDataControlSourceV1>; 2],
+        mime_types: HashMap<wayland_server::backend::ObjectId, Vec<String>>,
+    }
+
+    impl Compositor {
+        fn set_selection(
+            &mut self,
+            dh: &DisplayHandle,
+            selection: Selection,
+            source: Option<ExtDataControlSourceV1>,
+        ) {
+            let old = mem::replace(&mut self.selections[selection.index()], source);
+            if let Some(old) = old {
+                if !self.selections.contains(&Some(old.clone())) {
+                    old.cancelled();
+                }
+            }
+            for device in self.devices.clone() {
+                self.announce(dh, &device, selection);
+            }
+        }
+
+        fn announce(&self, dh: &DisplayHandle, device: &ExtDataControlDeviceV1, selection: Selection) {
+            let offer = self.selections[selection.index()].as_ref().map(|source| {
+                let client = device.client().unwrap();
+                let offer: ExtDataControlOfferV1 = client
+                    .create_resource::<_, _, Compositor>(dh, device.version(), source.clone())
+                    .unwrap();
+                device.data_offer(&offer);
+                for mime_type in &self.mime_types[&source.id()] {
+                    offer.offer(mime_type.clone());
+                }
+                offer
+            });
+            match selection {
+                Selection::Clipboard => device.selection(offer.as_ref()),
+                Selection::Primary => device.primary_selection(offer.as_ref()),
+            }
+        }
+    }
+
+    struct TestClient;
+
+    impl ClientData for TestClient {}
+
+    impl GlobalDispatch<WlSeat, ()> for Compositor {
+        fn bind(
+            _: &mut Self,
+            _: &DisplayHandle,
+            _: &Client,
+            seat: New<WlSeat>,
+            _: &(),
+            data_init: &mut DataInit<'_, Self>,
+        ) {
+            data_init.init(seat, ());
+        }
+    }
+
+    impl wayland_server::Dispatch<WlSeat, ()> for Compositor {
+        fn request(
+            _: &mut Self,
+            _: &Client,
+            _: &WlSeat,
+            _: wl_seat::Request,
+            _: &(),
+            _: &DisplayHandle,
+            _: &mut DataInit<'_, Self>,
+        ) {}
+    }
+
+    impl GlobalDispatch<ExtDataControlManagerV1, ()> for Compositor {
+        fn bind(
+            _: &mut Self,
+            _: &DisplayHandle,
+            _: &Client,
+            manager: New<ExtDataControlManagerV1>,
+            _: &(),
+            data_init: &mut DataInit<'_, Self>,
+        ) {
+            data_init.init(manager, ());
+        }
+    }
+
+    impl wayland_server::Dispatch<ExtDataControlManagerV1, ()> for Compositor {
+        fn request(
+            state: &mut Self,
+            _: &Client,
+            _: &ExtDataControlManagerV1,
+            request: ext_data_control_manager_v1::Request,
+            _: &(),
+            dh: &DisplayHandle,
+            data_init: &mut DataInit<'_, Self>,
+        ) {
+            match request {
+                ext_data_control_manager_v1::Request::CreateDataSource { id } => {
+                    let source = data_init.init(id, ());
+                    state.mime_types.insert(source.id(), Vec::new());
+                },
+                ext_data_control_manager_v1::Request::GetDataDevice { id, .. } => {
+                    let device = data_init.init(id, ());
+                    for &selection in &Selection::ALL {
+                        state.announce(dh, &device, selection);
+                    }
+                    state.devices.push(device);
+                },
+                _ => {},
+            }
+        }
+    }
+
+    impl wayland_server::Dispatch<ExtDataControlDeviceV1, ()> for Compositor {
+        fn request(
+            state: &mut Self,
+            _: &Client,
+            device: &ExtDataControlDeviceV1,
+            request: ext_data_control_device_v1::Request,
+            _: &(),
+            dh: &DisplayHandle,
+            _: &mut DataInit<'_, Self>,
+        ) {
+            match request {
+                ext_data_control_device_v1::Request::SetSelection { source } => {
+                    state.set_selection(dh, Selection::Clipboard, source);
+                },
+                ext_data_control_device_v1::Request::SetPrimarySelection { source } => {
+                    state.set_selection(dh, Selection::Primary, source);
+                },
+                ext_data_control_device_v1::Request::Destroy => {
+                    state.devices.retain(|d| d != device);
+                },
+                _ => {},
+            }
+        }
+    }
+
+    impl wayland_server::Dispatch<ExtDataControlSourceV1, ()> for Compositor {
+        fn request(
+            state: &mut Self,
+            _: &Client,
+            source: &ExtDataControlSourceV1,
+            request: ext_data_control_source_v1::Request,
+            _: &(),
+            dh: &DisplayHandle,
+            _: &mut DataInit<'_, Self>,
+        ) {
+            match request {
+                ext_data_control_source_v1::Request::Offer { mime_type } => {
+                    state.mime_types.get_mut(&source.id()).unwrap().push(mime_type);
+                },
+                ext_data_control_source_v1::Request::Destroy => {
+                    for &selection in &Selection::ALL {
+                        if state.selections[selection.index()].as_ref() == Some(source) {
+                            state.set_selection(dh, selection, None);
+                        }
+                    }
+                },
+                _ => {},
+            }
+        }
+    }
+
+    impl wayland_server::Dispatch<ExtDataControlOfferV1, ExtDataControlSourceV1> for Compositor {
+        fn request(
+            _: &mut Self,
+            _: &Client,
+            _: &ExtDataControlOfferV1,
+            request: ext_data_control_offer_v1::Request,
+            source: &ExtDataControlSourceV1,
+            _: &DisplayHandle,
+            _: &mut DataInit<'_, Self>,
+        ) {
+            if let ext_data_control_offer_v1::Request::Receive { mime_type, fd } = request {
+                if source.is_alive() {
+                    source.send(mime_type, fd.as_fd());
+                }
+            }
+        }
+    }
+
+    /// Runs a compositor on a background thread until dropped.
+    struct TestCompositor {
+        running: Arc<AtomicBool>,
+        thread: Option<JoinHandle<()>>,
+    }
+
+    impl TestCompositor {
+        /// Starts a compositor with `count` clients connected to it.
+        fn start(count: usize) -> (Self, Vec<Arc<Server>>) {
+            let (clients, streams): (Vec<_>, Vec<_>) = (0..count)
+                .map(|_| UnixStream::pair().unwrap())
+                .unzip();
+            let running = Arc::new(AtomicBool::new(true));
+
+            let thread = thread::spawn({
+                let running = running.clone();
+                move || {
+                    let mut display = Display::<Compositor>::new().unwrap();
+                    let dh = display.handle();
+                    dh.create_global::<Compositor, WlSeat, ()>(1, ());
+                    dh.create_global::<Compositor, ExtDataControlManagerV1, ()>(1, ());
+                    for stream in streams {
+                        display.handle().insert_client(stream, Arc::new(TestClient)).unwrap();
+                    }
+
+                    let mut state = Compositor::default();
+                    while running.load(Ordering::SeqCst) {
+                        display.dispatch_clients(&mut state).unwrap();
+                        display.flush_clients().unwrap();
+                        let fd = display.backend().poll_fd().as_raw_fd();
+                        let mut poll_fd = libc::pollfd { fd, events: libc::POLLIN, revents: 0 };
+                        unsafe { libc::poll(&mut poll_fd, 1, 10) };
+                    }
+                }
+            });
+
+            let servers = clients.into_iter()
+                .map(|stream| Server::new(Connection::from_socket(stream).unwrap()).unwrap())
+                .collect();
+            (TestCompositor { running, thread: Some(thread) }, servers)
+        }
+    }
+
+    impl Drop for TestCompositor {
+        fn drop(&mut self) {
+            self.running.store(false, Ordering::SeqCst);
+            self.thread.take().unwrap().join().unwrap();
+        }
+    }
+
+    fn wait_until<F: FnMut() -> bool>(mut condition: F) {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !condition() {
+            assert!(Instant::now() < deadline, "timed out");
+            main_loop::run_pending();
+            thread::sleep(Duration::from_millis(5));
+        }
+    }
+
+    #[test]
+    fn transfers_between_clients() {
+        let (_compositor, servers) = TestCompositor::start(2);
+        let (a, b) = (&servers[0], &servers[1]);
+        assert!(a.has_primary());
+
+        a.write(Selection::Clipboard, Contents::new(ClipboardData::new().text("héllo").html("<p>")));
+        wait_until(|| !b.targets(Selection::Clipboard).is_empty());
+
+        assert!(b.targets(Selection::Clipboard).contains(&"UTF8_STRING".to_owned()));
+        assert_eq!(b.read(Selection::Clipboard, mime::TEXT_PLAIN).unwrap(), "héllo".as_bytes());
+        assert_eq!(b.read(Selection::Clipboard, "STRING").unwrap(), b"h\xe9llo");
+        assert_eq!(b.read(Selection::Clipboard, mime::TEXT_HTML).unwrap(), b"<p>");
+        assert!(b.targets(Selection::Primary).is_empty());
+
+        a.write(Selection::Primary, Contents::new(ClipboardData::new().text("primary")));
+        wait_until(|| !b.targets(Selection::Primary).is_empty());
+        assert_eq!(b.read(Selection::Primary, "UTF8_STRING").unwrap(), b"primary");
+
+        a.write(Selection::Clipboard, Contents::default());
+        wait_until(|| b.targets(Selection::Clipboard).is_empty());
+        assert!(a.targets(Selection::Clipboard).is_empty());
+        assert!(!b.targets(Selection::Primary).is_empty());
+    }
+
+    #[test]
+    fn replaced_source_is_cancelled() {
+        let (_compositor, servers) = TestCompositor::start(2);
+        let (a, b) = (&servers[0], &servers[1]);
+
+        a.write(Selection::Clipboard, Contents::new(ClipboardData::new().text("a")));
+        wait_until(|| b.read(Selection::Clipboard, "UTF8_STRING") == Some(b"a".to_vec()));
+
+        b.write(Selection::Clipboard, Contents::new(ClipboardData::new().text("b")));
+        wait_until(|| a.owned(Selection::Clipboard).is_none());
+        wait_until(|| a.read(Selection::Clipboard, "UTF8_STRING") == Some(b"b".to_vec()));
+    }
+
+    #[test]
+    fn watchers_observe_changes() {
+        let (_compositor, servers) = TestCompositor::start(2);
+        let (a, b) = (&servers[0], &servers[1]);
+
+        let count = b.changes().count(Selection::Clipboard);
+        let clipboard = crate::Clipboard::from(Clipboard {
+            backend: Some(b.clone()),
+            selection: Selection::Clipboard,
+        });
+        let calls = Rc::new(Cell::new(0));
+        let watcher = clipboard.watch({
+            let calls = calls.clone();
+            move |clipboard| {
+                assert_eq!(clipboard.text().unwrap(), "watched");
+                calls.set(calls.get() + 1);
+            }
+        });
+
+        a.write(Selection::Clipboard, Contents::new(ClipboardData::new().text("watched")));
+        wait_until(|| calls.get() > 0);
+        assert!(b.changes().count(Selection::Clipboard) > count);
+
+        drop(watcher);
+        let calls_before = calls.get();
+        a.write(Selection::Clipboard, Contents::new(ClipboardData::new().text("ignored")));
+        wait_until(|| b.read(Selection::Clipboard, "UTF8_STRING") == Some(b"ignored".to_vec()));
+        main_loop::run_pending();
+        assert_eq!(calls.get(), calls_before);
+    }
+}
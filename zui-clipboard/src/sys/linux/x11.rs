@@ -0,0 +1,464 @@
+//! The `CLIPBOARD` and `PRIMARY` selections of an X11 display.
+//!
+//! A hidden window owns the selections on behalf of the process. Its events
+//! are handled on a background thread, which serves conversion requests,
+//! including incremental (`INCR`) transfers of large data, and collects the
+//! replies to conversions requested by this process.
+
+use std::{
+    collections::HashMap,
+    sync::{mpsc, Arc, Mutex},
+    thread,
+    time::Duration,
+};
+use x11rb::{
+    connection::{Connection, RequestConnection},
+    protocol::{
+        xfixes::{ConnectionExt as _, SelectionEventMask},
+        xproto::{
+            Atom,
+            AtomEnum,
+            ChangeWindowAttributesAux,
+            ConnectionExt as _,
+            CreateWindowAux,
+            EventMask,
+            PropMode,
+            Property,
+            PropertyNotifyEvent,
+            SelectionNotifyEvent,
+            SelectionRequestEvent,
+            Window,
+            WindowClass,
+            SELECTION_NOTIFY_EVENT,
+        },
+        Event,
+    },
+    rust_connection::RustConnection,
+    wrapper::ConnectionExt as _,
+    CURRENT_TIME,
+    NONE,
+};
+use shared::os::linux::DisplayBackend;
+use super::{Backend, Changes, Contents, Selection};
+
+/// How long to wait for the owner of a selection to make progress on a
+/// conversion before giving up.
+const TIMEOUT: Duration = Duration::from_secs(1);
+
+/// The property on the hidden window that conversions are stored in.
+const PROPERTY: &str = "ZUI_SELECTION";
+
+struct Atoms {
+    clipboard: Atom,
+    targets: Atom,
+    incr: Atom,
+    utf8_string: Atom,
+    property: Atom,
+}
+
+/// A conversion requested by this process.
+struct Conversion {
+    selection: Atom,
+    target: Atom,
+    reply: mpsc::Sender<Reply>,
+}
+
+enum Reply {
+    /// A chunk of an incremental transfer arrived.
+    Progress,
+    Done(Option<Vec<u8>>),
+}
+
+/// Data being sent incrementally to another client.
+struct Transfer {
+    requestor: Window,
+    property: Atom,
+    ty: Atom,
+    data: Vec<u8>,
+    offset: usize,
+    finished: bool,
+}
+
+pub struct Server {
+    conn: RustConnection,
+    window: Window,
+    atoms: Atoms,
+    xfixes: bool,
+    chunk_size: usize,
+    owned: [Mutex<Option<Contents>>; 2],
+    conversion: Mutex<Option<Conversion>>,
+    read_lock: Mutex<()>,
+    atom_names: Mutex<HashMap<Atom, String>>,
+    changes: Changes,
+}
+
+impl Server {
+    pub fn connect() -> Option<Arc<Self>> {
+        let (conn, screen) = RustConnection::connect(None).ok()?;
+        Self::new(conn, screen)
+    }
+
+    fn new(conn: RustConnection, screen: usize) -> Option<Arc<Self>> {
+        let root = conn.setup().roots.get(screen)?.root;
+        let window = conn.generate_id().ok()?;
+        conn.create_window(
+            0,
+            window,
+            root,
+            0, 0, 1, 1, 0,
+            WindowClass::INPUT_OUTPUT,
+            0,
+            &CreateWindowAux::new().event_mask(EventMask::PROPERTY_CHANGE),
+        ).ok()?;
+
+        let intern = |name: &str| -> Option<Atom> {
+            Some(conn.intern_atom(false, name.as_bytes()).ok()?.reply().ok()?.atom)
+        };
+        let atoms = Atoms {
+            clipboard: intern("CLIPBOARD")?,
+            targets: intern("TARGETS")?,
+            incr: intern("INCR")?,
+            utf8_string: intern("UTF8_STRING")?,
+            property: intern(PROPERTY)?,
+        };
+
+        // Without XFixes, only changes made by this process are counted.
+        let xfixes = conn.xfixes_query_version(5, 0).ok()
+            .and_then(|cookie| cookie.reply().ok())
+            .is_some();
+        if xfixes {
+            let mask = SelectionEventMask::SET_SELECTION_OWNER
+                | SelectionEventMask::SELECTION_WINDOW_DESTROY
+                | SelectionEventMask::SELECTION_CLIENT_CLOSE;
+            for &selection in &[atoms.clipboard, AtomEnum::PRIMARY.into()] {
+                conn.xfixes_select_selection_input(window, selection, mask).ok()?;
+            }
+        }
+        conn.flush().ok()?;
+
+        let server = Arc::new(Server {
+            chunk_size: conn.maximum_request_bytes() / 4,
+            conn,
+            window,
+            atoms,
+            xfixes,
+            owned: Default::default(),
+            conversion: Mutex::new(None),
+            read_lock: Mutex::new(()),
+            atom_names: Mutex::new(HashMap::new()),
+            changes: Changes::default(),
+        });
+
+        let events = server.clone();
+        thread::Builder::new()
+            .name("zui-clipboard-x11".into())
+            .spawn(move || events.run())
+            .ok()?;
+
+        Some(server)
+    }
+
+    fn selection_atom(&self, selection: Selection) -> Atom {
+        match selection {
+            Selection::Clipboard => self.atoms.clipboard,
+            Selection::Primary => AtomEnum::PRIMARY.into(),
+        }
+    }
+
+    fn selection(&self, atom: Atom) -> Option<Selection> {
+        Selection::ALL.iter().cloned().find(|&s| self.selection_atom(s) == atom)
+    }
+
+    fn owned(&self, selection: Selection) -> Option<Contents> {
+        self.owned[selection.index()].lock().unwrap().clone()
+    }
+
+    fn atom(&self, name: &str) -> Option<Atom> {
+        let atom = self.conn.intern_atom(false, name.as_bytes()).ok()?.reply().ok()?.atom;
+        self.atom_names.lock().unwrap().insert(atom, name.to_owned());
+        Some(atom)
+    }
+
+    fn atom_name(&self, atom: Atom) -> Option<String> {
+        if let Some(name) = self.atom_names.lock().unwrap().get(&atom) {
+            return Some(name.clone());
+        }
+        let name = self.conn.get_atom_name(atom).ok()?.reply().ok()?.name;
+        let name = String::from_utf8(name).ok()?;
+        self.atom_names.lock().unwrap().insert(atom, name.clone());
+        Some(name)
+    }
+
+    /// Asks the owner of `selection` to convert it to `target`, and waits for
+    /// the result.
+    fn convert(&self, selection: Selection, target: Atom) -> Option<Vec<u8>> {
+        let _guard = self.read_lock.lock().unwrap();
+        let selection = self.selection_atom(selection);
+        let (reply, replies) = mpsc::channel();
+        *self.conversion.lock().unwrap() = Some(Conversion { selection, target, reply });
+
+        let requested = self.conn
+            .convert_selection(self.window, selection, target, self.atoms.property, CURRENT_TIME)
+            .is_ok() && self.conn.flush().is_ok();
+
+        let result = if requested {
+            loop {
+                match replies.recv_timeout(TIMEOUT) {
+                    Ok(Reply::Progress) => continue,
+                    Ok(Reply::Done(data)) => break data,
+                    Err(_) => break None,
+                }
+            }
+        } else {
+            None
+        };
+
+        *self.conversion.lock().unwrap() = None;
+        result
+    }
+
+    fn run(&self) {
+        let mut incoming: Option<Vec<u8>> = None;
+        let mut transfers: Vec<Transfer> = Vec::new();
+
+        while let Ok(event) = self.conn.wait_for_event() {
+            match event {
+                Event::SelectionRequest(event) => {
+                    self.handle_request(&event, &mut transfers);
+                },
+                Event::SelectionNotify(event) if event.requestor == self.window => {
+                    self.handle_notify(&event, &mut incoming);
+                },
+                Event::PropertyNotify(event) => {
+                    self.handle_property(&event, &mut incoming, &mut transfers);
+                },
+                Event::SelectionClear(event) => {
+                    if let Some(selection) = self.selection(event.selection) {
+                        self.owned[selection.index()].lock().unwrap().take();
+                        if !self.xfixes {
+                            self.changes.changed(selection);
+                        }
+                    }
+                },
+                Event::XfixesSelectionNotify(event) => {
+                    if let Some(selection) = self.selection(event.selection) {
+                        self.changes.changed(selection);
+                    }
+                },
+                _ => {},
+            }
+            let _ = self.conn.flush();
+        }
+    }
+
+    fn handle_request(&self, event: &SelectionRequestEvent, transfers: &mut Vec<Transfer>) {
+        // Obsolete clients leave the property unset.
+        let property = if event.property == NONE { event.target } else { event.property };
+        let contents = self.selection(event.selection).and_then(|s| self.owned(s));
+        let served = contents
+            .and_then(|contents| self.serve(&contents, event.requestor, property, event.target))
+            .map(|transfer| transfers.extend(transfer))
+            .is_some();
+
+        let notify = SelectionNotifyEvent {
+            response_type: SELECTION_NOTIFY_EVENT,
+            sequence: 0,
+            time: event.time,
+            requestor: event.requestor,
+            selection: event.selection,
+            target: event.target,
+            property: if served { property } else { NONE },
+        };
+        let _ = self.conn.send_event(false, event.requestor, EventMask::NO_EVENT, notify);
+    }
+
+    /// Stores `contents` converted to `target` in `property` of `requestor`,
+    /// returning a transfer if the data has to be sent incrementally.
+    fn serve(
+        &self,
+        contents: &Contents,
+        requestor: Window,
+        property: Atom,
+        target: Atom,
+    ) -> Option<Option<Transfer>> {
+        let conn = &self.conn;
+
+        if target == self.atoms.targets {
+            let mut atoms = vec![self.atoms.targets];
+            atoms.extend(contents.targets().iter().filter_map(|t| self.atom(t)));
+            conn.change_property32(PropMode::REPLACE, requestor, property, AtomEnum::ATOM, &atoms)
+                .ok()?;
+            return Some(None);
+        }
+
+        let name = self.atom_name(target)?;
+        let data = contents.convert(&name)?;
+        let ty = match name.as_str() {
+            "UTF8_STRING" | "TEXT" => self.atoms.utf8_string,
+            _ => target,
+        };
+
+        if data.len() <= self.chunk_size {
+            conn.change_property8(PropMode::REPLACE, requestor, property, ty, &data).ok()?;
+            return Some(None);
+        }
+
+        // The requestor deletes the property to ask for each chunk.
+        let attributes = ChangeWindowAttributesAux::new().event_mask(EventMask::PROPERTY_CHANGE);
+        conn.change_window_attributes(requestor, &attributes).ok()?;
+        conn.change_property32(
+            PropMode::REPLACE,
+            requestor,
+            property,
+            self.atoms.incr,
+            &[data.len() as u32],
+        ).ok()?;
+
+        Some(Some(Transfer { requestor, property, ty, data, offset: 0, finished: false }))
+    }
+
+    fn handle_notify(&self, event: &SelectionNotifyEvent, incoming: &mut Option<Vec<u8>>) {
+        let conversion = self.conversion.lock().unwrap();
+        let conversion = match &*conversion {
+            Some(c) if c.selection == event.selection && c.target == event.target => c,
+            // A reply to a conversion that timed out.
+            _ => return,
+        };
+
+        if event.property == NONE {
+            let _ = conversion.reply.send(Reply::Done(None));
+            return;
+        }
+
+        let property = self.conn
+            .get_property(true, self.window, event.property, AtomEnum::ANY, 0, u32::MAX)
+            .ok()
+            .and_then(|cookie| cookie.reply().ok());
+
+        let reply = match property {
+            Some(ref property) if property.type_ == self.atoms.incr => {
+                // Deleting the property started the transfer.
+                *incoming = Some(Vec::new());
+                Reply::Progress
+            },
+            Some(property) => Reply::Done(Some(property.value)),
+            None => Reply::Done(None),
+        };
+        let _ = conversion.reply.send(reply);
+    }
+
+    fn handle_property(
+        &self,
+        event: &PropertyNotifyEvent,
+        incoming: &mut Option<Vec<u8>>,
+        transfers: &mut Vec<Transfer>,
+    ) {
+        if event.window == self.window {
+            if event.atom == self.atoms.property && event.state == Property::NEW_VALUE {
+                self.receive_chunk(incoming);
+            }
+        } else if event.state == Property::DELETE {
+            let index = transfers.iter()
+                .position(|t| t.requestor == event.window && t.property == event.atom);
+            if let Some(index) = index {
+                if !self.send_chunk(&mut transfers[index]) {
+                    transfers.swap_remove(index);
+                }
+            }
+        }
+    }
+
+    fn receive_chunk(&self, incoming: &mut Option<Vec<u8>>) {
+        let data = match incoming {
+            Some(data) => data,
+            None => return,
+        };
+        let chunk = self.conn
+            .get_property(true, self.window, self.atoms.property, AtomEnum::ANY, 0, u32::MAX)
+            .ok()
+            .and_then(|cookie| cookie.reply().ok());
+
+        let reply = match chunk {
+            Some(ref chunk) if !chunk.value.is_empty() => {
+                data.extend_from_slice(&chunk.value);
+                Reply::Progress
+            },
+            // An empty chunk ends the transfer.
+            Some(_) => Reply::Done(incoming.take()),
+            None => {
+                incoming.take();
+                Reply::Done(None)
+            },
+        };
+        if let Some(conversion) = &*self.conversion.lock().unwrap() {
+            let _ = conversion.reply.send(reply);
+        }
+    }
+
+    /// Sends the next chunk of `transfer`, returning `false` once it is over.
+    fn send_chunk(&self, transfer: &mut Transfer) -> bool {
+        if transfer.finished {
+            return false;
+        }
+        let end = transfer.data.len().min(transfer.offset + self.chunk_size);
+        let chunk = &transfer.data[transfer.offset..end];
+        transfer.finished = chunk.is_empty();
+        transfer.offset = end;
+        self.conn
+            .change_property8(PropMode::REPLACE, transfer.requestor, transfer.property, transfer.ty, chunk)
+            .is_ok()
+    }
+}
+
+impl Backend for Server {
+    #[inline]
+    fn display_backend(&self) -> DisplayBackend {
+        DisplayBackend::X11
+    }
+
+    #[inline]
+    fn has_primary(&self) -> bool {
+        true
+    }
+
+    fn targets(&self, selection: Selection) -> Vec<String> {
+        if let Some(contents) = self.owned(selection) {
+            return contents.targets();
+        }
+        let targets = match self.convert(selection, self.atoms.targets) {
+            Some(targets) => targets,
+            None => return Vec::new(),
+        };
+        targets.chunks_exact(4)
+            .map(|atom| u32::from_ne_bytes([atom[0], atom[1], atom[2], atom[3]]))
+            .filter_map(|atom| self.atom_name(atom))
+            .collect()
+    }
+
+    fn read(&self, selection: Selection, target: &str) -> Option<Vec<u8>> {
+        if let Some(contents) = self.owned(selection) {
+            return contents.convert(target);
+        }
+        let target = self.atom(target)?;
+        self.convert(selection, target)
+    }
+
+    fn write(&self, selection: Selection, contents: Contents) {
+        let owner = if contents.is_empty() { NONE } else { self.window };
+        *self.owned[selection.index()].lock().unwrap() = Some(contents).filter(|c| !c.is_empty());
+
+        let atom = self.selection_atom(selection);
+        let _ = self.conn.set_selection_owner(owner, atom, CURRENT_TIME);
+        let _ = self.conn.flush();
+
+        // XFixes reports changes made by this process as well.
+        if !self.xfixes {
+            self.changes.changed(selection);
+        }
+    }
+
+    #[inline]
+    fn changes(&self) -> &Changes {
+        &self.changes
+    }
+}
@@ -0,0 +1,254 @@
+use std::{
+    ffi::{c_void, CStr},
+    fmt,
+    slice,
+    sync::Once,
+};
+use cocoa::{
+    base::{id, nil, BOOL, YES},
+    foundation::{
+        NSArray,
+        NSInteger,
+        NSString,
+        NSUInteger,
+    },
+};
+use objc::{
+    declare::ClassDecl,
+    rc::StrongPtr,
+    runtime::{Class, Object, Sel},
+};
+use shared::{
+    os::macos::{mime_type, pasteboard_type, ZedStringExt},
+    ZedString,
+};
+use crate::{mime, ClipboardData};
+
+/// How often the change count is polled, since `NSPasteboard` does not post
+/// notifications.
+const WATCH_INTERVAL: f64 = 0.25;
+
+unsafe fn ns_string(s: &str) -> StrongPtr {
+    StrongPtr::new(NSString::alloc(nil).init_str(s))
+}
+
+unsafe fn to_utf8(ns_string: id) -> String {
+    CStr::from_ptr(ns_string.UTF8String()).to_string_lossy().into_owned()
+}
+
+#[derive(Clone)]
+pub struct Clipboard {
+    pub ns_pasteboard: StrongPtr,
+}
+
+impl fmt::Debug for Clipboard {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Clipboard")
+            .field("ns_pasteboard", &*self.ns_pasteboard) // No `Debug` for `StrongPtr`
+            .finish()
+    }
+}
+
+impl Clipboard {
+    #[inline]
+    pub fn general() -> Self {
+        unsafe {
+            let ns_pasteboard: id = msg_send![class!(NSPasteboard), generalPasteboard];
+            Clipboard { ns_pasteboard: StrongPtr::retain(ns_pasteboard) }
+        }
+    }
+
+    #[inline]
+    pub fn primary_selection() -> Option<Self> {
+        None
+    }
+
+    pub fn formats(&self) -> Vec<String> {
+        unsafe {
+            let types: id = msg_send![*self.ns_pasteboard, types];
+            if types == nil {
+                return Vec::new();
+            }
+            let count: NSUInteger = msg_send![types, count];
+            (0..count)
+                .map(|i| {
+                    let ty: id = msg_send![types, objectAtIndex:i];
+                    mime_type(&to_utf8(ty)).into()
+                })
+                .collect()
+        }
+    }
+
+    pub fn has_format(&self, mime: &str) -> bool {
+        unsafe {
+            let ty = ns_string(pasteboard_type(mime));
+            let types = NSArray::arrayWithObject(nil, *ty);
+            let available: id = msg_send![*self.ns_pasteboard, availableTypeFromArray:types];
+            available != nil
+        }
+    }
+
+    pub fn text(&self) -> Option<String> {
+        unsafe {
+            let ty = ns_string(pasteboard_type(mime::TEXT_PLAIN));
+            let string: id = msg_send![*self.ns_pasteboard, stringForType:*ty];
+            if string == nil {
+                None
+            } else {
+                Some(to_utf8(string))
+            }
+        }
+    }
+
+    pub fn data(&self, mime: &str) -> Option<Vec<u8>> {
+        unsafe {
+            let ty = ns_string(pasteboard_type(mime));
+            let data: id = msg_send![*self.ns_pasteboard, dataForType:*ty];
+            if data == nil {
+                return None;
+            }
+            let len: NSUInteger = msg_send![data, length];
+            if len == 0 {
+                return Some(Vec::new());
+            }
+            let bytes: *const c_void = msg_send![data, bytes];
+            Some(slice::from_raw_parts(bytes as *const u8, len as usize).to_vec())
+        }
+    }
+
+    pub fn set_text(&self, text: ZedString) {
+        unsafe {
+            let ty = ns_string(pasteboard_type(mime::TEXT_PLAIN));
+            let _: NSInteger = msg_send![*self.ns_pasteboard, clearContents];
+            let _: BOOL = msg_send![*self.ns_pasteboard, setString:**text.as_ns_string()
+                                                           forType:*ty];
+        }
+    }
+
+    pub fn write(&self, data: &ClipboardData) {
+        unsafe {
+            let _: NSInteger = msg_send![*self.ns_pasteboard, clearContents];
+            for (mime, bytes) in data.iter() {
+                let ty = ns_string(pasteboard_type(mime));
+                let ns_data: id = msg_send![
+                    class!(NSData),
+                    dataWithBytes:bytes.as_ptr() as *const c_void
+                           length:bytes.len() as NSUInteger
+                ];
+                let _: BOOL = msg_send![*self.ns_pasteboard, setData:ns_data
+                                                             forType:*ty];
+            }
+        }
+    }
+
+    #[inline]
+    pub fn clear(&self) {
+        unsafe {
+            let _: NSInteger = msg_send![*self.ns_pasteboard, clearContents];
+        }
+    }
+
+    #[inline]
+    pub fn change_count(&self) -> u64 {
+        let count: NSInteger = unsafe {
+            msg_send![*self.ns_pasteboard, changeCount]
+        };
+        count as u64
+    }
+
+    pub fn watch(
+        &self,
+        clipboard: crate::Clipboard,
+        callback: Box<dyn FnMut(&crate::Clipboard)>,
+    ) -> ClipboardWatcher {
+        let state = Box::new(WatcherState {
+            change_count: self.change_count(),
+            clipboard,
+            callback,
+        });
+
+        unsafe {
+            let target: id = msg_send![watcher_class(), alloc];
+            let target: id = msg_send![target, init];
+            (*target).set_ivar(STATE_IVAR, Box::into_raw(state) as *mut c_void);
+            let target = StrongPtr::new(target);
+
+            let timer: id = msg_send![
+                class!(NSTimer),
+                scheduledTimerWithTimeInterval:WATCH_INTERVAL
+                                        target:*target
+                                      selector:sel!(zuiPoll:)
+                                      userInfo:nil
+                                       repeats:YES
+            ];
+
+            ClipboardWatcher { timer: StrongPtr::retain(timer) }
+        }
+    }
+}
+
+const STATE_IVAR: &str = "zuiState";
+
+struct WatcherState {
+    change_count: u64,
+    clipboard: crate::Clipboard,
+    callback: Box<dyn FnMut(&crate::Clipboard)>,
+}
+
+pub struct ClipboardWatcher {
+    timer: StrongPtr,
+}
+
+impl Drop for ClipboardWatcher {
+    fn drop(&mut self) {
+        // The timer retains its target, which releases the watcher state once
+        // the timer is invalidated.
+        unsafe { msg_send![*self.timer, invalidate] };
+    }
+}
+
+fn watcher_class() -> &'static Class {
+    static REGISTER: Once = Once::new();
+
+    REGISTER.call_once(|| {
+        let superclass = class!(NSObject);
+        let mut decl = ClassDecl::new("ZUIClipboardWatcher", superclass).unwrap();
+        decl.add_ivar::<*mut c_void>(STATE_IVAR);
+        unsafe {
+            decl.add_method(
+                sel!(zuiPoll:),
+                poll as extern "C" fn(&Object, Sel, id),
+            );
+            decl.add_method(
+                sel!(dealloc),
+                dealloc as extern "C" fn(&Object, Sel),
+            );
+        }
+        decl.register();
+    });
+
+    class!(ZUIClipboardWatcher)
+}
+
+extern "C" fn poll(this: &Object, _: Sel, _timer: id) {
+    unsafe {
+        let state: *mut c_void = *this.get_ivar(STATE_IVAR);
+        let state = &mut *(state as *mut WatcherState);
+
+        let change_count = state.clipboard.change_count();
+        if change_count != state.change_count {
+            state.change_count = change_count;
+            (state.callback)(&state.clipboard);
+        }
+    }
+}
+
+extern "C" fn dealloc(this: &Object, _: Sel) {
+    unsafe {
+        let state: *mut c_void = *this.get_ivar(STATE_IVAR);
+        if !state.is_null() {
+            drop(Box::from_raw(state as *mut WatcherState));
+        }
+        msg_send![super(this, class!(NSObject)), dealloc];
+    }
+}
@@ -0,0 +1,121 @@
+//! Dialogs for choosing files and folders.
+
+use std::path::{Path, PathBuf};
+use shared::ZedString;
+use window::Window;
+use crate::sys;
+
+/// A filter restricting which files can be chosen in a
+/// [`FileDialog`](struct.FileDialog.html).
+#[derive(Clone, Debug)]
+pub struct FileFilter {
+    /// The displayed name of the filter, such as "Images".
+    pub name: String,
+    /// The file extensions allowed by the filter, without leading dots.
+    pub extensions: Vec<String>,
+}
+
+/// A native dialog for choosing files to open or save, or folders.
+///
+/// Each `pick_*` and `save_file` method blocks until the user dismisses the
+/// dialog, returning `None` if it was cancelled.
+///
+/// On Linux, dialogs are shown through the XDG desktop portal, or through
+/// GTK 3 from the main thread if no portal is running.
+#[derive(Clone, Debug, Default)]
+pub struct FileDialog {
+    pub(crate) title: Option<ZedString>,
+    pub(crate) filters: Vec<FileFilter>,
+    pub(crate) directory: Option<PathBuf>,
+    pub(crate) file_name: Option<String>,
+    pub(crate) parent: Option<Window>,
+}
+
+impl FileDialog {
+    /// Creates a dialog with no filters.
+    #[inline]
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Sets the displayed title.
+    #[inline]
+    pub fn title<S: Into<ZedString>>(&mut self, title: S) -> &mut Self {
+        self.title = Some(title.into());
+        self
+    }
+
+    /// Adds a filter named `name` allowing files with any of `extensions`.
+    ///
+    /// Without filters, every file can be chosen. On macOS, files matching any
+    /// of the filters can be chosen at once, and `name` is not displayed.
+    pub fn add_filter(&mut self, name: &str, extensions: &[&str]) -> &mut Self {
+        self.filters.push(FileFilter {
+            name: name.into(),
+            extensions: extensions.iter()
+                .map(|ext| ext.trim_start_matches('.').into())
+                .collect(),
+        });
+        self
+    }
+
+    /// Sets the directory initially shown.
+    #[inline]
+    pub fn directory<P: AsRef<Path>>(&mut self, directory: P) -> &mut Self {
+        self.directory = Some(directory.as_ref().into());
+        self
+    }
+
+    /// Sets the file name initially entered when saving.
+    #[inline]
+    pub fn file_name(&mut self, file_name: &str) -> &mut Self {
+        self.file_name = Some(file_name.into());
+        self
+    }
+
+    /// Makes the dialog modal for `parent`, attaching it to the window and
+    /// blocking input to it while shown.
+    ///
+    /// On Linux, dialogs are only attached to X11 windows, and only when
+    /// shown through the XDG desktop portal rather than GTK.
+    #[inline]
+    pub fn parent(&mut self, parent: &Window) -> &mut Self {
+        self.parent = Some(parent.clone());
+        self
+    }
+
+    /// Shows a dialog for choosing a single file to open.
+    #[inline]
+    pub fn pick_file(&self) -> Option<PathBuf> {
+        self.open(false, false)?.into_iter().next()
+    }
+
+    /// Shows a dialog for choosing one or more files to open.
+    #[inline]
+    pub fn pick_files(&self) -> Option<Vec<PathBuf>> {
+        self.open(false, true)
+    }
+
+    /// Shows a dialog for choosing a single folder.
+    #[inline]
+    pub fn pick_folder(&self) -> Option<PathBuf> {
+        self.open(true, false)?.into_iter().next()
+    }
+
+    /// Shows a dialog for choosing one or more folders.
+    #[inline]
+    pub fn pick_folders(&self) -> Option<Vec<PathBuf>> {
+        self.open(true, true)
+    }
+
+    /// Shows a dialog for choosing where to save a file.
+    #[inline]
+    pub fn save_file(&self) -> Option<PathBuf> {
+        sys::save_file(self)
+    }
+
+    #[inline]
+    fn open(&self, folders: bool, multiple: bool) -> Option<Vec<PathBuf>> {
+        sys::open(self, folders, multiple)
+    }
+}
@@ -0,0 +1,21 @@
+//! ZedUI native dialogs.
+
+#![deny(missing_docs)]
+#![doc(html_logo_url = "https://zed-ui.dev/static/logos/zed-ui.svg")]
+
+#[cfg(target_os = "macos")]
+#[macro_use]
+extern crate objc;
+
+extern crate zui_shared as shared;
+extern crate zui_window as window;
+
+mod sys;
+pub mod file;
+pub mod message;
+
+#[doc(inline)]
+pub use self::{
+    file::FileDialog,
+    message::MessageDialog,
+};
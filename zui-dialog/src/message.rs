@@ -0,0 +1,158 @@
+//! Dialogs for showing messages and asking for confirmation.
+
+use shared::ZedString;
+use window::Window;
+use crate::sys;
+
+/// The severity of a [`MessageDialog`](struct.MessageDialog.html), which
+/// determines its icon.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum MessageLevel {
+    /// Information for the user.
+    Info,
+    /// A warning about a potentially harmful action.
+    Warning,
+    /// An error that occurred.
+    Error,
+}
+
+impl Default for MessageLevel {
+    #[inline]
+    fn default() -> Self {
+        MessageLevel::Info
+    }
+}
+
+/// A common set of buttons for a [`MessageDialog`](struct.MessageDialog.html).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum MessageButtons {
+    /// "OK".
+    Ok,
+    /// "OK" and "Cancel".
+    OkCancel,
+    /// "Yes" and "No".
+    YesNo,
+    /// "Yes", "No" and "Cancel".
+    YesNoCancel,
+}
+
+impl MessageButtons {
+    fn labels(self) -> &'static [&'static str] {
+        match self {
+            MessageButtons::Ok => &["OK"],
+            MessageButtons::OkCancel => &["OK", "Cancel"],
+            MessageButtons::YesNo => &["Yes", "No"],
+            MessageButtons::YesNoCancel => &["Yes", "No", "Cancel"],
+        }
+    }
+}
+
+/// The outcome of showing a [`MessageDialog`](struct.MessageDialog.html).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct MessageResponse {
+    /// The index of the pressed button, in the order they were added, or
+    /// `None` if the dialog was dismissed without pressing one.
+    pub button: Option<usize>,
+    /// Whether the checkbox was checked, or `false` if there was none.
+    pub checked: bool,
+}
+
+/// A native dialog showing a message with a set of buttons.
+///
+/// Buttons are displayed in the order they are added, the first being the
+/// default. A dialog without buttons shows a single "OK" button.
+///
+/// On Linux, this is a GTK 3 message dialog, which is not attached to the
+/// parent window. Without GTK or off the main thread, the dialog is not
+/// shown and the response has no button.
+#[derive(Clone, Debug, Default)]
+pub struct MessageDialog {
+    pub(crate) level: MessageLevel,
+    pub(crate) title: Option<ZedString>,
+    pub(crate) body: Option<ZedString>,
+    pub(crate) buttons: Vec<ZedString>,
+    pub(crate) checkbox: Option<ZedString>,
+    pub(crate) parent: Option<Window>,
+}
+
+impl MessageDialog {
+    /// Creates an informational dialog with no text.
+    #[inline]
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Sets the severity of the message.
+    #[inline]
+    pub fn level(&mut self, level: MessageLevel) -> &mut Self {
+        self.level = level;
+        self
+    }
+
+    /// Sets the emphasized title of the message.
+    #[inline]
+    pub fn title<S: Into<ZedString>>(&mut self, title: S) -> &mut Self {
+        self.title = Some(title.into());
+        self
+    }
+
+    /// Sets the body text of the message.
+    #[inline]
+    pub fn body<S: Into<ZedString>>(&mut self, body: S) -> &mut Self {
+        self.body = Some(body.into());
+        self
+    }
+
+    /// Adds a button displaying `label`.
+    #[inline]
+    pub fn add_button<S: Into<ZedString>>(&mut self, label: S) -> &mut Self {
+        self.buttons.push(label.into());
+        self
+    }
+
+    /// Replaces the buttons with a common set.
+    pub fn buttons(&mut self, buttons: MessageButtons) -> &mut Self {
+        self.buttons = buttons.labels()
+            .iter()
+            .map(|&label| label.into())
+            .collect();
+        self
+    }
+
+    /// Adds a checkbox displaying `label`, such as "Don't ask again".
+    #[inline]
+    pub fn checkbox<S: Into<ZedString>>(&mut self, label: S) -> &mut Self {
+        self.checkbox = Some(label.into());
+        self
+    }
+
+    /// Makes the dialog modal for `parent`, attaching it to the window and
+    /// blocking input to it while shown.
+    #[inline]
+    pub fn parent(&mut self, parent: &Window) -> &mut Self {
+        self.parent = Some(parent.clone());
+        self
+    }
+
+    /// Shows the dialog and blocks until a button is pressed.
+    #[inline]
+    pub fn show(&self) -> MessageResponse {
+        sys::show_message(self)
+    }
+
+    /// Shows the dialog without blocking, calling `f` on the main thread once
+    /// a button is pressed.
+    ///
+    /// Without a parent window, the dialog is shown on the next iteration of
+    /// the application's run loop as an application-wide modal. This returns
+    /// right away, but the run loop then only handles the dialog until it is
+    /// dismissed, blocking input to every window. On Linux, the dialog is
+    /// always shown this way, from the loop of the calling thread.
+    #[inline]
+    pub fn show_async<F>(&self, f: F)
+    where
+        F: FnOnce(MessageResponse) + 'static,
+    {
+        sys::show_message_async(self, Box::new(f));
+    }
+}
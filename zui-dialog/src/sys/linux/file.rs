@@ -0,0 +1,30 @@
+use std::path::PathBuf;
+use zbus::blocking::Connection;
+use crate::file::FileDialog;
+use super::{gtk, portal};
+
+/// What a file dialog is for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(super) enum Mode {
+    Open { folders: bool, multiple: bool },
+    Save,
+}
+
+pub fn open(dialog: &FileDialog, folders: bool, multiple: bool) -> Option<Vec<PathBuf>> {
+    run(dialog, Mode::Open { folders, multiple })
+}
+
+pub fn save_file(dialog: &FileDialog) -> Option<PathBuf> {
+    run(dialog, Mode::Save)?.into_iter().next()
+}
+
+/// Shows a dialog through the portal, or through GTK if the portal is not
+/// running.
+fn run(dialog: &FileDialog, mode: Mode) -> Option<Vec<PathBuf>> {
+    Connection::session().map_err(drop)
+        .and_then(|connection| portal::run(&connection, dialog, mode))
+        .or_else(|()| gtk::run(dialog, mode))
+        .ok()
+        .flatten()
+        .filter(|paths| !paths.is_empty())
+}
@@ -0,0 +1,329 @@
+//! `GtkFileChooserDialog`, for desktops without a portal, and
+//! `GtkMessageDialog`.
+//!
+//! GTK 3 is loaded at runtime so that it is not needed where portals run.
+//! It owns its own main loop, so dialogs are run from the main thread and
+//! are not attached to their parent window.
+
+use std::{
+    convert::TryFrom,
+    ffi::{CStr, CString, OsString},
+    os::{
+        raw::{c_char, c_int, c_void},
+        unix::ffi::{OsStrExt, OsStringExt},
+    },
+    path::PathBuf,
+    ptr,
+    sync::OnceLock,
+};
+use libloading::Library;
+use shared::os::linux::ZedStringExt;
+use crate::{
+    file::FileDialog,
+    message::{MessageDialog, MessageLevel, MessageResponse},
+};
+use super::file::Mode;
+
+const ACTION_OPEN: c_int = 0;
+const ACTION_SAVE: c_int = 1;
+const ACTION_SELECT_FOLDER: c_int = 2;
+
+const RESPONSE_ACCEPT: c_int = -3;
+const RESPONSE_CANCEL: c_int = -6;
+
+const MESSAGE_INFO: c_int = 0;
+const MESSAGE_WARNING: c_int = 1;
+const MESSAGE_ERROR: c_int = 3;
+
+const BUTTONS_NONE: c_int = 0;
+
+type GtkWidget = c_void;
+
+#[repr(C)]
+struct GSList {
+    data: *mut c_void,
+    next: *mut GSList,
+}
+
+/// The GTK functions that dialogs use.
+struct Gtk {
+    _library: Library,
+    init_check: unsafe extern "C" fn(*mut c_int, *mut *mut *mut c_char) -> c_int,
+    dialog_new: unsafe extern "C" fn(*const c_char, *mut GtkWidget, c_int, *const c_char, ...) -> *mut GtkWidget,
+    dialog_run: unsafe extern "C" fn(*mut GtkWidget) -> c_int,
+    widget_destroy: unsafe extern "C" fn(*mut GtkWidget),
+    events_pending: unsafe extern "C" fn() -> c_int,
+    main_iteration: unsafe extern "C" fn() -> c_int,
+    set_select_multiple: unsafe extern "C" fn(*mut GtkWidget, c_int),
+    set_do_overwrite_confirmation: unsafe extern "C" fn(*mut GtkWidget, c_int),
+    set_current_folder: unsafe extern "C" fn(*mut GtkWidget, *const c_char) -> c_int,
+    set_current_name: unsafe extern "C" fn(*mut GtkWidget, *const c_char),
+    add_filter: unsafe extern "C" fn(*mut GtkWidget, *mut c_void),
+    get_filenames: unsafe extern "C" fn(*mut GtkWidget) -> *mut GSList,
+    filter_new: unsafe extern "C" fn() -> *mut c_void,
+    filter_set_name: unsafe extern "C" fn(*mut c_void, *const c_char),
+    filter_add_pattern: unsafe extern "C" fn(*mut c_void, *const c_char),
+    message_new: unsafe extern "C" fn(*mut GtkWidget, c_int, c_int, c_int, *const c_char, ...) -> *mut GtkWidget,
+    format_secondary_text: unsafe extern "C" fn(*mut GtkWidget, *const c_char, ...),
+    message_area: unsafe extern "C" fn(*mut GtkWidget) -> *mut GtkWidget,
+    add_button: unsafe extern "C" fn(*mut GtkWidget, *const c_char, c_int) -> *mut GtkWidget,
+    set_default_response: unsafe extern "C" fn(*mut GtkWidget, c_int),
+    set_title: unsafe extern "C" fn(*mut GtkWidget, *const c_char),
+    container_add: unsafe extern "C" fn(*mut GtkWidget, *mut GtkWidget),
+    widget_show: unsafe extern "C" fn(*mut GtkWidget),
+    check_button_new: unsafe extern "C" fn(*const c_char) -> *mut GtkWidget,
+    toggle_get_active: unsafe extern "C" fn(*mut GtkWidget) -> c_int,
+    slist_free: unsafe extern "C" fn(*mut GSList),
+    free: unsafe extern "C" fn(*mut c_void),
+}
+
+// SAFETY: the functions are only called from the main thread.
+unsafe impl Send for Gtk {}
+unsafe impl Sync for Gtk {}
+
+impl Gtk {
+    /// Returns GTK, loading and initializing it on first use.
+    fn get() -> Option<&'static Gtk> {
+        static GTK: OnceLock<Option<Gtk>> = OnceLock::new();
+        GTK.get_or_init(|| unsafe {
+            let gtk = Gtk::load()?;
+            if (gtk.init_check)(ptr::null_mut(), ptr::null_mut()) == 0 {
+                return None;
+            }
+            Some(gtk)
+        }).as_ref()
+    }
+
+    unsafe fn load() -> Option<Gtk> {
+        let library = Library::new("libgtk-3.so.0").ok()?;
+        macro_rules! load {
+            ($name:literal) => {
+                *library.get(concat!($name, "\0").as_bytes()).ok()?
+            };
+        }
+        Some(Gtk {
+            init_check: load!("gtk_init_check"),
+            dialog_new: load!("gtk_file_chooser_dialog_new"),
+            dialog_run: load!("gtk_dialog_run"),
+            widget_destroy: load!("gtk_widget_destroy"),
+            events_pending: load!("gtk_events_pending"),
+            main_iteration: load!("gtk_main_iteration"),
+            set_select_multiple: load!("gtk_file_chooser_set_select_multiple"),
+            set_do_overwrite_confirmation: load!("gtk_file_chooser_set_do_overwrite_confirmation"),
+            set_current_folder: load!("gtk_file_chooser_set_current_folder"),
+            set_current_name: load!("gtk_file_chooser_set_current_name"),
+            add_filter: load!("gtk_file_chooser_add_filter"),
+            get_filenames: load!("gtk_file_chooser_get_filenames"),
+            filter_new: load!("gtk_file_filter_new"),
+            filter_set_name: load!("gtk_file_filter_set_name"),
+            filter_add_pattern: load!("gtk_file_filter_add_pattern"),
+            message_new: load!("gtk_message_dialog_new"),
+            format_secondary_text: load!("gtk_message_dialog_format_secondary_text"),
+            message_area: load!("gtk_message_dialog_get_message_area"),
+            add_button: load!("gtk_dialog_add_button"),
+            set_default_response: load!("gtk_dialog_set_default_response"),
+            set_title: load!("gtk_window_set_title"),
+            container_add: load!("gtk_container_add"),
+            widget_show: load!("gtk_widget_show"),
+            check_button_new: load!("gtk_check_button_new_with_label"),
+            toggle_get_active: load!("gtk_toggle_button_get_active"),
+            slist_free: load!("g_slist_free"),
+            free: load!("g_free"),
+            _library: library,
+        })
+    }
+
+    /// Destroys `widget`, letting it disappear before returning.
+    unsafe fn destroy(&self, widget: *mut GtkWidget) {
+        (self.widget_destroy)(widget);
+        while (self.events_pending)() != 0 {
+            (self.main_iteration)();
+        }
+    }
+}
+
+/// Returns GTK if it is available and this is the main thread.
+fn gtk() -> Result<&'static Gtk, ()> {
+    if !shared::os::linux::is_main_thread() {
+        return Err(());
+    }
+    Gtk::get().ok_or(())
+}
+
+/// Shows a dialog through GTK, returning an error if GTK is not available
+/// or this is not the main thread.
+pub(super) fn run(dialog: &FileDialog, mode: Mode) -> Result<Option<Vec<PathBuf>>, ()> {
+    let gtk = gtk()?;
+    let (action, accept, title) = match mode {
+        Mode::Open { folders: false, multiple: false } => (ACTION_OPEN, "_Open", "Open File"),
+        Mode::Open { folders: false, multiple: true } => (ACTION_OPEN, "_Open", "Open Files"),
+        Mode::Open { folders: true, .. } => (ACTION_SELECT_FOLDER, "_Select", "Select Folder"),
+        Mode::Save => (ACTION_SAVE, "_Save", "Save File"),
+    };
+    let title = c_string(dialog.title.as_ref().map_or(title, |title| title.as_str()));
+    let accept = c_string(accept);
+
+    unsafe {
+        let chooser = (gtk.dialog_new)(
+            title.as_ptr(),
+            ptr::null_mut(),
+            action,
+            b"_Cancel\0".as_ptr().cast::<c_char>(),
+            RESPONSE_CANCEL,
+            accept.as_ptr(),
+            RESPONSE_ACCEPT,
+            ptr::null::<c_char>(),
+        );
+        if chooser.is_null() {
+            return Err(());
+        }
+
+        match mode {
+            Mode::Open { multiple, .. } => (gtk.set_select_multiple)(chooser, multiple as c_int),
+            Mode::Save => {
+                (gtk.set_do_overwrite_confirmation)(chooser, 1);
+                if let Some(file_name) = &dialog.file_name {
+                    (gtk.set_current_name)(chooser, c_string(file_name).as_ptr());
+                }
+            },
+        }
+        if let Some(directory) = &dialog.directory {
+            let directory = CString::new(directory.as_os_str().as_bytes()).unwrap_or_default();
+            (gtk.set_current_folder)(chooser, directory.as_ptr());
+        }
+        if !matches!(mode, Mode::Open { folders: true, .. }) {
+            for filter in &dialog.filters {
+                let gtk_filter = (gtk.filter_new)();
+                (gtk.filter_set_name)(gtk_filter, c_string(&filter.name).as_ptr());
+                for ext in &filter.extensions {
+                    (gtk.filter_add_pattern)(gtk_filter, c_string(&format!("*.{}", ext)).as_ptr());
+                }
+                (gtk.add_filter)(chooser, gtk_filter);
+            }
+        }
+
+        let paths = if (gtk.dialog_run)(chooser) == RESPONSE_ACCEPT {
+            Some(take_filenames(gtk, (gtk.get_filenames)(chooser)))
+        } else {
+            None
+        };
+        gtk.destroy(chooser);
+        Ok(paths)
+    }
+}
+
+/// Shows a message through GTK, returning an error if GTK is not available
+/// or this is not the main thread.
+pub(super) fn run_message(dialog: &MessageDialog) -> Result<MessageResponse, ()> {
+    let gtk = gtk()?;
+    let kind = match dialog.level {
+        MessageLevel::Info => MESSAGE_INFO,
+        MessageLevel::Warning => MESSAGE_WARNING,
+        MessageLevel::Error => MESSAGE_ERROR,
+    };
+    // The title is the emphasized primary text, like the message text of
+    // an `NSAlert`, so the body is only secondary text if there is a title.
+    let (primary, secondary) = match (&dialog.title, &dialog.body) {
+        (Some(title), body) => (title.as_str(), body.as_ref().map(|body| body.as_str())),
+        (None, Some(body)) => (body.as_str(), None),
+        (None, None) => ("", None),
+    };
+    let primary = c_string(primary);
+    let labels = button_labels(dialog);
+
+    unsafe {
+        let percent_s = b"%s\0".as_ptr().cast::<c_char>();
+        let message = (gtk.message_new)(ptr::null_mut(), 0, kind, BUTTONS_NONE, percent_s, primary.as_ptr());
+        if message.is_null() {
+            return Err(());
+        }
+        (gtk.set_title)(message, b"\0".as_ptr().cast());
+        if let Some(secondary) = secondary {
+            (gtk.format_secondary_text)(message, percent_s, c_string(secondary).as_ptr());
+        }
+        for (response, label) in labels.iter().enumerate() {
+            (gtk.add_button)(message, c_string(label).as_ptr(), response as c_int);
+        }
+        (gtk.set_default_response)(message, 0);
+
+        let checkbox = match &dialog.checkbox {
+            Some(label) => {
+                let checkbox = (gtk.check_button_new)(c_string(label.as_str()).as_ptr());
+                (gtk.container_add)((gtk.message_area)(message), checkbox);
+                (gtk.widget_show)(checkbox);
+                checkbox
+            },
+            None => ptr::null_mut(),
+        };
+
+        let response = (gtk.dialog_run)(message);
+        let checked = !checkbox.is_null() && (gtk.toggle_get_active)(checkbox) != 0;
+        gtk.destroy(message);
+        Ok(MessageResponse {
+            button: button_index(response, labels.len()),
+            checked,
+        })
+    }
+}
+
+/// Returns the labels of the buttons of `dialog`, which has a single "OK"
+/// button if none were added.
+fn button_labels(dialog: &MessageDialog) -> Vec<&str> {
+    if dialog.buttons.is_empty() {
+        vec!["OK"]
+    } else {
+        dialog.buttons.iter().map(|label| label.as_str()).collect()
+    }
+}
+
+/// Returns the index of the button that `response` identifies, which is
+/// `None` for negative codes such as that of closing the dialog.
+fn button_index(response: c_int, count: usize) -> Option<usize> {
+    usize::try_from(response).ok().filter(|&index| index < count)
+}
+
+/// Returns the paths of a list of filenames, freeing it.
+unsafe fn take_filenames(gtk: &Gtk, list: *mut GSList) -> Vec<PathBuf> {
+    let mut paths = Vec::new();
+    let mut node = list;
+    while !node.is_null() {
+        let name = (*node).data;
+        let bytes = CStr::from_ptr(name.cast()).to_bytes().to_vec();
+        paths.push(PathBuf::from(OsString::from_vec(bytes)));
+        (gtk.free)(name);
+        node = (*node).next;
+    }
+    (gtk.slist_free)(list);
+    paths
+}
+
+/// Returns `s` up to any null byte within it.
+fn c_string(s: &str) -> CString {
+    let s = s.split('\0').next().unwrap_or_default();
+    CString::new(s).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::MessageButtons;
+
+    #[test]
+    fn message_buttons() {
+        let mut dialog = MessageDialog::new();
+        assert_eq!(button_labels(&dialog), ["OK"]);
+        dialog.buttons(MessageButtons::YesNoCancel);
+        assert_eq!(button_labels(&dialog), ["Yes", "No", "Cancel"]);
+
+        assert_eq!(button_index(2, 3), Some(2));
+        assert_eq!(button_index(3, 3), None);
+        // Closing the dialog gives `GTK_RESPONSE_DELETE_EVENT`.
+        assert_eq!(button_index(-4, 3), None);
+    }
+
+    #[test]
+    fn c_strings() {
+        assert_eq!(c_string("a\0b").as_bytes(), b"a");
+        assert_eq!(c_string("Save As").as_bytes(), b"Save As");
+    }
+}
@@ -0,0 +1,16 @@
+use std::time::Duration;
+use shared::os::linux::main_loop;
+use crate::message::{MessageDialog, MessageResponse};
+use super::gtk;
+
+/// The response of a dialog that could not be shown.
+const NO_RESPONSE: MessageResponse = MessageResponse { button: None, checked: false };
+
+pub fn show_message(dialog: &MessageDialog) -> MessageResponse {
+    gtk::run_message(dialog).unwrap_or(NO_RESPONSE)
+}
+
+pub fn show_message_async(dialog: &MessageDialog, f: Box<dyn FnOnce(MessageResponse)>) {
+    let dialog = dialog.clone();
+    main_loop::add_timeout(Duration::from_secs(0), move || f(show_message(&dialog)));
+}
@@ -0,0 +1,34 @@
+//! File dialogs shown through the XDG desktop portal, which desktop
+//! environments and sandboxes provide, or through GTK 3 where no portal is
+//! running. The portal has no message dialogs, so those always use GTK.
+
+use std::path::PathBuf;
+use window::os::linux::WindowExt;
+use crate::file::FileDialog;
+
+mod file;
+mod gtk;
+mod message;
+mod portal;
+
+pub use file::*;
+pub use message::*;
+
+/// Returns the identifier of the parent window of `dialog` as portals expect
+/// it, which is empty if there is none.
+///
+/// Wayland surfaces need to be exported through `xdg-foreign` to be named,
+/// so dialogs are not attached to Wayland windows.
+fn parent_handle(dialog: &FileDialog) -> String {
+    match dialog.parent.as_ref().and_then(WindowExt::xid) {
+        Some(xid) => format!("x11:{:x}", xid),
+        None => String::new(),
+    }
+}
+
+/// Returns the paths of `uris`, skipping those that are not local files.
+fn paths_from_uris<S: AsRef<str>>(uris: &[S]) -> Vec<PathBuf> {
+    uris.iter()
+        .filter_map(|uri| shared::os::linux::path_from_file_uri(uri.as_ref().as_bytes()))
+        .collect()
+}
@@ -0,0 +1,258 @@
+//! The `org.freedesktop.portal.FileChooser` interface.
+//!
+//! Each call returns a request object, whose `Response` signal carries the
+//! result once the user dismisses the dialog. Its path is derived from a
+//! token that we choose, so that the signal can be subscribed to before the
+//! call and not be missed.
+
+use std::{
+    collections::HashMap,
+    convert::TryFrom,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicU32, Ordering},
+};
+use shared::os::linux::ZedStringExt;
+use zbus::{
+    blocking::{Connection, MessageIterator},
+    message,
+    zvariant::{OwnedObjectPath, OwnedValue, Value},
+    MatchRule,
+};
+use crate::file::{FileDialog, FileFilter};
+use super::{file::Mode, parent_handle, paths_from_uris};
+
+pub(super) const PORTAL_NAME: &str = "org.freedesktop.portal.Desktop";
+pub(super) const PORTAL_PATH: &str = "/org/freedesktop/portal/desktop";
+pub(super) const FILE_CHOOSER: &str = "org.freedesktop.portal.FileChooser";
+pub(super) const REQUEST: &str = "org.freedesktop.portal.Request";
+
+/// The `Response` code of a request that the user completed.
+const RESPONSE_SUCCESS: u32 = 0;
+
+/// Shows a dialog through the portal on `connection`, returning an error if
+/// the portal is not running.
+pub(super) fn run(connection: &Connection, dialog: &FileDialog, mode: Mode) -> Result<Option<Vec<PathBuf>>, ()> {
+    static NEXT_TOKEN: AtomicU32 = AtomicU32::new(1);
+    let token = format!("zui{}", NEXT_TOKEN.fetch_add(1, Ordering::Relaxed));
+    let sender = connection.unique_name().ok_or(())?.trim_start_matches(':').replace('.', "_");
+    let path = format!("{}/request/{}/{}", PORTAL_PATH, sender, token);
+    let mut responses = subscribe(connection, &path)?;
+
+    let mut options = options(dialog, mode);
+    options.insert("handle_token", Value::from(token));
+    let (method, title) = match mode {
+        Mode::Open { folders: false, multiple: false } => ("OpenFile", "Open File"),
+        Mode::Open { folders: false, multiple: true } => ("OpenFile", "Open Files"),
+        Mode::Open { folders: true, .. } => ("OpenFile", "Select Folder"),
+        Mode::Save => ("SaveFile", "Save File"),
+    };
+    let title = dialog.title.as_ref().map_or(title, |title| title.as_str());
+    let reply = connection.call_method(
+        Some(PORTAL_NAME),
+        PORTAL_PATH,
+        Some(FILE_CHOOSER),
+        method,
+        &(parent_handle(dialog), title, options),
+    ).map_err(drop)?;
+
+    // Portals older than version 0.9 ignore the token.
+    let handle: OwnedObjectPath = reply.body().deserialize().map_err(drop)?;
+    if handle.as_str() != path {
+        responses = subscribe(connection, handle.as_str())?;
+    }
+
+    let message = responses.next().ok_or(())?.map_err(drop)?;
+    let (response, results): (u32, HashMap<String, OwnedValue>) = message.body().deserialize().map_err(drop)?;
+    if response != RESPONSE_SUCCESS {
+        return Ok(None);
+    }
+    let uris = results.get("uris")
+        .and_then(|uris| Vec::<String>::try_from(uris.try_clone().ok()?).ok())
+        .unwrap_or_default();
+    Ok(Some(paths_from_uris(&uris)))
+}
+
+fn subscribe(connection: &Connection, path: &str) -> Result<MessageIterator, ()> {
+    let rule = MatchRule::builder()
+        .msg_type(message::Type::Signal)
+        .interface(REQUEST).map_err(drop)?
+        .member("Response").map_err(drop)?
+        .path(path.to_owned()).map_err(drop)?
+        .build();
+    MessageIterator::for_match_rule(rule, connection, None).map_err(drop)
+}
+
+/// Returns the options of the portal call for `dialog`.
+fn options(dialog: &FileDialog, mode: Mode) -> HashMap<&'static str, Value<'static>> {
+    let mut options = HashMap::new();
+    options.insert("modal", Value::from(dialog.parent.is_some()));
+    match mode {
+        Mode::Open { folders, multiple } => {
+            options.insert("multiple", Value::from(multiple));
+            options.insert("directory", Value::from(folders));
+        },
+        Mode::Save => {
+            if let Some(file_name) = &dialog.file_name {
+                options.insert("current_name", Value::from(file_name.clone()));
+            }
+        },
+    }
+    if let Some(directory) = &dialog.directory {
+        options.insert("current_folder", Value::from(null_terminated(directory)));
+    }
+    if !dialog.filters.is_empty() && !matches!(mode, Mode::Open { folders: true, .. }) {
+        let filters: Vec<_> = dialog.filters.iter().map(filter).collect();
+        options.insert("filters", Value::from(filters));
+    }
+    options
+}
+
+/// Returns `filter` as a name and a list of glob patterns, which have the
+/// type 0.
+fn filter(filter: &FileFilter) -> (String, Vec<(u32, String)>) {
+    let patterns = filter.extensions.iter().map(|ext| (0, format!("*.{}", ext))).collect();
+    (filter.name.clone(), patterns)
+}
+
+/// Returns the bytes of `path` followed by a null byte, as portals expect
+/// paths.
+fn null_terminated(path: &Path) -> Vec<u8> {
+    use std::os::unix::ffi::OsStrExt;
+    let mut bytes = path.as_os_str().as_bytes().to_vec();
+    bytes.push(0);
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+    use shared::os::linux::test_bus::TestBus;
+    use zbus::{blocking::connection, interface, message::Header};
+
+    type Call = (String, String, String, HashMap<String, OwnedValue>);
+
+    /// A portal that answers every request with `response` and `uris`,
+    /// recording the calls made to it.
+    struct Portal {
+        response: u32,
+        uris: Vec<String>,
+        calls: Arc<Mutex<Vec<Call>>>,
+    }
+
+    impl Portal {
+        async fn respond(
+            &self,
+            header: Header<'_>,
+            connection: &zbus::Connection,
+            method: &str,
+            parent_window: String,
+            title: String,
+            options: HashMap<String, OwnedValue>,
+        ) -> OwnedObjectPath {
+            let sender = header.sender().unwrap().to_string();
+            let token = String::try_from(options["handle_token"].try_clone().unwrap()).unwrap();
+            let path = format!("{}/request/{}/{}", PORTAL_PATH, sender.trim_start_matches(':').replace('.', "_"), token);
+            self.calls.lock().unwrap().push((method.to_owned(), parent_window, title, options));
+
+            let mut results = HashMap::new();
+            results.insert("uris", Value::from(self.uris.clone()));
+            connection.emit_signal(Some(sender), path.as_str(), REQUEST, "Response", &(self.response, results))
+                .await
+                .unwrap();
+            OwnedObjectPath::try_from(path).unwrap()
+        }
+    }
+
+    #[interface(name = "org.freedesktop.portal.FileChooser")]
+    impl Portal {
+        async fn open_file(
+            &self,
+            #[zbus(header)] header: Header<'_>,
+            #[zbus(connection)] connection: &zbus::Connection,
+            parent_window: String,
+            title: String,
+            options: HashMap<String, OwnedValue>,
+        ) -> OwnedObjectPath {
+            self.respond(header, connection, "OpenFile", parent_window, title, options).await
+        }
+
+        async fn save_file(
+            &self,
+            #[zbus(header)] header: Header<'_>,
+            #[zbus(connection)] connection: &zbus::Connection,
+            parent_window: String,
+            title: String,
+            options: HashMap<String, OwnedValue>,
+        ) -> OwnedObjectPath {
+            self.respond(header, connection, "SaveFile", parent_window, title, options).await
+        }
+    }
+
+    fn connect(bus: &TestBus) -> Connection {
+        connection::Builder::address(bus.address()).unwrap().build().unwrap()
+    }
+
+    fn serve(bus: &TestBus, response: u32, uris: &[&str]) -> (Connection, Arc<Mutex<Vec<Call>>>) {
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let portal = Portal {
+            response,
+            uris: uris.iter().map(|&uri| uri.to_owned()).collect(),
+            calls: calls.clone(),
+        };
+        let connection = connection::Builder::address(bus.address()).unwrap()
+            .name(PORTAL_NAME).unwrap()
+            .serve_at(PORTAL_PATH, portal).unwrap()
+            .build()
+            .unwrap();
+        (connection, calls)
+    }
+
+    #[test]
+    fn open_files() {
+        let bus = TestBus::start().expect("dbus-daemon is not available");
+        let client = connect(&bus);
+        let mut dialog = FileDialog::new();
+        dialog.add_filter("Images", &["png", ".jpg"]).directory("/tmp");
+
+        // Without a portal, dialogs fall back to GTK.
+        assert!(run(&client, &dialog, Mode::Save).is_err());
+
+        let (_portal, calls) = serve(&bus, 0, &["file:///tmp/a%20b.png", "https://example.com/c.png"]);
+        let mode = Mode::Open { folders: false, multiple: true };
+        assert_eq!(run(&client, &dialog, mode), Ok(Some(vec![PathBuf::from("/tmp/a b.png")])));
+
+        let calls = calls.lock().unwrap();
+        let (method, parent, title, options) = &calls[0];
+        assert_eq!((method.as_str(), parent.as_str(), title.as_str()), ("OpenFile", "", "Open Files"));
+        assert!(bool::try_from(&options["multiple"]).unwrap());
+        assert!(!bool::try_from(&options["directory"]).unwrap());
+        let folder = Vec::<u8>::try_from(options["current_folder"].try_clone().unwrap()).unwrap();
+        assert_eq!(folder, b"/tmp\0");
+        let filters = Vec::<(String, Vec<(u32, String)>)>::try_from(options["filters"].try_clone().unwrap()).unwrap();
+        assert_eq!(filters, [("Images".to_owned(), vec![(0, "*.png".to_owned()), (0, "*.jpg".to_owned())])]);
+    }
+
+    #[test]
+    fn save_and_cancel() {
+        let bus = TestBus::start().expect("dbus-daemon is not available");
+        let client = connect(&bus);
+        let mut dialog = FileDialog::new();
+        dialog.title("Export").file_name("out.txt");
+
+        let (portal, calls) = serve(&bus, 0, &["file:///home/out.txt"]);
+        assert_eq!(run(&client, &dialog, Mode::Save), Ok(Some(vec![PathBuf::from("/home/out.txt")])));
+        {
+            let calls = calls.lock().unwrap();
+            let (method, _, title, options) = &calls[0];
+            assert_eq!((method.as_str(), title.as_str()), ("SaveFile", "Export"));
+            assert_eq!(String::try_from(options["current_name"].try_clone().unwrap()).unwrap(), "out.txt");
+            assert!(!options.contains_key("filters"));
+        }
+        drop(portal);
+
+        // Cancelling does not fall back to GTK.
+        let (_portal, _) = serve(&bus, 1, &[]);
+        assert_eq!(run(&client, &dialog, Mode::Open { folders: true, multiple: false }), Ok(None));
+    }
+}
@@ -0,0 +1,99 @@
+use std::path::PathBuf;
+use block::ConcreteBlock;
+use cocoa::{
+    base::{id, nil, NO, YES},
+    foundation::{NSArray, NSInteger, NSUInteger},
+};
+use objc::rc::StrongPtr;
+use shared::os::macos::ZedStringExt;
+use window::os::macos::WindowExt;
+use crate::file::FileDialog;
+use super::{ns_string, ns_url_from_path, path_from_ns_url};
+
+const NS_MODAL_RESPONSE_OK: NSInteger = 1;
+
+/// Applies the options shared by open and save panels.
+unsafe fn configure(panel: id, dialog: &FileDialog) {
+    if let Some(title) = &dialog.title {
+        msg_send![panel, setTitle:**title.as_ns_string()];
+        msg_send![panel, setMessage:**title.as_ns_string()];
+    }
+
+    if let Some(directory) = &dialog.directory {
+        msg_send![panel, setDirectoryURL:ns_url_from_path(directory)];
+    }
+
+    // Panels have no notion of separate filters, so their extensions are
+    // combined and their names go unused.
+    let extensions: Vec<StrongPtr> = dialog.filters.iter()
+        .flat_map(|filter| filter.extensions.iter())
+        .map(|ext| ns_string(ext))
+        .collect();
+    if !extensions.is_empty() {
+        let extensions: Vec<id> = extensions.iter().map(|ext| **ext).collect();
+        let extensions = NSArray::arrayWithObjects(nil, &extensions);
+        msg_send![panel, setAllowedFileTypes:extensions];
+    }
+}
+
+/// Runs `panel` until dismissed, attached as a sheet to the parent if any.
+unsafe fn run(panel: id, dialog: &FileDialog) -> bool {
+    if let Some(parent) = &dialog.parent {
+        // `runModal` returns the response once the sheet ends, so the
+        // completion handler has nothing to do.
+        let handler = ConcreteBlock::new(|_response: NSInteger| {}).copy();
+        let handler: *const _ = &*handler;
+        msg_send![panel, beginSheetModalForWindow:parent.ns_window()
+                                completionHandler:handler];
+    }
+    let response: NSInteger = msg_send![panel, runModal];
+    response == NS_MODAL_RESPONSE_OK
+}
+
+pub fn open(dialog: &FileDialog, folders: bool, multiple: bool) -> Option<Vec<PathBuf>> {
+    unsafe {
+        let panel: id = msg_send![class!(NSOpenPanel), openPanel];
+        configure(panel, dialog);
+        msg_send![panel, setCanChooseFiles:if folders { NO } else { YES }];
+        msg_send![panel, setCanChooseDirectories:if folders { YES } else { NO }];
+        msg_send![panel, setCanCreateDirectories:if folders { YES } else { NO }];
+        msg_send![panel, setAllowsMultipleSelection:if multiple { YES } else { NO }];
+
+        if !run(panel, dialog) {
+            return None;
+        }
+
+        let urls: id = msg_send![panel, URLs];
+        let count: NSUInteger = msg_send![urls, count];
+        Some(
+            (0..count)
+                .map(|i| {
+                    let url: id = msg_send![urls, objectAtIndex:i];
+                    path_from_ns_url(url)
+                })
+                .collect(),
+        )
+    }
+}
+
+pub fn save_file(dialog: &FileDialog) -> Option<PathBuf> {
+    unsafe {
+        let panel: id = msg_send![class!(NSSavePanel), savePanel];
+        configure(panel, dialog);
+        msg_send![panel, setCanCreateDirectories:YES];
+        if let Some(file_name) = &dialog.file_name {
+            msg_send![panel, setNameFieldStringValue:*ns_string(file_name)];
+        }
+
+        if !run(panel, dialog) {
+            return None;
+        }
+
+        let url: id = msg_send![panel, URL];
+        if url == nil {
+            None
+        } else {
+            Some(path_from_ns_url(url))
+        }
+    }
+}
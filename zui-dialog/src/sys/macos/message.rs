@@ -0,0 +1,133 @@
+use std::cell::RefCell;
+use block::ConcreteBlock;
+use cocoa::{
+    base::{id, YES},
+    foundation::{NSInteger, NSUInteger},
+};
+use objc::rc::StrongPtr;
+use shared::os::macos::ZedStringExt;
+use window::os::macos::WindowExt;
+use crate::message::{MessageDialog, MessageLevel, MessageResponse};
+use super::ns_string;
+
+const NS_ALERT_STYLE_WARNING: NSUInteger = 0;
+const NS_ALERT_STYLE_INFORMATIONAL: NSUInteger = 1;
+const NS_ALERT_STYLE_CRITICAL: NSUInteger = 2;
+
+const NS_ALERT_FIRST_BUTTON_RETURN: NSInteger = 1000;
+
+const NS_CONTROL_STATE_VALUE_ON: NSInteger = 1;
+
+unsafe fn ns_alert(dialog: &MessageDialog) -> StrongPtr {
+    let alert: id = msg_send![class!(NSAlert), alloc];
+    let alert = StrongPtr::new(msg_send![alert, init]);
+
+    let style = match dialog.level {
+        MessageLevel::Info => NS_ALERT_STYLE_INFORMATIONAL,
+        MessageLevel::Warning => NS_ALERT_STYLE_WARNING,
+        MessageLevel::Error => NS_ALERT_STYLE_CRITICAL,
+    };
+    msg_send![*alert, setAlertStyle:style];
+
+    if let Some(title) = &dialog.title {
+        msg_send![*alert, setMessageText:**title.as_ns_string()];
+    }
+    if let Some(body) = &dialog.body {
+        msg_send![*alert, setInformativeText:**body.as_ns_string()];
+    }
+
+    if dialog.buttons.is_empty() {
+        let _: id = msg_send![*alert, addButtonWithTitle:*ns_string("OK")];
+    }
+    for label in &dialog.buttons {
+        let _: id = msg_send![*alert, addButtonWithTitle:**label.as_ns_string()];
+    }
+
+    if let Some(label) = &dialog.checkbox {
+        msg_send![*alert, setShowsSuppressionButton:YES];
+        let checkbox: id = msg_send![*alert, suppressionButton];
+        msg_send![checkbox, setTitle:**label.as_ns_string()];
+    }
+
+    alert
+}
+
+unsafe fn response(alert: id, response: NSInteger) -> MessageResponse {
+    let checkbox: id = msg_send![alert, suppressionButton];
+    let state: NSInteger = msg_send![checkbox, state];
+    let buttons: id = msg_send![alert, buttons];
+    let count: NSUInteger = msg_send![buttons, count];
+
+    // Codes outside the button range, such as those of an aborted modal
+    // session, don't correspond to a button.
+    let index = response - NS_ALERT_FIRST_BUTTON_RETURN;
+    MessageResponse {
+        button: if index >= 0 && (index as NSUInteger) < count {
+            Some(index as usize)
+        } else {
+            None
+        },
+        checked: state == NS_CONTROL_STATE_VALUE_ON,
+    }
+}
+
+pub fn show_message(dialog: &MessageDialog) -> MessageResponse {
+    unsafe {
+        let alert = ns_alert(dialog);
+
+        let code: NSInteger = match &dialog.parent {
+            Some(parent) => {
+                // Runs the sheet modally, stopping once it ends with the
+                // pressed button as the return code.
+                let handler = ConcreteBlock::new(|response: NSInteger| {
+                    let ns_app: id = msg_send![class!(NSApplication), sharedApplication];
+                    msg_send![ns_app, stopModalWithCode:response];
+                }).copy();
+                let handler: *const _ = &*handler;
+                msg_send![*alert, beginSheetModalForWindow:parent.ns_window()
+                                         completionHandler:handler];
+
+                let ns_app: id = msg_send![class!(NSApplication), sharedApplication];
+                let alert_window: id = msg_send![*alert, window];
+                msg_send![ns_app, runModalForWindow:alert_window]
+            },
+            None => msg_send![*alert, runModal],
+        };
+
+        response(*alert, code)
+    }
+}
+
+pub fn show_message_async(dialog: &MessageDialog, f: Box<dyn FnOnce(MessageResponse)>) {
+    unsafe {
+        let alert = ns_alert(dialog);
+
+        // Blocks must be `Fn`, so `f` is taken out on the only call.
+        let f = RefCell::new(Some(f));
+
+        match &dialog.parent {
+            Some(parent) => {
+                let ns_alert = alert.clone();
+                let handler = ConcreteBlock::new(move |code: NSInteger| {
+                    if let Some(f) = f.borrow_mut().take() {
+                        f(response(*ns_alert, code));
+                    }
+                }).copy();
+                let handler: *const _ = &*handler;
+                msg_send![*alert, beginSheetModalForWindow:parent.ns_window()
+                                         completionHandler:handler];
+            },
+            None => {
+                let operation = ConcreteBlock::new(move || {
+                    if let Some(f) = f.borrow_mut().take() {
+                        let code: NSInteger = msg_send![*alert, runModal];
+                        f(response(*alert, code));
+                    }
+                }).copy();
+                let operation: *const _ = &*operation;
+                let queue: id = msg_send![class!(NSOperationQueue), mainQueue];
+                msg_send![queue, addOperationWithBlock:operation];
+            },
+        }
+    }
+}
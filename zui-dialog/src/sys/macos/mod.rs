@@ -0,0 +1,31 @@
+use std::{
+    ffi::{CStr, OsStr},
+    os::unix::ffi::OsStrExt,
+    path::{Path, PathBuf},
+};
+use cocoa::{
+    base::{id, nil},
+    foundation::NSString,
+};
+use objc::rc::StrongPtr;
+
+mod file;
+mod message;
+
+pub use file::*;
+pub use message::*;
+
+unsafe fn ns_string(s: &str) -> StrongPtr {
+    StrongPtr::new(NSString::alloc(nil).init_str(s))
+}
+
+unsafe fn ns_url_from_path(path: &Path) -> id {
+    let path = ns_string(&path.to_string_lossy());
+    msg_send![class!(NSURL), fileURLWithPath:*path]
+}
+
+unsafe fn path_from_ns_url(url: id) -> PathBuf {
+    let path: id = msg_send![url, path];
+    let path = CStr::from_ptr(path.UTF8String());
+    PathBuf::from(OsStr::from_bytes(path.to_bytes()))
+}
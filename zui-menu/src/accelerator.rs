@@ -0,0 +1,364 @@
+//! Keyboard shortcuts that activate menu items.
+
+use std::{
+    error::Error,
+    fmt,
+    ops,
+    str::FromStr,
+};
+
+/// A set of modifier keys held down as part of an
+/// [`Accelerator`](struct.Accelerator.html).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Modifiers(u8);
+
+impl ops::BitOr for Modifiers {
+    type Output = Self;
+
+    #[inline]
+    fn bitor(self, other: Self) -> Self {
+        Modifiers(self.0 | other.0)
+    }
+}
+
+impl ops::BitOrAssign for Modifiers {
+    #[inline]
+    fn bitor_assign(&mut self, other: Self) {
+        self.0 |= other.0;
+    }
+}
+
+impl Modifiers {
+    /// No modifiers.
+    pub const NONE: Self = Modifiers(0);
+
+    /// The Shift key.
+    pub const SHIFT: Self = Modifiers(1);
+
+    /// The Control key.
+    pub const CTRL: Self = Modifiers(1 << 1);
+
+    /// The Alt key, which is labeled Option on macOS.
+    pub const ALT: Self = Modifiers(1 << 2);
+
+    /// The Super key, which is the Command key on macOS and the Windows key
+    /// elsewhere.
+    pub const SUPER: Self = Modifiers(1 << 3);
+
+    /// The modifier conventionally used for shortcuts on the current platform:
+    /// Command on macOS and Control elsewhere.
+    #[cfg(target_os = "macos")]
+    pub const PRIMARY: Self = Modifiers::SUPER;
+
+    /// The modifier conventionally used for shortcuts on the current platform:
+    /// Command on macOS and Control elsewhere.
+    #[cfg(not(target_os = "macos"))]
+    pub const PRIMARY: Self = Modifiers::CTRL;
+
+    /// Returns whether every modifier in `other` is in `self`.
+    #[inline]
+    pub fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Returns whether `self` has no modifiers.
+    #[inline]
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+}
+
+/// A key pressed as part of an
+/// [`Accelerator`](struct.Accelerator.html).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Key {
+    /// A key that produces a character, such as `S` or `/`.
+    ///
+    /// Letters are stored in uppercase, unless that takes more than one
+    /// character.
+    Char(char),
+    /// A function key from `F1` to `F24`.
+    F(u8),
+    /// The Enter or Return key.
+    Enter,
+    /// The Tab key.
+    Tab,
+    /// The Space bar.
+    Space,
+    /// The Backspace key, which deletes backwards.
+    Backspace,
+    /// The Delete key, which deletes forwards.
+    Delete,
+    /// The Escape key.
+    Escape,
+    /// The Insert key.
+    Insert,
+    /// The Home key.
+    Home,
+    /// The End key.
+    End,
+    /// The Page Up key.
+    PageUp,
+    /// The Page Down key.
+    PageDown,
+    /// The Up arrow key.
+    Up,
+    /// The Down arrow key.
+    Down,
+    /// The Left arrow key.
+    Left,
+    /// The Right arrow key.
+    Right,
+}
+
+const NAMED_KEYS: &[(&str, Key)] = &[
+    ("Enter", Key::Enter),
+    ("Return", Key::Enter),
+    ("Tab", Key::Tab),
+    ("Space", Key::Space),
+    ("Backspace", Key::Backspace),
+    ("Delete", Key::Delete),
+    ("Del", Key::Delete),
+    ("Escape", Key::Escape),
+    ("Esc", Key::Escape),
+    ("Insert", Key::Insert),
+    ("Home", Key::Home),
+    ("End", Key::End),
+    ("PageUp", Key::PageUp),
+    ("PageDown", Key::PageDown),
+    ("Up", Key::Up),
+    ("Down", Key::Down),
+    ("Left", Key::Left),
+    ("Right", Key::Right),
+    ("Plus", Key::Char('+')),
+];
+
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Key::Char(c) => write!(f, "{}", c),
+            Key::F(n) => write!(f, "F{}", n),
+            key => {
+                // Every other key has a name, the first of which is canonical.
+                let (name, _) = NAMED_KEYS.iter()
+                    .find(|(_, k)| *k == key)
+                    .unwrap();
+                f.write_str(name)
+            },
+        }
+    }
+}
+
+impl FromStr for Key {
+    type Err = ParseAcceleratorError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut chars = s.chars();
+        if let (Some(c), None) = (chars.next(), chars.next()) {
+            if c.is_whitespace() || c.is_control() {
+                return Err(ParseAcceleratorError::UnknownKey(s.into()));
+            }
+            // Characters without a single-character uppercase form, such as
+            // `ß`, are kept as they are.
+            let mut upper = c.to_uppercase();
+            let c = match (upper.next(), upper.next()) {
+                (Some(upper), None) => upper,
+                _ => c,
+            };
+            return Ok(Key::Char(c));
+        }
+
+        if let Some((_, key)) = NAMED_KEYS.iter().find(|(n, _)| n.eq_ignore_ascii_case(s)) {
+            return Ok(*key);
+        }
+
+        if s.starts_with('F') || s.starts_with('f') {
+            if let Ok(n) = s[1..].parse::<u8>() {
+                if (1..=24).contains(&n) {
+                    return Ok(Key::F(n));
+                }
+            }
+        }
+
+        Err(ParseAcceleratorError::UnknownKey(s.into()))
+    }
+}
+
+/// A keyboard shortcut, such as `Ctrl+Shift+S`.
+///
+/// Accelerators can be parsed from strings of `+`-separated modifiers
+/// followed by a key. Recognized modifiers are `Ctrl`/`Control`, `Shift`,
+/// `Alt`/`Option`, `Super`/`Cmd`/`Command` and `CmdOrCtrl`, which maps to
+/// [`Modifiers::PRIMARY`](struct.Modifiers.html#associatedconstant.PRIMARY).
+/// Parsing is case-insensitive.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Accelerator {
+    /// The modifiers that must be held down.
+    pub modifiers: Modifiers,
+    /// The key that must be pressed.
+    pub key: Key,
+}
+
+impl fmt::Display for Accelerator {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        const NAMES: &[(Modifiers, &str)] = &[
+            (Modifiers::CTRL, "Ctrl"),
+            (Modifiers::ALT, "Alt"),
+            (Modifiers::SHIFT, "Shift"),
+            (Modifiers::SUPER, "Super"),
+        ];
+        for (modifier, name) in NAMES {
+            if self.modifiers.contains(*modifier) {
+                write!(f, "{}+", name)?;
+            }
+        }
+        self.key.fmt(f)
+    }
+}
+
+impl FromStr for Accelerator {
+    type Err = ParseAcceleratorError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut modifiers = Modifiers::NONE;
+
+        // A trailing `+` is the key itself, as in `+` and `Ctrl++`, unless it
+        // only separates the modifiers from a missing key, as in `Ctrl+`.
+        let s = s.trim();
+        let (rest, key) = if let Some(rest) = s.strip_suffix('+') {
+            let rest = rest.trim_end();
+            if rest.is_empty() {
+                ("", "+")
+            } else if let Some(rest) = rest.strip_suffix('+') {
+                (rest, "+")
+            } else {
+                return Err(ParseAcceleratorError::MissingKey);
+            }
+        } else {
+            match s.rfind('+') {
+                Some(i) => (&s[..i], &s[i + 1..]),
+                None => ("", s),
+            }
+        };
+
+        if !rest.is_empty() {
+            for name in rest.split('+') {
+                let modifier = match name.trim().to_ascii_lowercase().as_str() {
+                    "ctrl" | "control" => Modifiers::CTRL,
+                    "shift" => Modifiers::SHIFT,
+                    "alt" | "option" => Modifiers::ALT,
+                    "super" | "cmd" | "command" | "meta" => Modifiers::SUPER,
+                    "cmdorctrl" | "commandorcontrol" => Modifiers::PRIMARY,
+                    _ => return Err(ParseAcceleratorError::UnknownModifier(name.into())),
+                };
+                modifiers |= modifier;
+            }
+        }
+
+        let key = key.trim();
+        if key.is_empty() {
+            return Err(ParseAcceleratorError::MissingKey);
+        }
+        Ok(Accelerator { modifiers, key: key.parse()? })
+    }
+}
+
+impl Accelerator {
+    /// Creates a new accelerator of `key` pressed with `modifiers`.
+    #[inline]
+    pub const fn new(modifiers: Modifiers, key: Key) -> Self {
+        Accelerator { modifiers, key }
+    }
+}
+
+/// The error returned when parsing an
+/// [`Accelerator`](struct.Accelerator.html) fails.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ParseAcceleratorError {
+    /// No key was given after the modifiers.
+    MissingKey,
+    /// A modifier was not recognized.
+    UnknownModifier(String),
+    /// The key was not recognized.
+    UnknownKey(String),
+}
+
+impl fmt::Display for ParseAcceleratorError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseAcceleratorError::MissingKey => {
+                f.write_str("accelerator has no key")
+            },
+            ParseAcceleratorError::UnknownModifier(m) => {
+                write!(f, "unknown accelerator modifier {:?}", m)
+            },
+            ParseAcceleratorError::UnknownKey(k) => {
+                write!(f, "unknown accelerator key {:?}", k)
+            },
+        }
+    }
+}
+
+impl Error for ParseAcceleratorError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(s: &str) -> Result<Accelerator, ParseAcceleratorError> {
+        s.parse()
+    }
+
+    #[test]
+    fn parse_modifiers() {
+        assert_eq!(
+            parse("Ctrl+Shift+S"),
+            Ok(Accelerator::new(Modifiers::CTRL | Modifiers::SHIFT, Key::Char('S'))),
+        );
+        assert_eq!(
+            parse("cmd + alt + f4"),
+            Ok(Accelerator::new(Modifiers::SUPER | Modifiers::ALT, Key::F(4))),
+        );
+        assert_eq!(parse("CmdOrCtrl+Q"), Ok(Accelerator::new(Modifiers::PRIMARY, Key::Char('Q'))));
+        assert_eq!(
+            parse("Hyper+Q"),
+            Err(ParseAcceleratorError::UnknownModifier("Hyper".into())),
+        );
+    }
+
+    #[test]
+    fn parse_plus() {
+        let plus = Key::Char('+');
+        assert_eq!(parse("+"), Ok(Accelerator::new(Modifiers::NONE, plus)));
+        assert_eq!(parse("Cmd++"), Ok(Accelerator::new(Modifiers::SUPER, plus)));
+        assert_eq!(parse("Ctrl+Shift++"), Ok(Accelerator::new(Modifiers::CTRL | Modifiers::SHIFT, plus)));
+        assert_eq!(parse("Ctrl + +"), Ok(Accelerator::new(Modifiers::CTRL, plus)));
+        assert_eq!(parse("Ctrl+Plus"), Ok(Accelerator::new(Modifiers::CTRL, plus)));
+    }
+
+    #[test]
+    fn parse_missing_key() {
+        assert_eq!(parse(""), Err(ParseAcceleratorError::MissingKey));
+        assert_eq!(parse("Ctrl+"), Err(ParseAcceleratorError::MissingKey));
+        assert_eq!(parse("Ctrl+Shift+ "), Err(ParseAcceleratorError::MissingKey));
+    }
+
+    #[test]
+    fn parse_keys() {
+        assert_eq!("a".parse(), Ok(Key::Char('A')));
+        assert_eq!("é".parse(), Ok(Key::Char('É')));
+        assert_eq!("ß".parse(), Ok(Key::Char('ß')));
+        assert_eq!("esc".parse(), Ok(Key::Escape));
+        assert_eq!("F24".parse(), Ok(Key::F(24)));
+        assert_eq!("F25".parse::<Key>(), Err(ParseAcceleratorError::UnknownKey("F25".into())));
+        assert_eq!(" ".parse::<Key>(), Err(ParseAcceleratorError::UnknownKey(" ".into())));
+    }
+
+    #[test]
+    fn display_round_trips() {
+        for s in &["Ctrl+Shift+S", "Alt+Super+F1", "Ctrl++", "PageUp", "Shift+Enter"] {
+            assert_eq!(parse(s).unwrap().to_string(), *s);
+            assert_eq!(parse(&parse(s).unwrap().to_string()), parse(s));
+        }
+    }
+}
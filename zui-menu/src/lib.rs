@@ -0,0 +1,353 @@
+//! ZedUI native menus.
+//!
+//! A [`Menu`](struct.Menu.html) is a plain description of items that is
+//! turned into a native menu whenever it is shown. Activating an item calls
+//! the handler set with [`set_event_handler`](fn.set_event_handler.html).
+
+#![deny(missing_docs)]
+#![doc(html_logo_url = "https://zed-ui.dev/static/logos/zed-ui.svg")]
+
+#[cfg(target_os = "macos")]
+#[macro_use]
+extern crate objc;
+
+extern crate zui_shared as shared;
+
+use std::{
+    cell::RefCell,
+    sync::atomic::{AtomicU64, Ordering},
+};
+use shared::ZedString;
+
+mod sys;
+pub mod accelerator;
+pub mod os;
+
+#[doc(inline)]
+pub use accelerator::Accelerator;
+
+type EventHandler = Box<dyn FnMut(MenuEvent)>;
+
+thread_local! {
+    static EVENT_HANDLER: RefCell<Option<EventHandler>> = RefCell::new(None);
+}
+
+/// Sets the function called on the main thread whenever a menu item is
+/// activated, replacing any previous one.
+#[inline]
+pub fn set_event_handler<F>(handler: F)
+where
+    F: FnMut(MenuEvent) + 'static,
+{
+    EVENT_HANDLER.with(|h| *h.borrow_mut() = Some(Box::new(handler)));
+}
+
+/// Passes `event` to the event handler, if any.
+pub(crate) fn emit(event: MenuEvent) {
+    // The handler is taken out for the duration of the call so that it may
+    // replace itself.
+    let handler = EVENT_HANDLER.with(|h| h.borrow_mut().take());
+    if let Some(mut handler) = handler {
+        handler(event);
+        EVENT_HANDLER.with(|h| {
+            let mut slot = h.borrow_mut();
+            if slot.is_none() {
+                *slot = Some(handler);
+            }
+        });
+    }
+}
+
+/// Sets the menu displayed by the application.
+///
+/// On macOS, this is the menu bar and the first submenu of `menu` becomes the
+/// application menu.
+///
+/// On Linux, `menu` is exported on the session bus, where global menu bars
+/// find it once a window is registered with
+/// [`os::linux::register_window`](os/linux/fn.register_window.html).
+#[inline]
+pub fn set_application_menu(menu: &Menu) {
+    sys::set_application_menu(menu);
+}
+
+/// An event emitted when a menu item is activated.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MenuEvent {
+    /// The identifier of the activated item.
+    pub id: MenuItemId,
+    /// The checked state of the item after activation, if it is checkable.
+    pub checked: Option<bool>,
+}
+
+/// A unique identifier of a [`MenuItem`](struct.MenuItem.html).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct MenuItemId(u64);
+
+impl MenuItemId {
+    fn next() -> Self {
+        static NEXT: AtomicU64 = AtomicU64::new(1);
+        MenuItemId(NEXT.fetch_add(1, Ordering::Relaxed))
+    }
+
+    /// Returns the raw value of `self`.
+    #[inline]
+    pub fn as_u64(self) -> u64 {
+        self.0
+    }
+}
+
+/// A list of entries displayed as a menu.
+///
+/// Cloning a menu clones its items, which get new identifiers.
+#[derive(Clone, Debug, Default)]
+pub struct Menu {
+    entries: Vec<MenuEntry>,
+}
+
+impl Menu {
+    /// Creates an empty menu.
+    #[inline]
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Appends `entry`.
+    #[inline]
+    pub fn add<E: Into<MenuEntry>>(&mut self, entry: E) -> &mut Self {
+        self.entries.push(entry.into());
+        self
+    }
+
+    /// Appends a separator.
+    #[inline]
+    pub fn add_separator(&mut self) -> &mut Self {
+        self.add(MenuEntry::Separator)
+    }
+
+    /// Returns the entries of `self`.
+    #[inline]
+    pub fn entries(&self) -> &[MenuEntry] {
+        &self.entries
+    }
+
+    /// Returns a mutable reference to the entries of `self`.
+    #[inline]
+    pub fn entries_mut(&mut self) -> &mut Vec<MenuEntry> {
+        &mut self.entries
+    }
+
+    /// Returns the item identified by `id` within `self` or its submenus.
+    pub fn find(&self, id: MenuItemId) -> Option<&MenuItem> {
+        self.entries.iter().find_map(|entry| match entry {
+            MenuEntry::Item(item) if item.id == id => Some(item),
+            MenuEntry::Submenu(submenu) => submenu.menu.find(id),
+            _ => None,
+        })
+    }
+
+    /// Updates the item activated by `event` within `self` or its submenus
+    /// to match the native menu, returning it if found.
+    ///
+    /// This keeps the checked state of checkable items in sync, since
+    /// activating them toggles it in the native menu only.
+    pub fn apply(&mut self, event: MenuEvent) -> Option<&mut MenuItem> {
+        let item = self.find_mut(event.id)?;
+        if event.checked.is_some() {
+            item.checked = event.checked;
+        }
+        Some(item)
+    }
+
+    /// Returns the item identified by `id` within `self` or its submenus.
+    pub fn find_mut(&mut self, id: MenuItemId) -> Option<&mut MenuItem> {
+        self.entries.iter_mut().find_map(|entry| match entry {
+            MenuEntry::Item(item) if item.id == id => Some(item),
+            MenuEntry::Submenu(submenu) => submenu.menu.find_mut(id),
+            _ => None,
+        })
+    }
+}
+
+/// An entry within a [`Menu`](struct.Menu.html).
+#[derive(Clone, Debug)]
+pub enum MenuEntry {
+    /// An item that can be activated.
+    Item(MenuItem),
+    /// An item that opens a nested menu.
+    Submenu(Submenu),
+    /// A line separating groups of entries.
+    Separator,
+}
+
+impl From<MenuItem> for MenuEntry {
+    #[inline]
+    fn from(item: MenuItem) -> Self {
+        MenuEntry::Item(item)
+    }
+}
+
+impl From<Submenu> for MenuEntry {
+    #[inline]
+    fn from(submenu: Submenu) -> Self {
+        MenuEntry::Submenu(submenu)
+    }
+}
+
+/// An item that emits a [`MenuEvent`](struct.MenuEvent.html) when activated.
+///
+/// Cloning an item gives the clone a new identifier, so that activating
+/// either one can be told apart.
+#[derive(Debug)]
+pub struct MenuItem {
+    id: MenuItemId,
+    label: ZedString,
+    enabled: bool,
+    checked: Option<bool>,
+    accelerator: Option<Accelerator>,
+}
+
+impl Clone for MenuItem {
+    #[inline]
+    fn clone(&self) -> Self {
+        MenuItem {
+            id: MenuItemId::next(),
+            label: self.label.clone(),
+            enabled: self.enabled,
+            checked: self.checked,
+            accelerator: self.accelerator,
+        }
+    }
+}
+
+impl MenuItem {
+    /// Creates an enabled item displaying `label` with a new identifier.
+    #[inline]
+    pub fn new<S: Into<ZedString>>(label: S) -> Self {
+        MenuItem {
+            id: MenuItemId::next(),
+            label: label.into(),
+            enabled: true,
+            checked: None,
+            accelerator: None,
+        }
+    }
+
+    /// Returns the identifier reported when `self` is activated.
+    #[inline]
+    pub fn id(&self) -> MenuItemId {
+        self.id
+    }
+
+    /// Returns the displayed label.
+    #[inline]
+    pub fn label(&self) -> &ZedString {
+        &self.label
+    }
+
+    /// Sets the displayed label.
+    #[inline]
+    pub fn set_label<S: Into<ZedString>>(&mut self, label: S) -> &mut Self {
+        self.label = label.into();
+        self
+    }
+
+    /// Returns whether `self` can be activated.
+    #[inline]
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Sets whether `self` can be activated.
+    #[inline]
+    pub fn set_enabled(&mut self, enabled: bool) -> &mut Self {
+        self.enabled = enabled;
+        self
+    }
+
+    /// Returns the checked state, or `None` if `self` is not checkable.
+    #[inline]
+    pub fn checked(&self) -> Option<bool> {
+        self.checked
+    }
+
+    /// Makes `self` checkable with the given state, or not checkable if
+    /// `None`.
+    ///
+    /// Activating a checkable item toggles its state in the native menu. The
+    /// new state is reported by the [`MenuEvent`](struct.MenuEvent.html),
+    /// which can be passed to [`Menu::apply`](struct.Menu.html#method.apply)
+    /// to update `self` to match.
+    #[inline]
+    pub fn set_checked<C: Into<Option<bool>>>(&mut self, checked: C) -> &mut Self {
+        self.checked = checked.into();
+        self
+    }
+
+    /// Returns the keyboard shortcut, if any.
+    #[inline]
+    pub fn accelerator(&self) -> Option<Accelerator> {
+        self.accelerator
+    }
+
+    /// Sets the keyboard shortcut.
+    #[inline]
+    pub fn set_accelerator<A: Into<Option<Accelerator>>>(&mut self, accelerator: A) -> &mut Self {
+        self.accelerator = accelerator.into();
+        self
+    }
+}
+
+/// An item that opens a nested [`Menu`](struct.Menu.html).
+#[derive(Clone, Debug)]
+pub struct Submenu {
+    label: ZedString,
+    enabled: bool,
+    menu: Menu,
+}
+
+impl Submenu {
+    /// Creates an enabled submenu displaying `label` and opening `menu`.
+    #[inline]
+    pub fn new<S: Into<ZedString>>(label: S, menu: Menu) -> Self {
+        Submenu { label: label.into(), enabled: true, menu }
+    }
+
+    /// Returns the displayed label.
+    #[inline]
+    pub fn label(&self) -> &ZedString {
+        &self.label
+    }
+
+    /// Sets the displayed label.
+    #[inline]
+    pub fn set_label<S: Into<ZedString>>(&mut self, label: S) -> &mut Self {
+        self.label = label.into();
+        self
+    }
+
+    /// Returns whether `self` can be opened.
+    #[inline]
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Sets whether `self` can be opened.
+    #[inline]
+    pub fn set_enabled(&mut self, enabled: bool) -> &mut Self {
+        self.enabled = enabled;
+        self
+    }
+
+    /// Returns the nested menu.
+    #[inline]
+    pub fn menu(&self) -> &Menu {
+        &self.menu
+    }
+
+    /// Returns a mutable reference to the nested menu.
+    #[inline]
+    pub fn menu_mut(&mut self) -> &mut Menu {
+        &mut self.menu
+    }
+}
@@ -0,0 +1,82 @@
+//! Linux-specific extensions.
+//!
+//! Menus are exported on the session bus through the
+//! [`com.canonical.dbusmenu`](https://github.com/AyatanaIndicators/libdbusmenu/blob/master/libdbusmenu-glib/dbus-menu.xml)
+//! interface, which global menu bars and tray hosts display.
+
+use crate::{Menu, MenuEvent, sys};
+
+/// The object path at which
+/// [`set_application_menu`](../../fn.set_application_menu.html) exports the
+/// menu.
+pub const APPLICATION_MENU_PATH: &str = sys::APPLICATION_MENU_PATH;
+
+/// Registers the application menu as the menu of the X11 window `xid` with
+/// the `com.canonical.AppMenu.Registrar` service of global menu bars.
+///
+/// This fails if no global menu bar is running.
+#[inline]
+#[allow(clippy::result_unit_err)]
+pub fn register_window(xid: u32) -> Result<(), ()> {
+    sys::register_window(xid)
+}
+
+/// Calls the menu event handler with `event`, for menus that are drawn by
+/// other ZedUI crates rather than exported.
+#[doc(hidden)]
+#[inline]
+pub fn emit(event: MenuEvent) {
+    crate::emit(event);
+}
+
+/// Linux-specific extensions for [`Menu`](../../struct.Menu.html).
+pub trait MenuExt {
+    /// Exports the entries of `self` at the object path `path` on the session
+    /// bus.
+    ///
+    /// Activating its items calls the menu event handler on the current
+    /// thread, while its main loop runs. The menu stays exported until the
+    /// returned value is dropped.
+    #[allow(clippy::result_unit_err)]
+    fn export(&self, path: &str) -> Result<ExportedMenu, ()>;
+}
+
+impl MenuExt for Menu {
+    #[inline]
+    fn export(&self, path: &str) -> Result<ExportedMenu, ()> {
+        let exported = sys::Exported::new(&sys::session()?, path, self)?;
+        Ok(ExportedMenu(exported))
+    }
+}
+
+/// Exports `menu` at `path` on `connection` instead of the shared session
+/// bus connection, for objects of other ZedUI crates that refer to it.
+#[doc(hidden)]
+#[allow(clippy::result_unit_err)]
+pub fn export_on(connection: &zbus::blocking::Connection, path: &str, menu: &Menu) -> Result<ExportedMenu, ()> {
+    sys::Exported::new(connection, path, menu).map(ExportedMenu)
+}
+
+/// A menu exported on the session bus by
+/// [`MenuExt::export`](trait.MenuExt.html#tymethod.export).
+pub struct ExportedMenu(sys::Exported);
+
+impl ExportedMenu {
+    /// Replaces the exported entries with those of `menu`.
+    #[inline]
+    pub fn update(&self, menu: &Menu) {
+        self.0.update(menu);
+    }
+
+    /// Returns the unique bus name of the connection exporting the menu.
+    #[inline]
+    pub fn bus_name(&self) -> Option<String> {
+        self.0.connection().unique_name().map(|name| name.to_string())
+    }
+
+    /// Returns the object path of the menu.
+    #[inline]
+    pub fn path(&self) -> &str {
+        self.0.path()
+    }
+}
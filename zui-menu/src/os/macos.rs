@@ -0,0 +1,21 @@
+//! macOS-specific extensions.
+
+use objc::rc::StrongPtr;
+use crate::{Menu, sys};
+
+/// macOS-specific extensions for [`Menu`](../../struct.Menu.html).
+pub trait MenuExt {
+    /// Creates an
+    /// [`NSMenu`](https://developer.apple.com/documentation/appkit/nsmenu)
+    /// displaying the entries of `self`.
+    ///
+    /// Activating its items calls the menu event handler.
+    fn to_ns_menu(&self) -> StrongPtr;
+}
+
+impl MenuExt for Menu {
+    #[inline]
+    fn to_ns_menu(&self) -> StrongPtr {
+        unsafe { sys::ns_menu(self) }
+    }
+}
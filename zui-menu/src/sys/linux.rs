@@ -0,0 +1,582 @@
+//! Menus exported over D-Bus through the `com.canonical.dbusmenu` interface,
+//! which desktop panels, global menu bars and tray hosts display.
+//!
+//! The exported layout is a snapshot of a [`Menu`](../../struct.Menu.html)
+//! that is served from the bus connection's own thread. Activations are sent
+//! back to the thread that exported the menu and emitted while its main loop
+//! runs.
+
+use std::{
+    collections::HashMap,
+    convert::TryFrom,
+    sync::{Arc, Mutex, OnceLock},
+};
+use serde::Serialize;
+use shared::os::linux::{main_loop, ZedStringExt};
+use zbus::{
+    blocking::Connection,
+    fdo,
+    interface,
+    object_server::SignalEmitter,
+    zvariant::{OwnedObjectPath, OwnedValue, StructureBuilder, Type, Value},
+};
+use crate::{
+    accelerator::{Accelerator, Key, Modifiers},
+    Menu,
+    MenuEntry,
+    MenuEvent,
+    MenuItemId,
+};
+
+/// The path of the menu set by `set_application_menu`.
+pub const APPLICATION_MENU_PATH: &str = "/MenuBar";
+
+const INTERFACE: &str = "com.canonical.dbusmenu";
+
+thread_local! {
+    static APPLICATION_MENU: std::cell::RefCell<Option<Exported>> = const { std::cell::RefCell::new(None) };
+}
+
+/// Returns the connection to the session bus, connecting on first use.
+pub fn session() -> Result<Connection, ()> {
+    static SESSION: OnceLock<Option<Connection>> = OnceLock::new();
+    SESSION.get_or_init(|| Connection::session().ok()).clone().ok_or(())
+}
+
+pub fn set_application_menu(menu: &Menu) {
+    APPLICATION_MENU.with(|current| {
+        let mut current = current.borrow_mut();
+        match &*current {
+            Some(exported) => exported.update(menu),
+            None => *current = session().and_then(|c| Exported::new(&c, APPLICATION_MENU_PATH, menu)).ok(),
+        }
+    });
+}
+
+/// Registers the application menu as the menu of the X11 window `xid` with
+/// the `com.canonical.AppMenu.Registrar` service of global menu bars.
+pub fn register_window(xid: u32) -> Result<(), ()> {
+    let connection = session()?;
+    let path = OwnedObjectPath::try_from(APPLICATION_MENU_PATH).map_err(drop)?;
+    connection.call_method(
+        Some("com.canonical.AppMenu.Registrar"),
+        "/com/canonical/AppMenu/Registrar",
+        Some("com.canonical.AppMenu.Registrar"),
+        "RegisterWindow",
+        &(xid, path),
+    ).map(drop).map_err(drop)
+}
+
+/// A menu exported at an object path, which is removed when dropped.
+pub struct Exported {
+    connection: Connection,
+    path: OwnedObjectPath,
+    layout: Arc<Mutex<Layout>>,
+}
+
+impl Drop for Exported {
+    fn drop(&mut self) {
+        let _ = self.connection.object_server().remove::<DbusMenu, _>(&self.path);
+    }
+}
+
+impl Exported {
+    pub fn new(connection: &Connection, path: &str, menu: &Menu) -> Result<Self, ()> {
+        let path = OwnedObjectPath::try_from(path).map_err(drop)?;
+        let layout = Arc::new(Mutex::new(Layout::new(menu, 1)));
+        let interface = DbusMenu {
+            layout: layout.clone(),
+            sender: main_loop::sender(),
+        };
+        if !connection.object_server().at(&path, interface).map_err(drop)? {
+            return Err(());
+        }
+        Ok(Exported { connection: connection.clone(), path, layout })
+    }
+
+    /// Replaces the exported menu, telling clients to fetch it again.
+    pub fn update(&self, menu: &Menu) {
+        let revision = {
+            let mut layout = self.layout.lock().unwrap();
+            *layout = Layout::new(menu, layout.revision.wrapping_add(1));
+            layout.revision
+        };
+        let _ = self.connection.emit_signal(
+            None::<&str>,
+            &self.path,
+            INTERFACE,
+            "LayoutUpdated",
+            &(revision, 0i32),
+        );
+    }
+
+    #[inline]
+    pub fn connection(&self) -> &Connection {
+        &self.connection
+    }
+
+    #[inline]
+    pub fn path(&self) -> &str {
+        self.path.as_str()
+    }
+}
+
+/// A menu flattened into items numbered by their position, with the root at
+/// 0 as dbusmenu requires.
+#[derive(Debug)]
+struct Layout {
+    revision: u32,
+    items: Vec<Item>,
+}
+
+#[derive(Debug, PartialEq)]
+struct Item {
+    kind: Kind,
+    label: String,
+    enabled: bool,
+    checked: Option<bool>,
+    shortcut: Option<Vec<String>>,
+    children: Vec<i32>,
+}
+
+#[derive(Debug, PartialEq)]
+enum Kind {
+    Root,
+    Item(MenuItemId),
+    Submenu,
+    Separator,
+}
+
+impl Item {
+    fn new(kind: Kind) -> Self {
+        Item {
+            kind,
+            label: String::new(),
+            enabled: true,
+            checked: None,
+            shortcut: None,
+            children: Vec::new(),
+        }
+    }
+
+    /// Returns the properties of `self` that differ from their defaults and
+    /// are named in `names`, or all of them if `names` is empty.
+    fn properties(&self, names: &[String]) -> HashMap<String, OwnedValue> {
+        let mut properties = HashMap::new();
+        let mut insert = |name: &str, value: Value| {
+            if names.is_empty() || names.iter().any(|n| n == name) {
+                if let Ok(value) = OwnedValue::try_from(value) {
+                    properties.insert(name.to_owned(), value);
+                }
+            }
+        };
+        match self.kind {
+            Kind::Root => {
+                insert("children-display", Value::from("submenu"));
+                return properties;
+            },
+            Kind::Separator => {
+                insert("type", Value::from("separator"));
+                return properties;
+            },
+            Kind::Submenu => insert("children-display", Value::from("submenu")),
+            Kind::Item(_) => {},
+        }
+        insert("label", Value::from(self.label.as_str()));
+        if !self.enabled {
+            insert("enabled", Value::from(false));
+        }
+        if let Some(checked) = self.checked {
+            insert("toggle-type", Value::from("checkmark"));
+            insert("toggle-state", Value::from(checked as i32));
+        }
+        if let Some(shortcut) = &self.shortcut {
+            insert("shortcut", Value::from(vec![shortcut.clone()]));
+        }
+        properties
+    }
+}
+
+impl Layout {
+    fn new(menu: &Menu, revision: u32) -> Self {
+        let mut layout = Layout { revision, items: vec![Item::new(Kind::Root)] };
+        layout.add_menu(0, menu);
+        layout
+    }
+
+    fn add_menu(&mut self, parent: usize, menu: &Menu) {
+        for entry in menu.entries() {
+            let id = self.items.len();
+            let item = match entry {
+                MenuEntry::Item(item) => Item {
+                    label: escape_label(item.label().as_str()),
+                    enabled: item.is_enabled(),
+                    checked: item.checked(),
+                    shortcut: item.accelerator().map(shortcut),
+                    ..Item::new(Kind::Item(item.id()))
+                },
+                MenuEntry::Submenu(submenu) => Item {
+                    label: escape_label(submenu.label().as_str()),
+                    enabled: submenu.is_enabled(),
+                    ..Item::new(Kind::Submenu)
+                },
+                MenuEntry::Separator => Item::new(Kind::Separator),
+            };
+            self.items.push(item);
+            self.items[parent].children.push(id as i32);
+            if let MenuEntry::Submenu(submenu) = entry {
+                self.add_menu(id, submenu.menu());
+            }
+        }
+    }
+
+    fn get(&self, id: i32) -> Option<&Item> {
+        usize::try_from(id).ok().and_then(|id| self.items.get(id))
+    }
+
+    /// Returns the layout of the item `id` with children down to `depth`
+    /// levels, or all of them if `depth` is negative.
+    fn node(&self, id: i32, depth: i32, names: &[String]) -> Option<Node> {
+        let item = self.get(id)?;
+        let children = if depth == 0 {
+            Vec::new()
+        } else {
+            item.children.iter()
+                .filter_map(|&child| self.node(child, depth - 1, names))
+                .filter_map(|child| child.into_value().ok())
+                .collect()
+        };
+        Some(Node { id, properties: item.properties(names), children })
+    }
+
+    /// Handles a click on the item `id`, toggling it if it is checkable.
+    fn click(&mut self, id: i32) -> Option<MenuEvent> {
+        let item = usize::try_from(id).ok().and_then(|id| self.items.get_mut(id))?;
+        match item.kind {
+            Kind::Item(item_id) if item.enabled => {
+                item.checked = item.checked.map(|checked| !checked);
+                Some(MenuEvent { id: item_id, checked: item.checked })
+            },
+            _ => None,
+        }
+    }
+}
+
+/// An item and its children in the `(ia{sv}av)` form of `GetLayout`.
+#[derive(Debug, Serialize, Type)]
+struct Node {
+    id: i32,
+    properties: HashMap<String, OwnedValue>,
+    children: Vec<OwnedValue>,
+}
+
+impl Node {
+    fn into_value(self) -> zbus::zvariant::Result<OwnedValue> {
+        let structure = StructureBuilder::new()
+            .add_field(self.id)
+            .add_field(self.properties)
+            .add_field(self.children)
+            .build()?;
+        OwnedValue::try_from(Value::from(structure))
+    }
+}
+
+/// Escapes underscores, which dbusmenu labels use to mark access keys.
+fn escape_label(label: &str) -> String {
+    label.replace('_', "__")
+}
+
+/// Returns the key names of `accelerator`, as used by GTK.
+fn shortcut(accelerator: Accelerator) -> Vec<String> {
+    let mut keys = Vec::new();
+    for &(modifier, name) in &[
+        (Modifiers::CTRL, "Control"),
+        (Modifiers::ALT, "Alt"),
+        (Modifiers::SHIFT, "Shift"),
+        (Modifiers::SUPER, "Super"),
+    ] {
+        if accelerator.modifiers.contains(modifier) {
+            keys.push(name.to_owned());
+        }
+    }
+    let key = match accelerator.key {
+        Key::Char('+') => "plus".to_owned(),
+        Key::Char('-') => "minus".to_owned(),
+        Key::Char('=') => "equal".to_owned(),
+        Key::Char(',') => "comma".to_owned(),
+        Key::Char('.') => "period".to_owned(),
+        Key::Char('/') => "slash".to_owned(),
+        Key::Char(c) => c.to_string(),
+        Key::F(n) => format!("F{}", n),
+        Key::Enter => "Return".to_owned(),
+        Key::Tab => "Tab".to_owned(),
+        Key::Space => "space".to_owned(),
+        Key::Backspace => "BackSpace".to_owned(),
+        Key::Delete => "Delete".to_owned(),
+        Key::Escape => "Escape".to_owned(),
+        Key::Insert => "Insert".to_owned(),
+        Key::Home => "Home".to_owned(),
+        Key::End => "End".to_owned(),
+        Key::PageUp => "Page_Up".to_owned(),
+        Key::PageDown => "Page_Down".to_owned(),
+        Key::Up => "Up".to_owned(),
+        Key::Down => "Down".to_owned(),
+        Key::Left => "Left".to_owned(),
+        Key::Right => "Right".to_owned(),
+    };
+    keys.push(key);
+    keys
+}
+
+struct DbusMenu {
+    layout: Arc<Mutex<Layout>>,
+    /// Runs activations on the thread that exported the menu.
+    sender: main_loop::Sender,
+}
+
+impl DbusMenu {
+    /// Handles an event on the item `id`, returning whether it exists.
+    async fn handle_event(&self, emitter: &SignalEmitter<'_>, id: i32, event_id: &str) -> bool {
+        if event_id != "clicked" {
+            return self.layout.lock().unwrap().get(id).is_some();
+        }
+        let (event, properties) = {
+            let mut layout = self.layout.lock().unwrap();
+            let event = layout.click(id);
+            let properties = layout.get(id).map(|item| item.properties(&["toggle-state".to_owned()]));
+            match properties {
+                Some(properties) => (event, properties),
+                None => return false,
+            }
+        };
+        if let Some(event) = event {
+            if event.checked.is_some() {
+                let _ = Self::items_properties_updated(emitter, vec![(id, properties)], Vec::new()).await;
+            }
+            let _ = self.sender.send(move || crate::emit(event));
+        }
+        true
+    }
+}
+
+#[interface(name = "com.canonical.dbusmenu")]
+impl DbusMenu {
+    fn get_layout(
+        &self,
+        parent_id: i32,
+        recursion_depth: i32,
+        property_names: Vec<String>,
+    ) -> fdo::Result<(u32, Node)> {
+        let layout = self.layout.lock().unwrap();
+        let node = layout.node(parent_id, recursion_depth, &property_names)
+            .ok_or_else(|| fdo::Error::InvalidArgs(format!("no item with id {}", parent_id)))?;
+        Ok((layout.revision, node))
+    }
+
+    fn get_group_properties(
+        &self,
+        ids: Vec<i32>,
+        property_names: Vec<String>,
+    ) -> Vec<(i32, HashMap<String, OwnedValue>)> {
+        let layout = self.layout.lock().unwrap();
+        // An empty list asks for every item.
+        let ids = if ids.is_empty() { (0..layout.items.len() as i32).collect() } else { ids };
+        ids.into_iter()
+            .filter_map(|id| Some((id, layout.get(id)?.properties(&property_names))))
+            .collect()
+    }
+
+    fn get_property(&self, id: i32, name: String) -> fdo::Result<OwnedValue> {
+        let layout = self.layout.lock().unwrap();
+        let item = layout.get(id)
+            .ok_or_else(|| fdo::Error::InvalidArgs(format!("no item with id {}", id)))?;
+        item.properties(std::slice::from_ref(&name)).remove(&name)
+            .ok_or_else(|| fdo::Error::InvalidArgs(format!("no property {}", name)))
+    }
+
+    async fn event(
+        &self,
+        #[zbus(signal_emitter)] emitter: SignalEmitter<'_>,
+        id: i32,
+        event_id: String,
+        _data: OwnedValue,
+        _timestamp: u32,
+    ) -> fdo::Result<()> {
+        if self.handle_event(&emitter, id, &event_id).await {
+            Ok(())
+        } else {
+            Err(fdo::Error::InvalidArgs(format!("no item with id {}", id)))
+        }
+    }
+
+    async fn event_group(
+        &self,
+        #[zbus(signal_emitter)] emitter: SignalEmitter<'_>,
+        events: Vec<(i32, String, OwnedValue, u32)>,
+    ) -> Vec<i32> {
+        let mut errors = Vec::new();
+        for (id, event_id, ..) in events {
+            if !self.handle_event(&emitter, id, &event_id).await {
+                errors.push(id);
+            }
+        }
+        errors
+    }
+
+    /// The layout never changes when a submenu opens.
+    fn about_to_show(&self, _id: i32) -> bool {
+        false
+    }
+
+    fn about_to_show_group(&self, ids: Vec<i32>) -> (Vec<i32>, Vec<i32>) {
+        let layout = self.layout.lock().unwrap();
+        let errors = ids.into_iter().filter(|&id| layout.get(id).is_none()).collect();
+        (Vec::new(), errors)
+    }
+
+    #[zbus(signal)]
+    async fn items_properties_updated(
+        emitter: &SignalEmitter<'_>,
+        updated_props: Vec<(i32, HashMap<String, OwnedValue>)>,
+        removed_props: Vec<(i32, Vec<String>)>,
+    ) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    async fn layout_updated(emitter: &SignalEmitter<'_>, revision: u32, parent: i32) -> zbus::Result<()>;
+
+    #[zbus(property)]
+    fn version(&self) -> u32 {
+        3
+    }
+
+    #[zbus(property)]
+    fn text_direction(&self) -> &str {
+        "ltr"
+    }
+
+    #[zbus(property)]
+    fn status(&self) -> &str {
+        "normal"
+    }
+
+    #[zbus(property)]
+    fn icon_theme_path(&self) -> Vec<String> {
+        Vec::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+    use shared::os::linux::test_bus::TestBus;
+    use zbus::{
+        blocking::{connection, MessageIterator},
+        message::{self, Message},
+        zvariant::DynamicType,
+        MatchRule,
+    };
+    use crate::{MenuItem, Submenu};
+
+    type LayoutReply = (u32, (i32, HashMap<String, OwnedValue>, Vec<OwnedValue>));
+
+    fn connect(bus: &TestBus) -> Connection {
+        connection::Builder::address(bus.address()).unwrap().build().unwrap()
+    }
+
+    fn call<B: Serialize + DynamicType>(client: &Connection, name: &str, method: &str, body: &B) -> Message {
+        client.call_method(Some(name), "/test/menu", Some(INTERFACE), method, body).unwrap()
+    }
+
+    /// Returns a menu with an item, a separator and a submenu holding a
+    /// checkable item, along with the identifiers of both items.
+    fn menu() -> (Menu, MenuItemId, MenuItemId) {
+        let mut open = MenuItem::new("_Open");
+        open.set_accelerator("Ctrl+O".parse::<Accelerator>().unwrap());
+        let mut wrap = MenuItem::new("Wrap");
+        wrap.set_checked(false);
+        let ids = (open.id(), wrap.id());
+
+        let mut view = Menu::new();
+        view.add(wrap);
+        let mut menu = Menu::new();
+        menu.add(open).add_separator().add(Submenu::new("View", view));
+        (menu, ids.0, ids.1)
+    }
+
+    #[test]
+    fn flatten_menus() {
+        let (menu, open, wrap) = menu();
+        let layout = Layout::new(&menu, 1);
+        assert_eq!(layout.items.len(), 5);
+        assert_eq!(layout.items[0].children, [1, 2, 3]);
+        assert_eq!(layout.items[1].kind, Kind::Item(open));
+        assert_eq!(layout.items[1].label, "__Open");
+        assert_eq!(layout.items[1].shortcut, Some(vec!["Control".to_owned(), "O".to_owned()]));
+        assert_eq!(layout.items[2].kind, Kind::Separator);
+        assert_eq!(layout.items[3].children, [4]);
+        assert_eq!(layout.items[4].kind, Kind::Item(wrap));
+        assert_eq!(layout.items[4].checked, Some(false));
+
+        let properties = layout.items[4].properties(&[]);
+        assert_eq!(i32::try_from(&properties["toggle-state"]).unwrap(), 0);
+        assert!(!properties.contains_key("enabled"));
+        let properties = layout.items[1].properties(&["label".to_owned()]);
+        assert_eq!(properties.len(), 1);
+    }
+
+    #[test]
+    fn export_over_dbus() {
+        let bus = TestBus::start().expect("dbus-daemon is not available");
+        let server = connect(&bus);
+        let client = connect(&bus);
+        let name = server.unique_name().unwrap().to_string();
+        let (menu, _, wrap) = menu();
+
+        let events = Arc::new(Mutex::new(Vec::new()));
+        crate::set_event_handler({
+            let events = events.clone();
+            move |event| events.lock().unwrap().push(event)
+        });
+        let exported = Exported::new(&server, "/test/menu", &menu).unwrap();
+        let mut signals = MessageIterator::for_match_rule(
+            MatchRule::builder()
+                .msg_type(message::Type::Signal)
+                .interface(INTERFACE).unwrap()
+                .build(),
+            &client,
+            None,
+        ).unwrap();
+
+        let reply = call(&client, &name, "GetLayout", &(0i32, -1i32, Vec::<String>::new()));
+        let (revision, (id, _, children)): LayoutReply = reply.body().deserialize().unwrap();
+        assert_eq!((revision, id, children.len()), (1, 0, 3));
+
+        let reply = call(&client, &name, "GetProperty", &(1i32, "label"));
+        let label: OwnedValue = reply.body().deserialize().unwrap();
+        assert_eq!(String::try_from(label).unwrap(), "__Open");
+
+        // Clicking the checkable item toggles it and emits an event on this
+        // thread.
+        call(&client, &name, "Event", &(4i32, "clicked", Value::from(0i32), 0u32));
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while events.lock().unwrap().is_empty() && Instant::now() < deadline {
+            main_loop::run_pending();
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(*events.lock().unwrap(), [MenuEvent { id: wrap, checked: Some(true) }]);
+        let signal = signals.next().unwrap().unwrap();
+        assert_eq!(signal.header().member().unwrap().as_str(), "ItemsPropertiesUpdated");
+
+        exported.update(&Menu::new());
+        let signal = signals.next().unwrap().unwrap();
+        assert_eq!(signal.header().member().unwrap().as_str(), "LayoutUpdated");
+        let reply = call(&client, &name, "GetLayout", &(0i32, -1i32, Vec::<String>::new()));
+        let (revision, (_, _, children)): LayoutReply = reply.body().deserialize().unwrap();
+        assert_eq!((revision, children.len()), (2, 0));
+
+        drop(exported);
+        let error = client.call_method(Some(name.as_str()), "/test/menu", Some(INTERFACE), "AboutToShow", &0i32);
+        assert!(error.is_err());
+    }
+}
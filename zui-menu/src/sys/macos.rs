@@ -0,0 +1,214 @@
+use std::sync::Once;
+use cocoa::{
+    base::{id, nil, NO, YES},
+    foundation::{NSInteger, NSString, NSUInteger},
+};
+use objc::{
+    declare::ClassDecl,
+    rc::StrongPtr,
+    runtime::{Class, Object, Sel},
+};
+use shared::os::macos::ZedStringExt;
+use crate::{
+    accelerator::{Accelerator, Key, Modifiers},
+    Menu,
+    MenuEntry,
+    MenuEvent,
+    MenuItem,
+    MenuItemId,
+    Submenu,
+};
+
+const NS_CONTROL_STATE_VALUE_OFF: NSInteger = 0;
+const NS_CONTROL_STATE_VALUE_ON: NSInteger = 1;
+
+const NS_EVENT_MODIFIER_FLAG_SHIFT: NSUInteger = 1 << 17;
+const NS_EVENT_MODIFIER_FLAG_CONTROL: NSUInteger = 1 << 18;
+const NS_EVENT_MODIFIER_FLAG_OPTION: NSUInteger = 1 << 19;
+const NS_EVENT_MODIFIER_FLAG_COMMAND: NSUInteger = 1 << 20;
+
+pub fn set_application_menu(menu: &Menu) {
+    unsafe {
+        let ns_menu = ns_menu(menu);
+        let ns_app: id = msg_send![class!(NSApplication), sharedApplication];
+        msg_send![ns_app, setMainMenu:*ns_menu];
+    }
+}
+
+/// Creates an `NSMenu` from `menu`.
+pub unsafe fn ns_menu(menu: &Menu) -> StrongPtr {
+    let title = StrongPtr::new(NSString::alloc(nil).init_str(""));
+    let ns_menu: id = msg_send![class!(NSMenu), alloc];
+    let ns_menu = StrongPtr::new(msg_send![ns_menu, initWithTitle:*title]);
+
+    // Items are enabled according to the model rather than by walking the
+    // responder chain.
+    msg_send![*ns_menu, setAutoenablesItems:NO];
+
+    for entry in menu.entries() {
+        let ns_item = match entry {
+            MenuEntry::Item(item) => ns_menu_item(item),
+            MenuEntry::Submenu(submenu) => ns_submenu_item(submenu),
+            MenuEntry::Separator => {
+                StrongPtr::retain(msg_send![class!(NSMenuItem), separatorItem])
+            },
+        };
+        msg_send![*ns_menu, addItem:*ns_item];
+    }
+
+    ns_menu
+}
+
+unsafe fn ns_menu_item(item: &MenuItem) -> StrongPtr {
+    let (key_equivalent, modifier_mask) = match item.accelerator() {
+        Some(accelerator) => key_equivalent(accelerator),
+        None => (String::new(), 0),
+    };
+    let key_equivalent = StrongPtr::new(NSString::alloc(nil).init_str(&key_equivalent));
+
+    let ns_item: id = msg_send![class!(NSMenuItem), alloc];
+    let ns_item = StrongPtr::new(msg_send![
+        ns_item,
+        initWithTitle:**item.label().as_ns_string()
+               action:sel!(zuiMenuItemActivated:)
+        keyEquivalent:*key_equivalent
+    ]);
+    msg_send![*ns_item, setKeyEquivalentModifierMask:modifier_mask];
+    msg_send![*ns_item, setTarget:target()];
+    msg_send![*ns_item, setTag:item.id().as_u64() as NSInteger];
+    msg_send![*ns_item, setEnabled:if item.is_enabled() { YES } else { NO }];
+    if let Some(checked) = item.checked() {
+        let state = if checked {
+            NS_CONTROL_STATE_VALUE_ON
+        } else {
+            NS_CONTROL_STATE_VALUE_OFF
+        };
+        msg_send![*ns_item, setState:state];
+        msg_send![*ns_item, setRepresentedObject:checkable_marker()];
+    }
+    ns_item
+}
+
+unsafe fn ns_submenu_item(submenu: &Submenu) -> StrongPtr {
+    let ns_submenu = ns_menu(submenu.menu());
+    let title = **submenu.label().as_ns_string();
+    msg_send![*ns_submenu, setTitle:title];
+
+    let key_equivalent = StrongPtr::new(NSString::alloc(nil).init_str(""));
+    let ns_item: id = msg_send![class!(NSMenuItem), alloc];
+    let ns_item = StrongPtr::new(msg_send![
+        ns_item,
+        initWithTitle:title
+               action:nil
+        keyEquivalent:*key_equivalent
+    ]);
+    msg_send![*ns_item, setSubmenu:*ns_submenu];
+    msg_send![*ns_item, setEnabled:if submenu.is_enabled() { YES } else { NO }];
+    ns_item
+}
+
+/// Returns the character that AppKit uses for `key`.
+fn key_character(key: Key) -> u32 {
+    match key {
+        Key::Char(c) => c as u32,
+        Key::F(n) => 0xF704 + u32::from(n) - 1, // NSF1FunctionKey
+        Key::Enter => 0x0D,
+        Key::Tab => 0x09,
+        Key::Space => 0x20,
+        Key::Backspace => 0x08,
+        Key::Delete => 0xF728,   // NSDeleteFunctionKey
+        Key::Escape => 0x1B,
+        Key::Insert => 0xF727,   // NSInsertFunctionKey
+        Key::Home => 0xF729,     // NSHomeFunctionKey
+        Key::End => 0xF72B,      // NSEndFunctionKey
+        Key::PageUp => 0xF72C,   // NSPageUpFunctionKey
+        Key::PageDown => 0xF72D, // NSPageDownFunctionKey
+        Key::Up => 0xF700,       // NSUpArrowFunctionKey
+        Key::Down => 0xF701,     // NSDownArrowFunctionKey
+        Key::Left => 0xF702,     // NSLeftArrowFunctionKey
+        Key::Right => 0xF703,    // NSRightArrowFunctionKey
+    }
+}
+
+/// Returns the `keyEquivalent` and `keyEquivalentModifierMask` of
+/// `accelerator`.
+fn key_equivalent(accelerator: Accelerator) -> (String, NSUInteger) {
+    // Shift is given by the modifier mask, so letters must be lowercase.
+    let key: String = match accelerator.key {
+        Key::Char(c) => c.to_lowercase().collect(),
+        key => std::char::from_u32(key_character(key))
+            .map(String::from)
+            .unwrap_or_default(),
+    };
+
+    const FLAGS: &[(Modifiers, NSUInteger)] = &[
+        (Modifiers::SHIFT, NS_EVENT_MODIFIER_FLAG_SHIFT),
+        (Modifiers::CTRL, NS_EVENT_MODIFIER_FLAG_CONTROL),
+        (Modifiers::ALT, NS_EVENT_MODIFIER_FLAG_OPTION),
+        (Modifiers::SUPER, NS_EVENT_MODIFIER_FLAG_COMMAND),
+    ];
+    let mask = FLAGS.iter()
+        .filter(|(modifier, _)| accelerator.modifiers.contains(*modifier))
+        .fold(0, |mask, (_, flag)| mask | flag);
+
+    (key, mask)
+}
+
+/// Returns the object that marks a native item as checkable.
+fn checkable_marker() -> id {
+    // Any shared object works, since only its identity is compared.
+    target()
+}
+
+thread_local! {
+    // Menus are only used on the main thread.
+    static TARGET: StrongPtr = unsafe {
+        StrongPtr::new(msg_send![target_class(), new])
+    };
+}
+
+/// Returns the shared target of every native menu item.
+fn target() -> id {
+    TARGET.with(|target| **target)
+}
+
+fn target_class() -> &'static Class {
+    static REGISTER: Once = Once::new();
+
+    REGISTER.call_once(|| {
+        let superclass = class!(NSObject);
+        let mut decl = ClassDecl::new("ZUIMenuTarget", superclass).unwrap();
+        unsafe {
+            decl.add_method(
+                sel!(zuiMenuItemActivated:),
+                item_activated as extern "C" fn(&Object, Sel, id),
+            );
+        }
+        decl.register();
+    });
+
+    class!(ZUIMenuTarget)
+}
+
+extern "C" fn item_activated(_this: &Object, _: Sel, sender: id) {
+    unsafe {
+        let tag: NSInteger = msg_send![sender, tag];
+        let marker: id = msg_send![sender, representedObject];
+
+        let checked = if marker == checkable_marker() {
+            let state: NSInteger = msg_send![sender, state];
+            let checked = state != NS_CONTROL_STATE_VALUE_ON;
+            let state = if checked {
+                NS_CONTROL_STATE_VALUE_ON
+            } else {
+                NS_CONTROL_STATE_VALUE_OFF
+            };
+            msg_send![sender, setState:state];
+            Some(checked)
+        } else {
+            None
+        };
+
+        crate::emit(MenuEvent { id: MenuItemId(tag as u64), checked });
+    }
+}
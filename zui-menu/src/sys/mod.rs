@@ -0,0 +1,11 @@
+cfg_if::cfg_if! {
+    if #[cfg(target_os = "macos")] {
+        mod macos;
+        pub use macos::*;
+    } else if #[cfg(target_os = "linux")] {
+        mod linux;
+        pub use linux::*;
+    } else {
+        compile_error!("`zui-menu` does not compile for this platform");
+    }
+}
@@ -0,0 +1,181 @@
+//! ZedUI desktop notifications.
+
+#![deny(missing_docs)]
+#![doc(html_logo_url = "https://zed-ui.dev/static/logos/zed-ui.svg")]
+
+#[cfg(target_os = "macos")]
+#[macro_use]
+extern crate objc;
+
+extern crate zui_shared as shared;
+
+use std::{
+    cell::RefCell,
+    fmt,
+    marker::PhantomData,
+    rc::Rc,
+    time::Duration,
+};
+use shared::{Icon, ZedString};
+
+mod sys;
+
+/// The action reported when the body of a notification, rather than one of
+/// its buttons, is clicked.
+pub const DEFAULT_ACTION: &str = "default";
+
+type ActionHandler = Rc<RefCell<dyn FnMut(&str)>>;
+
+/// How urgently a [`Notification`](struct.Notification.html) needs the
+/// user's attention.
+///
+/// On macOS 12 and later, low urgency notifications are delivered quietly,
+/// and critical ones are time-sensitive, which needs the application to have
+/// the time-sensitive notifications entitlement. Earlier versions ignore
+/// this.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Urgency {
+    /// Can be shown unobtrusively.
+    Low,
+    /// The default.
+    Normal,
+    /// Should remain visible until dismissed.
+    Critical,
+}
+
+impl Default for Urgency {
+    #[inline]
+    fn default() -> Self {
+        Urgency::Normal
+    }
+}
+
+/// A desktop notification.
+#[derive(Clone, Default)]
+pub struct Notification {
+    pub(crate) summary: Option<ZedString>,
+    pub(crate) body: Option<ZedString>,
+    pub(crate) icon: Option<Icon>,
+    pub(crate) urgency: Urgency,
+    pub(crate) actions: Vec<(String, ZedString)>,
+    pub(crate) timeout: Option<Duration>,
+    pub(crate) action_handler: Option<ActionHandler>,
+}
+
+impl fmt::Debug for Notification {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Notification")
+            .field("summary", &self.summary)
+            .field("body", &self.body)
+            .field("icon", &self.icon)
+            .field("urgency", &self.urgency)
+            .field("actions", &self.actions)
+            .field("timeout", &self.timeout)
+            .finish()
+    }
+}
+
+impl Notification {
+    /// Creates a notification with `summary` as its title.
+    #[inline]
+    pub fn new<S: Into<ZedString>>(summary: S) -> Self {
+        Notification {
+            summary: Some(summary.into()),
+            ..Default::default()
+        }
+    }
+
+    /// Sets the body text.
+    #[inline]
+    pub fn body<S: Into<ZedString>>(&mut self, body: S) -> &mut Self {
+        self.body = Some(body.into());
+        self
+    }
+
+    /// Sets the displayed icon.
+    #[inline]
+    pub fn icon(&mut self, icon: Icon) -> &mut Self {
+        self.icon = Some(icon);
+        self
+    }
+
+    /// Sets how urgently the notification needs attention.
+    ///
+    /// See [`Urgency`](enum.Urgency.html) for how platforms treat it.
+    #[inline]
+    pub fn urgency(&mut self, urgency: Urgency) -> &mut Self {
+        self.urgency = urgency;
+        self
+    }
+
+    /// Adds a button displaying `label` that reports `id` when clicked.
+    #[inline]
+    pub fn add_action<S: Into<ZedString>>(&mut self, id: &str, label: S) -> &mut Self {
+        self.actions.push((id.into(), label.into()));
+        self
+    }
+
+    /// Sets how long the notification is shown before being removed.
+    ///
+    /// By default, the platform decides.
+    #[inline]
+    pub fn timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Sets the function called on the main thread with the identifier of
+    /// the clicked action, or [`DEFAULT_ACTION`](constant.DEFAULT_ACTION.html)
+    /// if the notification itself was clicked.
+    ///
+    /// Clicking removes the notification, so this is called at most once per
+    /// shown notification.
+    #[inline]
+    pub fn on_action<F>(&mut self, handler: F) -> &mut Self
+    where
+        F: FnMut(&str) + 'static,
+    {
+        self.action_handler = Some(Rc::new(RefCell::new(handler)));
+        self
+    }
+
+    /// Shows the notification, returning an error upon failure.
+    ///
+    /// On macOS, this fails unless the application is bundled, and the
+    /// notification is not shown until the user allows notifications, which
+    /// is asked the first time. On Linux, this fails unless a notification
+    /// service is running on the session bus.
+    #[inline]
+    #[allow(clippy::result_unit_err)]
+    pub fn show(&self) -> Result<NotificationHandle, ()> {
+        Ok(NotificationHandle {
+            sys: sys::show(self)?,
+            _marker: PhantomData,
+        })
+    }
+}
+
+/// A handle to a shown [`Notification`](struct.Notification.html).
+///
+/// Dropping the handle does not remove the notification.
+pub struct NotificationHandle {
+    sys: sys::NotificationHandle,
+    // !Send + !Sync
+    _marker: PhantomData<*mut ()>,
+}
+
+impl fmt::Debug for NotificationHandle {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.sys.fmt(f)
+    }
+}
+
+impl NotificationHandle {
+    /// Removes the notification, after which its actions are no longer
+    /// reported.
+    #[inline]
+    pub fn close(self) {
+        self.sys.close();
+    }
+}
@@ -0,0 +1,312 @@
+//! Notifications shown through the `org.freedesktop.Notifications` service
+//! on the session bus, which desktop environments provide.
+//!
+//! The service reports clicks and dismissals through signals, which a thread
+//! receives and sends back to the thread that first showed a notification.
+//! Action handlers are kept there and called while its main loop runs.
+
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    convert::TryFrom,
+    env,
+    fmt,
+    sync::OnceLock,
+    thread,
+};
+use shared::os::linux::{main_loop, IconExt, ZedStringExt};
+use zbus::{
+    blocking::{Connection, MessageIterator},
+    message,
+    zvariant::Value,
+    MatchRule,
+};
+use crate::{ActionHandler, Notification, Urgency, DEFAULT_ACTION};
+
+const NAME: &str = "org.freedesktop.Notifications";
+const PATH: &str = "/org/freedesktop/Notifications";
+const INTERFACE: &str = "org.freedesktop.Notifications";
+
+/// The timeout that lets the service decide.
+const EXPIRE_DEFAULT: i32 = -1;
+
+thread_local! {
+    /// The action handlers of shown notifications, by identifier.
+    static HANDLERS: RefCell<HashMap<u32, ActionHandler>> = RefCell::new(HashMap::new());
+}
+
+/// A connection to the notification service.
+#[derive(Clone)]
+struct Client {
+    connection: Connection,
+}
+
+impl Client {
+    /// Listens for the signals of the service on `connection`, reporting them
+    /// to the current thread.
+    fn new(connection: Connection) -> Result<Self, ()> {
+        let rule = MatchRule::builder()
+            .msg_type(message::Type::Signal)
+            .interface(INTERFACE).map_err(drop)?
+            .path(PATH).map_err(drop)?
+            .build();
+        let signals = MessageIterator::for_match_rule(rule, &connection, None).map_err(drop)?;
+        let sender = main_loop::sender();
+        thread::Builder::new()
+            .name("zui-notification".into())
+            .spawn(move || {
+                // Ends once the connection closes or the thread exits.
+                for message in signals.flatten() {
+                    let body = message.body();
+                    let task: Box<dyn FnOnce() + Send> = match message.header().member().map(|m| m.as_str()) {
+                        Some("ActionInvoked") => match body.deserialize::<(u32, String)>() {
+                            Ok((id, action)) => Box::new(move || invoke(id, &action)),
+                            Err(_) => continue,
+                        },
+                        Some("NotificationClosed") => match body.deserialize::<(u32, u32)>() {
+                            Ok((id, _reason)) => Box::new(move || drop(remove(id))),
+                            Err(_) => continue,
+                        },
+                        _ => continue,
+                    };
+                    if sender.send(task).is_err() {
+                        break;
+                    }
+                }
+            })
+            .map_err(drop)?;
+        Ok(Client { connection })
+    }
+
+    fn show(&self, notification: &Notification) -> Result<NotificationHandle, ()> {
+        let app_name = env::current_exe().ok()
+            .and_then(|path| Some(path.file_stem()?.to_string_lossy().into_owned()))
+            .unwrap_or_else(|| "zui".to_owned());
+        let summary = notification.summary.as_ref().map_or("", |s| s.as_str());
+        let body = notification.body.as_ref().map_or("", |s| s.as_str());
+        let expire_timeout = notification.timeout.map_or(EXPIRE_DEFAULT, |timeout| {
+            i32::try_from(timeout.as_millis()).unwrap_or(i32::MAX)
+        });
+
+        let reply = self.connection.call_method(
+            Some(NAME),
+            PATH,
+            Some(INTERFACE),
+            "Notify",
+            &(app_name, 0u32, "", summary, body, actions(notification), hints(notification), expire_timeout),
+        ).map_err(drop)?;
+        let id: u32 = reply.body().deserialize().map_err(drop)?;
+
+        if let Some(handler) = &notification.action_handler {
+            HANDLERS.with(|h| h.borrow_mut().insert(id, handler.clone()));
+        }
+        Ok(NotificationHandle { id, client: self.clone() })
+    }
+}
+
+/// Returns the client of the session bus, connecting on first use.
+fn session() -> Result<Client, ()> {
+    static SESSION: OnceLock<Option<Client>> = OnceLock::new();
+    SESSION.get_or_init(|| Client::new(Connection::session().ok()?).ok()).clone().ok_or(())
+}
+
+/// Returns the identifiers and labels of the actions of `notification`,
+/// interleaved.
+///
+/// With an action handler, clicking the notification itself reports the
+/// `default` action, which services do not show as a button.
+fn actions(notification: &Notification) -> Vec<&str> {
+    let mut actions = Vec::new();
+    if notification.action_handler.is_some() {
+        actions.extend_from_slice(&[DEFAULT_ACTION, ""]);
+    }
+    for (id, label) in &notification.actions {
+        actions.extend_from_slice(&[id.as_str(), label.as_str()]);
+    }
+    actions
+}
+
+fn hints(notification: &Notification) -> HashMap<&'static str, Value<'static>> {
+    let mut hints = HashMap::new();
+    let urgency: u8 = match notification.urgency {
+        Urgency::Low => 0,
+        Urgency::Normal => 1,
+        Urgency::Critical => 2,
+    };
+    hints.insert("urgency", Value::from(urgency));
+    if let Some(icon) = &notification.icon {
+        // The width, height, row stride, whether there is alpha, the bits per
+        // sample, the number of channels and the RGBA pixels.
+        let (width, height) = (icon.width() as i32, icon.height() as i32);
+        let image = (width, height, width * 4, true, 8i32, 4i32, icon.rgba().to_vec());
+        hints.insert("image-data", Value::from(image));
+    }
+    hints
+}
+
+/// Calls the action handler of notification `id`, which the service then
+/// removes.
+fn invoke(id: u32, action: &str) {
+    if let Some(handler) = remove(id) {
+        (*handler.borrow_mut())(action);
+    }
+}
+
+fn remove(id: u32) -> Option<ActionHandler> {
+    HANDLERS.with(|h| h.borrow_mut().remove(&id))
+}
+
+pub struct NotificationHandle {
+    id: u32,
+    client: Client,
+}
+
+impl fmt::Debug for NotificationHandle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("NotificationHandle")
+            .field("id", &self.id)
+            .finish()
+    }
+}
+
+impl NotificationHandle {
+    pub fn close(self) {
+        remove(self.id);
+        let _ = self.client.connection.call_method(Some(NAME), PATH, Some(INTERFACE), "CloseNotification", &self.id);
+    }
+}
+
+pub fn show(notification: &Notification) -> Result<NotificationHandle, ()> {
+    session()?.show(notification)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        rc::Rc,
+        sync::{Arc, Mutex},
+        time::{Duration, Instant},
+    };
+    use shared::{os::linux::test_bus::TestBus, Icon, ZedString};
+    use zbus::{blocking::connection, interface, zvariant::OwnedValue};
+
+    /// The summary, body, actions, hints and timeout of a `Notify` call.
+    type Notify = (String, String, Vec<String>, HashMap<String, OwnedValue>, i32);
+
+    /// The `Notify` calls and the identifiers of closed notifications.
+    #[derive(Default)]
+    struct Calls {
+        notify: Vec<Notify>,
+        closed: Vec<u32>,
+    }
+
+    /// A notification service that records the calls made to it.
+    struct Daemon {
+        calls: Arc<Mutex<Calls>>,
+    }
+
+    #[interface(name = "org.freedesktop.Notifications")]
+    impl Daemon {
+        #[allow(clippy::too_many_arguments)]
+        fn notify(
+            &self,
+            _app_name: String,
+            _replaces_id: u32,
+            _app_icon: String,
+            summary: String,
+            body: String,
+            actions: Vec<String>,
+            hints: HashMap<String, OwnedValue>,
+            expire_timeout: i32,
+        ) -> u32 {
+            let mut calls = self.calls.lock().unwrap();
+            calls.notify.push((summary, body, actions, hints, expire_timeout));
+            calls.notify.len() as u32
+        }
+
+        fn close_notification(&self, id: u32) {
+            self.calls.lock().unwrap().closed.push(id);
+        }
+    }
+
+    fn connect(bus: &TestBus) -> Connection {
+        connection::Builder::address(bus.address()).unwrap().build().unwrap()
+    }
+
+    /// Runs the loop of this thread until `done` returns `true`.
+    fn run_until<F: Fn() -> bool>(done: F) {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !done() && Instant::now() < deadline {
+            main_loop::run_pending();
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    fn has_handler(id: u32) -> bool {
+        HANDLERS.with(|h| h.borrow().contains_key(&id))
+    }
+
+    #[test]
+    fn notify_over_dbus() {
+        let bus = TestBus::start().expect("dbus-daemon is not available");
+        let client = Client::new(connect(&bus)).unwrap();
+
+        // There are no notifications without a service.
+        assert!(client.show(&Notification::new("Hi")).is_err());
+
+        let calls = Arc::new(Mutex::new(Calls::default()));
+        let daemon = connection::Builder::address(bus.address()).unwrap()
+            .name(NAME).unwrap()
+            .serve_at(PATH, Daemon { calls: calls.clone() }).unwrap()
+            .build()
+            .unwrap();
+
+        let clicked = Rc::new(RefCell::new(Vec::new()));
+        let mut notification = Notification::new("Build finished");
+        notification
+            .body(ZedString::from_string("2 warnings".to_owned()))
+            .icon(Icon::from_rgba(1, 1, vec![0x10, 0x20, 0x30, 0x40]).unwrap())
+            .urgency(Urgency::Critical)
+            .add_action("open", "Open")
+            .timeout(Duration::from_millis(1500))
+            .on_action({
+                let clicked = clicked.clone();
+                move |action| clicked.borrow_mut().push(action.to_owned())
+            });
+        let handle = client.show(&notification).unwrap();
+        {
+            let calls = calls.lock().unwrap();
+            let (summary, body, actions, hints, expire_timeout) = &calls.notify[0];
+            assert_eq!((summary.as_str(), body.as_str(), *expire_timeout), ("Build finished", "2 warnings", 1500));
+            assert_eq!(*actions, ["default", "", "open", "Open"]);
+            assert_eq!(u8::try_from(&hints["urgency"]).unwrap(), 2);
+            type Image = (i32, i32, i32, bool, i32, i32, Vec<u8>);
+            let image = Image::try_from(hints["image-data"].try_clone().unwrap()).unwrap();
+            assert_eq!(image, (1, 1, 4, true, 8, 4, vec![0x10, 0x20, 0x30, 0x40]));
+        }
+
+        // Actions are reported on this thread, once.
+        let id = handle.id;
+        daemon.emit_signal(None::<&str>, PATH, INTERFACE, "ActionInvoked", &(id, "open")).unwrap();
+        run_until(|| !clicked.borrow().is_empty());
+        assert_eq!(*clicked.borrow(), ["open"]);
+        assert!(!has_handler(id));
+
+        // Dismissed notifications forget their handlers.
+        let handle = client.show(&notification).unwrap();
+        let id = handle.id;
+        assert!(has_handler(id));
+        daemon.emit_signal(None::<&str>, PATH, INTERFACE, "NotificationClosed", &(id, 2u32)).unwrap();
+        run_until(|| !has_handler(id));
+        assert!(!has_handler(id));
+
+        // Closing asks the service to remove the notification.
+        let handle = client.show(&notification).unwrap();
+        let id = handle.id;
+        handle.close();
+        assert!(!has_handler(id));
+        assert_eq!(calls.lock().unwrap().closed, [id]);
+        assert_eq!(*clicked.borrow(), ["open"]);
+    }
+}
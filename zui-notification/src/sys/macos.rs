@@ -0,0 +1,395 @@
+//! Notifications shown through `UNUserNotificationCenter`.
+//!
+//! The center only serves bundled applications, and shows nothing until the
+//! user allows notifications, which is asked the first time one is shown.
+
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+    env,
+    ffi::CStr,
+    ptr,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Once,
+    },
+};
+use block::{Block, ConcreteBlock};
+use cocoa::{
+    base::{id, nil, BOOL, YES},
+    foundation::{NSArray, NSString, NSUInteger},
+};
+use objc::{
+    declare::ClassDecl,
+    rc::StrongPtr,
+    runtime::{Class, Object, Sel},
+};
+use shared::os::macos::{IconExt, ZedStringExt};
+use crate::{ActionHandler, Notification, Urgency, DEFAULT_ACTION};
+
+#[link(name = "UserNotifications", kind = "framework")]
+extern "C" {
+    static UNNotificationDefaultActionIdentifier: id;
+    static UNNotificationDismissActionIdentifier: id;
+}
+
+const UN_AUTHORIZATION_OPTION_SOUND: NSUInteger = 1 << 1;
+const UN_AUTHORIZATION_OPTION_ALERT: NSUInteger = 1 << 2;
+
+const UN_NOTIFICATION_PRESENTATION_OPTION_SOUND: NSUInteger = 1 << 1;
+const UN_NOTIFICATION_PRESENTATION_OPTION_ALERT: NSUInteger = 1 << 2;
+
+const UN_NOTIFICATION_CATEGORY_OPTION_CUSTOM_DISMISS_ACTION: NSUInteger = 1 << 0;
+
+const UN_NOTIFICATION_INTERRUPTION_LEVEL_PASSIVE: NSUInteger = 0;
+const UN_NOTIFICATION_INTERRUPTION_LEVEL_ACTIVE: NSUInteger = 1;
+const UN_NOTIFICATION_INTERRUPTION_LEVEL_TIME_SENSITIVE: NSUInteger = 2;
+
+const NS_BITMAP_IMAGE_FILE_TYPE_PNG: NSUInteger = 4;
+
+thread_local! {
+    /// Action handlers of shown notifications, keyed by notification
+    /// identifier.
+    static HANDLERS: RefCell<HashMap<String, ActionHandler>> = RefCell::new(HashMap::new());
+
+    /// Categories of shown notifications, which hold their actions and are
+    /// keyed by notification identifier.
+    static CATEGORIES: RefCell<HashMap<String, StrongPtr>> = RefCell::new(HashMap::new());
+}
+
+unsafe fn ns_string(s: &str) -> StrongPtr {
+    StrongPtr::new(NSString::alloc(nil).init_str(s))
+}
+
+unsafe fn to_utf8(ns_string: id) -> String {
+    CStr::from_ptr(ns_string.UTF8String()).to_string_lossy().into_owned()
+}
+
+fn next_identifier() -> String {
+    static NEXT: AtomicU64 = AtomicU64::new(0);
+    format!("zui-notification-{}", NEXT.fetch_add(1, Ordering::Relaxed))
+}
+
+thread_local! {
+    // The center does not retain its delegate, which is kept for as long as
+    // the thread.
+    static DELEGATE: StrongPtr = unsafe {
+        StrongPtr::new(msg_send![delegate_class(), new])
+    };
+}
+
+/// Returns the notification center, with our delegate installed.
+unsafe fn center() -> id {
+    static INIT: Once = Once::new();
+
+    let center: id = msg_send![class!(UNUserNotificationCenter), currentNotificationCenter];
+    INIT.call_once(|| {
+        msg_send![center, setDelegate:delegate()];
+    });
+    center
+}
+
+fn delegate() -> id {
+    DELEGATE.with(|delegate| **delegate)
+}
+
+/// Returns whether the application has a bundle identifier, without which
+/// the center raises an exception.
+unsafe fn is_bundled() -> bool {
+    let bundle: id = msg_send![class!(NSBundle), mainBundle];
+    let identifier: id = msg_send![bundle, bundleIdentifier];
+    identifier != nil
+}
+
+/// Runs `f` on the main thread, where handlers are kept.
+///
+/// The center calls completion handlers on other threads.
+unsafe fn on_main_thread<F: FnOnce() + 'static>(f: F) {
+    let is_main: BOOL = msg_send![class!(NSThread), isMainThread];
+    if is_main == YES {
+        return f();
+    }
+    // Blocks must be `Fn`, so `f` is taken out on the only call.
+    let f = RefCell::new(Some(f));
+    let operation = ConcreteBlock::new(move || {
+        if let Some(f) = f.borrow_mut().take() {
+            f();
+        }
+    }).copy();
+    let operation: *const _ = &*operation;
+    let queue: id = msg_send![class!(NSOperationQueue), mainQueue];
+    msg_send![queue, addOperationWithBlock:operation];
+}
+
+/// Replaces the categories of the center with those of shown notifications.
+unsafe fn update_categories(center: id) {
+    let categories: Vec<id> = CATEGORIES.with(|c| c.borrow().values().map(|c| **c).collect());
+    let categories = NSArray::arrayWithObjects(nil, &categories);
+    let categories: id = msg_send![class!(NSSet), setWithArray:categories];
+    msg_send![center, setNotificationCategories:categories];
+}
+
+/// Forgets the handler and category of notification `identifier`.
+unsafe fn remove(identifier: &str) -> Option<ActionHandler> {
+    if CATEGORIES.with(|c| c.borrow_mut().remove(identifier)).is_some() {
+        update_categories(center());
+    }
+    HANDLERS.with(|h| h.borrow_mut().remove(identifier))
+}
+
+/// Removes notification `identifier` from the center.
+unsafe fn remove_from_center(identifier: &str) {
+    let identifiers = NSArray::arrayWithObjects(nil, &[*ns_string(identifier)]);
+    let center = center();
+    msg_send![center, removePendingNotificationRequestsWithIdentifiers:identifiers];
+    msg_send![center, removeDeliveredNotificationsWithIdentifiers:identifiers];
+}
+
+/// Forgets the notifications that are no longer delivered, such as those the
+/// user cleared all at once, which does not report each dismissal.
+///
+/// This is done whenever another notification is shown. Notifications shown
+/// after this is called are kept, since they may not be delivered yet.
+unsafe fn remove_stale(center: id) {
+    let known: HashSet<String> = HANDLERS.with(|h| h.borrow().keys().cloned().collect());
+    if known.is_empty() {
+        return;
+    }
+    let handler = ConcreteBlock::new(move |delivered: id| {
+        let count: NSUInteger = msg_send![delivered, count];
+        let delivered: HashSet<String> = (0..count)
+            .map(|i| {
+                let notification: id = msg_send![delivered, objectAtIndex:i];
+                let request: id = msg_send![notification, request];
+                to_utf8(msg_send![request, identifier])
+            })
+            .collect();
+        let known = known.clone();
+        on_main_thread(move || {
+            for identifier in known.difference(&delivered) {
+                remove(identifier);
+            }
+        });
+    }).copy();
+    let handler: *const _ = &*handler;
+    msg_send![center, getDeliveredNotificationsWithCompletionHandler:handler];
+}
+
+/// Returns an attachment holding `icon`, which needs to be written to a
+/// file, or `nil` upon failure.
+unsafe fn icon_attachment(icon: id, identifier: &str) -> id {
+    let tiff: id = msg_send![icon, TIFFRepresentation];
+    let rep: id = msg_send![class!(NSBitmapImageRep), imageRepWithData:tiff];
+    if rep == nil {
+        return nil;
+    }
+    let properties: id = msg_send![class!(NSDictionary), dictionary];
+    let png: id = msg_send![rep, representationUsingType:NS_BITMAP_IMAGE_FILE_TYPE_PNG
+                                              properties:properties];
+    // The center moves the file into its own storage.
+    let path = env::temp_dir().join(format!("{}.png", identifier));
+    let url: id = msg_send![class!(NSURL), fileURLWithPath:*ns_string(&path.to_string_lossy())];
+    let written: BOOL = msg_send![png, writeToURL:url atomically:YES];
+    if written != YES {
+        return nil;
+    }
+    msg_send![
+        class!(UNNotificationAttachment),
+        attachmentWithIdentifier:*ns_string(identifier)
+                             URL:url
+                         options:nil
+                           error:ptr::null_mut::<id>()
+    ]
+}
+
+#[derive(Debug)]
+pub struct NotificationHandle {
+    identifier: String,
+}
+
+impl NotificationHandle {
+    pub fn close(self) {
+        unsafe {
+            remove(&self.identifier);
+            remove_from_center(&self.identifier);
+        }
+    }
+}
+
+pub fn show(notification: &Notification) -> Result<NotificationHandle, ()> {
+    unsafe {
+        if !is_bundled() {
+            return Err(());
+        }
+        let content: id = msg_send![class!(UNMutableNotificationContent), new];
+        if content == nil {
+            return Err(());
+        }
+        let content = StrongPtr::new(content);
+
+        let identifier = next_identifier();
+
+        if let Some(summary) = &notification.summary {
+            msg_send![*content, setTitle:**summary.as_ns_string()];
+        }
+        if let Some(body) = &notification.body {
+            msg_send![*content, setBody:**body.as_ns_string()];
+        }
+        if let Some(icon) = &notification.icon {
+            let attachment = icon_attachment(**icon.as_ns_image(), &identifier);
+            if attachment != nil {
+                let attachments = NSArray::arrayWithObjects(nil, &[attachment]);
+                msg_send![*content, setAttachments:attachments];
+            }
+        }
+
+        // Interruption levels are available from macOS 12. Time-sensitive
+        // notifications need an entitlement, without which they are active.
+        let responds: BOOL = msg_send![*content, respondsToSelector:sel!(setInterruptionLevel:)];
+        if responds == YES {
+            let level = match notification.urgency {
+                Urgency::Low => UN_NOTIFICATION_INTERRUPTION_LEVEL_PASSIVE,
+                Urgency::Normal => UN_NOTIFICATION_INTERRUPTION_LEVEL_ACTIVE,
+                Urgency::Critical => UN_NOTIFICATION_INTERRUPTION_LEVEL_TIME_SENSITIVE,
+            };
+            msg_send![*content, setInterruptionLevel:level];
+        }
+
+        let center = center();
+        remove_stale(center);
+
+        // Each notification with actions or a handler has its own category,
+        // which also reports dismissals so that the handler is forgotten.
+        if !notification.actions.is_empty() || notification.action_handler.is_some() {
+            let actions: Vec<id> = notification.actions.iter()
+                .map(|(action_id, label)| msg_send![
+                    class!(UNNotificationAction),
+                    actionWithIdentifier:*ns_string(action_id)
+                                   title:**label.as_ns_string()
+                                 options:0 as NSUInteger
+                ])
+                .collect();
+            let actions = NSArray::arrayWithObjects(nil, &actions);
+            let intents = NSArray::arrayWithObjects(nil, &[]);
+            let category: id = msg_send![
+                class!(UNNotificationCategory),
+                categoryWithIdentifier:*ns_string(&identifier)
+                               actions:actions
+                     intentIdentifiers:intents
+                               options:UN_NOTIFICATION_CATEGORY_OPTION_CUSTOM_DISMISS_ACTION
+            ];
+            CATEGORIES.with(|c| c.borrow_mut().insert(identifier.clone(), StrongPtr::retain(category)));
+            update_categories(center);
+            msg_send![*content, setCategoryIdentifier:*ns_string(&identifier)];
+        }
+
+        if let Some(handler) = &notification.action_handler {
+            HANDLERS.with(|h| h.borrow_mut().insert(identifier.clone(), handler.clone()));
+        }
+
+        let request: id = msg_send![
+            class!(UNNotificationRequest),
+            requestWithIdentifier:*ns_string(&identifier)
+                          content:*content
+                          trigger:nil
+        ];
+        let request = StrongPtr::retain(request);
+
+        // Asking again returns the stored answer, so the request is added
+        // once the user has answered the first time.
+        let options = UN_AUTHORIZATION_OPTION_ALERT | UN_AUTHORIZATION_OPTION_SOUND;
+        let authorized = ConcreteBlock::new(move |granted: BOOL, _error: id| {
+            if granted == YES {
+                let center: id = msg_send![class!(UNUserNotificationCenter), currentNotificationCenter];
+                msg_send![center, addNotificationRequest:*request withCompletionHandler:nil];
+            }
+        }).copy();
+        let authorized: *const _ = &*authorized;
+        msg_send![center, requestAuthorizationWithOptions:options completionHandler:authorized];
+
+        if let Some(timeout) = notification.timeout {
+            let delay = timeout.as_secs() as f64 + f64::from(timeout.subsec_nanos()) * 1e-9;
+            msg_send![
+                delegate(),
+                performSelector:sel!(zuiExpireNotification:)
+                     withObject:*ns_string(&identifier)
+                     afterDelay:delay
+            ];
+        }
+
+        Ok(NotificationHandle { identifier })
+    }
+}
+
+fn delegate_class() -> &'static Class {
+    static REGISTER: Once = Once::new();
+
+    REGISTER.call_once(|| {
+        let superclass = class!(NSObject);
+        let mut decl = ClassDecl::new("ZUINotificationDelegate", superclass).unwrap();
+        unsafe {
+            decl.add_method(
+                sel!(userNotificationCenter:willPresentNotification:withCompletionHandler:),
+                will_present as extern "C" fn(&Object, Sel, id, id, id),
+            );
+            decl.add_method(
+                sel!(userNotificationCenter:didReceiveNotificationResponse:withCompletionHandler:),
+                did_receive as extern "C" fn(&Object, Sel, id, id, id),
+            );
+            decl.add_method(
+                sel!(zuiExpireNotification:),
+                expire as extern "C" fn(&Object, Sel, id),
+            );
+        }
+        decl.register();
+    });
+
+    class!(ZUINotificationDelegate)
+}
+
+extern "C" fn will_present(_this: &Object, _: Sel, _center: id, _notification: id, handler: id) {
+    // Show notifications even while the application is active.
+    let options = UN_NOTIFICATION_PRESENTATION_OPTION_ALERT | UN_NOTIFICATION_PRESENTATION_OPTION_SOUND;
+    unsafe {
+        let handler = &*(handler as *const Block<(NSUInteger,), ()>);
+        handler.call((options,));
+    }
+}
+
+extern "C" fn did_receive(_this: &Object, _: Sel, _center: id, response: id, handler: id) {
+    unsafe {
+        let notification: id = msg_send![response, notification];
+        let request: id = msg_send![notification, request];
+        let identifier = to_utf8(msg_send![request, identifier]);
+
+        let action_id: id = msg_send![response, actionIdentifier];
+        let is_default: BOOL = msg_send![action_id, isEqualToString:UNNotificationDefaultActionIdentifier];
+        let is_dismiss: BOOL = msg_send![action_id, isEqualToString:UNNotificationDismissActionIdentifier];
+        let action = if is_default == YES {
+            Some(DEFAULT_ACTION.to_string())
+        } else if is_dismiss == YES {
+            None
+        } else {
+            Some(to_utf8(action_id))
+        };
+
+        // Responding removes the notification, along with its handler, which
+        // is taken out first so that it may show other notifications.
+        on_main_thread(move || {
+            if let (Some(handler), Some(action)) = (remove(&identifier), action) {
+                (*handler.borrow_mut())(&action);
+            }
+        });
+
+        let handler = &*(handler as *const Block<(), ()>);
+        handler.call(());
+    }
+}
+
+extern "C" fn expire(_this: &Object, _: Sel, identifier: id) {
+    unsafe {
+        let identifier = to_utf8(identifier);
+        remove(&identifier);
+        remove_from_center(&identifier);
+    }
+}
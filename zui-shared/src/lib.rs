@@ -3,7 +3,12 @@
 #![deny(missing_docs)]
 #![doc(html_logo_url = "https://zed-ui.dev/static/logos/zed-ui.svg")]
 
+#[cfg(target_os = "macos")]
+#[macro_use]
+extern crate objc;
+
 mod sys;
+pub mod mime;
 pub mod os;
 
 /// A string type specific to the targeted ZedUI platform.
@@ -24,3 +29,19 @@ impl ZedString {
         ZedString(sys::ZedString::from_utf8(s))
     }
 }
+
+/// An image used for icons, such as those of drags and tray items.
+#[derive(Clone, Debug)]
+pub struct Icon(sys::Icon);
+
+impl Icon {
+    /// Creates an icon from encoded image data, such as the contents of a
+    /// PNG file.
+    ///
+    /// Returns `None` if `data` is not in a format supported by the platform.
+    /// On Linux, only PNG is supported.
+    #[inline]
+    pub fn from_bytes(data: &[u8]) -> Option<Self> {
+        sys::Icon::from_bytes(data).map(Icon)
+    }
+}
@@ -0,0 +1,79 @@
+//! MIME types shared by the clipboard and drag and drop.
+
+/// UTF-8 encoded plain text.
+pub const TEXT_PLAIN: &str = "text/plain;charset=utf-8";
+
+/// An HTML fragment or document.
+pub const TEXT_HTML: &str = "text/html";
+
+/// Rich text format.
+pub const TEXT_RTF: &str = "text/rtf";
+
+/// A PNG encoded image.
+pub const IMAGE_PNG: &str = "image/png";
+
+/// A TIFF encoded image.
+pub const IMAGE_TIFF: &str = "image/tiff";
+
+/// Returns the type and subtype of `mime` without any parameters, such as
+/// `text/plain` for `text/plain;charset=utf-8`.
+#[inline]
+pub fn essence(mime: &str) -> &str {
+    mime.split(';').next().unwrap_or(mime).trim()
+}
+
+/// Returns whether `a` and `b` name the same type, ignoring case and
+/// parameters.
+#[inline]
+pub fn same_type(a: &str, b: &str) -> bool {
+    essence(a).eq_ignore_ascii_case(essence(b))
+}
+
+/// Returns whether `mime` refers to plain text, regardless of parameters.
+#[inline]
+pub fn is_text_plain(mime: &str) -> bool {
+    same_type(mime, TEXT_PLAIN)
+}
+
+/// Sets `data` of type `mime` in `formats`, replacing any data previously
+/// set with the same type.
+pub fn set_format(formats: &mut Vec<(String, Vec<u8>)>, mime: &str, data: &[u8]) {
+    match formats.iter_mut().find(|(m, _)| same_type(m, mime)) {
+        Some(format) => *format = (mime.into(), data.to_vec()),
+        None => formats.push((mime.into(), data.to_vec())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn essence_strips_parameters() {
+        assert_eq!(essence("text/plain"), "text/plain");
+        assert_eq!(essence("text/plain;charset=utf-8"), "text/plain");
+        assert_eq!(essence("text/html ; charset=utf-16"), "text/html");
+    }
+
+    #[test]
+    fn same_type_ignores_case_and_parameters() {
+        assert!(same_type("text/plain", TEXT_PLAIN));
+        assert!(same_type("Text/HTML;charset=utf-8", TEXT_HTML));
+        assert!(!same_type("text/plain", TEXT_HTML));
+        assert!(is_text_plain("text/plain;charset=us-ascii"));
+        assert!(!is_text_plain("text/plainer"));
+    }
+
+    #[test]
+    fn set_format_replaces_same_type() {
+        let mut formats = Vec::new();
+        set_format(&mut formats, TEXT_HTML, b"a");
+        set_format(&mut formats, IMAGE_PNG, b"b");
+        set_format(&mut formats, "text/html;charset=utf-8", b"c");
+
+        assert_eq!(formats, vec![
+            ("text/html;charset=utf-8".to_owned(), b"c".to_vec()),
+            (IMAGE_PNG.to_owned(), b"b".to_vec()),
+        ]);
+    }
+}
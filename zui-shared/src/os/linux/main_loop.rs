@@ -0,0 +1,407 @@
+//! A per-thread loop that waits on file descriptors and timers.
+//!
+//! Every backend registers its connections with the loop of the thread that
+//! uses them, so that events are dispatched on that thread while
+//! [`run`](fn.run.html) is running. Other threads hand work to a loop
+//! through a [`Sender`](struct.Sender.html).
+
+use std::{
+    cell::{Cell, RefCell},
+    collections::{BTreeMap, VecDeque},
+    io,
+    os::unix::io::RawFd,
+    rc::Rc,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc,
+        Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+type Callback = Rc<RefCell<Box<dyn FnMut()>>>;
+type Task = Box<dyn FnOnce() + Send>;
+
+thread_local! {
+    static LOOP: Rc<Loop> = Rc::new(Loop::new());
+}
+
+/// Identifies a callback registered with a loop.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SourceId(u64);
+
+impl SourceId {
+    fn next() -> Self {
+        static NEXT: AtomicU64 = AtomicU64::new(1);
+        SourceId(NEXT.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
+struct Timer {
+    deadline: Instant,
+    callback: Box<dyn FnOnce()>,
+}
+
+struct Loop {
+    fds: RefCell<BTreeMap<SourceId, (RawFd, Callback)>>,
+    timers: RefCell<BTreeMap<SourceId, Timer>>,
+    prepares: RefCell<BTreeMap<SourceId, Callback>>,
+    remote: Arc<Remote>,
+    quit: Cell<bool>,
+}
+
+/// The part of a loop that other threads may reach.
+struct Remote {
+    tasks: Mutex<VecDeque<Task>>,
+    // Written to whenever a task is queued, so that a waiting loop wakes up.
+    wake_read: RawFd,
+    wake_write: RawFd,
+    // Set once the owning thread exits, after which tasks are dropped.
+    closed: AtomicBool,
+}
+
+impl Drop for Remote {
+    fn drop(&mut self) {
+        unsafe {
+            libc::close(self.wake_read);
+            libc::close(self.wake_write);
+        }
+    }
+}
+
+impl Remote {
+    fn wake(&self) {
+        let byte = 1u8;
+        // A full pipe already wakes the loop, so errors are ignored.
+        unsafe { libc::write(self.wake_write, &byte as *const u8 as *const _, 1) };
+    }
+
+    fn drain_wake(&self) {
+        let mut buf = [0u8; 64];
+        loop {
+            let n = unsafe { libc::read(self.wake_read, buf.as_mut_ptr() as *mut _, buf.len()) };
+            if n <= 0 {
+                break;
+            }
+        }
+    }
+}
+
+impl Drop for Loop {
+    fn drop(&mut self) {
+        self.remote.closed.store(true, Ordering::SeqCst);
+        self.remote.tasks.lock().unwrap().clear();
+    }
+}
+
+impl Loop {
+    fn new() -> Self {
+        let mut fds = [0; 2];
+        let result = unsafe { libc::pipe2(fds.as_mut_ptr(), libc::O_CLOEXEC | libc::O_NONBLOCK) };
+        if result != 0 {
+            panic!("failed to create main loop pipe: {}", io::Error::last_os_error());
+        }
+        Loop {
+            fds: Default::default(),
+            timers: Default::default(),
+            prepares: Default::default(),
+            remote: Arc::new(Remote {
+                tasks: Default::default(),
+                wake_read: fds[0],
+                wake_write: fds[1],
+                closed: AtomicBool::new(false),
+            }),
+            quit: Cell::new(false),
+        }
+    }
+
+    /// Dispatches whatever is ready, waiting for something to become ready
+    /// first if `block` is set.
+    fn iterate(&self, block: bool) {
+        let prepares: Vec<Callback> = self.prepares.borrow().values().cloned().collect();
+        for prepare in prepares {
+            call(&prepare);
+        }
+        if block && self.quit.get() {
+            return;
+        }
+
+        let mut timeout = if block { -1 } else { 0 };
+        if !self.remote.tasks.lock().unwrap().is_empty() {
+            timeout = 0;
+        }
+        if let Some(deadline) = self.timers.borrow().values().map(|t| t.deadline).min() {
+            let wait = deadline.saturating_duration_since(Instant::now());
+            // Rounded up so that the timer has expired upon waking.
+            let ms = wait.as_micros().div_ceil(1000);
+            let ms = ms.min(i32::MAX as u128) as i32;
+            if timeout < 0 || ms < timeout {
+                timeout = ms;
+            }
+        }
+
+        let sources: Vec<(SourceId, RawFd)> = self.fds.borrow()
+            .iter()
+            .map(|(&id, &(fd, _))| (id, fd))
+            .collect();
+        let mut pollfds: Vec<libc::pollfd> = sources.iter()
+            .map(|&(_, fd)| libc::pollfd { fd, events: libc::POLLIN, revents: 0 })
+            .collect();
+        pollfds.push(libc::pollfd { fd: self.remote.wake_read, events: libc::POLLIN, revents: 0 });
+
+        let n = unsafe {
+            libc::poll(pollfds.as_mut_ptr(), pollfds.len() as libc::nfds_t, timeout)
+        };
+        if n < 0 {
+            // Interrupted by a signal, which is handled like a spurious wake.
+            return;
+        }
+
+        if pollfds.last().is_some_and(|p| p.revents != 0) {
+            self.remote.drain_wake();
+        }
+        let tasks: Vec<Task> = self.remote.tasks.lock().unwrap().drain(..).collect();
+        for task in tasks {
+            task();
+        }
+
+        for (&(id, _), pollfd) in sources.iter().zip(&pollfds) {
+            if pollfd.revents == 0 {
+                continue;
+            }
+            // An earlier callback may have removed this one.
+            let callback = self.fds.borrow().get(&id).map(|(_, c)| c.clone());
+            if let Some(callback) = callback {
+                call(&callback);
+            }
+        }
+
+        let now = Instant::now();
+        let expired: Vec<SourceId> = self.timers.borrow()
+            .iter()
+            .filter(|(_, t)| t.deadline <= now)
+            .map(|(&id, _)| id)
+            .collect();
+        for id in expired {
+            let timer = self.timers.borrow_mut().remove(&id);
+            if let Some(timer) = timer {
+                (timer.callback)();
+            }
+        }
+    }
+}
+
+/// Calls `callback` unless it is already running further up the stack.
+fn call(callback: &Callback) {
+    if let Ok(mut callback) = callback.try_borrow_mut() {
+        (*callback)();
+    }
+}
+
+fn with_loop<F: FnOnce(&Loop) -> T, T>(f: F) -> T {
+    LOOP.with(|l| f(&l.clone()))
+}
+
+/// Runs the loop of the current thread until [`quit`](fn.quit.html) is
+/// called.
+pub fn run() {
+    let lp = LOOP.with(Rc::clone);
+    lp.quit.set(false);
+    while !lp.quit.get() {
+        lp.iterate(true);
+    }
+    lp.quit.set(false);
+}
+
+/// Makes the innermost call to [`run`](fn.run.html) on the current thread
+/// return.
+pub fn quit() {
+    with_loop(|l| l.quit.set(true));
+}
+
+/// Dispatches everything that is ready on the current thread without
+/// waiting.
+pub fn run_pending() {
+    let lp = LOOP.with(Rc::clone);
+    lp.iterate(false);
+}
+
+/// Calls `callback` whenever `fd` is readable.
+pub fn watch_fd<F: FnMut() + 'static>(fd: RawFd, callback: F) -> SourceId {
+    let id = SourceId::next();
+    let callback: Callback = Rc::new(RefCell::new(Box::new(callback)));
+    with_loop(|l| l.fds.borrow_mut().insert(id, (fd, callback)));
+    id
+}
+
+/// Calls `callback` once, after `delay`.
+pub fn add_timeout<F: FnOnce() + 'static>(delay: Duration, callback: F) -> SourceId {
+    let id = SourceId::next();
+    let timer = Timer { deadline: Instant::now() + delay, callback: Box::new(callback) };
+    with_loop(|l| l.timers.borrow_mut().insert(id, timer));
+    id
+}
+
+/// Calls `callback` each time before the loop waits, such as to flush
+/// buffered requests.
+pub fn add_prepare<F: FnMut() + 'static>(callback: F) -> SourceId {
+    let id = SourceId::next();
+    let callback: Callback = Rc::new(RefCell::new(Box::new(callback)));
+    with_loop(|l| l.prepares.borrow_mut().insert(id, callback));
+    id
+}
+
+/// Unregisters the callback identified by `id`, if it is still registered
+/// on the current thread.
+pub fn remove_source(id: SourceId) {
+    // The loop may already be destroyed if this thread is exiting.
+    let _ = LOOP.try_with(|l| {
+        l.fds.borrow_mut().remove(&id);
+        l.timers.borrow_mut().remove(&id);
+        l.prepares.borrow_mut().remove(&id);
+    });
+}
+
+/// Returns a handle for running tasks on the loop of the current thread.
+pub fn sender() -> Sender {
+    with_loop(|l| Sender { remote: l.remote.clone() })
+}
+
+/// A handle for running tasks on the loop of another thread.
+#[derive(Clone)]
+pub struct Sender {
+    remote: Arc<Remote>,
+}
+
+impl Sender {
+    /// Queues `task` to run on the loop's thread, returning an error if the
+    /// thread has exited.
+    #[allow(clippy::result_unit_err)]
+    pub fn send<F: FnOnce() + Send + 'static>(&self, task: F) -> Result<(), ()> {
+        if self.remote.closed.load(Ordering::SeqCst) {
+            return Err(());
+        }
+        self.remote.tasks.lock().unwrap().push_back(Box::new(task));
+        self.remote.wake();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{sync::mpsc, thread};
+
+    // Each test runs on its own thread so that it gets a fresh loop.
+    fn on_thread<F: FnOnce() + Send + 'static>(f: F) {
+        thread::spawn(f).join().unwrap();
+    }
+
+    #[test]
+    fn timeouts_fire_in_order() {
+        on_thread(|| {
+            let fired = Rc::new(RefCell::new(Vec::new()));
+            for &(ms, n) in &[(30, 3), (10, 1), (20, 2)] {
+                let fired = fired.clone();
+                add_timeout(Duration::from_millis(ms), move || {
+                    fired.borrow_mut().push(n);
+                    if n == 3 {
+                        quit();
+                    }
+                });
+            }
+            run();
+            assert_eq!(*fired.borrow(), [1, 2, 3]);
+        });
+    }
+
+    #[test]
+    fn removed_timeout_does_not_fire() {
+        on_thread(|| {
+            let id = add_timeout(Duration::from_millis(1), || panic!("fired"));
+            remove_source(id);
+            add_timeout(Duration::from_millis(10), quit);
+            run();
+        });
+    }
+
+    #[test]
+    fn sender_wakes_loop() {
+        on_thread(|| {
+            let sender = sender();
+            let (tx, rx) = mpsc::channel();
+            thread::spawn(move || {
+                sender.send(move || {
+                    tx.send(thread::current().id()).unwrap();
+                    quit();
+                }).unwrap();
+            });
+            run();
+            assert_eq!(rx.recv().unwrap(), thread::current().id());
+        });
+    }
+
+    #[test]
+    fn sender_fails_after_thread_exits() {
+        let (tx, rx) = mpsc::channel();
+        on_thread(move || tx.send(sender()).unwrap());
+        let sender = rx.recv().unwrap();
+        assert_eq!(sender.send(|| {}), Err(()));
+    }
+
+    #[test]
+    fn fd_callback_runs_while_readable() {
+        on_thread(|| {
+            let mut fds = [0; 2];
+            assert_eq!(unsafe { libc::pipe(fds.as_mut_ptr()) }, 0);
+            let [read, write] = fds;
+
+            let count = Rc::new(Cell::new(0));
+            let id = {
+                let count = count.clone();
+                watch_fd(read, move || {
+                    let mut byte = 0u8;
+                    unsafe { libc::read(read, &mut byte as *mut u8 as *mut _, 1) };
+                    count.set(count.get() + 1);
+                })
+            };
+
+            unsafe { libc::write(write, b"ab".as_ptr() as *const _, 2) };
+            run_pending();
+            run_pending();
+            assert_eq!(count.get(), 2);
+
+            remove_source(id);
+            unsafe { libc::write(write, b"c".as_ptr() as *const _, 1) };
+            run_pending();
+            assert_eq!(count.get(), 2);
+
+            unsafe {
+                libc::close(read);
+                libc::close(write);
+            }
+        });
+    }
+
+    #[test]
+    fn prepare_runs_before_waiting() {
+        on_thread(|| {
+            let count = Rc::new(Cell::new(0));
+            {
+                let count = count.clone();
+                add_prepare(move || {
+                    count.set(count.get() + 1);
+                    if count.get() == 3 {
+                        quit();
+                    }
+                });
+            }
+            // Something to wake up for between iterations.
+            for ms in 1..=2 {
+                add_timeout(Duration::from_millis(ms), || {});
+            }
+            run();
+            assert_eq!(count.get(), 3);
+        });
+    }
+}
@@ -0,0 +1,198 @@
+//! Linux-specific extensions.
+//!
+//! Objects such as clipboard watchers deliver their events on the thread
+//! that created them, while that thread runs its
+//! [main loop](main_loop/index.html).
+
+use std::{
+    env,
+    ffi::OsStr,
+    os::unix::ffi::OsStrExt,
+    path::PathBuf,
+};
+use crate::{Icon, ZedString, sys};
+
+pub mod main_loop;
+#[doc(hidden)]
+pub mod test_bus;
+
+/// Linux-specific extensions for [`ZedString`](../../struct.ZedString.html).
+pub trait ZedStringExt {
+    /// Creates an instance from an owned UTF-8 string.
+    fn from_string(string: String) -> Self;
+
+    /// Returns the underlying UTF-8 string.
+    fn as_str(&self) -> &str;
+
+    /// Transfers ownership of the string out of `self`.
+    fn into_string(self) -> String;
+}
+
+impl ZedStringExt for ZedString {
+    #[inline]
+    fn from_string(string: String) -> Self {
+        ZedString(sys::ZedString { string })
+    }
+
+    #[inline]
+    fn as_str(&self) -> &str {
+        &self.0.string
+    }
+
+    #[inline]
+    fn into_string(self) -> String {
+        self.0.string
+    }
+}
+
+/// Linux-specific extensions for [`Icon`](../../struct.Icon.html).
+pub trait IconExt: Sized {
+    /// Creates an instance from non-premultiplied RGBA pixels, row by row.
+    ///
+    /// Returns `None` if `rgba` does not hold exactly `width * height`
+    /// pixels or if either dimension is zero.
+    fn from_rgba(width: u32, height: u32, rgba: Vec<u8>) -> Option<Self>;
+
+    /// Returns the width of `self` in pixels.
+    fn width(&self) -> u32;
+
+    /// Returns the height of `self` in pixels.
+    fn height(&self) -> u32;
+
+    /// Returns the non-premultiplied RGBA pixels of `self`, row by row.
+    fn rgba(&self) -> &[u8];
+
+    /// Returns the pixels of `self` as premultiplied ARGB words in native
+    /// byte order, as used by `wl_shm` buffers and 32-bit X11 images.
+    fn argb_premultiplied(&self) -> Vec<u8>;
+}
+
+impl IconExt for Icon {
+    #[inline]
+    fn from_rgba(width: u32, height: u32, rgba: Vec<u8>) -> Option<Self> {
+        sys::Icon::from_rgba(width, height, rgba).map(Icon)
+    }
+
+    #[inline]
+    fn width(&self) -> u32 {
+        self.0.width
+    }
+
+    #[inline]
+    fn height(&self) -> u32 {
+        self.0.height
+    }
+
+    #[inline]
+    fn rgba(&self) -> &[u8] {
+        &self.0.rgba
+    }
+
+    #[inline]
+    fn argb_premultiplied(&self) -> Vec<u8> {
+        self.0.argb_premultiplied()
+    }
+}
+
+/// Returns whether the current thread is the main thread of the process.
+#[inline]
+pub fn is_main_thread() -> bool {
+    unsafe { libc::syscall(libc::SYS_gettid) == libc::getpid() as libc::c_long }
+}
+
+/// Returns the local path of a `file:` URI, decoding percent-escaped bytes,
+/// or `None` if `uri` has another scheme or names another host.
+pub fn path_from_file_uri(uri: &[u8]) -> Option<PathBuf> {
+    let rest = uri.strip_prefix(b"file:")?;
+    // The authority is either empty or the local host.
+    let path = match rest.strip_prefix(b"//") {
+        Some(rest) => {
+            let start = rest.iter().position(|&b| b == b'/')?;
+            if !matches!(&rest[..start], b"" | b"localhost") {
+                return None;
+            }
+            &rest[start..]
+        },
+        None => rest,
+    };
+    let mut bytes = Vec::with_capacity(path.len());
+    let mut i = 0;
+    while i < path.len() {
+        let hex = |b: u8| (b as char).to_digit(16);
+        match (path[i], path.get(i + 1), path.get(i + 2)) {
+            (b'%', Some(&h), Some(&l)) if hex(h).is_some() && hex(l).is_some() => {
+                bytes.push((hex(h).unwrap() * 16 + hex(l).unwrap()) as u8);
+                i += 3;
+            },
+            (b, _, _) => {
+                bytes.push(b);
+                i += 1;
+            },
+        }
+    }
+    Some(PathBuf::from(OsStr::from_bytes(&bytes)))
+}
+
+/// A display server protocol that ZedUI connects to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum DisplayBackend {
+    /// The X Window System, including XWayland.
+    X11,
+    /// A Wayland compositor.
+    Wayland,
+}
+
+impl DisplayBackend {
+    /// Returns the backend to connect to, based on the environment.
+    ///
+    /// Setting `ZUI_BACKEND` to `x11` or `wayland` selects that backend.
+    /// Otherwise, Wayland is preferred if `WAYLAND_DISPLAY` or
+    /// `WAYLAND_SOCKET` is set, then X11 if `DISPLAY` is set. Returns `None`
+    /// if there is no display to connect to.
+    pub fn current() -> Option<Self> {
+        let var = |name| env::var_os(name).is_some_and(|v| !v.is_empty());
+        Self::select(
+            env::var("ZUI_BACKEND").ok().as_deref(),
+            var("WAYLAND_DISPLAY") || var("WAYLAND_SOCKET"),
+            var("DISPLAY"),
+        )
+    }
+
+    fn select(requested: Option<&str>, wayland: bool, x11: bool) -> Option<Self> {
+        match requested.map(str::to_ascii_lowercase).as_deref() {
+            Some("x11") => Some(DisplayBackend::X11),
+            Some("wayland") => Some(DisplayBackend::Wayland),
+            _ if wayland => Some(DisplayBackend::Wayland),
+            _ if x11 => Some(DisplayBackend::X11),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn file_uris() {
+        let path = |uri: &str| path_from_file_uri(uri.as_bytes());
+        assert_eq!(path("file:///home/user/a%20b.txt"), Some(PathBuf::from("/home/user/a b.txt")));
+        assert_eq!(path("file://localhost/tmp/%C3%A9"), Some(PathBuf::from("/tmp/é")));
+        assert_eq!(path("file:/etc/hosts"), Some(PathBuf::from("/etc/hosts")));
+        assert_eq!(path("file:///100%"), Some(PathBuf::from("/100%")));
+        assert_eq!(path("file://example.com/etc/hosts"), None);
+        assert_eq!(path("https://example.com/file"), None);
+    }
+
+    #[test]
+    fn backend_selection() {
+        use DisplayBackend::*;
+
+        assert_eq!(DisplayBackend::select(None, true, true), Some(Wayland));
+        assert_eq!(DisplayBackend::select(None, false, true), Some(X11));
+        assert_eq!(DisplayBackend::select(None, false, false), None);
+        assert_eq!(DisplayBackend::select(Some("X11"), true, true), Some(X11));
+        assert_eq!(DisplayBackend::select(Some("wayland"), false, true), Some(Wayland));
+        assert_eq!(DisplayBackend::select(Some("other"), false, true), Some(X11));
+    }
+}
@@ -0,0 +1,47 @@
+//! A private message bus for testing backends that use the session bus.
+
+use std::{
+    io::{BufRead, BufReader},
+    process::{Child, Command, Stdio},
+};
+
+/// A `dbus-daemon` that is killed when dropped.
+pub struct TestBus {
+    daemon: Child,
+    address: String,
+}
+
+impl Drop for TestBus {
+    fn drop(&mut self) {
+        let _ = self.daemon.kill();
+        let _ = self.daemon.wait();
+    }
+}
+
+impl TestBus {
+    /// Starts a bus with the session configuration, returning `None` if
+    /// `dbus-daemon` could not be run.
+    pub fn start() -> Option<Self> {
+        let mut daemon = Command::new("dbus-daemon")
+            .args(["--session", "--nofork", "--nopidfile", "--print-address"])
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .ok()?;
+        let mut address = String::new();
+        let read = daemon.stdout.take().map(|stdout| BufReader::new(stdout).read_line(&mut address));
+        let address = address.trim().to_owned();
+        let bus = TestBus { daemon, address };
+        match read {
+            Some(Ok(_)) if !bus.address.is_empty() => Some(bus),
+            _ => None,
+        }
+    }
+
+    /// Returns the address that clients connect to.
+    #[inline]
+    pub fn address(&self) -> &str {
+        &self.address
+    }
+}
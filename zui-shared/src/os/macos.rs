@@ -5,7 +5,36 @@ use objc::{
     rc::StrongPtr,
     runtime,
 };
-use crate::{ZedString, sys};
+use crate::{mime, Icon, ZedString, sys};
+
+/// Maps MIME types to the uniform type identifiers used by `NSPasteboard`.
+const PASTEBOARD_TYPES: &[(&str, &str)] = &[
+    (mime::TEXT_PLAIN, "public.utf8-plain-text"),
+    (mime::TEXT_HTML, "public.html"),
+    (mime::TEXT_RTF, "public.rtf"),
+    (mime::IMAGE_PNG, "public.png"),
+    (mime::IMAGE_TIFF, "public.tiff"),
+];
+
+/// Returns the uniform type identifier that data of type `mime` is stored
+/// under on a pasteboard, or `mime` itself if there is none.
+///
+/// Parameters of `mime`, such as `charset`, are ignored.
+pub fn pasteboard_type(mime: &str) -> &str {
+    PASTEBOARD_TYPES.iter()
+        .find(|(m, _)| mime::same_type(m, mime))
+        .map(|(_, ty)| *ty)
+        .unwrap_or(mime)
+}
+
+/// Returns the MIME type of data stored under `pasteboard_type`, or
+/// `pasteboard_type` itself if there is none.
+pub fn mime_type(pasteboard_type: &str) -> &str {
+    PASTEBOARD_TYPES.iter()
+        .find(|(_, ty)| *ty == pasteboard_type)
+        .map(|(m, _)| *m)
+        .unwrap_or(pasteboard_type)
+}
 
 /// macOS-specific extensions for [`ZedString`](../../struct.ZedString.html).
 pub trait ZedStringExt {
@@ -56,6 +85,29 @@ impl ZedStringExt for ZedString {
     }
 }
 
+/// macOS-specific extensions for [`Icon`](../../struct.Icon.html).
+pub trait IconExt {
+    /// Creates an instance wrapped around an
+    /// [`NSImage`](https://developer.apple.com/documentation/appkit/nsimage).
+    unsafe fn from_ns_image(ns_image: StrongPtr) -> Self;
+
+    /// Returns a handle to the underlying `NSImage` without changing ownership
+    /// semantics.
+    fn as_ns_image(&self) -> &StrongPtr;
+}
+
+impl IconExt for Icon {
+    #[inline]
+    unsafe fn from_ns_image(ns_image: StrongPtr) -> Self {
+        Icon(sys::Icon { ns_image })
+    }
+
+    #[inline]
+    fn as_ns_image(&self) -> &StrongPtr {
+        &self.0.ns_image
+    }
+}
+
 /// An Objective-C autorelease pool.
 #[derive(Debug, PartialEq, Eq)]
 pub struct AutoreleasePool(*mut c_void);
@@ -90,3 +142,24 @@ impl AutoreleasePool {
         value
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pasteboard_types() {
+        assert_eq!(pasteboard_type(mime::TEXT_PLAIN), "public.utf8-plain-text");
+        assert_eq!(pasteboard_type("text/plain"), "public.utf8-plain-text");
+        assert_eq!(pasteboard_type("text/html;charset=utf-8"), "public.html");
+        assert_eq!(pasteboard_type("IMAGE/PNG"), "public.png");
+        assert_eq!(pasteboard_type("application/x-custom"), "application/x-custom");
+    }
+
+    #[test]
+    fn mime_types() {
+        assert_eq!(mime_type("public.utf8-plain-text"), mime::TEXT_PLAIN);
+        assert_eq!(mime_type("public.tiff"), mime::IMAGE_TIFF);
+        assert_eq!(mime_type("com.example.custom"), "com.example.custom");
+    }
+}
@@ -3,6 +3,8 @@
 cfg_if::cfg_if! {
     if #[cfg(target_os = "macos")] {
         pub mod macos;
+    } else if #[cfg(target_os = "linux")] {
+        pub mod linux;
     } else {
         compile_error!("`zui-shared` does not compile for this platform");
     }
@@ -0,0 +1,126 @@
+use std::{
+    fmt,
+    io::Cursor,
+    sync::Arc,
+};
+
+#[derive(Clone, Debug)]
+pub struct ZedString {
+    pub string: String,
+}
+
+impl ZedString {
+    #[inline]
+    pub fn from_utf8(s: &str) -> Self {
+        ZedString { string: s.into() }
+    }
+}
+
+#[derive(Clone)]
+pub struct Icon {
+    pub width: u32,
+    pub height: u32,
+    /// Non-premultiplied RGBA pixels, row by row.
+    pub rgba: Arc<[u8]>,
+}
+
+impl fmt::Debug for Icon {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Icon")
+            .field("width", &self.width)
+            .field("height", &self.height)
+            .finish()
+    }
+}
+
+impl Icon {
+    pub fn from_bytes(data: &[u8]) -> Option<Self> {
+        let mut decoder = png::Decoder::new(Cursor::new(data));
+        decoder.set_transformations(png::Transformations::normalize_to_color8());
+        let mut reader = decoder.read_info().ok()?;
+        let mut buffer = vec![0; reader.output_buffer_size()];
+        let frame = reader.next_frame(&mut buffer).ok()?;
+        let pixels = &buffer[..frame.buffer_size()];
+
+        let rgba = match frame.color_type {
+            png::ColorType::Rgba => pixels.to_vec(),
+            png::ColorType::Rgb => pixels.chunks_exact(3)
+                .flat_map(|p| [p[0], p[1], p[2], 0xff])
+                .collect(),
+            png::ColorType::GrayscaleAlpha => pixels.chunks_exact(2)
+                .flat_map(|p| [p[0], p[0], p[0], p[1]])
+                .collect(),
+            png::ColorType::Grayscale => pixels.iter()
+                .flat_map(|&p| [p, p, p, 0xff])
+                .collect(),
+            png::ColorType::Indexed => return None,
+        };
+        Self::from_rgba(frame.width, frame.height, rgba)
+    }
+
+    pub fn from_rgba(width: u32, height: u32, rgba: Vec<u8>) -> Option<Self> {
+        let len = (width as usize).checked_mul(height as usize)?.checked_mul(4)?;
+        if width == 0 || height == 0 || rgba.len() != len {
+            return None;
+        }
+        Some(Icon { width, height, rgba: rgba.into() })
+    }
+
+    /// Returns the pixels as premultiplied ARGB words in native byte order,
+    /// the layout of `wl_shm` buffers and 32-bit X11 images.
+    pub fn argb_premultiplied(&self) -> Vec<u8> {
+        let mut pixels = Vec::with_capacity(self.rgba.len());
+        for p in self.rgba.chunks_exact(4) {
+            let alpha = p[3] as u32;
+            let channel = |c: u8| ((c as u32 * alpha + 127) / 255) as u8;
+            let argb = u32::from_be_bytes([p[3], channel(p[0]), channel(p[1]), channel(p[2])]);
+            pixels.extend_from_slice(&argb.to_ne_bytes());
+        }
+        pixels
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode(width: u32, height: u32, color: png::ColorType, data: &[u8]) -> Vec<u8> {
+        let mut png = Vec::new();
+        let mut encoder = png::Encoder::new(&mut png, width, height);
+        encoder.set_color(color);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header().unwrap();
+        writer.write_image_data(data).unwrap();
+        writer.finish().unwrap();
+        png
+    }
+
+    #[test]
+    fn decode_png() {
+        let rgba = [255, 0, 0, 255, 0, 0, 255, 128];
+        let icon = Icon::from_bytes(&encode(2, 1, png::ColorType::Rgba, &rgba)).unwrap();
+        assert_eq!((icon.width, icon.height), (2, 1));
+        assert_eq!(&icon.rgba[..], &rgba[..]);
+
+        let icon = Icon::from_bytes(&encode(1, 2, png::ColorType::Rgb, &[1, 2, 3, 4, 5, 6])).unwrap();
+        assert_eq!(&icon.rgba[..], &[1, 2, 3, 255, 4, 5, 6, 255][..]);
+
+        let icon = Icon::from_bytes(&encode(1, 1, png::ColorType::GrayscaleAlpha, &[9, 7])).unwrap();
+        assert_eq!(&icon.rgba[..], &[9, 9, 9, 7][..]);
+
+        assert!(Icon::from_bytes(b"not a png").is_none());
+    }
+
+    #[test]
+    fn premultiplied_argb() {
+        let icon = Icon::from_rgba(2, 1, vec![255, 128, 0, 255, 255, 255, 255, 128]).unwrap();
+        let pixels: Vec<u32> = icon.argb_premultiplied()
+            .chunks_exact(4)
+            .map(|p| u32::from_ne_bytes([p[0], p[1], p[2], p[3]]))
+            .collect();
+        assert_eq!(pixels, [0xff_ff_80_00, 0x80_80_80_80]);
+
+        assert!(Icon::from_rgba(2, 2, vec![0; 4]).is_none());
+        assert!(Icon::from_rgba(0, 0, Vec::new()).is_none());
+    }
+}
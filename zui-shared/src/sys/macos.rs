@@ -1,11 +1,11 @@
 
 use std::{
-    ffi::CStr,
+    ffi::{c_void, CStr},
     fmt,
 };
 use cocoa::{
-    base::nil,
-    foundation::NSString,
+    base::{id, nil},
+    foundation::{NSString, NSUInteger},
 };
 use objc::rc::StrongPtr;
 
@@ -35,3 +35,35 @@ impl ZedString {
         std::str::from_utf8_unchecked(c_str.to_bytes())
     }
 }
+
+#[derive(Clone)]
+pub struct Icon {
+    pub ns_image: StrongPtr,
+}
+
+impl fmt::Debug for Icon {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Icon")
+            .field("ns_image", &*self.ns_image) // No `Debug` for `StrongPtr`
+            .finish()
+    }
+}
+
+impl Icon {
+    pub fn from_bytes(data: &[u8]) -> Option<Self> {
+        unsafe {
+            let ns_data: id = msg_send![
+                class!(NSData),
+                dataWithBytes:data.as_ptr() as *const c_void
+                       length:data.len() as NSUInteger
+            ];
+            let ns_image: id = msg_send![class!(NSImage), alloc];
+            let ns_image: id = msg_send![ns_image, initWithData:ns_data];
+            if ns_image == nil {
+                None
+            } else {
+                Some(Icon { ns_image: StrongPtr::new(ns_image) })
+            }
+        }
+    }
+}
@@ -0,0 +1,167 @@
+//! ZedUI system tray icons.
+
+#![deny(missing_docs)]
+#![doc(html_logo_url = "https://zed-ui.dev/static/logos/zed-ui.svg")]
+
+#[cfg(target_os = "macos")]
+#[macro_use]
+extern crate objc;
+
+extern crate zui_menu as menu;
+extern crate zui_shared as shared;
+
+use std::{
+    fmt,
+    marker::PhantomData,
+};
+use menu::Menu;
+use shared::{Icon, ZedString};
+
+mod sys;
+pub mod os;
+
+/// A handle to an icon displayed in the system tray, which is the status
+/// area of the menu bar on macOS.
+///
+/// `TrayIcon` implements the [`Clone`] trait with reference counting
+/// semantics. The icon is removed from the tray once every handle is dropped.
+///
+/// [`Clone`]: https://doc.rust-lang.org/std/clone/trait.Clone.html
+#[derive(Clone)]
+pub struct TrayIcon {
+    sys: sys::TrayIcon,
+    // !Send + !Sync
+    _marker: PhantomData<*mut ()>,
+}
+
+#[doc(hidden)]
+impl From<sys::TrayIcon> for TrayIcon {
+    #[inline]
+    fn from(sys: sys::TrayIcon) -> Self {
+        TrayIcon { sys, _marker: PhantomData }
+    }
+}
+
+impl fmt::Debug for TrayIcon {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.sys.fmt(f)
+    }
+}
+
+impl TrayIcon {
+    /// Creates a builder suitable for constructing a new `TrayIcon` instance.
+    #[inline]
+    pub fn builder() -> TrayIconBuilder {
+        Default::default()
+    }
+
+    /// Sets the displayed icon.
+    #[inline]
+    pub fn set_icon(&self, icon: &Icon) {
+        self.sys.set_icon(icon);
+    }
+
+    /// Sets the text displayed when hovering over the icon.
+    #[inline]
+    pub fn set_tooltip<S: Into<ZedString>>(&self, tooltip: S) {
+        self.sys.set_tooltip(Some(tooltip.into()));
+    }
+
+    /// Removes the text displayed when hovering over the icon.
+    #[inline]
+    pub fn remove_tooltip(&self) {
+        self.sys.set_tooltip(None);
+    }
+
+    /// Sets the menu opened by clicking the icon, or removes it if `None`.
+    ///
+    /// While a menu is set, clicks open it instead of emitting
+    /// [`TrayEvent::Click`](enum.TrayEvent.html#variant.Click).
+    #[inline]
+    pub fn set_menu(&self, menu: Option<&Menu>) {
+        self.sys.set_menu(menu);
+    }
+
+    /// Sets the function called with each event received by `self`,
+    /// replacing any previous one.
+    #[inline]
+    pub fn set_event_handler<F>(&self, handler: F)
+    where
+        F: FnMut(&TrayIcon, TrayEvent) + 'static,
+    {
+        self.sys.set_event_handler(Box::new(handler));
+    }
+}
+
+/// An event received by a [`TrayIcon`](struct.TrayIcon.html).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TrayEvent {
+    /// The icon was clicked.
+    Click {
+        /// The mouse button used.
+        button: TrayButton,
+    },
+}
+
+/// A mouse button used to click a [`TrayIcon`](struct.TrayIcon.html).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TrayButton {
+    /// The primary button, which activates the application.
+    Left,
+    /// The secondary button.
+    Right,
+    /// The middle button.
+    Middle,
+}
+
+/// A type for configuring how a `TrayIcon` instance should be constructed.
+#[derive(Default)]
+pub struct TrayIconBuilder {
+    icon: Option<Icon>,
+    tooltip: Option<ZedString>,
+    menu: Option<Menu>,
+}
+
+impl TrayIconBuilder {
+    /// Creates a builder suitable for constructing a new `TrayIcon` instance.
+    #[inline]
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Sets the displayed icon.
+    #[inline]
+    pub fn icon(&mut self, icon: Icon) -> &mut Self {
+        self.icon = Some(icon);
+        self
+    }
+
+    /// Sets the text displayed when hovering over the icon.
+    #[inline]
+    pub fn tooltip<S: Into<ZedString>>(&mut self, tooltip: S) -> &mut Self {
+        self.tooltip = Some(tooltip.into());
+        self
+    }
+
+    /// Sets the menu opened by clicking the icon.
+    #[inline]
+    pub fn menu(&mut self, menu: Menu) -> &mut Self {
+        self.menu = Some(menu);
+        self
+    }
+
+    /// Creates a new instance, returning an error upon failure.
+    #[allow(clippy::result_unit_err)]
+    pub fn build(&self) -> Result<TrayIcon, ()> {
+        let tray_icon = TrayIcon::from(sys::TrayIcon::new()?);
+        if let Some(icon) = &self.icon {
+            tray_icon.set_icon(icon);
+        }
+        if let Some(tooltip) = &self.tooltip {
+            tray_icon.set_tooltip(tooltip.clone());
+        }
+        tray_icon.set_menu(self.menu.as_ref());
+        Ok(tray_icon)
+    }
+}
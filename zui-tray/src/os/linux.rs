@@ -0,0 +1,22 @@
+//! Linux-specific extensions.
+//!
+//! Tray icons are exported on the session bus through the
+//! [StatusNotifierItem](https://www.freedesktop.org/wiki/Specifications/StatusNotifierItem/)
+//! interface and registered with the `org.kde.StatusNotifierWatcher`
+//! service, so building one fails if no tray host is running.
+
+use crate::TrayIcon;
+
+/// Linux-specific extensions for [`TrayIcon`](../../struct.TrayIcon.html).
+pub trait TrayIconExt {
+    /// Returns the unique bus name of the connection exporting `self`, which
+    /// is the service registered with the watcher.
+    fn bus_name(&self) -> Option<String>;
+}
+
+impl TrayIconExt for TrayIcon {
+    #[inline]
+    fn bus_name(&self) -> Option<String> {
+        self.sys.bus_name()
+    }
+}
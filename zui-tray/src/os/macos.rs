@@ -0,0 +1,19 @@
+//! macOS-specific extensions.
+
+use cocoa::base::id;
+use crate::TrayIcon;
+
+/// macOS-specific extensions for [`TrayIcon`](../../struct.TrayIcon.html).
+pub trait TrayIconExt {
+    /// Returns the
+    /// [`NSStatusItem`](https://developer.apple.com/documentation/appkit/nsstatusitem)
+    /// handle for `self`.
+    fn ns_status_item(&self) -> id;
+}
+
+impl TrayIconExt for TrayIcon {
+    #[inline]
+    fn ns_status_item(&self) -> id {
+        self.sys.ns_status_item()
+    }
+}
@@ -0,0 +1,428 @@
+//! Tray icons exported over D-Bus through the `org.kde.StatusNotifierItem`
+//! interface, which tray hosts find through the
+//! `org.kde.StatusNotifierWatcher` service.
+//!
+//! Each icon has its own bus connection, whose unique name is registered
+//! with the watcher. Its menu is exported on that connection through
+//! `com.canonical.dbusmenu`. Clicks are sent back to the thread that created
+//! the icon and emitted while its main loop runs.
+
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    convert::TryFrom,
+    env,
+    fmt,
+    rc::{Rc, Weak},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+        Mutex,
+    },
+};
+use menu::{
+    os::linux::{self as menu_linux, ExportedMenu},
+    Menu,
+};
+use shared::{
+    os::linux::{main_loop, IconExt, ZedStringExt},
+    Icon,
+    ZedString,
+};
+use zbus::{
+    blocking::Connection,
+    interface,
+    zvariant::{ObjectPath, OwnedObjectPath, Value},
+};
+use crate::{TrayButton, TrayEvent};
+
+pub type EventHandler = Box<dyn FnMut(&crate::TrayIcon, TrayEvent)>;
+
+const ITEM_PATH: &str = "/StatusNotifierItem";
+const MENU_PATH: &str = "/StatusNotifierItem/Menu";
+const INTERFACE: &str = "org.kde.StatusNotifierItem";
+
+const WATCHER_NAME: &str = "org.kde.StatusNotifierWatcher";
+const WATCHER_PATH: &str = "/StatusNotifierWatcher";
+
+/// An icon as sent over D-Bus: its width, height and ARGB32 pixels in network
+/// byte order.
+type Pixmap = (i32, i32, Vec<u8>);
+
+thread_local! {
+    /// The icons created on this thread, by identifier.
+    static ICONS: RefCell<HashMap<u64, Weak<Inner>>> = RefCell::new(HashMap::new());
+}
+
+#[derive(Clone)]
+pub struct TrayIcon {
+    inner: Rc<Inner>,
+}
+
+struct Inner {
+    id: u64,
+    connection: Connection,
+    properties: Arc<Mutex<Properties>>,
+    menu: RefCell<Option<ExportedMenu>>,
+    event_handler: RefCell<Option<EventHandler>>,
+}
+
+impl Drop for Inner {
+    fn drop(&mut self) {
+        // Watchers remove the icon once the connection closes.
+        let _ = self.connection.object_server().remove::<StatusNotifierItem, _>(ITEM_PATH);
+        let _ = ICONS.try_with(|icons| icons.borrow_mut().remove(&self.id));
+    }
+}
+
+impl fmt::Debug for TrayIcon {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("TrayIcon")
+            .field("bus_name", &self.bus_name())
+            .finish()
+    }
+}
+
+/// The properties of an icon that hosts read.
+#[derive(Default)]
+struct Properties {
+    id: String,
+    icon: Vec<Pixmap>,
+    tooltip: String,
+    has_menu: bool,
+}
+
+impl TrayIcon {
+    pub fn new() -> Result<Self, ()> {
+        let connection = Connection::session().map_err(drop)?;
+        Self::with_connection(connection)
+    }
+
+    /// Exports a new icon on `connection` and registers it with the watcher.
+    fn with_connection(connection: Connection) -> Result<Self, ()> {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+        let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+
+        // Hosts show the identifier when the icon has no tooltip.
+        let name = env::current_exe().ok()
+            .and_then(|path| Some(path.file_stem()?.to_string_lossy().into_owned()))
+            .unwrap_or_else(|| "zui".to_owned());
+        let properties = Arc::new(Mutex::new(Properties { id: name, ..Default::default() }));
+        let item = StatusNotifierItem {
+            id,
+            properties: properties.clone(),
+            sender: main_loop::sender(),
+        };
+        if !connection.object_server().at(ITEM_PATH, item).map_err(drop)? {
+            return Err(());
+        }
+
+        let inner = Rc::new(Inner {
+            id,
+            connection,
+            properties,
+            menu: RefCell::new(None),
+            event_handler: RefCell::new(None),
+        });
+        ICONS.with(|icons| icons.borrow_mut().insert(id, Rc::downgrade(&inner)));
+
+        // Without a watcher, there is no tray to show the icon in.
+        let service = inner.connection.unique_name().ok_or(())?.to_string();
+        inner.connection.call_method(
+            Some(WATCHER_NAME),
+            WATCHER_PATH,
+            Some(WATCHER_NAME),
+            "RegisterStatusNotifierItem",
+            &service,
+        ).map_err(drop)?;
+        Ok(TrayIcon { inner })
+    }
+
+    pub fn set_icon(&self, icon: &Icon) {
+        // Hosts expect non-premultiplied ARGB in network byte order.
+        let argb = icon.rgba()
+            .chunks_exact(4)
+            .flat_map(|rgba| [rgba[3], rgba[0], rgba[1], rgba[2]])
+            .collect();
+        let pixmap = (icon.width() as i32, icon.height() as i32, argb);
+        self.inner.properties.lock().unwrap().icon = vec![pixmap];
+        self.emit_signal("NewIcon");
+    }
+
+    pub fn set_tooltip(&self, tooltip: Option<ZedString>) {
+        let tooltip = tooltip.map_or_else(String::new, ZedString::into_string);
+        self.inner.properties.lock().unwrap().tooltip = tooltip;
+        self.emit_signal("NewToolTip");
+    }
+
+    pub fn set_menu(&self, menu: Option<&Menu>) {
+        let mut exported = self.inner.menu.borrow_mut();
+        match (menu, &*exported) {
+            (Some(menu), Some(current)) => current.update(menu),
+            (Some(menu), None) => *exported = menu_linux::export_on(&self.inner.connection, MENU_PATH, menu).ok(),
+            (None, _) => *exported = None,
+        }
+        let has_menu = exported.is_some();
+        drop(exported);
+
+        let changed = std::mem::replace(&mut self.inner.properties.lock().unwrap().has_menu, has_menu) != has_menu;
+        if changed {
+            let _ = self.inner.connection.emit_signal(
+                None::<&str>,
+                ITEM_PATH,
+                "org.freedesktop.DBus.Properties",
+                "PropertiesChanged",
+                &(INTERFACE, HashMap::<&str, Value>::new(), vec!["ItemIsMenu", "Menu"]),
+            );
+        }
+    }
+
+    #[inline]
+    pub fn bus_name(&self) -> Option<String> {
+        self.inner.connection.unique_name().map(|name| name.to_string())
+    }
+
+    #[inline]
+    pub fn set_event_handler(&self, handler: EventHandler) {
+        *self.inner.event_handler.borrow_mut() = Some(handler);
+    }
+
+    fn emit_signal(&self, name: &str) {
+        let _ = self.inner.connection.emit_signal(None::<&str>, ITEM_PATH, INTERFACE, name, &());
+    }
+}
+
+/// Passes `event` to the event handler of the icon `id`, if it still exists.
+fn emit(id: u64, event: TrayEvent) {
+    let inner = ICONS.with(|icons| icons.borrow().get(&id).and_then(Weak::upgrade));
+    let inner = match inner {
+        Some(inner) => inner,
+        None => return,
+    };
+    // The handler is taken out for the duration of the call so that it may
+    // replace itself.
+    let handler = inner.event_handler.borrow_mut().take();
+    if let Some(mut handler) = handler {
+        let tray_icon = crate::TrayIcon::from(TrayIcon { inner: inner.clone() });
+        handler(&tray_icon, event);
+
+        let mut slot = inner.event_handler.borrow_mut();
+        if slot.is_none() {
+            *slot = Some(handler);
+        }
+    }
+}
+
+struct StatusNotifierItem {
+    id: u64,
+    properties: Arc<Mutex<Properties>>,
+    /// Runs clicks on the thread that created the icon.
+    sender: main_loop::Sender,
+}
+
+impl StatusNotifierItem {
+    /// Emits a click unless the icon has a menu, which hosts open instead.
+    fn click(&self, button: TrayButton) {
+        if self.properties.lock().unwrap().has_menu {
+            return;
+        }
+        let id = self.id;
+        let _ = self.sender.send(move || emit(id, TrayEvent::Click { button }));
+    }
+}
+
+#[interface(name = "org.kde.StatusNotifierItem")]
+impl StatusNotifierItem {
+    fn activate(&self, _x: i32, _y: i32) {
+        self.click(TrayButton::Left);
+    }
+
+    fn secondary_activate(&self, _x: i32, _y: i32) {
+        self.click(TrayButton::Middle);
+    }
+
+    fn context_menu(&self, _x: i32, _y: i32) {
+        self.click(TrayButton::Right);
+    }
+
+    fn scroll(&self, _delta: i32, _orientation: String) {}
+
+    #[zbus(property)]
+    fn category(&self) -> &str {
+        "ApplicationStatus"
+    }
+
+    #[zbus(property)]
+    fn id(&self) -> String {
+        self.properties.lock().unwrap().id.clone()
+    }
+
+    #[zbus(property)]
+    fn title(&self) -> String {
+        self.properties.lock().unwrap().id.clone()
+    }
+
+    #[zbus(property)]
+    fn status(&self) -> &str {
+        "Active"
+    }
+
+    #[zbus(property)]
+    fn window_id(&self) -> i32 {
+        0
+    }
+
+    #[zbus(property)]
+    fn icon_name(&self) -> &str {
+        ""
+    }
+
+    #[zbus(property)]
+    fn icon_pixmap(&self) -> Vec<Pixmap> {
+        self.properties.lock().unwrap().icon.clone()
+    }
+
+    #[zbus(property)]
+    fn overlay_icon_name(&self) -> &str {
+        ""
+    }
+
+    #[zbus(property)]
+    fn attention_icon_name(&self) -> &str {
+        ""
+    }
+
+    /// The icon name, icon, title and description of the tooltip.
+    #[zbus(property)]
+    fn tool_tip(&self) -> (String, Vec<Pixmap>, String, String) {
+        let tooltip = self.properties.lock().unwrap().tooltip.clone();
+        (String::new(), Vec::new(), tooltip, String::new())
+    }
+
+    #[zbus(property)]
+    fn item_is_menu(&self) -> bool {
+        self.properties.lock().unwrap().has_menu
+    }
+
+    /// The path of the menu, or the root path if there is none.
+    #[zbus(property)]
+    fn menu(&self) -> OwnedObjectPath {
+        let path = if self.properties.lock().unwrap().has_menu { MENU_PATH } else { "/" };
+        ObjectPath::try_from(path).unwrap().into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+    use shared::os::linux::test_bus::TestBus;
+    use zbus::{
+        blocking::{connection, fdo::PropertiesProxy, MessageIterator},
+        message,
+        names::InterfaceName,
+        zvariant::OwnedValue,
+        MatchRule,
+    };
+
+    /// A watcher that records the services registered with it.
+    struct Watcher {
+        items: Arc<Mutex<Vec<String>>>,
+    }
+
+    #[interface(name = "org.kde.StatusNotifierWatcher")]
+    impl Watcher {
+        fn register_status_notifier_item(&self, service: String) {
+            self.items.lock().unwrap().push(service);
+        }
+    }
+
+    fn connect(bus: &TestBus) -> Connection {
+        connection::Builder::address(bus.address()).unwrap().build().unwrap()
+    }
+
+    fn property(client: &Connection, name: &str, property: &str) -> OwnedValue {
+        let proxy = PropertiesProxy::builder(client)
+            .destination(name.to_owned()).unwrap()
+            .path(ITEM_PATH).unwrap()
+            .build()
+            .unwrap();
+        proxy.get(InterfaceName::try_from(INTERFACE).unwrap(), property).unwrap()
+    }
+
+    #[test]
+    fn export_over_dbus() {
+        let bus = TestBus::start().expect("dbus-daemon is not available");
+        let client = connect(&bus);
+
+        // There is no tray without a watcher.
+        assert!(TrayIcon::with_connection(connect(&bus)).is_err());
+
+        let items = Arc::new(Mutex::new(Vec::new()));
+        let watcher = connection::Builder::address(bus.address()).unwrap()
+            .name(WATCHER_NAME).unwrap()
+            .serve_at(WATCHER_PATH, Watcher { items: items.clone() }).unwrap()
+            .build()
+            .unwrap();
+
+        let tray_icon = TrayIcon::with_connection(connect(&bus)).unwrap();
+        let name = tray_icon.bus_name().unwrap();
+        assert_eq!(*items.lock().unwrap(), std::slice::from_ref(&name));
+
+        let mut signals = MessageIterator::for_match_rule(
+            MatchRule::builder()
+                .msg_type(message::Type::Signal)
+                .interface(INTERFACE).unwrap()
+                .build(),
+            &client,
+            None,
+        ).unwrap();
+        let icon = Icon::from_rgba(1, 1, vec![0x10, 0x20, 0x30, 0x40]).unwrap();
+        tray_icon.set_icon(&icon);
+        let signal = signals.next().unwrap().unwrap();
+        assert_eq!(signal.header().member().unwrap().as_str(), "NewIcon");
+        let pixmaps = Vec::<Pixmap>::try_from(property(&client, &name, "IconPixmap")).unwrap();
+        assert_eq!(pixmaps, [(1, 1, vec![0x40, 0x10, 0x20, 0x30])]);
+
+        tray_icon.set_tooltip(Some(ZedString::from_string("Tip".to_owned())));
+        let tooltip = <(String, Vec<Pixmap>, String, String)>::try_from(property(&client, &name, "ToolTip")).unwrap();
+        assert_eq!(tooltip.2, "Tip");
+
+        // Clicks are emitted on this thread.
+        let events = Rc::new(RefCell::new(Vec::new()));
+        tray_icon.set_event_handler(Box::new({
+            let events = events.clone();
+            move |_, event| events.borrow_mut().push(event)
+        }));
+        client.call_method(Some(name.as_str()), ITEM_PATH, Some(INTERFACE), "Activate", &(0i32, 0i32)).unwrap();
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while events.borrow().is_empty() && Instant::now() < deadline {
+            main_loop::run_pending();
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(*events.borrow(), [TrayEvent::Click { button: TrayButton::Left }]);
+
+        // The menu is exported on the same connection.
+        let mut menu = Menu::new();
+        menu.add(menu::MenuItem::new("Quit"));
+        tray_icon.set_menu(Some(&menu));
+        assert!(bool::try_from(property(&client, &name, "ItemIsMenu")).unwrap());
+        let path = OwnedObjectPath::try_from(property(&client, &name, "Menu")).unwrap();
+        assert_eq!(path.as_str(), MENU_PATH);
+        let reply = client.call_method(
+            Some(name.as_str()),
+            MENU_PATH,
+            Some("com.canonical.dbusmenu"),
+            "GetLayout",
+            &(0i32, -1i32, Vec::<String>::new()),
+        );
+        assert!(reply.is_ok());
+
+        tray_icon.set_menu(None);
+        let path = OwnedObjectPath::try_from(property(&client, &name, "Menu")).unwrap();
+        assert_eq!(path.as_str(), "/");
+
+        drop(tray_icon);
+        drop(watcher);
+    }
+}
@@ -0,0 +1,229 @@
+use std::{
+    cell::RefCell,
+    ffi::c_void,
+    fmt,
+    rc::{Rc, Weak},
+    sync::Once,
+};
+use cocoa::{
+    base::{id, nil},
+    foundation::{NSSize, NSUInteger},
+};
+use objc::{
+    declare::ClassDecl,
+    rc::StrongPtr,
+    runtime::{Class, Object, Sel},
+};
+use menu::{
+    os::macos::MenuExt,
+    Menu,
+};
+use shared::{
+    os::macos::{IconExt, ZedStringExt},
+    Icon,
+    ZedString,
+};
+use crate::{TrayButton, TrayEvent};
+
+pub type EventHandler = Box<dyn FnMut(&crate::TrayIcon, TrayEvent)>;
+
+const NS_VARIABLE_STATUS_ITEM_LENGTH: f64 = -1.0;
+
+/// The height of icons in the menu bar, in points.
+const ICON_SIZE: f64 = 18.0;
+
+const NS_EVENT_MASK_LEFT_MOUSE_UP: NSUInteger = 1 << 2;
+const NS_EVENT_MASK_RIGHT_MOUSE_UP: NSUInteger = 1 << 4;
+const NS_EVENT_MASK_OTHER_MOUSE_UP: NSUInteger = 1 << 26;
+
+const NS_EVENT_TYPE_RIGHT_MOUSE_UP: NSUInteger = 4;
+const NS_EVENT_TYPE_OTHER_MOUSE_UP: NSUInteger = 26;
+
+const STATE_IVAR: &str = "zuiState";
+
+#[derive(Clone)]
+pub struct TrayIcon {
+    inner: Rc<Inner>,
+}
+
+struct Inner {
+    ns_status_item: StrongPtr,
+    // The target of the status item's button, which only holds a weak
+    // reference back to `self`.
+    _target: StrongPtr,
+    event_handler: RefCell<Option<EventHandler>>,
+}
+
+impl Drop for Inner {
+    fn drop(&mut self) {
+        unsafe {
+            let button: id = msg_send![*self.ns_status_item, button];
+            msg_send![button, setTarget:nil];
+            let status_bar: id = msg_send![class!(NSStatusBar), systemStatusBar];
+            msg_send![status_bar, removeStatusItem:*self.ns_status_item];
+        }
+    }
+}
+
+impl fmt::Debug for TrayIcon {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("TrayIcon")
+            .field("ns_status_item", &*self.inner.ns_status_item) // No `Debug` for `StrongPtr`
+            .finish()
+    }
+}
+
+impl TrayIcon {
+    pub fn new() -> Result<Self, ()> {
+        unsafe {
+            let status_bar: id = msg_send![class!(NSStatusBar), systemStatusBar];
+            let ns_status_item: id = msg_send![
+                status_bar,
+                statusItemWithLength:NS_VARIABLE_STATUS_ITEM_LENGTH
+            ];
+            if ns_status_item == nil {
+                return Err(());
+            }
+
+            let target: id = msg_send![target_class(), new];
+            let inner = Rc::new(Inner {
+                ns_status_item: StrongPtr::retain(ns_status_item),
+                _target: StrongPtr::new(target),
+                event_handler: RefCell::new(None),
+            });
+            let weak = Box::new(Rc::downgrade(&inner));
+            (*target).set_ivar(STATE_IVAR, Box::into_raw(weak) as *mut c_void);
+
+            let button: id = msg_send![ns_status_item, button];
+            msg_send![button, setTarget:target];
+            msg_send![button, setAction:sel!(zuiTrayClicked:)];
+            let mask = NS_EVENT_MASK_LEFT_MOUSE_UP
+                | NS_EVENT_MASK_RIGHT_MOUSE_UP
+                | NS_EVENT_MASK_OTHER_MOUSE_UP;
+            let _: NSUInteger = msg_send![button, sendActionOn:mask];
+
+            Ok(TrayIcon { inner })
+        }
+    }
+
+    pub fn set_icon(&self, icon: &Icon) {
+        unsafe {
+            // The copy is resized so that the icon's own image is unaffected.
+            let image: id = msg_send![**icon.as_ns_image(), copy];
+            let image = StrongPtr::new(image);
+            let size: NSSize = msg_send![*image, size];
+            if size.height > 0.0 {
+                let width = size.width * ICON_SIZE / size.height;
+                msg_send![*image, setSize:NSSize::new(width, ICON_SIZE)];
+            }
+
+            let button: id = msg_send![*self.inner.ns_status_item, button];
+            msg_send![button, setImage:*image];
+        }
+    }
+
+    pub fn set_tooltip(&self, tooltip: Option<ZedString>) {
+        unsafe {
+            let button: id = msg_send![*self.inner.ns_status_item, button];
+            let tooltip = tooltip.as_ref().map_or(nil, |t| **t.as_ns_string());
+            msg_send![button, setToolTip:tooltip];
+        }
+    }
+
+    pub fn set_menu(&self, menu: Option<&Menu>) {
+        let ns_menu = menu.map(MenuExt::to_ns_menu);
+        let ns_menu = ns_menu.as_ref().map_or(nil, |ns_menu| **ns_menu);
+        unsafe {
+            msg_send![*self.inner.ns_status_item, setMenu:ns_menu];
+        }
+    }
+
+    #[inline]
+    pub fn ns_status_item(&self) -> id {
+        *self.inner.ns_status_item
+    }
+
+    #[inline]
+    pub fn set_event_handler(&self, handler: EventHandler) {
+        *self.inner.event_handler.borrow_mut() = Some(handler);
+    }
+}
+
+impl Inner {
+    /// Passes `event` to the event handler, if any.
+    fn emit(self: Rc<Self>, event: TrayEvent) {
+        // The handler is taken out for the duration of the call so that it
+        // may replace itself.
+        let handler = self.event_handler.borrow_mut().take();
+        if let Some(mut handler) = handler {
+            let tray_icon = crate::TrayIcon::from(TrayIcon { inner: self.clone() });
+            handler(&tray_icon, event);
+
+            let mut slot = self.event_handler.borrow_mut();
+            if slot.is_none() {
+                *slot = Some(handler);
+            }
+        }
+    }
+}
+
+fn target_class() -> &'static Class {
+    static REGISTER: Once = Once::new();
+
+    REGISTER.call_once(|| {
+        let superclass = class!(NSObject);
+        let mut decl = ClassDecl::new("ZUITrayTarget", superclass).unwrap();
+        decl.add_ivar::<*mut c_void>(STATE_IVAR);
+        unsafe {
+            decl.add_method(
+                sel!(zuiTrayClicked:),
+                clicked as extern "C" fn(&Object, Sel, id),
+            );
+            decl.add_method(
+                sel!(dealloc),
+                dealloc as extern "C" fn(&Object, Sel),
+            );
+        }
+        decl.register();
+    });
+
+    class!(ZUITrayTarget)
+}
+
+extern "C" fn clicked(this: &Object, _: Sel, _sender: id) {
+    unsafe {
+        let state: *mut c_void = *this.get_ivar(STATE_IVAR);
+        if state.is_null() {
+            return;
+        }
+        let inner = match (*(state as *const Weak<Inner>)).upgrade() {
+            Some(inner) => inner,
+            None => return,
+        };
+
+        let ns_app: id = msg_send![class!(NSApplication), sharedApplication];
+        let event: id = msg_send![ns_app, currentEvent];
+        let event_type: NSUInteger = if event == nil {
+            0
+        } else {
+            msg_send![event, type]
+        };
+        let button = match event_type {
+            NS_EVENT_TYPE_RIGHT_MOUSE_UP => TrayButton::Right,
+            NS_EVENT_TYPE_OTHER_MOUSE_UP => TrayButton::Middle,
+            _ => TrayButton::Left,
+        };
+
+        inner.emit(TrayEvent::Click { button });
+    }
+}
+
+extern "C" fn dealloc(this: &Object, _: Sel) {
+    unsafe {
+        let state: *mut c_void = *this.get_ivar(STATE_IVAR);
+        if !state.is_null() {
+            drop(Box::from_raw(state as *mut Weak<Inner>));
+        }
+        msg_send![super(this, class!(NSObject)), dealloc];
+    }
+}
@@ -8,7 +8,7 @@
 extern crate objc;
 
 extern crate zui_window as window;
-use window::Window;
+use window::{ViewHandle, Window};
 
 mod sys;
 mod builder;
@@ -56,6 +56,18 @@ impl WebView {
         &self.sys.window
     }
 
+    /// Returns a handle to the native view of `self`, which stays valid for
+    /// as long as `self` does.
+    ///
+    /// This can be used to convert positions within `self` with
+    /// [`Window::convert_position`].
+    ///
+    /// [`Window::convert_position`]: ../zui_window/struct.Window.html#method.convert_position
+    #[inline]
+    pub fn view(&self) -> ViewHandle {
+        self.sys.view()
+    }
+
     /// Sets the content displayed within `self`.
     #[inline]
     pub fn set_content(&self, content: &Content) {
@@ -8,6 +8,7 @@ use crate::{
     WebViewBuildError,
     Window,
 };
+use window::{os::macos::ViewHandleExt, ViewHandle};
 
 #[derive(Clone)]
 pub struct WebView {
@@ -26,6 +27,11 @@ impl WebView {
         msg_send![*self.wk_web_view, loadRequest:req];
     }
 
+    #[inline]
+    pub fn view(&self) -> ViewHandle {
+        unsafe { ViewHandle::from_ns_view(*self.wk_web_view) }
+    }
+
     pub fn set_content(&self, content: &Content) {
         match content.0 {
             ContentInner::Html(html) => unsafe {
@@ -0,0 +1,131 @@
+//! Window settings that can be loaded from configuration files.
+
+use serde::{Deserialize, Serialize};
+use crate::{
+    dpi::{Position, Size},
+    WindowBuilder,
+    WindowKind,
+};
+
+/// The settings of a [`WindowBuilder`](struct.WindowBuilder.html) in a
+/// serializable form.
+///
+/// Every field is optional, and settings that are left unset keep their
+/// value in the builder when applied with
+/// [`WindowBuilder::config`](struct.WindowBuilder.html#method.config).
+/// Settings that refer to other windows, such as the parent, can be set on
+/// the builder afterwards.
+///
+/// This requires the `serde` feature.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WindowConfig {
+    /// The window title.
+    pub title: Option<String>,
+    /// The size of the content area.
+    pub size: Option<Size>,
+    /// The position of the window's top-left corner on the desktop.
+    pub position: Option<Position>,
+    /// Whether the window starts out maximized.
+    pub maximized: Option<bool>,
+    /// Whether the window starts out fullscreen.
+    pub fullscreen: Option<bool>,
+    /// The role of the window.
+    pub kind: Option<WindowKind>,
+    /// Whether the window blocks input to its parent while open.
+    pub modal: Option<bool>,
+    /// Whether the titlebar is hidden.
+    ///
+    /// This only has an effect on macOS, as with
+    /// [`WindowBuilderExt::titlebar_hidden`].
+    ///
+    /// [`WindowBuilderExt::titlebar_hidden`]: os/macos/trait.WindowBuilderExt.html#tymethod.titlebar_hidden
+    pub titlebar_hidden: Option<bool>,
+    /// The name that the window state is saved and restored under, in the
+    /// [current store](state/struct.WindowStateStore.html#method.current).
+    pub restore_state: Option<String>,
+}
+
+impl WindowConfig {
+    /// Creates a builder with the settings of `self`.
+    #[inline]
+    pub fn builder(&self) -> WindowBuilder {
+        let mut builder = WindowBuilder::new();
+        builder.config(self);
+        builder
+    }
+}
+
+impl WindowBuilder {
+    /// Applies the settings of `config`, replacing those set previously.
+    ///
+    /// Settings that `config` leaves unset, such as a `title` of `None`, are
+    /// not changed.
+    pub fn config(&mut self, config: &WindowConfig) -> &mut Self {
+        if let Some(title) = &config.title {
+            self.title(title.as_str());
+        }
+        if let Some(size) = config.size {
+            self.size(size);
+        }
+        if let Some(position) = config.position {
+            self.position(position);
+        }
+        if let Some(name) = &config.restore_state {
+            self.restore_state(name.as_str());
+        }
+        if let Some(maximized) = config.maximized {
+            self.maximized(maximized);
+        }
+        if let Some(fullscreen) = config.fullscreen {
+            self.fullscreen(fullscreen);
+        }
+        if let Some(kind) = config.kind {
+            self.kind(kind);
+        }
+        if let Some(modal) = config.modal {
+            self.modal(modal);
+        }
+        #[cfg(target_os = "macos")]
+        {
+            use crate::os::macos::WindowBuilderExt;
+            if let Some(hidden) = config.titlebar_hidden {
+                self.titlebar_hidden(hidden);
+            }
+        }
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dpi::{LogicalPosition, PhysicalSize};
+
+    #[test]
+    fn round_trips() {
+        let config = WindowConfig {
+            title: Some("Editor".to_owned()),
+            size: Some(PhysicalSize::new(1600.0, 1200.0).into()),
+            position: Some(LogicalPosition::new(40.0, 60.0).into()),
+            maximized: Some(false),
+            kind: Some(WindowKind::Utility),
+            restore_state: Some("main".to_owned()),
+            ..WindowConfig::default()
+        };
+        let json = serde_json::to_string(&config).unwrap();
+        assert_eq!(serde_json::from_str::<WindowConfig>(&json).unwrap(), config);
+    }
+
+    #[test]
+    fn missing_fields_are_unset() {
+        let config: WindowConfig = serde_json::from_str(r#"{
+            "size": { "logical": { "width": 800.0, "height": 600.0 } },
+            "kind": "dialog"
+        }"#).unwrap();
+        assert_eq!(config.size, Some(Size::Logical(crate::dpi::LogicalSize::new(800.0, 600.0))));
+        assert_eq!(config.kind, Some(WindowKind::Dialog));
+        assert_eq!(config.title, None);
+        assert_eq!(config.modal, None);
+    }
+}
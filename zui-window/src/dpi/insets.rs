@@ -0,0 +1,317 @@
+use super::*;
+use super::rect::inset_span;
+
+/// Moves the start of the span at `origin` of `len` back by `start` and its
+/// end forward by `end`, returning the new origin and length.
+#[inline]
+fn outset_span<P: Pixel>(origin: P, len: P, start: P, end: P) -> (P, P) {
+    (origin.saturating_sub(start), len.saturating_add(start.saturating_add(end)))
+}
+
+/// Distances from each edge of a rectangle, represented in physical pixels.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PhysicalInsets<P = f64> {
+    /// The distance from the top edge.
+    pub top: P,
+    /// The distance from the left edge.
+    pub left: P,
+    /// The distance from the bottom edge.
+    pub bottom: P,
+    /// The distance from the right edge.
+    pub right: P,
+}
+
+impl<P> PhysicalInsets<P> {
+    /// Creates new insets from each edge.
+    #[inline]
+    pub const fn new(top: P, left: P, bottom: P, right: P) -> Self {
+        PhysicalInsets { top, left, bottom, right }
+    }
+}
+
+impl<P: Pixel> PhysicalInsets<P> {
+    /// Creates insets of `value` from every edge.
+    #[inline]
+    pub fn uniform(value: P) -> Self {
+        Self::new(value, value, value, value)
+    }
+
+    /// Returns the sum of the left and right insets.
+    ///
+    /// For integer pixel types, this stops at the bounds of the type.
+    #[inline]
+    pub fn horizontal(&self) -> P {
+        self.left.saturating_add(self.right)
+    }
+
+    /// Returns the sum of the top and bottom insets.
+    ///
+    /// For integer pixel types, this stops at the bounds of the type.
+    #[inline]
+    pub fn vertical(&self) -> P {
+        self.top.saturating_add(self.bottom)
+    }
+
+    /// Converts `self` to another pixel type without loss.
+    #[inline]
+    pub fn cast<T: Pixel + From<P>>(self) -> PhysicalInsets<T> {
+        PhysicalInsets::new(
+            self.top.into(),
+            self.left.into(),
+            self.bottom.into(),
+            self.right.into(),
+        )
+    }
+
+    /// Converts `self` to another pixel type exactly, returning `None` if
+    /// any component can't be represented.
+    #[inline]
+    pub fn try_cast<T: Pixel>(self) -> Option<PhysicalInsets<T>> {
+        Some(PhysicalInsets::new(
+            T::checked_from_f64(self.top.to_f64())?,
+            T::checked_from_f64(self.left.to_f64())?,
+            T::checked_from_f64(self.bottom.to_f64())?,
+            T::checked_from_f64(self.right.to_f64())?,
+        ))
+    }
+
+    /// Converts `self` to another pixel type, clamping each component to
+    /// the range of `T`.
+    #[inline]
+    pub fn saturating_cast<T: Pixel>(self) -> PhysicalInsets<T> {
+        PhysicalInsets::new(
+            T::saturating_from_f64(self.top.to_f64()),
+            T::saturating_from_f64(self.left.to_f64()),
+            T::saturating_from_f64(self.bottom.to_f64()),
+            T::saturating_from_f64(self.right.to_f64()),
+        )
+    }
+
+    /// Scales `self` to `LogicalInsets` by `scale_factor`.
+    #[inline]
+    pub fn to_logical_with(&self, scale_factor: ScaleFactor) -> LogicalInsets {
+        let scale = |v: P| v.to_f64() / scale_factor.get();
+        LogicalInsets::new(
+            scale(self.top),
+            scale(self.left),
+            scale(self.bottom),
+            scale(self.right),
+        )
+    }
+}
+
+impl PhysicalInsets {
+    /// Scales `LogicalInsets` to `PhysicalInsets` by `scale_factor`.
+    #[inline]
+    pub fn from_logical_with<P: Pixel>(insets: LogicalInsets<P>, scale_factor: ScaleFactor) -> Self {
+        insets.to_physical_with(scale_factor)
+    }
+}
+
+/// Distances from each edge of a rectangle, represented in logical pixels,
+/// or "points".
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LogicalInsets<P = f64> {
+    /// The distance from the top edge.
+    pub top: P,
+    /// The distance from the left edge.
+    pub left: P,
+    /// The distance from the bottom edge.
+    pub bottom: P,
+    /// The distance from the right edge.
+    pub right: P,
+}
+
+impl<P> LogicalInsets<P> {
+    /// Creates new insets from each edge.
+    #[inline]
+    pub const fn new(top: P, left: P, bottom: P, right: P) -> Self {
+        LogicalInsets { top, left, bottom, right }
+    }
+}
+
+impl<P: Pixel> LogicalInsets<P> {
+    /// Creates insets of `value` from every edge.
+    #[inline]
+    pub fn uniform(value: P) -> Self {
+        Self::new(value, value, value, value)
+    }
+
+    /// Returns the sum of the left and right insets.
+    ///
+    /// For integer pixel types, this stops at the bounds of the type.
+    #[inline]
+    pub fn horizontal(&self) -> P {
+        self.left.saturating_add(self.right)
+    }
+
+    /// Returns the sum of the top and bottom insets.
+    ///
+    /// For integer pixel types, this stops at the bounds of the type.
+    #[inline]
+    pub fn vertical(&self) -> P {
+        self.top.saturating_add(self.bottom)
+    }
+
+    /// Converts `self` to another pixel type without loss.
+    #[inline]
+    pub fn cast<T: Pixel + From<P>>(self) -> LogicalInsets<T> {
+        LogicalInsets::new(
+            self.top.into(),
+            self.left.into(),
+            self.bottom.into(),
+            self.right.into(),
+        )
+    }
+
+    /// Converts `self` to another pixel type exactly, returning `None` if
+    /// any component can't be represented.
+    #[inline]
+    pub fn try_cast<T: Pixel>(self) -> Option<LogicalInsets<T>> {
+        Some(LogicalInsets::new(
+            T::checked_from_f64(self.top.to_f64())?,
+            T::checked_from_f64(self.left.to_f64())?,
+            T::checked_from_f64(self.bottom.to_f64())?,
+            T::checked_from_f64(self.right.to_f64())?,
+        ))
+    }
+
+    /// Converts `self` to another pixel type, clamping each component to
+    /// the range of `T`.
+    #[inline]
+    pub fn saturating_cast<T: Pixel>(self) -> LogicalInsets<T> {
+        LogicalInsets::new(
+            T::saturating_from_f64(self.top.to_f64()),
+            T::saturating_from_f64(self.left.to_f64()),
+            T::saturating_from_f64(self.bottom.to_f64()),
+            T::saturating_from_f64(self.right.to_f64()),
+        )
+    }
+
+    /// Scales `self` to `PhysicalInsets` by `scale_factor`.
+    #[inline]
+    pub fn to_physical_with(&self, scale_factor: ScaleFactor) -> PhysicalInsets {
+        let scale = |v: P| v.to_f64() * scale_factor.get();
+        PhysicalInsets::new(
+            scale(self.top),
+            scale(self.left),
+            scale(self.bottom),
+            scale(self.right),
+        )
+    }
+}
+
+impl LogicalInsets {
+    /// Scales `PhysicalInsets` to `LogicalInsets` by `scale_factor`.
+    #[inline]
+    pub fn from_physical_with<P: Pixel>(insets: PhysicalInsets<P>, scale_factor: ScaleFactor) -> Self {
+        insets.to_logical_with(scale_factor)
+    }
+}
+
+impl<P: Pixel> PhysicalRect<P> {
+    /// Moves each edge inward by the matching inset.
+    ///
+    /// When the insets exceed the size, `self` collapses to where the edges
+    /// meet, in proportion to the insets. For integer pixel types, each
+    /// component stops at the bounds of the type.
+    #[inline]
+    pub fn inset_by(&self, insets: PhysicalInsets<P>) -> Self {
+        let (x, width) = inset_span(self.origin.x, self.size.width, insets.left, insets.right);
+        let (y, height) = inset_span(self.origin.y, self.size.height, insets.top, insets.bottom);
+        Self::new(PhysicalPosition::new(x, y), PhysicalSize::new(width, height))
+    }
+
+    /// Moves each edge outward by the matching inset.
+    ///
+    /// This undoes [`inset_by`](#method.inset_by) unless the size stopped at
+    /// zero. For integer pixel types, each component stops at the bounds of
+    /// the type.
+    #[inline]
+    pub fn outset_by(&self, insets: PhysicalInsets<P>) -> Self {
+        let (x, width) = outset_span(self.origin.x, self.size.width, insets.left, insets.right);
+        let (y, height) = outset_span(self.origin.y, self.size.height, insets.top, insets.bottom);
+        Self::new(PhysicalPosition::new(x, y), PhysicalSize::new(width, height))
+    }
+}
+
+impl<P: Pixel> LogicalRect<P> {
+    /// Moves each edge inward by the matching inset.
+    ///
+    /// When the insets exceed the size, `self` collapses to where the edges
+    /// meet, in proportion to the insets. For integer pixel types, each
+    /// component stops at the bounds of the type.
+    #[inline]
+    pub fn inset_by(&self, insets: LogicalInsets<P>) -> Self {
+        let (x, width) = inset_span(self.origin.x, self.size.width, insets.left, insets.right);
+        let (y, height) = inset_span(self.origin.y, self.size.height, insets.top, insets.bottom);
+        Self::new(LogicalPosition::new(x, y), LogicalSize::new(width, height))
+    }
+
+    /// Moves each edge outward by the matching inset.
+    ///
+    /// This undoes [`inset_by`](#method.inset_by) unless the size stopped at
+    /// zero. For integer pixel types, each component stops at the bounds of
+    /// the type.
+    #[inline]
+    pub fn outset_by(&self, insets: LogicalInsets<P>) -> Self {
+        let (x, width) = outset_span(self.origin.x, self.size.width, insets.left, insets.right);
+        let (y, height) = outset_span(self.origin.y, self.size.height, insets.top, insets.bottom);
+        Self::new(LogicalPosition::new(x, y), LogicalSize::new(width, height))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rect() -> LogicalRect {
+        LogicalRect::new(LogicalPosition::new(10.0, 20.0), LogicalSize::new(100.0, 50.0))
+    }
+
+    #[test]
+    fn inset_and_outset_round_trip() {
+        let insets = LogicalInsets::new(5.0, 1.0, 10.0, 4.0);
+        let inset = rect().inset_by(insets);
+        assert_eq!(inset, LogicalRect::new(LogicalPosition::new(11.0, 25.0), LogicalSize::new(95.0, 35.0)));
+        assert_eq!(inset.outset_by(insets), rect());
+        assert_eq!(insets.horizontal(), 5.0);
+        assert_eq!(insets.vertical(), 15.0);
+    }
+
+    #[test]
+    fn oversized_insets_collapse_in_proportion() {
+        // The left inset is a quarter of the total, so the edges meet a
+        // quarter of the way across.
+        let insets = LogicalInsets::new(0.0, 50.0, 0.0, 150.0);
+        let inset = rect().inset_by(insets);
+        assert_eq!(inset.origin.x, 35.0);
+        assert_eq!(inset.size.width, 0.0);
+        assert_eq!(inset.size.height, 50.0);
+    }
+
+    #[test]
+    fn integer_insets_saturate() {
+        let rect = PhysicalRect::new(PhysicalPosition::new(0u32, 0), PhysicalSize::new(10u32, 10));
+        let collapsed = rect.inset_by(PhysicalInsets::uniform(u32::MAX));
+        assert_eq!(collapsed.size, PhysicalSize::new(0, 0));
+
+        let outset = rect.outset_by(PhysicalInsets::uniform(5));
+        assert_eq!(outset.origin, PhysicalPosition::new(0, 0));
+        assert_eq!(outset.size, PhysicalSize::new(20, 20));
+
+        let rect = PhysicalRect::new(PhysicalPosition::new(i32::MAX - 1, 0), PhysicalSize::new(1i32, 1));
+        assert_eq!(rect.inset_by(PhysicalInsets::new(0, i32::MAX, 0, 0)).origin.x, i32::MAX);
+    }
+
+    #[test]
+    fn scales_insets() {
+        let insets = LogicalInsets::new(28.0, 0.0, 0.5, 1.0);
+        let physical = insets.to_physical_with(ScaleFactor::TWO);
+        assert_eq!(physical, PhysicalInsets::new(56.0, 0.0, 1.0, 2.0));
+        assert_eq!(physical.to_logical_with(ScaleFactor::TWO), insets);
+        assert_eq!(LogicalInsets::uniform(3u32).cast::<f64>(), LogicalInsets::uniform(3.0));
+    }
+}
@@ -0,0 +1,211 @@
+// Conversions to and from the types of other math libraries, each behind a
+// feature of the same name.
+
+#[allow(unused_imports)]
+use super::*;
+
+// Implements `From` in both directions between each dpi type and another
+// library's type, constructing the latter with `$new` and reading it back
+// through fields `$a` and `$b`. Fields are converted with `Into`, so that
+// rectangles can convert their origin and size.
+#[allow(unused_macros)]
+macro_rules! impl_interop {
+    ($($dpi:ident { $x:ident, $y:ident } <=> $other:ty { $a:ident, $b:ident } via $new:path;)+) => {
+        $(
+            impl<P: Pixel> From<$dpi<P>> for $other {
+                #[inline]
+                fn from(value: $dpi<P>) -> Self {
+                    $new(value.$x.into(), value.$y.into())
+                }
+            }
+
+            impl<P: Pixel> From<$other> for $dpi<P> {
+                #[inline]
+                fn from(value: $other) -> Self {
+                    $dpi::new(value.$a.into(), value.$b.into())
+                }
+            }
+        )+
+    };
+}
+
+#[cfg(feature = "mint")]
+impl_interop! {
+    PhysicalPosition { x, y } <=> mint::Point2<P> { x, y } via point2;
+    LogicalPosition { x, y } <=> mint::Point2<P> { x, y } via point2;
+    PhysicalVector { x, y } <=> mint::Vector2<P> { x, y } via vector2;
+    LogicalVector { x, y } <=> mint::Vector2<P> { x, y } via vector2;
+    PhysicalSize { width, height } <=> mint::Vector2<P> { x, y } via vector2;
+    LogicalSize { width, height } <=> mint::Vector2<P> { x, y } via vector2;
+}
+
+#[cfg(feature = "mint")]
+#[inline]
+fn point2<P>(x: P, y: P) -> mint::Point2<P> {
+    mint::Point2 { x, y }
+}
+
+#[cfg(feature = "mint")]
+#[inline]
+fn vector2<P>(x: P, y: P) -> mint::Vector2<P> {
+    mint::Vector2 { x, y }
+}
+
+/// The `euclid` unit of types in physical pixels, such as
+/// [`PhysicalSize`](struct.PhysicalSize.html).
+///
+/// This requires the `euclid` feature.
+#[cfg(feature = "euclid")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PhysicalUnit {}
+
+/// The `euclid` unit of types in logical pixels, such as
+/// [`LogicalSize`](struct.LogicalSize.html).
+///
+/// This requires the `euclid` feature.
+#[cfg(feature = "euclid")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum LogicalUnit {}
+
+#[cfg(feature = "euclid")]
+impl_interop! {
+    PhysicalSize { width, height } <=> euclid::Size2D<P, PhysicalUnit> { width, height } via euclid::Size2D::new;
+    LogicalSize { width, height } <=> euclid::Size2D<P, LogicalUnit> { width, height } via euclid::Size2D::new;
+    PhysicalPosition { x, y } <=> euclid::Point2D<P, PhysicalUnit> { x, y } via euclid::Point2D::new;
+    LogicalPosition { x, y } <=> euclid::Point2D<P, LogicalUnit> { x, y } via euclid::Point2D::new;
+    PhysicalVector { x, y } <=> euclid::Vector2D<P, PhysicalUnit> { x, y } via euclid::Vector2D::new;
+    LogicalVector { x, y } <=> euclid::Vector2D<P, LogicalUnit> { x, y } via euclid::Vector2D::new;
+    PhysicalRect { origin, size } <=> euclid::Rect<P, PhysicalUnit> { origin, size } via euclid::Rect::new;
+    LogicalRect { origin, size } <=> euclid::Rect<P, LogicalUnit> { origin, size } via euclid::Rect::new;
+}
+
+// `SideOffsets2D` orders its sides clockwise from the top, unlike insets.
+#[cfg(feature = "euclid")]
+macro_rules! impl_euclid_insets {
+    ($($insets:ident $unit:ident;)+) => {
+        $(
+            impl<P: Pixel> From<$insets<P>> for euclid::SideOffsets2D<P, $unit> {
+                #[inline]
+                fn from($insets { top, left, bottom, right }: $insets<P>) -> Self {
+                    euclid::SideOffsets2D::new(top, right, bottom, left)
+                }
+            }
+
+            impl<P: Pixel> From<euclid::SideOffsets2D<P, $unit>> for $insets<P> {
+                #[inline]
+                fn from(offsets: euclid::SideOffsets2D<P, $unit>) -> Self {
+                    $insets::new(offsets.top, offsets.left, offsets.bottom, offsets.right)
+                }
+            }
+        )+
+    };
+}
+
+#[cfg(feature = "euclid")]
+impl_euclid_insets! {
+    PhysicalInsets PhysicalUnit;
+    LogicalInsets LogicalUnit;
+}
+
+#[cfg(feature = "euclid")]
+impl From<ScaleFactor> for euclid::Scale<f64, LogicalUnit, PhysicalUnit> {
+    #[inline]
+    fn from(scale_factor: ScaleFactor) -> Self {
+        euclid::Scale::new(scale_factor.get())
+    }
+}
+
+// `DVec2` only has an `f64` representation.
+#[cfg(feature = "glam")]
+macro_rules! impl_glam {
+    ($($dpi:ident { $x:ident, $y:ident };)+) => {
+        $(
+            impl From<$dpi> for glam::DVec2 {
+                #[inline]
+                fn from(value: $dpi) -> Self {
+                    glam::DVec2::new(value.$x, value.$y)
+                }
+            }
+
+            impl From<glam::DVec2> for $dpi {
+                #[inline]
+                fn from(value: glam::DVec2) -> Self {
+                    $dpi::new(value.x, value.y)
+                }
+            }
+        )+
+    };
+}
+
+#[cfg(feature = "glam")]
+impl_glam! {
+    PhysicalSize { width, height };
+    LogicalSize { width, height };
+    PhysicalPosition { x, y };
+    LogicalPosition { x, y };
+    PhysicalVector { x, y };
+    LogicalVector { x, y };
+}
+
+#[cfg(test)]
+mod tests {
+    #[allow(unused_imports)]
+    use super::*;
+
+    #[test]
+    #[cfg(feature = "mint")]
+    fn mint_round_trips() {
+        let position = LogicalPosition::new(1.5, -2.0);
+        let point: mint::Point2<f64> = position.into();
+        assert_eq!((point.x, point.y), (1.5, -2.0));
+        assert_eq!(LogicalPosition::from(point), position);
+
+        let size = PhysicalSize::new(640u32, 480);
+        let vector: mint::Vector2<u32> = size.into();
+        assert_eq!((vector.x, vector.y), (640, 480));
+        assert_eq!(PhysicalSize::from(vector), size);
+    }
+
+    #[test]
+    #[cfg(feature = "euclid")]
+    fn euclid_round_trips() {
+        let rect = LogicalRect::new(LogicalPosition::new(1.0, 2.0), LogicalSize::new(3.0, 4.0));
+        let euclid_rect: euclid::Rect<f64, LogicalUnit> = rect.into();
+        assert_eq!(euclid_rect, euclid::rect(1.0, 2.0, 3.0, 4.0));
+        assert_eq!(LogicalRect::from(euclid_rect), rect);
+
+        let vector = PhysicalVector::new(-1i32, 7);
+        let euclid_vector: euclid::Vector2D<i32, PhysicalUnit> = vector.into();
+        assert_eq!(PhysicalVector::from(euclid_vector), vector);
+    }
+
+    #[test]
+    #[cfg(feature = "euclid")]
+    fn euclid_insets_keep_their_sides() {
+        let insets = LogicalInsets::new(1.0, 2.0, 3.0, 4.0);
+        let offsets: euclid::SideOffsets2D<f64, LogicalUnit> = insets.into();
+        assert_eq!((offsets.top, offsets.right, offsets.bottom, offsets.left), (1.0, 4.0, 3.0, 2.0));
+        assert_eq!(LogicalInsets::from(offsets), insets);
+    }
+
+    #[test]
+    #[cfg(feature = "euclid")]
+    fn euclid_scale_matches_scale_factor() {
+        let scale: euclid::Scale<f64, LogicalUnit, PhysicalUnit> = ScaleFactor::TWO.into();
+        let size = euclid::Size2D::<f64, LogicalUnit>::new(10.0, 5.0) * scale;
+        let expected = LogicalSize::new(10.0, 5.0).to_physical_with(ScaleFactor::TWO);
+        assert_eq!(PhysicalSize::from(size), expected);
+    }
+
+    #[test]
+    #[cfg(feature = "glam")]
+    fn glam_round_trips() {
+        let size = LogicalSize::new(800.0, 600.0);
+        let vec = glam::DVec2::from(size);
+        assert_eq!(vec, glam::DVec2::new(800.0, 600.0));
+        assert_eq!(LogicalSize::from(vec), size);
+
+        let vector = PhysicalVector::new(0.25, -0.5);
+        assert_eq!(PhysicalVector::from(glam::DVec2::from(vector)), vector);
+    }
+}
@@ -1,26 +1,45 @@
 //! Functionality around dots-per-inch (DPI).
+//!
+//! With the `serde` feature, every type here except errors implements
+//! `Serialize` and `Deserialize`. The `mint`, `euclid` and `glam` features
+//! add conversions to and from the types of those libraries.
 
 use std::ops;
 
+mod insets;
+mod interop;
+mod pixel;
 mod size;
 mod position;
+mod rect;
+mod rounding;
+mod scale;
+mod vector;
 
+pub use insets::*;
+#[cfg(feature = "euclid")]
+pub use interop::{LogicalUnit, PhysicalUnit};
+pub use pixel::*;
 pub use size::*;
 pub use position::*;
+pub use rect::*;
+pub use rounding::*;
+pub use scale::*;
+pub use vector::*;
 
 macro_rules! impl_ops {
-    ($($t:ty)+ => $op:ident $op_f:ident $op_assign:ident $op_assign_f:ident) => {
+    ($($t:ident)+ => $op:ident $op_f:ident $op_assign:ident $op_assign_f:ident) => {
         $(
-            impl ops::$op<f64> for $t {
+            impl<P: Pixel + ops::$op<Output = P>> ops::$op<P> for $t<P> {
                 type Output = Self;
 
-                fn $op_f(self, other: f64) -> Self {
+                fn $op_f(self, other: P) -> Self {
                     self.map(|v| v.$op_f(other))
                 }
             }
 
-            impl ops::$op_assign<f64> for $t {
-                fn $op_assign_f(&mut self, other: f64) {
+            impl<P: Pixel + ops::$op_assign> ops::$op_assign<P> for $t<P> {
+                fn $op_assign_f(&mut self, other: P) {
                     self.apply(|v| v.$op_assign_f(other));
                 }
             }
@@ -29,17 +48,173 @@ macro_rules! impl_ops {
 }
 
 impl_ops! {
-    PhysicalSize LogicalSize PhysicalPosition LogicalPosition => Add add AddAssign add_assign
+    PhysicalSize LogicalSize PhysicalPosition LogicalPosition PhysicalVector LogicalVector => Add add AddAssign add_assign
 }
 
 impl_ops! {
-    PhysicalSize LogicalSize PhysicalPosition LogicalPosition => Sub sub SubAssign sub_assign
+    PhysicalSize LogicalSize PhysicalPosition LogicalPosition PhysicalVector LogicalVector => Sub sub SubAssign sub_assign
 }
 
 impl_ops! {
-    PhysicalSize LogicalSize PhysicalPosition LogicalPosition => Mul mul MulAssign mul_assign
+    PhysicalSize LogicalSize PhysicalPosition LogicalPosition PhysicalVector LogicalVector => Mul mul MulAssign mul_assign
 }
 
 impl_ops! {
-    PhysicalSize LogicalSize PhysicalPosition LogicalPosition => Div div DivAssign div_assign
+    PhysicalSize LogicalSize PhysicalPosition LogicalPosition PhysicalVector LogicalVector => Div div DivAssign div_assign
+}
+
+// Operations between dpi types of the same unit, such as the difference
+// between two positions. Operands are converted to arrays since their field
+// names differ.
+//
+// The difference between two positions is only defined for signed pixel
+// types, since it can be negative.
+macro_rules! impl_unit_ops {
+    ($($lhs:ident $op:ident $op_f:ident $rhs:ident = $out:ident;)+) => {
+        impl_unit_ops!($($lhs $op $op_f $rhs = $out where [Pixel];)+);
+    };
+    ($($lhs:ident $op:ident $op_f:ident $rhs:ident = $out:ident where [$($bound:tt)+];)+) => {
+        $(
+            impl<P: $($bound)+> ops::$op<$rhs<P>> for $lhs<P> {
+                type Output = $out<P>;
+
+                fn $op_f(self, other: $rhs<P>) -> $out<P> {
+                    let [a, b]: [P; 2] = self.into();
+                    let [c, d]: [P; 2] = other.into();
+                    $out::new(a.$op_f(c), b.$op_f(d))
+                }
+            }
+        )+
+    };
+}
+
+macro_rules! impl_unit_assign_ops {
+    ($($lhs:ident $op_assign:ident $op_assign_f:ident $op:ident $op_f:ident $rhs:ident;)+) => {
+        $(
+            impl<P: Pixel> ops::$op_assign<$rhs<P>> for $lhs<P> {
+                fn $op_assign_f(&mut self, other: $rhs<P>) {
+                    *self = ops::$op::$op_f(*self, other);
+                }
+            }
+        )+
+    };
+}
+
+impl_unit_ops! {
+    PhysicalPosition Sub sub PhysicalPosition = PhysicalVector where [Pixel + ops::Neg<Output = P>];
+    LogicalPosition Sub sub LogicalPosition = LogicalVector where [Pixel + ops::Neg<Output = P>];
+}
+
+impl_unit_ops! {
+    PhysicalPosition Add add PhysicalVector = PhysicalPosition;
+    PhysicalPosition Sub sub PhysicalVector = PhysicalPosition;
+    PhysicalPosition Add add PhysicalSize = PhysicalPosition;
+    PhysicalVector Add add PhysicalVector = PhysicalVector;
+    PhysicalVector Sub sub PhysicalVector = PhysicalVector;
+    PhysicalSize Add add PhysicalSize = PhysicalSize;
+    PhysicalSize Sub sub PhysicalSize = PhysicalSize;
+
+    LogicalPosition Add add LogicalVector = LogicalPosition;
+    LogicalPosition Sub sub LogicalVector = LogicalPosition;
+    LogicalPosition Add add LogicalSize = LogicalPosition;
+    LogicalVector Add add LogicalVector = LogicalVector;
+    LogicalVector Sub sub LogicalVector = LogicalVector;
+    LogicalSize Add add LogicalSize = LogicalSize;
+    LogicalSize Sub sub LogicalSize = LogicalSize;
+}
+
+impl_unit_assign_ops! {
+    PhysicalPosition AddAssign add_assign Add add PhysicalVector;
+    PhysicalPosition SubAssign sub_assign Sub sub PhysicalVector;
+    PhysicalPosition AddAssign add_assign Add add PhysicalSize;
+    PhysicalVector AddAssign add_assign Add add PhysicalVector;
+    PhysicalVector SubAssign sub_assign Sub sub PhysicalVector;
+    PhysicalSize AddAssign add_assign Add add PhysicalSize;
+    PhysicalSize SubAssign sub_assign Sub sub PhysicalSize;
+
+    LogicalPosition AddAssign add_assign Add add LogicalVector;
+    LogicalPosition SubAssign sub_assign Sub sub LogicalVector;
+    LogicalPosition AddAssign add_assign Add add LogicalSize;
+    LogicalVector AddAssign add_assign Add add LogicalVector;
+    LogicalVector SubAssign sub_assign Sub sub LogicalVector;
+    LogicalSize AddAssign add_assign Add add LogicalSize;
+    LogicalSize SubAssign sub_assign Sub sub LogicalSize;
+}
+
+macro_rules! impl_vector_ops {
+    ($($vector:ident $rect:ident;)+) => {
+        $(
+            impl<P: Pixel + ops::Neg<Output = P>> ops::Neg for $vector<P> {
+                type Output = Self;
+
+                fn neg(self) -> Self {
+                    self.map(ops::Neg::neg)
+                }
+            }
+
+            impl<P: Pixel> ops::Add<$vector<P>> for $rect<P> {
+                type Output = Self;
+
+                fn add(self, offset: $vector<P>) -> Self {
+                    Self::new(self.origin + offset, self.size)
+                }
+            }
+
+            impl<P: Pixel> ops::Sub<$vector<P>> for $rect<P> {
+                type Output = Self;
+
+                fn sub(self, offset: $vector<P>) -> Self {
+                    Self::new(self.origin - offset, self.size)
+                }
+            }
+
+            impl<P: Pixel> ops::AddAssign<$vector<P>> for $rect<P> {
+                fn add_assign(&mut self, offset: $vector<P>) {
+                    self.origin += offset;
+                }
+            }
+
+            impl<P: Pixel> ops::SubAssign<$vector<P>> for $rect<P> {
+                fn sub_assign(&mut self, offset: $vector<P>) {
+                    self.origin -= offset;
+                }
+            }
+        )+
+    };
+}
+
+impl_vector_ops! {
+    PhysicalVector PhysicalRect;
+    LogicalVector LogicalRect;
+}
+
+// Scaling between units, e.g. `LogicalSize * ScaleFactor = PhysicalSize`.
+macro_rules! impl_scale_ops {
+    ($($logical:ident $physical:ident;)+) => {
+        $(
+            impl<P: Pixel> ops::Mul<ScaleFactor> for $logical<P> {
+                type Output = $physical;
+
+                fn mul(self, scale_factor: ScaleFactor) -> $physical {
+                    self.to_physical_with(scale_factor)
+                }
+            }
+
+            impl<P: Pixel> ops::Div<ScaleFactor> for $physical<P> {
+                type Output = $logical;
+
+                fn div(self, scale_factor: ScaleFactor) -> $logical {
+                    self.to_logical_with(scale_factor)
+                }
+            }
+        )+
+    };
+}
+
+impl_scale_ops! {
+    LogicalSize PhysicalSize;
+    LogicalPosition PhysicalPosition;
+    LogicalVector PhysicalVector;
+    LogicalRect PhysicalRect;
+    LogicalInsets PhysicalInsets;
 }
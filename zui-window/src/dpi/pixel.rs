@@ -0,0 +1,255 @@
+use std::{fmt, ops};
+
+mod sealed {
+    pub trait Sealed {}
+}
+
+/// A scalar type that dpi types can be represented in.
+///
+/// This is implemented for `u32`, `i32`, `f32` and `f64`. Every pixel type
+/// converts to `f64` without loss, which is what conversions between pixel
+/// types go through.
+pub trait Pixel:
+    Copy
+    + Default
+    + PartialOrd
+    + fmt::Debug
+    + ops::Add<Output = Self>
+    + ops::Sub<Output = Self>
+    + ops::Mul<Output = Self>
+    + ops::Div<Output = Self>
+    + sealed::Sealed
+    + 'static
+{
+    /// Converts `self` to `f64` without loss.
+    fn to_f64(self) -> f64;
+
+    /// Converts `value` exactly, returning `None` if it can't be represented.
+    ///
+    /// For integer types, this fails if `value` is NaN, out of range or has a
+    /// fractional part. For `f32`, this fails if a finite `value` is out of
+    /// range.
+    fn checked_from_f64(value: f64) -> Option<Self>;
+
+    /// Converts `value`, clamping finite values to the range of `Self`.
+    ///
+    /// For integer types, the fractional part is truncated and NaN becomes
+    /// zero.
+    fn saturating_from_f64(value: f64) -> Self;
+
+    /// Rounds `self` to the nearest integer, with halfway cases rounded away
+    /// from zero.
+    ///
+    /// This returns `self` for integer types.
+    fn round(self) -> Self;
+
+    /// Rounds `self` down to an integer.
+    ///
+    /// This returns `self` for integer types.
+    fn floor(self) -> Self;
+
+    /// Rounds `self` up to an integer.
+    ///
+    /// This returns `self` for integer types.
+    fn ceil(self) -> Self;
+
+    /// Adds `other` to `self`, clamping the result to the range of integer
+    /// types.
+    fn saturating_add(self, other: Self) -> Self;
+
+    /// Subtracts `other` from `self`, clamping the result to the range of
+    /// integer types.
+    fn saturating_sub(self, other: Self) -> Self;
+}
+
+macro_rules! impl_pixel_int {
+    ($($t:ident)+) => {
+        $(
+            impl sealed::Sealed for $t {}
+
+            impl Pixel for $t {
+                #[inline]
+                fn to_f64(self) -> f64 {
+                    self.into()
+                }
+
+                #[inline]
+                fn checked_from_f64(value: f64) -> Option<Self> {
+                    let in_range = value >= f64::from($t::MIN)
+                        && value <= f64::from($t::MAX);
+
+                    if in_range && value.fract() == 0.0 {
+                        Some(value as $t)
+                    } else {
+                        None
+                    }
+                }
+
+                #[inline]
+                fn saturating_from_f64(value: f64) -> Self {
+                    if value.is_nan() {
+                        0
+                    } else if value <= f64::from($t::MIN) {
+                        $t::MIN
+                    } else if value >= f64::from($t::MAX) {
+                        $t::MAX
+                    } else {
+                        value as $t
+                    }
+                }
+
+                #[inline]
+                fn round(self) -> Self {
+                    self
+                }
+
+                #[inline]
+                fn floor(self) -> Self {
+                    self
+                }
+
+                #[inline]
+                fn ceil(self) -> Self {
+                    self
+                }
+
+                #[inline]
+                fn saturating_add(self, other: Self) -> Self {
+                    $t::saturating_add(self, other)
+                }
+
+                #[inline]
+                fn saturating_sub(self, other: Self) -> Self {
+                    $t::saturating_sub(self, other)
+                }
+            }
+        )+
+    };
+}
+
+impl_pixel_int!(u32 i32);
+
+macro_rules! impl_pixel_float {
+    ($($t:ident)+) => {
+        $(
+            impl sealed::Sealed for $t {}
+
+            impl Pixel for $t {
+                #[inline]
+                fn to_f64(self) -> f64 {
+                    self.into()
+                }
+
+                #[inline]
+                fn checked_from_f64(value: f64) -> Option<Self> {
+                    let result = value as $t;
+                    if result.is_infinite() && value.is_finite() {
+                        None
+                    } else {
+                        Some(result)
+                    }
+                }
+
+                #[inline]
+                fn saturating_from_f64(value: f64) -> Self {
+                    // Infinities and NaN are representable as is.
+                    if value.is_finite() {
+                        let min = f64::from($t::MIN);
+                        let max = f64::from($t::MAX);
+                        value.max(min).min(max) as $t
+                    } else {
+                        value as $t
+                    }
+                }
+
+                #[inline]
+                fn round(self) -> Self {
+                    $t::round(self)
+                }
+
+                #[inline]
+                fn floor(self) -> Self {
+                    $t::floor(self)
+                }
+
+                #[inline]
+                fn ceil(self) -> Self {
+                    $t::ceil(self)
+                }
+
+                #[inline]
+                fn saturating_add(self, other: Self) -> Self {
+                    self + other
+                }
+
+                #[inline]
+                fn saturating_sub(self, other: Self) -> Self {
+                    self - other
+                }
+            }
+        )+
+    };
+}
+
+impl_pixel_float!(f32 f64);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checked_integers() {
+        assert_eq!(u32::checked_from_f64(42.0), Some(42));
+        assert_eq!(i32::checked_from_f64(-42.0), Some(-42));
+        assert_eq!(u32::checked_from_f64(f64::from(u32::MAX)), Some(u32::MAX));
+        assert_eq!(i32::checked_from_f64(f64::from(i32::MIN)), Some(i32::MIN));
+
+        assert_eq!(u32::checked_from_f64(-1.0), None);
+        assert_eq!(u32::checked_from_f64(f64::from(u32::MAX) + 1.0), None);
+        assert_eq!(i32::checked_from_f64(0.5), None);
+        assert_eq!(i32::checked_from_f64(f64::NAN), None);
+        assert_eq!(i32::checked_from_f64(f64::INFINITY), None);
+    }
+
+    #[test]
+    fn checked_floats() {
+        assert_eq!(f32::checked_from_f64(0.5), Some(0.5));
+        assert_eq!(f32::checked_from_f64(f64::INFINITY), Some(f32::INFINITY));
+        assert!(f32::checked_from_f64(f64::NAN).unwrap().is_nan());
+        assert_eq!(f32::checked_from_f64(f64::MAX), None);
+        assert_eq!(f64::checked_from_f64(f64::MAX), Some(f64::MAX));
+    }
+
+    #[test]
+    fn saturating() {
+        assert_eq!(u32::saturating_from_f64(-1.0), 0);
+        assert_eq!(u32::saturating_from_f64(1e12), u32::MAX);
+        assert_eq!(i32::saturating_from_f64(-1e12), i32::MIN);
+        assert_eq!(i32::saturating_from_f64(-2.9), -2);
+        assert_eq!(i32::saturating_from_f64(f64::NAN), 0);
+        assert_eq!(u32::saturating_from_f64(f64::INFINITY), u32::MAX);
+
+        assert_eq!(f32::saturating_from_f64(f64::MAX), f32::MAX);
+        assert_eq!(f32::saturating_from_f64(f64::MIN), f32::MIN);
+        assert_eq!(f32::saturating_from_f64(f64::NEG_INFINITY), f32::NEG_INFINITY);
+    }
+
+    #[test]
+    fn rounding() {
+        assert_eq!(Pixel::round(2.5f64), 3.0);
+        assert_eq!(Pixel::round(-2.5f32), -3.0);
+        assert_eq!(Pixel::floor(-0.5f64), -1.0);
+        assert_eq!(Pixel::ceil(0.25f32), 1.0);
+
+        assert_eq!(Pixel::round(7u32), 7);
+        assert_eq!(Pixel::floor(-7i32), -7);
+        assert_eq!(Pixel::ceil(-7i32), -7);
+    }
+
+    #[test]
+    fn to_f64_is_lossless() {
+        assert_eq!(u32::MAX.to_f64(), 4294967295.0);
+        assert_eq!(i32::MIN.to_f64(), -2147483648.0);
+        assert_eq!(0.1f32.to_f64(), f64::from(0.1f32));
+    }
+}
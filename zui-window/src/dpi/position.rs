@@ -1,22 +1,29 @@
+use super::{Pixel, PhysicalVector, LogicalVector, ScaleFactor};
+
 /// A position represented in physical pixels.
-#[derive(Clone, Copy, Debug, PartialEq)]
-pub struct PhysicalPosition {
+///
+/// The scalar type `P` defaults to `f64`. Integer positions can be obtained
+/// with [`try_cast`](#method.try_cast) or
+/// [`saturating_cast`](#method.saturating_cast) after rounding.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PhysicalPosition<P = f64> {
     /// The X offset in pixels.
-    pub x: f64,
+    pub x: P,
     /// The Y offset in pixels.
-    pub y: f64,
+    pub y: P,
 }
 
-impl From<(f64, f64)> for PhysicalPosition {
+impl<P: Pixel> From<(P, P)> for PhysicalPosition<P> {
     #[inline]
-    fn from((x, y): (f64, f64)) -> Self {
+    fn from((x, y): (P, P)) -> Self {
         Self::new(x, y)
     }
 }
 
-impl From<[f64; 2]> for PhysicalPosition {
+impl<P: Pixel> From<[P; 2]> for PhysicalPosition<P> {
     #[inline]
-    fn from([x, y]: [f64; 2]) -> Self {
+    fn from([x, y]: [P; 2]) -> Self {
         Self::new(x, y)
     }
 }
@@ -35,86 +42,156 @@ impl From<[i32; 2]> for PhysicalPosition {
     }
 }
 
-impl From<PhysicalPosition> for (f64, f64) {
+impl<P: Pixel> From<PhysicalPosition<P>> for (P, P) {
     #[inline]
-    fn from(PhysicalPosition { x, y }: PhysicalPosition) -> Self {
+    fn from(PhysicalPosition { x, y }: PhysicalPosition<P>) -> Self {
         (x, y)
     }
 }
 
-impl From<PhysicalPosition> for [f64; 2] {
+impl<P: Pixel> From<PhysicalPosition<P>> for [P; 2] {
     #[inline]
-    fn from(PhysicalPosition { x, y }: PhysicalPosition) -> Self {
+    fn from(PhysicalPosition { x, y }: PhysicalPosition<P>) -> Self {
         [x, y]
     }
 }
 
-impl From<PhysicalPosition> for (i32, i32) {
-    #[inline]
-    fn from(PhysicalPosition { x, y }: PhysicalPosition) -> Self {
-        (x.round() as _, y.round() as _)
-    }
-}
-
-impl From<PhysicalPosition> for [i32; 2] {
+impl<P> PhysicalPosition<P> {
+    /// Creates a new position of `x` and `y`.
     #[inline]
-    fn from(PhysicalPosition { x, y }: PhysicalPosition) -> Self {
-        [x.round() as _, y.round() as _]
+    pub const fn new(x: P, y: P) -> Self {
+        PhysicalPosition { x, y }
     }
 }
 
-impl PhysicalPosition {
-    /// Creates a new position of `x` and `y`.
+impl<P: Pixel> PhysicalPosition<P> {
+    /// Returns the offset of `self` from the origin.
     #[inline]
-    pub const fn new(x: f64, y: f64) -> Self {
-        PhysicalPosition { x, y }
+    pub fn to_vector(self) -> PhysicalVector<P> {
+        PhysicalVector::new(self.x, self.y)
     }
 
     /// Returns a new position with X and Y updated by `f`.
     #[inline]
-    pub fn map<F: FnMut(f64) -> f64>(self, mut f: F) -> Self {
+    pub fn map<F: FnMut(P) -> P>(self, mut f: F) -> Self {
         Self::new(f(self.x), f(self.y))
     }
 
     /// Updates X and Y with `f`.
     #[inline]
-    pub fn apply<'a, F: FnMut(&'a mut f64)>(&'a mut self, mut f: F) {
+    pub fn apply<'a, F: FnMut(&'a mut P)>(&'a mut self, mut f: F) {
         f(&mut self.x);
         f(&mut self.y);
     }
 
-    /// Scales a `LogicalPosition` to a `PhysicalPosition` by `dpi_factor`.
+    /// Converts `self` to another pixel type without loss.
+    ///
+    /// For example, a `PhysicalPosition<u32>` can be cast to `PhysicalPosition<f64>`.
+    #[inline]
+    pub fn cast<T: Pixel + From<P>>(self) -> PhysicalPosition<T> {
+        PhysicalPosition::new(self.x.into(), self.y.into())
+    }
+
+    /// Converts `self` to another pixel type exactly, returning `None` if
+    /// either component can't be represented.
+    ///
+    /// Fractional values are not representable by integer types, so
+    /// positions should first be rounded with [`round`](#method.round),
+    /// [`floor`](#method.floor) or [`ceil`](#method.ceil).
+    #[inline]
+    pub fn try_cast<T: Pixel>(self) -> Option<PhysicalPosition<T>> {
+        Some(PhysicalPosition::new(
+            T::checked_from_f64(self.x.to_f64())?,
+            T::checked_from_f64(self.y.to_f64())?,
+        ))
+    }
+
+    /// Converts `self` to another pixel type, clamping each component to
+    /// the range of `T`.
+    ///
+    /// See [`Pixel::saturating_from_f64`](trait.Pixel.html#tymethod.saturating_from_f64)
+    /// for how fractional and NaN values are handled.
+    #[inline]
+    pub fn saturating_cast<T: Pixel>(self) -> PhysicalPosition<T> {
+        PhysicalPosition::new(
+            T::saturating_from_f64(self.x.to_f64()),
+            T::saturating_from_f64(self.y.to_f64()),
+        )
+    }
+
+    /// Rounds each component to the nearest integer, with halfway cases
+    /// rounded away from zero.
+    #[inline]
+    pub fn round(self) -> Self {
+        self.map(Pixel::round)
+    }
+
+    /// Rounds each component down to an integer.
+    #[inline]
+    pub fn floor(self) -> Self {
+        self.map(Pixel::floor)
+    }
+
+    /// Rounds each component up to an integer.
+    #[inline]
+    pub fn ceil(self) -> Self {
+        self.map(Pixel::ceil)
+    }
+
+    /// Scales `self` to a `LogicalPosition` by `scale_factor`.
     #[inline]
-    pub fn from_logical(position: LogicalPosition, dpi_factor: f64) -> Self {
-        position.to_physical(dpi_factor)
+    pub fn to_logical_with(&self, scale_factor: ScaleFactor) -> LogicalPosition {
+        LogicalPosition::new(
+            self.x.to_f64() / scale_factor.get(),
+            self.y.to_f64() / scale_factor.get(),
+        )
     }
 
     /// Scales `self` to a `LogicalPosition` by `dpi_factor`.
+    #[deprecated(note = "use `to_logical_with`, which takes a validated `ScaleFactor`")]
     #[inline]
     pub fn to_logical(&self, dpi_factor: f64) -> LogicalPosition {
-        (self.x / dpi_factor, self.y / dpi_factor).into()
+        self.to_logical_with(ScaleFactor::unchecked(dpi_factor))
+    }
+}
+
+impl PhysicalPosition {
+    /// Scales a `LogicalPosition` to a `PhysicalPosition` by `scale_factor`.
+    #[inline]
+    pub fn from_logical_with<P: Pixel>(position: LogicalPosition<P>, scale_factor: ScaleFactor) -> Self {
+        position.to_physical_with(scale_factor)
+    }
+
+    /// Scales a `LogicalPosition` to a `PhysicalPosition` by `dpi_factor`.
+    #[deprecated(note = "use `from_logical_with`, which takes a validated `ScaleFactor`")]
+    #[inline]
+    pub fn from_logical<P: Pixel>(position: LogicalPosition<P>, dpi_factor: f64) -> Self {
+        Self::from_logical_with(position, ScaleFactor::unchecked(dpi_factor))
     }
 }
 
 /// A position represented in logical pixels, or "points".
-#[derive(Clone, Copy, Debug, PartialEq)]
-pub struct LogicalPosition {
+///
+/// The scalar type `P` defaults to `f64`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LogicalPosition<P = f64> {
     /// The X offset in points.
-    pub x: f64,
+    pub x: P,
     /// The Y offset in points.
-    pub y: f64,
+    pub y: P,
 }
 
-impl From<(f64, f64)> for LogicalPosition {
+impl<P: Pixel> From<(P, P)> for LogicalPosition<P> {
     #[inline]
-    fn from((x, y): (f64, f64)) -> Self {
+    fn from((x, y): (P, P)) -> Self {
         Self::new(x, y)
     }
 }
 
-impl From<[f64; 2]> for LogicalPosition {
+impl<P: Pixel> From<[P; 2]> for LogicalPosition<P> {
     #[inline]
-    fn from([x, y]: [f64; 2]) -> Self {
+    fn from([x, y]: [P; 2]) -> Self {
         Self::new(x, y)
     }
 }
@@ -133,63 +210,198 @@ impl From<[i32; 2]> for LogicalPosition {
     }
 }
 
-impl From<LogicalPosition> for (f64, f64) {
+impl<P: Pixel> From<LogicalPosition<P>> for (P, P) {
     #[inline]
-    fn from(LogicalPosition { x, y }: LogicalPosition) -> Self {
+    fn from(LogicalPosition { x, y }: LogicalPosition<P>) -> Self {
         (x, y)
     }
 }
 
-impl From<LogicalPosition> for [f64; 2] {
+impl<P: Pixel> From<LogicalPosition<P>> for [P; 2] {
     #[inline]
-    fn from(LogicalPosition { x, y }: LogicalPosition) -> Self {
+    fn from(LogicalPosition { x, y }: LogicalPosition<P>) -> Self {
         [x, y]
     }
 }
 
-impl From<LogicalPosition> for (i32, i32) {
+impl<P> LogicalPosition<P> {
+    /// Creates a new position of `x` and `y`.
     #[inline]
-    fn from(LogicalPosition { x, y }: LogicalPosition) -> Self {
-        (x.round() as _, y.round() as _)
+    pub const fn new(x: P, y: P) -> Self {
+        LogicalPosition { x, y }
     }
 }
 
-impl From<LogicalPosition> for [i32; 2] {
+impl<P: Pixel> LogicalPosition<P> {
+    /// Returns the offset of `self` from the origin.
     #[inline]
-    fn from(LogicalPosition { x, y }: LogicalPosition) -> Self {
-        [x.round() as _, y.round() as _]
-    }
-}
-
-impl LogicalPosition {
-    /// Creates a new position of `x` and `y`.
-    #[inline]
-    pub const fn new(x: f64, y: f64) -> Self {
-        LogicalPosition { x, y }
+    pub fn to_vector(self) -> LogicalVector<P> {
+        LogicalVector::new(self.x, self.y)
     }
 
     /// Returns a new position with X and Y updated by `f`.
     #[inline]
-    pub fn map<F: FnMut(f64) -> f64>(self, mut f: F) -> Self {
+    pub fn map<F: FnMut(P) -> P>(self, mut f: F) -> Self {
         Self::new(f(self.x), f(self.y))
     }
 
     /// Updates X and Y with `f`.
     #[inline]
-    pub fn apply<'a, F: FnMut(&'a mut f64)>(&'a mut self, mut f: F) {
+    pub fn apply<'a, F: FnMut(&'a mut P)>(&'a mut self, mut f: F) {
         f(&mut self.x);
         f(&mut self.y);
     }
 
-    /// Scales a `LogicalPosition` to a `PhysicalPosition` by `dpi_factor`.
+    /// Converts `self` to another pixel type without loss.
+    ///
+    /// For example, a `LogicalPosition<u32>` can be cast to `LogicalPosition<f64>`.
     #[inline]
-    pub fn from_physical(position: PhysicalPosition, dpi_factor: f64) -> Self {
-        position.to_logical(dpi_factor)
+    pub fn cast<T: Pixel + From<P>>(self) -> LogicalPosition<T> {
+        LogicalPosition::new(self.x.into(), self.y.into())
     }
 
-    /// Scales `self` to a `LogicalPosition` by `dpi_factor`.
+    /// Converts `self` to another pixel type exactly, returning `None` if
+    /// either component can't be represented.
+    ///
+    /// Fractional values are not representable by integer types, so
+    /// positions should first be rounded with [`round`](#method.round),
+    /// [`floor`](#method.floor) or [`ceil`](#method.ceil).
+    #[inline]
+    pub fn try_cast<T: Pixel>(self) -> Option<LogicalPosition<T>> {
+        Some(LogicalPosition::new(
+            T::checked_from_f64(self.x.to_f64())?,
+            T::checked_from_f64(self.y.to_f64())?,
+        ))
+    }
+
+    /// Converts `self` to another pixel type, clamping each component to
+    /// the range of `T`.
+    ///
+    /// See [`Pixel::saturating_from_f64`](trait.Pixel.html#tymethod.saturating_from_f64)
+    /// for how fractional and NaN values are handled.
+    #[inline]
+    pub fn saturating_cast<T: Pixel>(self) -> LogicalPosition<T> {
+        LogicalPosition::new(
+            T::saturating_from_f64(self.x.to_f64()),
+            T::saturating_from_f64(self.y.to_f64()),
+        )
+    }
+
+    /// Rounds each component to the nearest integer, with halfway cases
+    /// rounded away from zero.
+    #[inline]
+    pub fn round(self) -> Self {
+        self.map(Pixel::round)
+    }
+
+    /// Rounds each component down to an integer.
+    #[inline]
+    pub fn floor(self) -> Self {
+        self.map(Pixel::floor)
+    }
+
+    /// Rounds each component up to an integer.
+    #[inline]
+    pub fn ceil(self) -> Self {
+        self.map(Pixel::ceil)
+    }
+
+    /// Scales `self` to a `PhysicalPosition` by `scale_factor`.
+    #[inline]
+    pub fn to_physical_with(&self, scale_factor: ScaleFactor) -> PhysicalPosition {
+        PhysicalPosition::new(
+            self.x.to_f64() * scale_factor.get(),
+            self.y.to_f64() * scale_factor.get(),
+        )
+    }
+
+    /// Scales `self` to a `PhysicalPosition` by `dpi_factor`.
+    #[deprecated(note = "use `to_physical_with`, which takes a validated `ScaleFactor`")]
+    #[inline]
+    pub fn to_physical(&self, dpi_factor: f64) -> PhysicalPosition {
+        self.to_physical_with(ScaleFactor::unchecked(dpi_factor))
+    }
+}
+
+impl LogicalPosition {
+    /// Scales a `PhysicalPosition` to a `LogicalPosition` by `scale_factor`.
+    #[inline]
+    pub fn from_physical_with<P: Pixel>(position: PhysicalPosition<P>, scale_factor: ScaleFactor) -> Self {
+        position.to_logical_with(scale_factor)
+    }
+
+    /// Scales a `PhysicalPosition` to a `LogicalPosition` by `dpi_factor`.
+    #[deprecated(note = "use `from_physical_with`, which takes a validated `ScaleFactor`")]
+    #[inline]
+    pub fn from_physical<P: Pixel>(position: PhysicalPosition<P>, dpi_factor: f64) -> Self {
+        Self::from_physical_with(position, ScaleFactor::unchecked(dpi_factor))
+    }
+}
+
+/// A position in either logical or physical pixels.
+///
+/// APIs that take this accept either unit, and resolve it against the
+/// relevant scale factor when needed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum Position {
+    /// A position in logical pixels.
+    Logical(LogicalPosition),
+    /// A position in physical pixels.
+    Physical(PhysicalPosition),
+}
+
+impl<P: Pixel> From<LogicalPosition<P>> for Position
+where
+    f64: From<P>,
+{
+    #[inline]
+    fn from(position: LogicalPosition<P>) -> Self {
+        Position::Logical(position.cast())
+    }
+}
+
+impl<P: Pixel> From<PhysicalPosition<P>> for Position
+where
+    f64: From<P>,
+{
+    #[inline]
+    fn from(position: PhysicalPosition<P>) -> Self {
+        Position::Physical(position.cast())
+    }
+}
+
+impl Position {
+    /// Returns `self` in logical pixels, scaling by `scale_factor` if needed.
+    #[inline]
+    pub fn to_logical_with(&self, scale_factor: ScaleFactor) -> LogicalPosition {
+        match *self {
+            Position::Logical(position) => position,
+            Position::Physical(position) => position.to_logical_with(scale_factor),
+        }
+    }
+
+    /// Returns `self` in logical pixels, scaling by `dpi_factor` if needed.
+    #[deprecated(note = "use `to_logical_with`, which takes a validated `ScaleFactor`")]
+    #[inline]
+    pub fn to_logical(&self, dpi_factor: f64) -> LogicalPosition {
+        self.to_logical_with(ScaleFactor::unchecked(dpi_factor))
+    }
+
+    /// Returns `self` in physical pixels, scaling by `scale_factor` if needed.
+    #[inline]
+    pub fn to_physical_with(&self, scale_factor: ScaleFactor) -> PhysicalPosition {
+        match *self {
+            Position::Logical(position) => position.to_physical_with(scale_factor),
+            Position::Physical(position) => position,
+        }
+    }
+
+    /// Returns `self` in physical pixels, scaling by `dpi_factor` if needed.
+    #[deprecated(note = "use `to_physical_with`, which takes a validated `ScaleFactor`")]
     #[inline]
     pub fn to_physical(&self, dpi_factor: f64) -> PhysicalPosition {
-        (self.x * dpi_factor, self.y * dpi_factor).into()
+        self.to_physical_with(ScaleFactor::unchecked(dpi_factor))
     }
 }
@@ -0,0 +1,385 @@
+use super::*;
+
+#[inline]
+fn min<P: Pixel>(a: P, b: P) -> P {
+    if b < a { b } else { a }
+}
+
+#[inline]
+fn max<P: Pixel>(a: P, b: P) -> P {
+    if b > a { b } else { a }
+}
+
+/// Moves the start of the span at `origin` of `len` forward by `start` and
+/// its end back by `end`, returning the new origin and length.
+///
+/// Once the ends would cross, the span collapses to where they meet, in
+/// proportion to the amounts.
+pub(super) fn inset_span<P: Pixel>(origin: P, len: P, start: P, end: P) -> (P, P) {
+    let total = start.saturating_add(end);
+    if total >= len && total > P::default() {
+        let ratio = start.to_f64() / total.to_f64();
+        let at = P::saturating_from_f64(origin.to_f64() + len.to_f64() * ratio);
+        (at, P::default())
+    } else {
+        (origin.saturating_add(start), len.saturating_sub(total))
+    }
+}
+
+/// A rectangle represented in physical pixels.
+///
+/// The origin is the top-left corner.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PhysicalRect<P = f64> {
+    /// The top-left corner.
+    pub origin: PhysicalPosition<P>,
+    /// The width and height.
+    pub size: PhysicalSize<P>,
+}
+
+impl<P: Pixel> From<(PhysicalPosition<P>, PhysicalSize<P>)> for PhysicalRect<P> {
+    #[inline]
+    fn from((origin, size): (PhysicalPosition<P>, PhysicalSize<P>)) -> Self {
+        Self::new(origin, size)
+    }
+}
+
+impl<P: Pixel> From<PhysicalSize<P>> for PhysicalRect<P> {
+    /// Creates a rectangle of `size` at the zero origin.
+    #[inline]
+    fn from(size: PhysicalSize<P>) -> Self {
+        Self::new(PhysicalPosition::new(P::default(), P::default()), size)
+    }
+}
+
+impl<P> PhysicalRect<P> {
+    /// Creates a new rectangle at `origin` of `size`.
+    #[inline]
+    pub const fn new(origin: PhysicalPosition<P>, size: PhysicalSize<P>) -> Self {
+        PhysicalRect { origin, size }
+    }
+}
+
+impl<P: Pixel> PhysicalRect<P> {
+    /// Returns the bottom-right corner.
+    ///
+    /// For integer pixel types, this stops at the largest representable
+    /// position.
+    #[inline]
+    pub fn max(&self) -> PhysicalPosition<P> {
+        PhysicalPosition::new(
+            self.origin.x.saturating_add(self.size.width),
+            self.origin.y.saturating_add(self.size.height),
+        )
+    }
+
+    /// Returns the center point.
+    #[inline]
+    pub fn center(&self) -> PhysicalPosition {
+        PhysicalPosition::new(
+            self.origin.x.to_f64() + self.size.width.to_f64() / 2.0,
+            self.origin.y.to_f64() + self.size.height.to_f64() / 2.0,
+        )
+    }
+
+    /// Returns whether `self` has no area.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        !(self.size.width > P::default() && self.size.height > P::default())
+    }
+
+    /// Returns whether `point` is within `self`.
+    ///
+    /// The right and bottom edges are not considered to be within `self`.
+    #[inline]
+    pub fn contains(&self, point: PhysicalPosition<P>) -> bool {
+        let max = self.max();
+        point.x >= self.origin.x &&
+        point.y >= self.origin.y &&
+        point.x < max.x &&
+        point.y < max.y
+    }
+
+    /// Returns the area shared by `self` and `other`, or `None` if they do
+    /// not overlap.
+    pub fn intersection(&self, other: &Self) -> Option<Self> {
+        let (a, b) = (self.max(), other.max());
+        let origin = PhysicalPosition::new(
+            max(self.origin.x, other.origin.x),
+            max(self.origin.y, other.origin.y),
+        );
+        let end = PhysicalPosition::new(min(a.x, b.x), min(a.y, b.y));
+
+        if end.x > origin.x && end.y > origin.y {
+            let size = PhysicalSize::new(end.x.saturating_sub(origin.x), end.y.saturating_sub(origin.y));
+            Some(Self::new(origin, size))
+        } else {
+            None
+        }
+    }
+
+    /// Returns the smallest rectangle that contains both `self` and
+    /// `other`.
+    ///
+    /// Empty rectangles are ignored. For integer pixel types, the size
+    /// stops at the largest representable value.
+    pub fn union(&self, other: &Self) -> Self {
+        if other.is_empty() {
+            return *self;
+        } else if self.is_empty() {
+            return *other;
+        }
+
+        let (a, b) = (self.max(), other.max());
+        let origin = PhysicalPosition::new(
+            min(self.origin.x, other.origin.x),
+            min(self.origin.y, other.origin.y),
+        );
+        let end = PhysicalPosition::new(max(a.x, b.x), max(a.y, b.y));
+
+        Self::new(origin, PhysicalSize::new(end.x.saturating_sub(origin.x), end.y.saturating_sub(origin.y)))
+    }
+
+    /// Moves each edge inward by `dx` horizontally and `dy` vertically.
+    ///
+    /// Negative amounts move edges outward. Insetting by half of the size or
+    /// more collapses `self` to its center. For integer pixel types, each
+    /// component stops at the bounds of the type.
+    #[inline]
+    pub fn inset(&self, dx: P, dy: P) -> Self {
+        let (x, width) = inset_span(self.origin.x, self.size.width, dx, dx);
+        let (y, height) = inset_span(self.origin.y, self.size.height, dy, dy);
+        Self::new(PhysicalPosition::new(x, y), PhysicalSize::new(width, height))
+    }
+
+    /// Converts `self` to another pixel type without loss.
+    #[inline]
+    pub fn cast<T: Pixel + From<P>>(self) -> PhysicalRect<T> {
+        PhysicalRect::new(self.origin.cast(), self.size.cast())
+    }
+
+    /// Converts `self` to another pixel type exactly, returning `None` if
+    /// any component can't be represented.
+    #[inline]
+    pub fn try_cast<T: Pixel>(self) -> Option<PhysicalRect<T>> {
+        Some(PhysicalRect::new(self.origin.try_cast()?, self.size.try_cast()?))
+    }
+
+    /// Converts `self` to another pixel type, clamping each component to
+    /// the range of `T`.
+    #[inline]
+    pub fn saturating_cast<T: Pixel>(self) -> PhysicalRect<T> {
+        PhysicalRect::new(self.origin.saturating_cast(), self.size.saturating_cast())
+    }
+
+    /// Scales `self` to a `LogicalRect` by `scale_factor`.
+    #[inline]
+    pub fn to_logical_with(&self, scale_factor: ScaleFactor) -> LogicalRect {
+        LogicalRect::new(
+            self.origin.to_logical_with(scale_factor),
+            self.size.to_logical_with(scale_factor),
+        )
+    }
+
+    /// Scales `self` to a `LogicalRect` by `dpi_factor`.
+    #[deprecated(note = "use `to_logical_with`, which takes a validated `ScaleFactor`")]
+    #[inline]
+    pub fn to_logical(&self, dpi_factor: f64) -> LogicalRect {
+        self.to_logical_with(ScaleFactor::unchecked(dpi_factor))
+    }
+}
+
+impl PhysicalRect {
+    /// Scales a `LogicalRect` to a `PhysicalRect` by `scale_factor`.
+    #[inline]
+    pub fn from_logical_with<P: Pixel>(rect: LogicalRect<P>, scale_factor: ScaleFactor) -> Self {
+        rect.to_physical_with(scale_factor)
+    }
+
+    /// Scales a `LogicalRect` to a `PhysicalRect` by `dpi_factor`.
+    #[deprecated(note = "use `from_logical_with`, which takes a validated `ScaleFactor`")]
+    #[inline]
+    pub fn from_logical<P: Pixel>(rect: LogicalRect<P>, dpi_factor: f64) -> Self {
+        Self::from_logical_with(rect, ScaleFactor::unchecked(dpi_factor))
+    }
+}
+
+/// A rectangle represented in logical pixels, or "points".
+///
+/// The origin is the top-left corner.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LogicalRect<P = f64> {
+    /// The top-left corner.
+    pub origin: LogicalPosition<P>,
+    /// The width and height.
+    pub size: LogicalSize<P>,
+}
+
+impl<P: Pixel> From<(LogicalPosition<P>, LogicalSize<P>)> for LogicalRect<P> {
+    #[inline]
+    fn from((origin, size): (LogicalPosition<P>, LogicalSize<P>)) -> Self {
+        Self::new(origin, size)
+    }
+}
+
+impl<P: Pixel> From<LogicalSize<P>> for LogicalRect<P> {
+    /// Creates a rectangle of `size` at the zero origin.
+    #[inline]
+    fn from(size: LogicalSize<P>) -> Self {
+        Self::new(LogicalPosition::new(P::default(), P::default()), size)
+    }
+}
+
+impl<P> LogicalRect<P> {
+    /// Creates a new rectangle at `origin` of `size`.
+    #[inline]
+    pub const fn new(origin: LogicalPosition<P>, size: LogicalSize<P>) -> Self {
+        LogicalRect { origin, size }
+    }
+}
+
+impl<P: Pixel> LogicalRect<P> {
+    /// Returns the bottom-right corner.
+    ///
+    /// For integer pixel types, this stops at the largest representable
+    /// position.
+    #[inline]
+    pub fn max(&self) -> LogicalPosition<P> {
+        LogicalPosition::new(
+            self.origin.x.saturating_add(self.size.width),
+            self.origin.y.saturating_add(self.size.height),
+        )
+    }
+
+    /// Returns the center point.
+    #[inline]
+    pub fn center(&self) -> LogicalPosition {
+        LogicalPosition::new(
+            self.origin.x.to_f64() + self.size.width.to_f64() / 2.0,
+            self.origin.y.to_f64() + self.size.height.to_f64() / 2.0,
+        )
+    }
+
+    /// Returns whether `self` has no area.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        !(self.size.width > P::default() && self.size.height > P::default())
+    }
+
+    /// Returns whether `point` is within `self`.
+    ///
+    /// The right and bottom edges are not considered to be within `self`.
+    #[inline]
+    pub fn contains(&self, point: LogicalPosition<P>) -> bool {
+        let max = self.max();
+        point.x >= self.origin.x &&
+        point.y >= self.origin.y &&
+        point.x < max.x &&
+        point.y < max.y
+    }
+
+    /// Returns the area shared by `self` and `other`, or `None` if they do
+    /// not overlap.
+    pub fn intersection(&self, other: &Self) -> Option<Self> {
+        let (a, b) = (self.max(), other.max());
+        let origin = LogicalPosition::new(
+            max(self.origin.x, other.origin.x),
+            max(self.origin.y, other.origin.y),
+        );
+        let end = LogicalPosition::new(min(a.x, b.x), min(a.y, b.y));
+
+        if end.x > origin.x && end.y > origin.y {
+            let size = LogicalSize::new(end.x.saturating_sub(origin.x), end.y.saturating_sub(origin.y));
+            Some(Self::new(origin, size))
+        } else {
+            None
+        }
+    }
+
+    /// Returns the smallest rectangle that contains both `self` and
+    /// `other`.
+    ///
+    /// Empty rectangles are ignored. For integer pixel types, the size
+    /// stops at the largest representable value.
+    pub fn union(&self, other: &Self) -> Self {
+        if other.is_empty() {
+            return *self;
+        } else if self.is_empty() {
+            return *other;
+        }
+
+        let (a, b) = (self.max(), other.max());
+        let origin = LogicalPosition::new(
+            min(self.origin.x, other.origin.x),
+            min(self.origin.y, other.origin.y),
+        );
+        let end = LogicalPosition::new(max(a.x, b.x), max(a.y, b.y));
+
+        Self::new(origin, LogicalSize::new(end.x.saturating_sub(origin.x), end.y.saturating_sub(origin.y)))
+    }
+
+    /// Moves each edge inward by `dx` horizontally and `dy` vertically.
+    ///
+    /// Negative amounts move edges outward. Insetting by half of the size or
+    /// more collapses `self` to its center. For integer pixel types, each
+    /// component stops at the bounds of the type.
+    #[inline]
+    pub fn inset(&self, dx: P, dy: P) -> Self {
+        let (x, width) = inset_span(self.origin.x, self.size.width, dx, dx);
+        let (y, height) = inset_span(self.origin.y, self.size.height, dy, dy);
+        Self::new(LogicalPosition::new(x, y), LogicalSize::new(width, height))
+    }
+
+    /// Converts `self` to another pixel type without loss.
+    #[inline]
+    pub fn cast<T: Pixel + From<P>>(self) -> LogicalRect<T> {
+        LogicalRect::new(self.origin.cast(), self.size.cast())
+    }
+
+    /// Converts `self` to another pixel type exactly, returning `None` if
+    /// any component can't be represented.
+    #[inline]
+    pub fn try_cast<T: Pixel>(self) -> Option<LogicalRect<T>> {
+        Some(LogicalRect::new(self.origin.try_cast()?, self.size.try_cast()?))
+    }
+
+    /// Converts `self` to another pixel type, clamping each component to
+    /// the range of `T`.
+    #[inline]
+    pub fn saturating_cast<T: Pixel>(self) -> LogicalRect<T> {
+        LogicalRect::new(self.origin.saturating_cast(), self.size.saturating_cast())
+    }
+
+    /// Scales `self` to a `PhysicalRect` by `scale_factor`.
+    #[inline]
+    pub fn to_physical_with(&self, scale_factor: ScaleFactor) -> PhysicalRect {
+        PhysicalRect::new(
+            self.origin.to_physical_with(scale_factor),
+            self.size.to_physical_with(scale_factor),
+        )
+    }
+
+    /// Scales `self` to a `PhysicalRect` by `dpi_factor`.
+    #[deprecated(note = "use `to_physical_with`, which takes a validated `ScaleFactor`")]
+    #[inline]
+    pub fn to_physical(&self, dpi_factor: f64) -> PhysicalRect {
+        self.to_physical_with(ScaleFactor::unchecked(dpi_factor))
+    }
+}
+
+impl LogicalRect {
+    /// Scales a `PhysicalRect` to a `LogicalRect` by `scale_factor`.
+    #[inline]
+    pub fn from_physical_with<P: Pixel>(rect: PhysicalRect<P>, scale_factor: ScaleFactor) -> Self {
+        rect.to_logical_with(scale_factor)
+    }
+
+    /// Scales a `PhysicalRect` to a `LogicalRect` by `dpi_factor`.
+    #[deprecated(note = "use `from_physical_with`, which takes a validated `ScaleFactor`")]
+    #[inline]
+    pub fn from_physical<P: Pixel>(rect: PhysicalRect<P>, dpi_factor: f64) -> Self {
+        Self::from_physical_with(rect, ScaleFactor::unchecked(dpi_factor))
+    }
+}
@@ -0,0 +1,269 @@
+use super::*;
+
+// Values this close to a pixel boundary are treated as on it, so that
+// floating-point error from scaling can't push an edge into the next pixel.
+const EPSILON: f64 = 1e-9;
+
+/// How fractional pixel values are rounded onto the pixel grid.
+///
+/// Rectangles are rounded by their edges rather than by their origin and
+/// size. Since adjacent rectangles share edges, they stay adjacent after
+/// rounding with the same policy, without gaps or overlaps.
+///
+/// Values within a billionth of a pixel boundary are considered to be on it
+/// by every policy.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum Rounding {
+    /// Rounds to the nearest pixel, with halfway cases rounded away from
+    /// zero.
+    Round,
+    /// Rounds down, towards negative infinity.
+    Floor,
+    /// Rounds up, towards positive infinity.
+    Ceil,
+    /// Rounds away from zero.
+    ///
+    /// For rectangles, this rounds the top and left edges down and the
+    /// bottom and right edges up, so that the result covers every pixel
+    /// that the original touches.
+    Outward,
+}
+
+impl Default for Rounding {
+    #[inline]
+    fn default() -> Self {
+        Rounding::Round
+    }
+}
+
+impl Rounding {
+    /// Rounds `value` according to `self`.
+    #[inline]
+    pub fn apply<P: Pixel>(self, value: P) -> P {
+        let nearest = value.round();
+        if (value.to_f64() - nearest.to_f64()).abs() < EPSILON {
+            return nearest;
+        }
+
+        match self {
+            Rounding::Round => value.round(),
+            Rounding::Floor => value.floor(),
+            Rounding::Ceil => value.ceil(),
+            Rounding::Outward if value < P::default() => value.floor(),
+            Rounding::Outward => value.ceil(),
+        }
+    }
+
+    /// Rounds the edges `min` and `max` of a span.
+    #[inline]
+    fn apply_span<P: Pixel>(self, min: P, max: P) -> (P, P) {
+        match self {
+            Rounding::Outward => (Rounding::Floor.apply(min), Rounding::Ceil.apply(max)),
+            _ => (self.apply(min), self.apply(max)),
+        }
+    }
+
+    /// Creates a rectangle from the edges `min` and `max` after rounding
+    /// them.
+    ///
+    /// The size never becomes negative.
+    fn apply_rect<P: Pixel>(
+        self,
+        min: PhysicalPosition<P>,
+        max: PhysicalPosition<P>,
+    ) -> PhysicalRect<P> {
+        let (x0, x1) = self.apply_span(min.x, max.x);
+        let (y0, y1) = self.apply_span(min.y, max.y);
+
+        let len = |min: P, max: P| if max > min { max - min } else { P::default() };
+        PhysicalRect::new(
+            PhysicalPosition::new(x0, y0),
+            PhysicalSize::new(len(x0, x1), len(y0, y1)),
+        )
+    }
+}
+
+macro_rules! impl_rounding {
+    ($($physical:ident $logical:ident;)+) => {
+        $(
+            impl<P: Pixel> $physical<P> {
+                /// Rounds each component according to `rounding`.
+                #[inline]
+                pub fn rounded(self, rounding: Rounding) -> Self {
+                    self.map(|v| rounding.apply(v))
+                }
+            }
+
+            impl<P: Pixel> $logical<P> {
+                /// Scales `self` by `scale_factor` and rounds the result
+                /// onto the physical pixel grid.
+                #[inline]
+                pub fn to_physical_rounded(
+                    &self,
+                    scale_factor: ScaleFactor,
+                    rounding: Rounding,
+                ) -> $physical {
+                    self.to_physical_with(scale_factor).rounded(rounding)
+                }
+
+                /// Moves `self` onto the nearest physical pixel boundaries at
+                /// `scale_factor`.
+                ///
+                /// Use [`to_physical_rounded`](#method.to_physical_rounded)
+                /// for other rounding policies.
+                #[inline]
+                pub fn snap_to_pixels(&self, scale_factor: ScaleFactor) -> $logical {
+                    self.to_physical_rounded(scale_factor, Rounding::Round)
+                        .to_logical_with(scale_factor)
+                }
+            }
+        )+
+    };
+}
+
+impl_rounding! {
+    PhysicalSize LogicalSize;
+    PhysicalPosition LogicalPosition;
+    PhysicalVector LogicalVector;
+}
+
+impl<P: Pixel> PhysicalRect<P> {
+    /// Rounds the edges of `self` according to `rounding`.
+    ///
+    /// The size never becomes negative.
+    #[inline]
+    pub fn rounded(self, rounding: Rounding) -> Self {
+        rounding.apply_rect(self.origin, self.max())
+    }
+}
+
+impl<P: Pixel> LogicalRect<P> {
+    /// Scales `self` by `scale_factor` and rounds its edges onto the
+    /// physical pixel grid.
+    #[inline]
+    pub fn to_physical_rounded(
+        &self,
+        scale_factor: ScaleFactor,
+        rounding: Rounding,
+    ) -> PhysicalRect {
+        // Edges are scaled directly, rather than through the size, so that
+        // rectangles sharing an edge get exactly the same scaled edge.
+        let min = self.origin.to_physical_with(scale_factor);
+        let max = self.max().to_physical_with(scale_factor);
+        rounding.apply_rect(min, max)
+    }
+
+    /// Moves the edges of `self` onto the nearest physical pixel boundaries
+    /// at `scale_factor`.
+    ///
+    /// Use [`to_physical_rounded`](#method.to_physical_rounded) for other
+    /// rounding policies.
+    #[inline]
+    pub fn snap_to_pixels(&self, scale_factor: ScaleFactor) -> LogicalRect {
+        self.to_physical_rounded(scale_factor, Rounding::Round)
+            .to_logical_with(scale_factor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCALES: &[f64] = &[1.0, 1.25, 1.5, 1.75, 2.0, 2.25, 3.0];
+
+    fn rect(x: f64, width: f64) -> LogicalRect {
+        LogicalRect::new(LogicalPosition::new(x, x), LogicalSize::new(width, width))
+    }
+
+    #[test]
+    fn adjacent_rects_stay_adjacent() {
+        for &scale in SCALES {
+            let scale_factor = ScaleFactor::new(scale).unwrap();
+            for &rounding in &[Rounding::Round, Rounding::Floor, Rounding::Ceil] {
+                for start in 0..40 {
+                    for width in 1..40 {
+                        let a = rect(f64::from(start) * 0.1, f64::from(width) * 0.1);
+                        let b_origin = LogicalPosition::new(a.max().x, a.origin.y);
+                        let b = LogicalRect::new(b_origin, a.size);
+
+                        let a = a.to_physical_rounded(scale_factor, rounding);
+                        let b = b.to_physical_rounded(scale_factor, rounding);
+                        assert_eq!(a.max().x, b.origin.x, "{:?} at {}", rounding, scale);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn outward_covers_touched_pixels() {
+        for &scale in SCALES {
+            let scale_factor = ScaleFactor::new(scale).unwrap();
+            for start in 0..40 {
+                for width in 1..40 {
+                    let rect = rect(f64::from(start) * 0.1, f64::from(width) * 0.1);
+                    let exact = rect.to_physical_with(scale_factor);
+                    let outward = rect.to_physical_rounded(scale_factor, Rounding::Outward);
+                    assert!(outward.origin.x <= exact.origin.x + EPSILON);
+                    assert!(outward.max().x >= exact.max().x - EPSILON);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn snapped_edges_land_on_pixels() {
+        for &scale in SCALES {
+            let scale_factor = ScaleFactor::new(scale).unwrap();
+            let snapped = rect(0.3, 10.1).snap_to_pixels(scale_factor);
+            let physical = snapped.to_physical_with(scale_factor);
+            for &edge in &[physical.origin.x, physical.max().x] {
+                assert!((edge - edge.round()).abs() < EPSILON, "{} at {}", edge, scale);
+            }
+        }
+    }
+
+    #[test]
+    fn policies() {
+        // A span from 0.3 to 10.4 points.
+        let rect = rect(0.3, 10.1);
+        let expected = [
+            // scale, Round, Floor, Ceil, Outward as (min, max) edges
+            (1.0, (0.0, 10.0), (0.0, 10.0), (1.0, 11.0), (0.0, 11.0)),
+            (1.25, (0.0, 13.0), (0.0, 13.0), (1.0, 13.0), (0.0, 13.0)),
+            (1.5, (0.0, 16.0), (0.0, 15.0), (1.0, 16.0), (0.0, 16.0)),
+            (1.75, (1.0, 18.0), (0.0, 18.0), (1.0, 19.0), (0.0, 19.0)),
+            (2.0, (1.0, 21.0), (0.0, 20.0), (1.0, 21.0), (0.0, 21.0)),
+            (2.25, (1.0, 23.0), (0.0, 23.0), (1.0, 24.0), (0.0, 24.0)),
+            (3.0, (1.0, 31.0), (0.0, 31.0), (1.0, 32.0), (0.0, 32.0)),
+        ];
+
+        for &(scale, round, floor, ceil, outward) in &expected {
+            let scale_factor = ScaleFactor::new(scale).unwrap();
+            for &(rounding, (min, max)) in &[
+                (Rounding::Round, round),
+                (Rounding::Floor, floor),
+                (Rounding::Ceil, ceil),
+                (Rounding::Outward, outward),
+            ] {
+                let rounded = rect.to_physical_rounded(scale_factor, rounding);
+                let edges = (rounded.origin.x, rounded.max().x);
+                assert_eq!(edges, (min, max), "{:?} at {}", rounding, scale);
+            }
+        }
+    }
+
+    #[test]
+    fn values_near_pixels_are_on_them() {
+        for &rounding in &[Rounding::Round, Rounding::Floor, Rounding::Ceil, Rounding::Outward] {
+            assert_eq!(rounding.apply(3.0 + 1e-12), 3.0);
+            assert_eq!(rounding.apply(-3.0 - 1e-12), -3.0);
+        }
+        assert_eq!(Rounding::Round.apply(2.5), 3.0);
+        assert_eq!(Rounding::Round.apply(-2.5), -3.0);
+        assert_eq!(Rounding::Outward.apply(-1.2), -2.0);
+        assert_eq!(Rounding::Outward.apply(1.2), 2.0);
+    }
+}
@@ -0,0 +1,171 @@
+use std::{convert::TryFrom, error::Error, fmt};
+
+/// The ratio of physical pixels to logical pixels, which is always finite and
+/// greater than zero.
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "f64", into = "f64"))]
+pub struct ScaleFactor(f64);
+
+impl Default for ScaleFactor {
+    #[inline]
+    fn default() -> Self {
+        ScaleFactor::ONE
+    }
+}
+
+impl TryFrom<f64> for ScaleFactor {
+    type Error = InvalidScaleFactor;
+
+    #[inline]
+    fn try_from(value: f64) -> Result<Self, Self::Error> {
+        ScaleFactor::new(value).ok_or(InvalidScaleFactor(value))
+    }
+}
+
+impl From<ScaleFactor> for f64 {
+    #[inline]
+    fn from(ScaleFactor(value): ScaleFactor) -> Self {
+        value
+    }
+}
+
+impl fmt::Display for ScaleFactor {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl ScaleFactor {
+    /// One physical pixel per logical pixel, as on standard displays.
+    pub const ONE: ScaleFactor = ScaleFactor(1.0);
+
+    /// 125% scaling.
+    pub const ONE_AND_A_QUARTER: ScaleFactor = ScaleFactor(1.25);
+
+    /// 150% scaling.
+    pub const ONE_AND_A_HALF: ScaleFactor = ScaleFactor(1.5);
+
+    /// 175% scaling.
+    pub const ONE_AND_THREE_QUARTERS: ScaleFactor = ScaleFactor(1.75);
+
+    /// Two physical pixels per logical pixel, as on Retina displays.
+    pub const TWO: ScaleFactor = ScaleFactor(2.0);
+
+    /// Three physical pixels per logical pixel, as on some phone displays.
+    pub const THREE: ScaleFactor = ScaleFactor(3.0);
+
+    /// Creates a scale factor of `value`, returning `None` if it is not
+    /// finite and greater than zero.
+    #[inline]
+    pub fn new(value: f64) -> Option<Self> {
+        if value.is_finite() && value > 0.0 {
+            Some(ScaleFactor(value))
+        } else {
+            None
+        }
+    }
+
+    /// Creates a scale factor from a percentage, such as `150` for
+    /// `ONE_AND_A_HALF`.
+    #[inline]
+    pub fn from_percent(percent: u32) -> Option<Self> {
+        Self::new(f64::from(percent) / 100.0)
+    }
+
+    /// Creates a scale factor without validating `value`, for the deprecated
+    /// methods that take a raw `f64`.
+    #[inline]
+    pub(crate) const fn unchecked(value: f64) -> Self {
+        ScaleFactor(value)
+    }
+
+    /// Returns the ratio as an `f64`.
+    #[inline]
+    pub fn get(self) -> f64 {
+        self.0
+    }
+}
+
+/// The error returned when creating a [`ScaleFactor`](struct.ScaleFactor.html)
+/// from a value that is not finite and greater than zero.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct InvalidScaleFactor(pub f64);
+
+impl fmt::Display for InvalidScaleFactor {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid scale factor {}, must be finite and greater than zero", self.0)
+    }
+}
+
+impl Error for InvalidScaleFactor {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dpi::{LogicalSize, PhysicalSize};
+
+    #[test]
+    fn rejects_invalid_values() {
+        for &value in &[0.0, -0.0, -1.0, f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+            assert_eq!(ScaleFactor::new(value), None, "{}", value);
+            assert!(ScaleFactor::try_from(value).is_err(), "{}", value);
+        }
+        assert_eq!(ScaleFactor::from_percent(0), None);
+    }
+
+    #[test]
+    fn accepts_valid_values() {
+        assert_eq!(ScaleFactor::new(1.5), Some(ScaleFactor::ONE_AND_A_HALF));
+        assert_eq!(ScaleFactor::try_from(2.0), Ok(ScaleFactor::TWO));
+        assert_eq!(ScaleFactor::new(f64::MIN_POSITIVE).map(ScaleFactor::get), Some(f64::MIN_POSITIVE));
+        assert_eq!(f64::from(ScaleFactor::THREE), 3.0);
+        assert_eq!(ScaleFactor::default(), ScaleFactor::ONE);
+    }
+
+    #[test]
+    fn percentages() {
+        let presets = [
+            (100, ScaleFactor::ONE),
+            (125, ScaleFactor::ONE_AND_A_QUARTER),
+            (150, ScaleFactor::ONE_AND_A_HALF),
+            (175, ScaleFactor::ONE_AND_THREE_QUARTERS),
+            (200, ScaleFactor::TWO),
+            (300, ScaleFactor::THREE),
+        ];
+        for &(percent, preset) in &presets {
+            assert_eq!(ScaleFactor::from_percent(percent), Some(preset), "{}%", percent);
+        }
+    }
+
+    #[test]
+    fn scales_dpi_types() {
+        let logical = LogicalSize::new(100.0, 50.0);
+        let physical = logical * ScaleFactor::ONE_AND_A_HALF;
+        assert_eq!(physical, PhysicalSize::new(150.0, 75.0));
+        assert_eq!(physical / ScaleFactor::ONE_AND_A_HALF, logical);
+        assert_eq!(logical.to_physical_with(ScaleFactor::TWO), PhysicalSize::new(200.0, 100.0));
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn serde_round_trips() {
+        let json = serde_json::to_string(&ScaleFactor::ONE_AND_A_HALF).unwrap();
+        assert_eq!(json, "1.5");
+        assert_eq!(serde_json::from_str::<ScaleFactor>(&json).unwrap(), ScaleFactor::ONE_AND_A_HALF);
+
+        let size = LogicalSize::new(10.0, 20.5);
+        let json = serde_json::to_string(&size).unwrap();
+        assert_eq!(serde_json::from_str::<LogicalSize>(&json).unwrap(), size);
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn serde_rejects_invalid_values() {
+        for json in &["0", "0.0", "-0.0", "-1.5"] {
+            assert!(serde_json::from_str::<ScaleFactor>(json).is_err(), "{}", json);
+        }
+    }
+}
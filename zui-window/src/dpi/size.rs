@@ -1,22 +1,29 @@
+use super::{Pixel, PhysicalVector, LogicalVector, ScaleFactor};
+
 /// A size represented in physical pixels.
-#[derive(Clone, Copy, Debug, PartialEq)]
-pub struct PhysicalSize {
+///
+/// The scalar type `P` defaults to `f64`. Integer sizes, such as those of
+/// GPU buffers, can be obtained with [`try_cast`](#method.try_cast) or
+/// [`saturating_cast`](#method.saturating_cast) after rounding.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PhysicalSize<P = f64> {
     /// The width in pixels.
-    pub width: f64,
+    pub width: P,
     /// The height in pixels.
-    pub height: f64,
+    pub height: P,
 }
 
-impl From<(f64, f64)> for PhysicalSize {
+impl<P: Pixel> From<(P, P)> for PhysicalSize<P> {
     #[inline]
-    fn from((w, h): (f64, f64)) -> Self {
+    fn from((w, h): (P, P)) -> Self {
         Self::new(w, h)
     }
 }
 
-impl From<[f64; 2]> for PhysicalSize {
+impl<P: Pixel> From<[P; 2]> for PhysicalSize<P> {
     #[inline]
-    fn from([w, h]: [f64; 2]) -> Self {
+    fn from([w, h]: [P; 2]) -> Self {
         Self::new(w, h)
     }
 }
@@ -35,86 +42,156 @@ impl From<[i32; 2]> for PhysicalSize {
     }
 }
 
-impl From<PhysicalSize> for (f64, f64) {
+impl<P: Pixel> From<PhysicalSize<P>> for (P, P) {
     #[inline]
-    fn from(PhysicalSize { width, height }: PhysicalSize) -> Self {
+    fn from(PhysicalSize { width, height }: PhysicalSize<P>) -> Self {
         (width, height)
     }
 }
 
-impl From<PhysicalSize> for [f64; 2] {
+impl<P: Pixel> From<PhysicalSize<P>> for [P; 2] {
     #[inline]
-    fn from(PhysicalSize { width, height }: PhysicalSize) -> Self {
+    fn from(PhysicalSize { width, height }: PhysicalSize<P>) -> Self {
         [width, height]
     }
 }
 
-impl From<PhysicalSize> for (i32, i32) {
-    #[inline]
-    fn from(PhysicalSize { width, height }: PhysicalSize) -> Self {
-        (width.round() as _, height.round() as _)
-    }
-}
-
-impl From<PhysicalSize> for [i32; 2] {
+impl<P> PhysicalSize<P> {
+    /// Creates a new size of `width` and `height`.
     #[inline]
-    fn from(PhysicalSize { width, height }: PhysicalSize) -> Self {
-        [width.round() as _, height.round() as _]
+    pub const fn new(width: P, height: P) -> Self {
+        PhysicalSize { width, height }
     }
 }
 
-impl PhysicalSize {
-    /// Creates a new size of `width` and `height`.
+impl<P: Pixel> PhysicalSize<P> {
+    /// Returns the vector spanning `self`.
     #[inline]
-    pub const fn new(width: f64, height: f64) -> Self {
-        PhysicalSize { width, height }
+    pub fn to_vector(self) -> PhysicalVector<P> {
+        PhysicalVector::new(self.width, self.height)
     }
 
     /// Returns a new size with the width and height updated by `f`.
     #[inline]
-    pub fn map<F: FnMut(f64) -> f64>(self, mut f: F) -> Self {
+    pub fn map<F: FnMut(P) -> P>(self, mut f: F) -> Self {
         Self::new(f(self.width), f(self.height))
     }
 
     /// Updates the width and height with `f`.
     #[inline]
-    pub fn apply<'a, F: FnMut(&'a mut f64)>(&'a mut self, mut f: F) {
+    pub fn apply<'a, F: FnMut(&'a mut P)>(&'a mut self, mut f: F) {
         f(&mut self.width);
         f(&mut self.height);
     }
 
-    /// Scales a `LogicalSize` to a `PhysicalSize` by `dpi_factor`.
+    /// Converts `self` to another pixel type without loss.
+    ///
+    /// For example, a `PhysicalSize<u32>` can be cast to `PhysicalSize<f64>`.
+    #[inline]
+    pub fn cast<T: Pixel + From<P>>(self) -> PhysicalSize<T> {
+        PhysicalSize::new(self.width.into(), self.height.into())
+    }
+
+    /// Converts `self` to another pixel type exactly, returning `None` if
+    /// either component can't be represented.
+    ///
+    /// Fractional values are not representable by integer types, so
+    /// sizes should first be rounded with [`round`](#method.round),
+    /// [`floor`](#method.floor) or [`ceil`](#method.ceil).
+    #[inline]
+    pub fn try_cast<T: Pixel>(self) -> Option<PhysicalSize<T>> {
+        Some(PhysicalSize::new(
+            T::checked_from_f64(self.width.to_f64())?,
+            T::checked_from_f64(self.height.to_f64())?,
+        ))
+    }
+
+    /// Converts `self` to another pixel type, clamping each component to
+    /// the range of `T`.
+    ///
+    /// See [`Pixel::saturating_from_f64`](trait.Pixel.html#tymethod.saturating_from_f64)
+    /// for how fractional and NaN values are handled.
+    #[inline]
+    pub fn saturating_cast<T: Pixel>(self) -> PhysicalSize<T> {
+        PhysicalSize::new(
+            T::saturating_from_f64(self.width.to_f64()),
+            T::saturating_from_f64(self.height.to_f64()),
+        )
+    }
+
+    /// Rounds each component to the nearest integer, with halfway cases
+    /// rounded away from zero.
+    #[inline]
+    pub fn round(self) -> Self {
+        self.map(Pixel::round)
+    }
+
+    /// Rounds each component down to an integer.
+    #[inline]
+    pub fn floor(self) -> Self {
+        self.map(Pixel::floor)
+    }
+
+    /// Rounds each component up to an integer.
+    #[inline]
+    pub fn ceil(self) -> Self {
+        self.map(Pixel::ceil)
+    }
+
+    /// Scales `self` to a `LogicalSize` by `scale_factor`.
     #[inline]
-    pub fn from_logical(size: LogicalSize, dpi_factor: f64) -> Self {
-        size.to_physical(dpi_factor)
+    pub fn to_logical_with(&self, scale_factor: ScaleFactor) -> LogicalSize {
+        LogicalSize::new(
+            self.width.to_f64() / scale_factor.get(),
+            self.height.to_f64() / scale_factor.get(),
+        )
     }
 
     /// Scales `self` to a `LogicalSize` by `dpi_factor`.
+    #[deprecated(note = "use `to_logical_with`, which takes a validated `ScaleFactor`")]
     #[inline]
     pub fn to_logical(&self, dpi_factor: f64) -> LogicalSize {
-        (self.width / dpi_factor, self.height / dpi_factor).into()
+        self.to_logical_with(ScaleFactor::unchecked(dpi_factor))
+    }
+}
+
+impl PhysicalSize {
+    /// Scales a `LogicalSize` to a `PhysicalSize` by `scale_factor`.
+    #[inline]
+    pub fn from_logical_with<P: Pixel>(size: LogicalSize<P>, scale_factor: ScaleFactor) -> Self {
+        size.to_physical_with(scale_factor)
+    }
+
+    /// Scales a `LogicalSize` to a `PhysicalSize` by `dpi_factor`.
+    #[deprecated(note = "use `from_logical_with`, which takes a validated `ScaleFactor`")]
+    #[inline]
+    pub fn from_logical<P: Pixel>(size: LogicalSize<P>, dpi_factor: f64) -> Self {
+        Self::from_logical_with(size, ScaleFactor::unchecked(dpi_factor))
     }
 }
 
 /// A size represented in logical pixels, or "points".
-#[derive(Clone, Copy, Debug, PartialEq)]
-pub struct LogicalSize {
+///
+/// The scalar type `P` defaults to `f64`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LogicalSize<P = f64> {
     /// The width in points.
-    pub width: f64,
+    pub width: P,
     /// The height in points.
-    pub height: f64,
+    pub height: P,
 }
 
-impl From<(f64, f64)> for LogicalSize {
+impl<P: Pixel> From<(P, P)> for LogicalSize<P> {
     #[inline]
-    fn from((w, h): (f64, f64)) -> Self {
+    fn from((w, h): (P, P)) -> Self {
         Self::new(w, h)
     }
 }
 
-impl From<[f64; 2]> for LogicalSize {
+impl<P: Pixel> From<[P; 2]> for LogicalSize<P> {
     #[inline]
-    fn from([w, h]: [f64; 2]) -> Self {
+    fn from([w, h]: [P; 2]) -> Self {
         Self::new(w, h)
     }
 }
@@ -133,63 +210,198 @@ impl From<[i32; 2]> for LogicalSize {
     }
 }
 
-impl From<LogicalSize> for (f64, f64) {
+impl<P: Pixel> From<LogicalSize<P>> for (P, P) {
     #[inline]
-    fn from(LogicalSize { width, height }: LogicalSize) -> Self {
+    fn from(LogicalSize { width, height }: LogicalSize<P>) -> Self {
         (width, height)
     }
 }
 
-impl From<LogicalSize> for [f64; 2] {
+impl<P: Pixel> From<LogicalSize<P>> for [P; 2] {
     #[inline]
-    fn from(LogicalSize { width, height }: LogicalSize) -> Self {
+    fn from(LogicalSize { width, height }: LogicalSize<P>) -> Self {
         [width, height]
     }
 }
 
-impl From<LogicalSize> for (i32, i32) {
+impl<P> LogicalSize<P> {
+    /// Creates a new size of `width` and `height`.
     #[inline]
-    fn from(LogicalSize { width, height }: LogicalSize) -> Self {
-        (width.round() as _, height.round() as _)
+    pub const fn new(width: P, height: P) -> Self {
+        LogicalSize { width, height }
     }
 }
 
-impl From<LogicalSize> for [i32; 2] {
+impl<P: Pixel> LogicalSize<P> {
+    /// Returns the vector spanning `self`.
     #[inline]
-    fn from(LogicalSize { width, height }: LogicalSize) -> Self {
-        [width.round() as _, height.round() as _]
-    }
-}
-
-impl LogicalSize {
-    /// Creates a new size of `width` and `height`.
-    #[inline]
-    pub const fn new(width: f64, height: f64) -> Self {
-        LogicalSize { width, height }
+    pub fn to_vector(self) -> LogicalVector<P> {
+        LogicalVector::new(self.width, self.height)
     }
 
     /// Returns a new size with the width and height updated by `f`.
     #[inline]
-    pub fn map<F: FnMut(f64) -> f64>(self, mut f: F) -> Self {
+    pub fn map<F: FnMut(P) -> P>(self, mut f: F) -> Self {
         Self::new(f(self.width), f(self.height))
     }
 
     /// Updates the width and height with `f`.
     #[inline]
-    pub fn apply<'a, F: FnMut(&'a mut f64)>(&'a mut self, mut f: F) {
+    pub fn apply<'a, F: FnMut(&'a mut P)>(&'a mut self, mut f: F) {
         f(&mut self.width);
         f(&mut self.height);
     }
 
-    /// Scales a `LogicalSize` to a `PhysicalSize` by `dpi_factor`.
+    /// Converts `self` to another pixel type without loss.
+    ///
+    /// For example, a `LogicalSize<u32>` can be cast to `LogicalSize<f64>`.
     #[inline]
-    pub fn from_physical(size: PhysicalSize, dpi_factor: f64) -> Self {
-        size.to_logical(dpi_factor)
+    pub fn cast<T: Pixel + From<P>>(self) -> LogicalSize<T> {
+        LogicalSize::new(self.width.into(), self.height.into())
     }
 
-    /// Scales `self` to a `LogicalSize` by `dpi_factor`.
+    /// Converts `self` to another pixel type exactly, returning `None` if
+    /// either component can't be represented.
+    ///
+    /// Fractional values are not representable by integer types, so
+    /// sizes should first be rounded with [`round`](#method.round),
+    /// [`floor`](#method.floor) or [`ceil`](#method.ceil).
+    #[inline]
+    pub fn try_cast<T: Pixel>(self) -> Option<LogicalSize<T>> {
+        Some(LogicalSize::new(
+            T::checked_from_f64(self.width.to_f64())?,
+            T::checked_from_f64(self.height.to_f64())?,
+        ))
+    }
+
+    /// Converts `self` to another pixel type, clamping each component to
+    /// the range of `T`.
+    ///
+    /// See [`Pixel::saturating_from_f64`](trait.Pixel.html#tymethod.saturating_from_f64)
+    /// for how fractional and NaN values are handled.
+    #[inline]
+    pub fn saturating_cast<T: Pixel>(self) -> LogicalSize<T> {
+        LogicalSize::new(
+            T::saturating_from_f64(self.width.to_f64()),
+            T::saturating_from_f64(self.height.to_f64()),
+        )
+    }
+
+    /// Rounds each component to the nearest integer, with halfway cases
+    /// rounded away from zero.
+    #[inline]
+    pub fn round(self) -> Self {
+        self.map(Pixel::round)
+    }
+
+    /// Rounds each component down to an integer.
+    #[inline]
+    pub fn floor(self) -> Self {
+        self.map(Pixel::floor)
+    }
+
+    /// Rounds each component up to an integer.
+    #[inline]
+    pub fn ceil(self) -> Self {
+        self.map(Pixel::ceil)
+    }
+
+    /// Scales `self` to a `PhysicalSize` by `scale_factor`.
+    #[inline]
+    pub fn to_physical_with(&self, scale_factor: ScaleFactor) -> PhysicalSize {
+        PhysicalSize::new(
+            self.width.to_f64() * scale_factor.get(),
+            self.height.to_f64() * scale_factor.get(),
+        )
+    }
+
+    /// Scales `self` to a `PhysicalSize` by `dpi_factor`.
+    #[deprecated(note = "use `to_physical_with`, which takes a validated `ScaleFactor`")]
+    #[inline]
+    pub fn to_physical(&self, dpi_factor: f64) -> PhysicalSize {
+        self.to_physical_with(ScaleFactor::unchecked(dpi_factor))
+    }
+}
+
+impl LogicalSize {
+    /// Scales a `PhysicalSize` to a `LogicalSize` by `scale_factor`.
+    #[inline]
+    pub fn from_physical_with<P: Pixel>(size: PhysicalSize<P>, scale_factor: ScaleFactor) -> Self {
+        size.to_logical_with(scale_factor)
+    }
+
+    /// Scales a `PhysicalSize` to a `LogicalSize` by `dpi_factor`.
+    #[deprecated(note = "use `from_physical_with`, which takes a validated `ScaleFactor`")]
+    #[inline]
+    pub fn from_physical<P: Pixel>(size: PhysicalSize<P>, dpi_factor: f64) -> Self {
+        Self::from_physical_with(size, ScaleFactor::unchecked(dpi_factor))
+    }
+}
+
+/// A size in either logical or physical pixels.
+///
+/// APIs that take this accept either unit, and resolve it against the
+/// relevant scale factor when needed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum Size {
+    /// A size in logical pixels.
+    Logical(LogicalSize),
+    /// A size in physical pixels.
+    Physical(PhysicalSize),
+}
+
+impl<P: Pixel> From<LogicalSize<P>> for Size
+where
+    f64: From<P>,
+{
+    #[inline]
+    fn from(size: LogicalSize<P>) -> Self {
+        Size::Logical(size.cast())
+    }
+}
+
+impl<P: Pixel> From<PhysicalSize<P>> for Size
+where
+    f64: From<P>,
+{
+    #[inline]
+    fn from(size: PhysicalSize<P>) -> Self {
+        Size::Physical(size.cast())
+    }
+}
+
+impl Size {
+    /// Returns `self` in logical pixels, scaling by `scale_factor` if needed.
+    #[inline]
+    pub fn to_logical_with(&self, scale_factor: ScaleFactor) -> LogicalSize {
+        match *self {
+            Size::Logical(size) => size,
+            Size::Physical(size) => size.to_logical_with(scale_factor),
+        }
+    }
+
+    /// Returns `self` in logical pixels, scaling by `dpi_factor` if needed.
+    #[deprecated(note = "use `to_logical_with`, which takes a validated `ScaleFactor`")]
+    #[inline]
+    pub fn to_logical(&self, dpi_factor: f64) -> LogicalSize {
+        self.to_logical_with(ScaleFactor::unchecked(dpi_factor))
+    }
+
+    /// Returns `self` in physical pixels, scaling by `scale_factor` if needed.
+    #[inline]
+    pub fn to_physical_with(&self, scale_factor: ScaleFactor) -> PhysicalSize {
+        match *self {
+            Size::Logical(size) => size.to_physical_with(scale_factor),
+            Size::Physical(size) => size,
+        }
+    }
+
+    /// Returns `self` in physical pixels, scaling by `dpi_factor` if needed.
+    #[deprecated(note = "use `to_physical_with`, which takes a validated `ScaleFactor`")]
     #[inline]
     pub fn to_physical(&self, dpi_factor: f64) -> PhysicalSize {
-        (self.width * dpi_factor, self.height * dpi_factor).into()
+        self.to_physical_with(ScaleFactor::unchecked(dpi_factor))
     }
 }
@@ -0,0 +1,260 @@
+use super::*;
+
+/// An offset represented in physical pixels.
+///
+/// Vectors are the difference between two positions, and positions can be
+/// offset by them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PhysicalVector<P = f64> {
+    /// The X offset in pixels.
+    pub x: P,
+    /// The Y offset in pixels.
+    pub y: P,
+}
+
+impl<P: Pixel> From<(P, P)> for PhysicalVector<P> {
+    #[inline]
+    fn from((x, y): (P, P)) -> Self {
+        Self::new(x, y)
+    }
+}
+
+impl<P: Pixel> From<[P; 2]> for PhysicalVector<P> {
+    #[inline]
+    fn from([x, y]: [P; 2]) -> Self {
+        Self::new(x, y)
+    }
+}
+
+impl<P: Pixel> From<PhysicalVector<P>> for (P, P) {
+    #[inline]
+    fn from(PhysicalVector { x, y }: PhysicalVector<P>) -> Self {
+        (x, y)
+    }
+}
+
+impl<P: Pixel> From<PhysicalVector<P>> for [P; 2] {
+    #[inline]
+    fn from(PhysicalVector { x, y }: PhysicalVector<P>) -> Self {
+        [x, y]
+    }
+}
+
+impl<P: Pixel> From<PhysicalSize<P>> for PhysicalVector<P> {
+    #[inline]
+    fn from(size: PhysicalSize<P>) -> Self {
+        size.to_vector()
+    }
+}
+
+impl<P> PhysicalVector<P> {
+    /// Creates a new vector of `x` and `y`.
+    #[inline]
+    pub const fn new(x: P, y: P) -> Self {
+        PhysicalVector { x, y }
+    }
+}
+
+impl<P: Pixel> PhysicalVector<P> {
+    /// Returns a new vector with X and Y updated by `f`.
+    #[inline]
+    pub fn map<F: FnMut(P) -> P>(self, mut f: F) -> Self {
+        Self::new(f(self.x), f(self.y))
+    }
+
+    /// Updates X and Y with `f`.
+    #[inline]
+    pub fn apply<'a, F: FnMut(&'a mut P)>(&'a mut self, mut f: F) {
+        f(&mut self.x);
+        f(&mut self.y);
+    }
+
+    /// Returns the position offset from the origin by `self`.
+    #[inline]
+    pub fn to_position(self) -> PhysicalPosition<P> {
+        PhysicalPosition::new(self.x, self.y)
+    }
+
+    /// Returns the length of `self`.
+    #[inline]
+    pub fn length(&self) -> f64 {
+        self.x.to_f64().hypot(self.y.to_f64())
+    }
+
+    /// Converts `self` to another pixel type without loss.
+    #[inline]
+    pub fn cast<T: Pixel + From<P>>(self) -> PhysicalVector<T> {
+        PhysicalVector::new(self.x.into(), self.y.into())
+    }
+
+    /// Converts `self` to another pixel type exactly, returning `None` if
+    /// either component can't be represented.
+    #[inline]
+    pub fn try_cast<T: Pixel>(self) -> Option<PhysicalVector<T>> {
+        self.to_position().try_cast().map(PhysicalPosition::to_vector)
+    }
+
+    /// Converts `self` to another pixel type, clamping each component to
+    /// the range of `T`.
+    #[inline]
+    pub fn saturating_cast<T: Pixel>(self) -> PhysicalVector<T> {
+        self.to_position().saturating_cast().to_vector()
+    }
+
+    /// Scales `self` to a `LogicalVector` by `scale_factor`.
+    #[inline]
+    pub fn to_logical_with(&self, scale_factor: ScaleFactor) -> LogicalVector {
+        self.to_position().to_logical_with(scale_factor).to_vector()
+    }
+
+    /// Scales `self` to a `LogicalVector` by `dpi_factor`.
+    #[deprecated(note = "use `to_logical_with`, which takes a validated `ScaleFactor`")]
+    #[inline]
+    pub fn to_logical(&self, dpi_factor: f64) -> LogicalVector {
+        self.to_logical_with(ScaleFactor::unchecked(dpi_factor))
+    }
+}
+
+impl PhysicalVector {
+    /// Scales a `LogicalVector` to a `PhysicalVector` by `scale_factor`.
+    #[inline]
+    pub fn from_logical_with<P: Pixel>(vector: LogicalVector<P>, scale_factor: ScaleFactor) -> Self {
+        vector.to_physical_with(scale_factor)
+    }
+
+    /// Scales a `LogicalVector` to a `PhysicalVector` by `dpi_factor`.
+    #[deprecated(note = "use `from_logical_with`, which takes a validated `ScaleFactor`")]
+    #[inline]
+    pub fn from_logical<P: Pixel>(vector: LogicalVector<P>, dpi_factor: f64) -> Self {
+        Self::from_logical_with(vector, ScaleFactor::unchecked(dpi_factor))
+    }
+}
+
+/// An offset represented in logical pixels, or "points".
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LogicalVector<P = f64> {
+    /// The X offset in points.
+    pub x: P,
+    /// The Y offset in points.
+    pub y: P,
+}
+
+impl<P: Pixel> From<(P, P)> for LogicalVector<P> {
+    #[inline]
+    fn from((x, y): (P, P)) -> Self {
+        Self::new(x, y)
+    }
+}
+
+impl<P: Pixel> From<[P; 2]> for LogicalVector<P> {
+    #[inline]
+    fn from([x, y]: [P; 2]) -> Self {
+        Self::new(x, y)
+    }
+}
+
+impl<P: Pixel> From<LogicalVector<P>> for (P, P) {
+    #[inline]
+    fn from(LogicalVector { x, y }: LogicalVector<P>) -> Self {
+        (x, y)
+    }
+}
+
+impl<P: Pixel> From<LogicalVector<P>> for [P; 2] {
+    #[inline]
+    fn from(LogicalVector { x, y }: LogicalVector<P>) -> Self {
+        [x, y]
+    }
+}
+
+impl<P: Pixel> From<LogicalSize<P>> for LogicalVector<P> {
+    #[inline]
+    fn from(size: LogicalSize<P>) -> Self {
+        size.to_vector()
+    }
+}
+
+impl<P> LogicalVector<P> {
+    /// Creates a new vector of `x` and `y`.
+    #[inline]
+    pub const fn new(x: P, y: P) -> Self {
+        LogicalVector { x, y }
+    }
+}
+
+impl<P: Pixel> LogicalVector<P> {
+    /// Returns a new vector with X and Y updated by `f`.
+    #[inline]
+    pub fn map<F: FnMut(P) -> P>(self, mut f: F) -> Self {
+        Self::new(f(self.x), f(self.y))
+    }
+
+    /// Updates X and Y with `f`.
+    #[inline]
+    pub fn apply<'a, F: FnMut(&'a mut P)>(&'a mut self, mut f: F) {
+        f(&mut self.x);
+        f(&mut self.y);
+    }
+
+    /// Returns the position offset from the origin by `self`.
+    #[inline]
+    pub fn to_position(self) -> LogicalPosition<P> {
+        LogicalPosition::new(self.x, self.y)
+    }
+
+    /// Returns the length of `self`.
+    #[inline]
+    pub fn length(&self) -> f64 {
+        self.x.to_f64().hypot(self.y.to_f64())
+    }
+
+    /// Converts `self` to another pixel type without loss.
+    #[inline]
+    pub fn cast<T: Pixel + From<P>>(self) -> LogicalVector<T> {
+        LogicalVector::new(self.x.into(), self.y.into())
+    }
+
+    /// Converts `self` to another pixel type exactly, returning `None` if
+    /// either component can't be represented.
+    #[inline]
+    pub fn try_cast<T: Pixel>(self) -> Option<LogicalVector<T>> {
+        self.to_position().try_cast().map(LogicalPosition::to_vector)
+    }
+
+    /// Converts `self` to another pixel type, clamping each component to
+    /// the range of `T`.
+    #[inline]
+    pub fn saturating_cast<T: Pixel>(self) -> LogicalVector<T> {
+        self.to_position().saturating_cast().to_vector()
+    }
+
+    /// Scales `self` to a `PhysicalVector` by `scale_factor`.
+    #[inline]
+    pub fn to_physical_with(&self, scale_factor: ScaleFactor) -> PhysicalVector {
+        self.to_position().to_physical_with(scale_factor).to_vector()
+    }
+
+    /// Scales `self` to a `PhysicalVector` by `dpi_factor`.
+    #[deprecated(note = "use `to_physical_with`, which takes a validated `ScaleFactor`")]
+    #[inline]
+    pub fn to_physical(&self, dpi_factor: f64) -> PhysicalVector {
+        self.to_physical_with(ScaleFactor::unchecked(dpi_factor))
+    }
+}
+
+impl LogicalVector {
+    /// Scales a `PhysicalVector` to a `LogicalVector` by `scale_factor`.
+    #[inline]
+    pub fn from_physical_with<P: Pixel>(vector: PhysicalVector<P>, scale_factor: ScaleFactor) -> Self {
+        vector.to_logical_with(scale_factor)
+    }
+
+    /// Scales a `PhysicalVector` to a `LogicalVector` by `dpi_factor`.
+    #[deprecated(note = "use `from_physical_with`, which takes a validated `ScaleFactor`")]
+    #[inline]
+    pub fn from_physical<P: Pixel>(vector: PhysicalVector<P>, dpi_factor: f64) -> Self {
+        Self::from_physical_with(vector, ScaleFactor::unchecked(dpi_factor))
+    }
+}
@@ -0,0 +1,129 @@
+//! Dragging data out of a [`Window`](../struct.Window.html).
+
+use std::ops;
+
+/// An operation that a drag destination may perform with dragged data.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum DragOperation {
+    /// The data is copied.
+    Copy,
+    /// The data is moved, so the source should remove its copy.
+    Move,
+    /// The data is linked to, such as with an alias or shortcut.
+    Link,
+}
+
+/// A set of [`DragOperation`](enum.DragOperation.html)s.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct DragOperations(u8);
+
+impl Default for DragOperations {
+    #[inline]
+    fn default() -> Self {
+        DragOperations::COPY
+    }
+}
+
+impl From<DragOperation> for DragOperations {
+    #[inline]
+    fn from(operation: DragOperation) -> Self {
+        match operation {
+            DragOperation::Copy => DragOperations::COPY,
+            DragOperation::Move => DragOperations::MOVE,
+            DragOperation::Link => DragOperations::LINK,
+        }
+    }
+}
+
+impl ops::BitOr for DragOperations {
+    type Output = Self;
+
+    #[inline]
+    fn bitor(self, other: Self) -> Self {
+        DragOperations(self.0 | other.0)
+    }
+}
+
+impl ops::BitOrAssign for DragOperations {
+    #[inline]
+    fn bitor_assign(&mut self, other: Self) {
+        self.0 |= other.0;
+    }
+}
+
+impl DragOperations {
+    /// No operations.
+    pub const NONE: Self = DragOperations(0);
+
+    /// Allows [`DragOperation::Copy`](enum.DragOperation.html#variant.Copy).
+    pub const COPY: Self = DragOperations(1);
+
+    /// Allows [`DragOperation::Move`](enum.DragOperation.html#variant.Move).
+    pub const MOVE: Self = DragOperations(1 << 1);
+
+    /// Allows [`DragOperation::Link`](enum.DragOperation.html#variant.Link).
+    pub const LINK: Self = DragOperations(1 << 2);
+
+    /// Allows every operation.
+    pub const ALL: Self = DragOperations(0b111);
+
+    /// Returns whether `self` allows `operation`.
+    #[inline]
+    pub fn contains(self, operation: DragOperation) -> bool {
+        let bits = DragOperations::from(operation).0;
+        self.0 & bits == bits
+    }
+
+    /// Returns whether `self` allows no operations.
+    #[inline]
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+}
+
+/// The data carried by a drag started with
+/// [`Window::start_drag`](../struct.Window.html#method.start_drag).
+#[derive(Clone, Debug, Default)]
+pub struct DragData {
+    pub(crate) text: Option<String>,
+    pub(crate) uris: Vec<String>,
+    pub(crate) data: Vec<(String, Vec<u8>)>,
+    pub(crate) operations: DragOperations,
+}
+
+impl DragData {
+    /// Creates an instance with no data that only allows copying.
+    #[inline]
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Sets the plain text being dragged.
+    #[inline]
+    pub fn text(&mut self, text: &str) -> &mut Self {
+        self.text = Some(text.into());
+        self
+    }
+
+    /// Adds a URI being dragged, such as a `file://` URL for exporting a file.
+    #[inline]
+    pub fn uri(&mut self, uri: &str) -> &mut Self {
+        self.uris.push(uri.into());
+        self
+    }
+
+    /// Adds `data` of type `mime`, replacing any data previously added with
+    /// the same type, regardless of parameters such as `charset`.
+    #[inline]
+    pub fn data(&mut self, mime: &str, data: &[u8]) -> &mut Self {
+        shared::mime::set_format(&mut self.data, mime, data);
+        self
+    }
+
+    /// Sets the operations that a destination may perform.
+    #[inline]
+    pub fn operations<O: Into<DragOperations>>(&mut self, operations: O) -> &mut Self {
+        self.operations = operations.into();
+        self
+    }
+}
@@ -0,0 +1,74 @@
+//! Events delivered to a [`Window`](../struct.Window.html).
+
+use std::{
+    ops::Range,
+    path::PathBuf,
+};
+use crate::{
+    dpi::LogicalPosition,
+    drag::DragOperation,
+};
+
+/// An event received by a [`Window`](../struct.Window.html).
+///
+/// Events are passed to the handler set with
+/// [`Window::set_event_handler`](../struct.Window.html#method.set_event_handler).
+#[derive(Clone, Debug, PartialEq)]
+pub enum WindowEvent {
+    /// A file is being dragged over the window.
+    ///
+    /// This is emitted once for each file when the drag enters the window,
+    /// and again whenever the drag moves within it.
+    HoveredFile {
+        /// The path of the dragged file.
+        path: PathBuf,
+        /// Where the drag is located within the content area.
+        position: LogicalPosition,
+    },
+    /// A file was dropped onto the window.
+    ///
+    /// This is emitted once for each dropped file.
+    DroppedFile {
+        /// The path of the dropped file.
+        path: PathBuf,
+        /// Where the file was dropped within the content area.
+        position: LogicalPosition,
+    },
+    /// Files that were being dragged over the window left it or the drag was
+    /// cancelled.
+    HoveredFileCancelled,
+    /// A drag started with
+    /// [`Window::start_drag`](../struct.Window.html#method.start_drag) ended.
+    DragEnded {
+        /// The operation performed by the destination, or `None` if the drag
+        /// was cancelled.
+        operation: Option<DragOperation>,
+    },
+    /// The text being composed by the input method editor (IME) changed.
+    ///
+    /// An empty `text` means that composition ended or was cancelled.
+    Preedit {
+        /// The text being composed, which has not yet been committed.
+        text: String,
+        /// The byte range within `text` of the cursor or selection, if any.
+        cursor: Option<Range<usize>>,
+    },
+    /// Text was committed by the input method editor (IME) or typed directly.
+    Commit {
+        /// The committed text.
+        text: String,
+    },
+    /// The content area was right-clicked while no context menu was set.
+    ContextMenuRequested {
+        /// Where the click occurred within the content area.
+        position: LogicalPosition,
+    },
+    /// The window gained or lost keyboard focus.
+    Focused(bool),
+    /// The window was minimized or restored from being minimized.
+    Minimized(bool),
+    /// The window was maximized or restored from being maximized.
+    Maximized(bool),
+    /// The window entered or exited fullscreen.
+    Fullscreen(bool),
+}
@@ -7,17 +7,35 @@
 #[macro_use]
 extern crate objc;
 
+extern crate zui_menu as menu;
 extern crate zui_shared as shared;
 
 use std::{
     fmt,
-    marker::PhantomData
+    hash::{Hash, Hasher},
+    marker::PhantomData,
+    sync::atomic::{AtomicU64, Ordering},
 };
+use menu::Menu;
 use shared::ZedString;
+use dpi::{LogicalInsets, LogicalPosition, LogicalRect, LogicalSize, Position, ScaleFactor, Size};
+use drag::DragData;
+use event::WindowEvent;
+use state::WindowStateStore;
 
 mod sys;
+#[cfg(feature = "serde")]
+mod config;
 pub mod dpi;
+pub mod drag;
+pub mod event;
 pub mod os;
+pub mod state;
+
+#[cfg(feature = "serde")]
+pub use config::WindowConfig;
+#[doc(inline)]
+pub use shared::Icon;
 
 /// A handle to a window instance.
 ///
@@ -47,6 +65,22 @@ impl fmt::Debug for Window {
     }
 }
 
+impl PartialEq for Window {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.sys == other.sys
+    }
+}
+
+impl Eq for Window {}
+
+impl Hash for Window {
+    #[inline]
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.sys.hash(state);
+    }
+}
+
 impl Window {
     /// Creates a builder suitable for constructing a new `Window` instance.
     #[inline]
@@ -54,17 +88,459 @@ impl Window {
         Default::default()
     }
 
+    /// Returns the open window identified by `id`, if any.
+    #[inline]
+    pub fn from_id(id: WindowId) -> Option<Window> {
+        sys::window(id).map(Into::into)
+    }
+
+    /// Returns the unique identifier of `self`.
+    #[inline]
+    pub fn id(&self) -> WindowId {
+        self.sys.id()
+    }
+
+    /// Creates a handle to `self` that does not keep it alive.
+    #[inline]
+    pub fn downgrade(&self) -> WeakWindow {
+        WeakWindow { id: self.id(), _marker: PhantomData }
+    }
+
     /// Sets the displayed title.
     #[inline]
     pub fn set_title<S: Into<ZedString>>(&self, title: S) {
         self.sys.set_title(title.into());
     }
+
+    /// Returns the ratio of physical pixels to logical pixels for the
+    /// monitor that the window is on.
+    #[inline]
+    pub fn scale_factor(&self) -> ScaleFactor {
+        self.sys.scale_factor()
+    }
+
+    /// Returns the size of the content area.
+    #[inline]
+    pub fn inner_size(&self) -> LogicalSize {
+        self.sys.inner_size()
+    }
+
+    /// Resizes the content area to `size`.
+    #[inline]
+    pub fn set_inner_size<S: Into<Size>>(&self, size: S) {
+        let size = size.into().to_logical_with(self.scale_factor());
+        self.sys.set_inner_size(size);
+    }
+
+    /// Returns the distances from each edge of the content area to the part
+    /// of it that isn't covered by window decorations.
+    ///
+    /// This is zero unless the content area extends under the titlebar, as
+    /// it does with [`WindowExt::set_titlebar_hidden`] on macOS. Content
+    /// placed inside these insets isn't obscured by the titlebar.
+    ///
+    /// [`WindowExt::set_titlebar_hidden`]: os/macos/trait.WindowExt.html#method.set_titlebar_hidden
+    #[inline]
+    pub fn safe_area_insets(&self) -> LogicalInsets {
+        self.sys.safe_area_insets()
+    }
+
+    /// Returns the height of the titlebar in logical pixels, including any
+    /// toolbar.
+    ///
+    /// A hidden titlebar still has a height, since the window buttons remain
+    /// at the top of the window.
+    ///
+    /// On X11, this is the top border drawn by the window manager. On
+    /// Wayland, this is zero since compositors don't report it.
+    #[inline]
+    pub fn titlebar_height(&self) -> f64 {
+        self.sys.titlebar_height()
+    }
+
+    /// Returns the position of the window's top-left corner on the desktop.
+    #[inline]
+    pub fn outer_position(&self) -> LogicalPosition {
+        self.sys.outer_position()
+    }
+
+    /// Moves the window's top-left corner to `position` on the desktop.
+    #[inline]
+    pub fn set_outer_position<P: Into<Position>>(&self, position: P) {
+        let position = position.into().to_logical_with(self.scale_factor());
+        self.sys.set_outer_position(position);
+    }
+
+    /// Converts `position` from the coordinate space `from` to `to`.
+    ///
+    /// Every space has its origin at the top-left and Y increasing
+    /// downwards, including on platforms where native coordinates start at
+    /// the bottom-left. On Wayland, where windows have no known position,
+    /// every space shares the same origin.
+    #[inline]
+    pub fn convert_position<P: Into<Position>>(
+        &self,
+        position: P,
+        from: CoordinateSpace,
+        to: CoordinateSpace,
+    ) -> LogicalPosition {
+        let position = position.into().to_logical_with(self.scale_factor());
+        position + (self.sys.origin_of(from) - self.sys.origin_of(to))
+    }
+
+    /// Converts `position` within the content area to screen coordinates.
+    #[inline]
+    pub fn content_to_screen<P: Into<Position>>(&self, position: P) -> LogicalPosition {
+        self.convert_position(position, CoordinateSpace::WindowContent, CoordinateSpace::Screen)
+    }
+
+    /// Converts `position` in screen coordinates to the content area.
+    #[inline]
+    pub fn screen_to_content<P: Into<Position>>(&self, position: P) -> LogicalPosition {
+        self.convert_position(position, CoordinateSpace::Screen, CoordinateSpace::WindowContent)
+    }
+
+    /// Returns whether the window is minimized.
+    #[inline]
+    pub fn is_minimized(&self) -> bool {
+        self.sys.is_minimized()
+    }
+
+    /// Minimizes the window or restores it from being minimized.
+    ///
+    /// Wayland compositors don't report whether windows are minimized, so
+    /// there a window counts as minimized from when it is minimized through
+    /// this until it is activated again.
+    #[inline]
+    pub fn set_minimized(&self, minimized: bool) {
+        self.sys.set_minimized(minimized);
+    }
+
+    /// Returns whether the window is maximized.
+    #[inline]
+    pub fn is_maximized(&self) -> bool {
+        self.sys.is_zoomed()
+    }
+
+    /// Maximizes the window or restores it from being maximized.
+    #[inline]
+    pub fn set_maximized(&self, maximized: bool) {
+        self.sys.set_zoomed(maximized);
+    }
+
+    /// Brings the window to the front and gives it keyboard focus, restoring
+    /// it if minimized.
+    ///
+    /// This also activates the application. On Wayland, this needs the
+    /// xdg-activation protocol, and compositors may draw attention to the
+    /// window instead unless the user just interacted with the application.
+    #[inline]
+    pub fn focus(&self) {
+        self.sys.focus();
+    }
+
+    /// Draws the user's attention to the application if it is not active.
+    ///
+    /// On macOS, this bounces the Dock icon. The request is cancelled once
+    /// the application is activated. On X11, this marks the window as
+    /// demanding attention, and critical requests also set its urgency hint
+    /// until it is focused. On Wayland, both types ask for attention through
+    /// the xdg-activation protocol.
+    #[inline]
+    pub fn request_user_attention(&self, ty: UserAttentionType) {
+        self.sys.request_user_attention(ty);
+    }
+
+    /// Sets the opacity of the whole window, from `0.0` for fully
+    /// transparent to `1.0` for fully opaque.
+    ///
+    /// Values outside of that range are clamped. On X11 this needs a
+    /// compositing manager, and on Wayland a compositor that supports the
+    /// alpha modifier protocol.
+    #[inline]
+    pub fn set_opacity(&self, opacity: f64) {
+        self.sys.set_opacity(opacity);
+    }
+
+    /// Sets whether the window receives pointer input, which is the default.
+    ///
+    /// When disabled, clicks pass through to whatever is behind the window,
+    /// which is useful for overlays.
+    #[inline]
+    pub fn set_cursor_hittest(&self, hittest: bool) {
+        self.sys.set_cursor_hittest(hittest);
+    }
+
+    /// Restricts pointer input to `region` within the content area, letting
+    /// clicks elsewhere pass through to whatever is behind the window.
+    ///
+    /// An empty `region` makes the whole window click-through.
+    #[inline]
+    pub fn set_input_region(&self, region: &[LogicalRect]) {
+        self.sys.set_input_region(Some(region.to_vec()));
+    }
+
+    /// Removes the region set with
+    /// [`set_input_region`](#method.set_input_region), so that the whole
+    /// window receives pointer input again.
+    #[inline]
+    pub fn clear_input_region(&self) {
+        self.sys.set_input_region(None);
+    }
+
+    /// Returns whether the window is fullscreen.
+    #[inline]
+    pub fn is_fullscreen(&self) -> bool {
+        self.sys.is_fullscreen()
+    }
+
+    /// Enters or exits fullscreen.
+    ///
+    /// On macOS, the transition is animated and the window gets its own
+    /// space.
+    #[inline]
+    pub fn set_fullscreen(&self, fullscreen: bool) {
+        self.sys.set_fullscreen(fullscreen);
+    }
+
+    /// Sets the function called with each event received by `self`,
+    /// replacing any previous one.
+    ///
+    /// Events are delivered on the main thread while the application's run
+    /// loop is running.
+    #[inline]
+    pub fn set_event_handler<F>(&self, handler: F)
+    where
+        F: FnMut(&Window, WindowEvent) + 'static,
+    {
+        self.sys.set_event_handler(Box::new(handler));
+    }
+
+    /// Starts dragging `data` out of `self`, displaying `icon` under the
+    /// pointer.
+    ///
+    /// This must be called while a mouse button is held over `self`, such
+    /// as while handling a mouse-down or mouse-dragged event, otherwise an
+    /// error is returned. Once the drag ends,
+    /// [`WindowEvent::DragEnded`](event/enum.WindowEvent.html#variant.DragEnded)
+    /// reports the operation chosen by the destination.
+    ///
+    /// On Wayland, only copying and moving are supported.
+    #[inline]
+    #[allow(clippy::result_unit_err)]
+    pub fn start_drag(&self, data: &DragData, icon: &Icon) -> Result<(), ()> {
+        self.sys.start_drag(data, icon)
+    }
+
+    /// Sets whether `self` receives text input through the input method
+    /// editor (IME), which is disallowed by default.
+    ///
+    /// When allowed, key presses are interpreted by the IME and reported as
+    /// [`Preedit`] and [`Commit`] events.
+    ///
+    /// [`Preedit`]: event/enum.WindowEvent.html#variant.Preedit
+    /// [`Commit`]: event/enum.WindowEvent.html#variant.Commit
+    #[inline]
+    pub fn set_ime_allowed(&self, allowed: bool) {
+        self.sys.set_ime_allowed(allowed);
+    }
+
+    /// Sets the area of the text cursor within the content area, which the
+    /// IME uses to place its candidate window.
+    #[inline]
+    pub fn set_ime_cursor_area<P, S>(&self, position: P, size: S)
+    where
+        P: Into<Position>,
+        S: Into<Size>,
+    {
+        let scale_factor = self.scale_factor();
+        let position = position.into().to_logical_with(scale_factor);
+        let size = size.into().to_logical_with(scale_factor);
+        self.sys.set_ime_cursor_area(position, size);
+    }
+
+    /// Pops up `menu` at `position` within the content area.
+    ///
+    /// Activating an item calls the menu event handler set with
+    /// [`menu::set_event_handler`].
+    ///
+    /// On macOS, this returns once the menu is dismissed. On Linux, the menu
+    /// is drawn by ZedUI and this returns right away, while the menu stays
+    /// open until an item is activated or it is dismissed.
+    ///
+    /// [`menu::set_event_handler`]: ../zui_menu/fn.set_event_handler.html
+    #[inline]
+    pub fn show_context_menu<P: Into<Position>>(&self, menu: &Menu, position: P) {
+        let position = position.into().to_logical_with(self.scale_factor());
+        self.sys.show_context_menu(menu, position);
+    }
+
+    /// Sets the menu shown when the content area is right-clicked, or removes
+    /// it if `None`.
+    ///
+    /// Without a context menu, right-clicking emits
+    /// [`WindowEvent::ContextMenuRequested`] so that a menu specific to the
+    /// clicked location can be shown with [`show_context_menu`].
+    ///
+    /// [`WindowEvent::ContextMenuRequested`]: event/enum.WindowEvent.html#variant.ContextMenuRequested
+    /// [`show_context_menu`]: #method.show_context_menu
+    #[inline]
+    pub fn set_context_menu(&self, menu: Option<&Menu>) {
+        self.sys.set_context_menu(menu);
+    }
+}
+
+/// Returns all open windows in the order that they were created.
+///
+/// Only windows created or accessed on the current thread are included,
+/// which is the main thread on most platforms.
+#[inline]
+pub fn windows() -> Vec<Window> {
+    sys::windows().into_iter().map(Into::into).collect()
+}
+
+/// A unique identifier of a [`Window`](struct.Window.html).
+///
+/// Identifiers are never reused within the same process.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct WindowId(u64);
+
+impl WindowId {
+    fn next() -> Self {
+        static NEXT: AtomicU64 = AtomicU64::new(1);
+        WindowId(NEXT.fetch_add(1, Ordering::Relaxed))
+    }
+
+    /// Returns the raw value of `self`.
+    #[inline]
+    pub fn as_u64(self) -> u64 {
+        self.0
+    }
+}
+
+/// A handle to a window that does not keep it alive, created with
+/// [`Window::downgrade`](struct.Window.html#method.downgrade).
+///
+/// This allows caches and event routing tables to refer to windows without
+/// preventing them from being closed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct WeakWindow {
+    id: WindowId,
+    // !Send + !Sync
+    _marker: PhantomData<*mut ()>,
+}
+
+impl WeakWindow {
+    /// Returns the identifier of the window referred to by `self`.
+    #[inline]
+    pub fn id(&self) -> WindowId {
+        self.id
+    }
+
+    /// Returns a handle to the window if it is still open.
+    #[inline]
+    pub fn upgrade(&self) -> Option<Window> {
+        Window::from_id(self.id)
+    }
+}
+
+/// A coordinate space for positions related to a window, used with
+/// [`Window::convert_position`].
+///
+/// Each space has its origin at its top-left corner.
+///
+/// [`Window::convert_position`]: struct.Window.html#method.convert_position
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum CoordinateSpace {
+    /// The desktop, relative to the top-left corner of the primary monitor.
+    ///
+    /// This is the space of
+    /// [`Window::outer_position`](struct.Window.html#method.outer_position).
+    Screen,
+    /// The whole window, including decorations such as the titlebar.
+    WindowOuter,
+    /// The content area of the window.
+    ///
+    /// This is the space of positions in
+    /// [`WindowEvent`](event/enum.WindowEvent.html)s.
+    WindowContent,
+    /// A native view within the window.
+    ///
+    /// The view must be in the view hierarchy of the window being converted
+    /// with.
+    View(ViewHandle),
+}
+
+/// A handle to a native view, used with
+/// [`CoordinateSpace::View`](enum.CoordinateSpace.html#variant.View).
+///
+/// Handles are obtained from the types that own views, such as web views,
+/// or from platform-specific extensions, such as
+/// [`ViewHandleExt::from_ns_view`] on macOS. A handle doesn't keep its view
+/// alive. Linux windows have no native views, so no handles exist there.
+///
+/// [`ViewHandleExt::from_ns_view`]: os/macos/trait.ViewHandleExt.html#tymethod.from_ns_view
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ViewHandle {
+    sys: sys::ViewHandle,
+}
+
+#[doc(hidden)]
+impl From<sys::ViewHandle> for ViewHandle {
+    #[inline]
+    fn from(sys: sys::ViewHandle) -> Self {
+        ViewHandle { sys }
+    }
+}
+
+/// How urgently [`Window::request_user_attention`] draws attention.
+///
+/// [`Window::request_user_attention`]: struct.Window.html#method.request_user_attention
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum UserAttentionType {
+    /// Draws attention until the application is activated.
+    Critical,
+    /// Draws attention briefly.
+    Informational,
+}
+
+/// The role of a window, which determines its decorations and how it is
+/// layered relative to other windows.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum WindowKind {
+    /// A regular top-level window.
+    Normal,
+    /// A dialog, which is centered on its parent if it has one.
+    Dialog,
+    /// A tool palette or inspector that floats above regular windows.
+    Utility,
+    /// A borderless menu or popover that floats above all other windows.
+    Popup,
+    /// A borderless tooltip that does not receive pointer input.
+    Tooltip,
+}
+
+impl Default for WindowKind {
+    #[inline]
+    fn default() -> Self {
+        WindowKind::Normal
+    }
 }
 
 /// A type for configuring how a `Window` instance should be constructed.
 pub struct WindowBuilder {
     sys: sys::WindowBuilder,
     title: Option<ZedString>,
+    size: Option<Size>,
+    position: Option<Position>,
+    maximized: bool,
+    fullscreen: bool,
+    restore_state: Option<(WindowStateStore, String)>,
+    kind: WindowKind,
+    parent: Option<Window>,
+    modal: bool,
 }
 
 impl Default for WindowBuilder {
@@ -73,6 +549,14 @@ impl Default for WindowBuilder {
         WindowBuilder {
             sys: Default::default(),
             title: None,
+            size: None,
+            position: None,
+            maximized: false,
+            fullscreen: false,
+            restore_state: None,
+            kind: WindowKind::Normal,
+            parent: None,
+            modal: false,
         }
     }
 }
@@ -91,8 +575,138 @@ impl WindowBuilder {
         self
     }
 
+    /// Sets the size of the content area, in either logical or physical
+    /// pixels.
+    ///
+    /// Physical sizes are scaled by the scale factor of the main monitor.
+    #[inline]
+    pub fn size<S: Into<Size>>(&mut self, size: S) -> &mut Self {
+        self.size = Some(size.into());
+        self
+    }
+
+    /// Sets the position of the window's top-left corner on the desktop, in
+    /// either logical or physical pixels.
+    #[inline]
+    pub fn position<P: Into<Position>>(&mut self, position: P) -> &mut Self {
+        self.position = Some(position.into());
+        self
+    }
+
+    /// Sets whether the window starts out maximized.
+    #[inline]
+    pub fn maximized(&mut self, maximized: bool) -> &mut Self {
+        self.maximized = maximized;
+        self
+    }
+
+    /// Sets whether the window starts out fullscreen.
+    #[inline]
+    pub fn fullscreen(&mut self, fullscreen: bool) -> &mut Self {
+        self.fullscreen = fullscreen;
+        self
+    }
+
+    /// Restores the state last saved under `name` in the
+    /// [current store](state/struct.WindowStateStore.html#method.current),
+    /// and saves the state there once the window stops moving or resizing,
+    /// and when it closes or the application terminates.
+    ///
+    /// The restored state takes precedence over the size, position,
+    /// maximized and fullscreen settings of `self`, which apply when there
+    /// is no saved state. The window is moved onto the currently visible
+    /// monitors if its saved monitor is no longer connected.
+    #[inline]
+    pub fn restore_state<S: Into<String>>(&mut self, name: S) -> &mut Self {
+        if let Some(store) = WindowStateStore::current() {
+            self.restore_state_in(store, name);
+        }
+        self
+    }
+
+    /// Like [`restore_state`](#method.restore_state), but using `store`.
+    #[inline]
+    pub fn restore_state_in<S: Into<String>>(
+        &mut self,
+        store: WindowStateStore,
+        name: S,
+    ) -> &mut Self {
+        self.restore_state = Some((store, name.into()));
+        self
+    }
+
+    /// Sets the role of the window, which is `WindowKind::Normal` by default.
+    ///
+    /// On Wayland, popups and tooltips are only placed above their
+    /// [`parent`](#method.parent), relative to its content area, and are
+    /// regular windows without one.
+    #[inline]
+    pub fn kind(&mut self, kind: WindowKind) -> &mut Self {
+        self.kind = kind;
+        self
+    }
+
+    /// Sets the window that owns the window.
+    ///
+    /// The window stays above and moves along with its parent. Unless a
+    /// position is set or restored, it is centered on its parent.
+    #[inline]
+    pub fn parent(&mut self, parent: &Window) -> &mut Self {
+        self.parent = Some(parent.clone());
+        self
+    }
+
+    /// Sets whether the window blocks input to its parent while open.
+    ///
+    /// This has no effect without a [`parent`](#method.parent). On macOS,
+    /// a modal window is shown as a sheet of its parent as soon as it is
+    /// built. On Linux, the window manager or compositor is asked to make
+    /// the window modal, and the parent gets no context menu requests or
+    /// text input until the window closes.
+    #[inline]
+    pub fn modal(&mut self, modal: bool) -> &mut Self {
+        self.modal = modal;
+        self
+    }
+
     /// Creates a new instance, returning an error upon failure.
+    #[allow(clippy::result_unit_err)]
     pub fn build(&self) -> Result<Window, ()> {
-        Ok(self.sys_build()?.into())
+        let window = self.sys_build()?;
+
+        let saved = self.restore_state.as_ref().and_then(|(store, name)| {
+            store.load(name).ok()?
+        });
+
+        let positioned = saved.is_some() || self.position.is_some();
+
+        match saved {
+            Some(mut state) => {
+                state.clamp_to_monitors();
+                window.sys.set_outer_frame(state.position, state.size);
+                window.sys.set_zoomed(state.maximized);
+                window.set_fullscreen(state.fullscreen);
+            },
+            None => {
+                if let Some(position) = self.position {
+                    window.set_outer_position(position);
+                }
+                window.sys.set_zoomed(self.maximized);
+                window.set_fullscreen(self.fullscreen);
+            },
+        }
+
+        if let Some((store, name)) = &self.restore_state {
+            window.sys.state().set_autosave(store.clone(), name.clone());
+        }
+
+        if let Some(parent) = &self.parent {
+            if !positioned {
+                window.sys.center_on(&parent.sys);
+            }
+            window.sys.set_parent(&parent.sys, self.modal);
+        }
+
+        Ok(window)
     }
 }
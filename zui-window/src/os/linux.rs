@@ -0,0 +1,49 @@
+//! Linux-specific extensions.
+
+use shared::os::linux::DisplayBackend;
+use crate::{
+    Window,
+    WindowBuilder,
+};
+
+/// Linux-specific extensions for [`Window`](../../struct.Window.html).
+pub trait WindowExt {
+    /// Returns the display server that `self` is shown on.
+    fn display_backend(&self) -> DisplayBackend;
+
+    /// Returns the X11 window ID of `self`, or `None` if it is shown on a
+    /// Wayland compositor.
+    fn xid(&self) -> Option<u32>;
+}
+
+impl WindowExt for Window {
+    #[inline]
+    fn display_backend(&self) -> DisplayBackend {
+        self.sys.display_backend()
+    }
+
+    #[inline]
+    fn xid(&self) -> Option<u32> {
+        self.sys.xid()
+    }
+}
+
+/// Linux-specific extensions for
+/// [`WindowBuilder`](../../struct.WindowBuilder.html).
+pub trait WindowBuilderExt {
+    /// Sets the application identifier of the window, which desktop
+    /// environments use to group windows and find the application's
+    /// `.desktop` file.
+    ///
+    /// This is the `xdg_toplevel` app ID on Wayland and the class of
+    /// `WM_CLASS` on X11.
+    fn app_id<S: Into<String>>(&mut self, app_id: S) -> &mut Self;
+}
+
+impl WindowBuilderExt for WindowBuilder {
+    #[inline]
+    fn app_id<S: Into<String>>(&mut self, app_id: S) -> &mut Self {
+        self.sys.app_id = Some(app_id.into());
+        self
+    }
+}
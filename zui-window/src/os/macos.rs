@@ -8,6 +8,7 @@ use cocoa::{
     base::id,
     foundation::{
         NSPoint,
+        NSRect,
         NSSize,
         NSUInteger,
     },
@@ -15,19 +16,38 @@ use cocoa::{
 use objc::rc::StrongPtr;
 use crate::{
     dpi::*,
+    ViewHandle,
     Window,
     WindowBuilder,
-    sys::Window as SysWindow,
+    sys::{ViewHandle as SysViewHandle, Window as SysWindow},
 };
 
 /// macOS-specific extensions for [`Window`](../../struct.Window.html).
 pub trait WindowExt {
     /// Creates a new instance from an `NSWindow` handle.
+    ///
+    /// The window's delegate is replaced with one that forwards to the
+    /// previous delegate, which still receives every notification along with
+    /// any other message that ZedUI doesn't handle itself.
     unsafe fn from_ns_window(ns_window: StrongPtr) -> Self;
 
     /// Returns the `NSWindow` handle for `self`.
     fn ns_window(&self) -> id;
 
+    /// Converts `point`, in the coordinate system of `view`, to a position
+    /// within the content area of `self`.
+    ///
+    /// `view` must be in the view hierarchy of `self`. Flipped and
+    /// non-flipped views are both handled.
+    unsafe fn view_to_content(&self, view: id, point: NSPoint) -> LogicalPosition;
+
+    /// Converts `position` within the content area of `self` to the
+    /// coordinate system of `view`.
+    ///
+    /// `view` must be in the view hierarchy of `self`. Flipped and
+    /// non-flipped views are both handled.
+    unsafe fn content_to_view(&self, view: id, position: LogicalPosition) -> NSPoint;
+
     /// Returns the title of `self` as an `NSString`.
     #[inline]
     fn title(&self) -> id {
@@ -109,13 +129,50 @@ pub trait WindowExt {
 impl WindowExt for Window {
     #[inline]
     unsafe fn from_ns_window(ns_window: StrongPtr) -> Self {
-        SysWindow { ns_window }.into()
+        let window: Window = SysWindow { ns_window }.into();
+        // Registers the window so that it can be looked up by its ID.
+        window.sys.state();
+        window
     }
 
     #[inline]
     fn ns_window(&self) -> id {
         *self.sys.ns_window
     }
+
+    #[inline]
+    unsafe fn view_to_content(&self, view: id, point: NSPoint) -> LogicalPosition {
+        self.sys.view_to_content(view, point)
+    }
+
+    #[inline]
+    unsafe fn content_to_view(&self, view: id, position: LogicalPosition) -> NSPoint {
+        self.sys.content_to_view(view, position)
+    }
+}
+
+/// macOS-specific extensions for
+/// [`ViewHandle`](../../struct.ViewHandle.html).
+pub trait ViewHandleExt {
+    /// Creates a handle to an `NSView`.
+    ///
+    /// The view is not retained, so it must outlive every use of the handle.
+    unsafe fn from_ns_view(ns_view: id) -> Self;
+
+    /// Returns the `NSView` handle for `self`.
+    fn ns_view(&self) -> id;
+}
+
+impl ViewHandleExt for ViewHandle {
+    #[inline]
+    unsafe fn from_ns_view(ns_view: id) -> Self {
+        SysViewHandle { ns_view }.into()
+    }
+
+    #[inline]
+    fn ns_view(&self) -> id {
+        self.sys.ns_view
+    }
 }
 
 /// macOS-specific extensions for
@@ -147,6 +204,10 @@ impl WindowBuilderExt for WindowBuilder {
     }
 }
 
+// Points are copied as is. AppKit coordinates usually have a bottom-left
+// origin, so converting between them and the top-left origin used by dpi
+// types also needs a reference frame, as in `WindowExt::view_to_content`.
+
 impl From<NSPoint> for PhysicalPosition {
     #[inline]
     fn from(NSPoint { x, y }: NSPoint) -> Self {
@@ -202,3 +263,31 @@ impl From<LogicalSize> for NSSize {
         NSSize { width, height }
     }
 }
+
+impl From<NSRect> for PhysicalRect {
+    #[inline]
+    fn from(NSRect { origin, size }: NSRect) -> Self {
+        PhysicalRect::new(origin.into(), size.into())
+    }
+}
+
+impl From<NSRect> for LogicalRect {
+    #[inline]
+    fn from(NSRect { origin, size }: NSRect) -> Self {
+        LogicalRect::new(origin.into(), size.into())
+    }
+}
+
+impl From<PhysicalRect> for NSRect {
+    #[inline]
+    fn from(PhysicalRect { origin, size }: PhysicalRect) -> Self {
+        NSRect::new(origin.into(), size.into())
+    }
+}
+
+impl From<LogicalRect> for NSRect {
+    #[inline]
+    fn from(LogicalRect { origin, size }: LogicalRect) -> Self {
+        NSRect::new(origin.into(), size.into())
+    }
+}
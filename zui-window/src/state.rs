@@ -0,0 +1,382 @@
+//! Saving and restoring window geometry across application launches.
+
+use std::{
+    env,
+    fs,
+    io,
+    path::{Path, PathBuf},
+};
+use crate::{
+    dpi::{LogicalPosition, LogicalRect, LogicalSize},
+    sys,
+    Window,
+};
+
+const EXTENSION: &str = "window";
+
+/// The geometry and display state of a window, as saved by a
+/// [`WindowStateStore`](struct.WindowStateStore.html).
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct WindowState {
+    /// The position of the window's top-left corner on the desktop, when it
+    /// is neither maximized nor fullscreen.
+    pub position: LogicalPosition,
+    /// The size of the window's frame, including its title bar and borders,
+    /// when it is neither maximized nor fullscreen.
+    pub size: LogicalSize,
+    /// Whether the window is maximized over its frame.
+    pub maximized: bool,
+    /// Whether the window is fullscreen over its frame.
+    pub fullscreen: bool,
+    /// An identifier for the monitor that the window is on, if any.
+    ///
+    /// This is only meaningful to the platform that produced it.
+    pub monitor: Option<String>,
+}
+
+impl WindowState {
+    /// Returns the current state of `window`.
+    ///
+    /// The frame of a maximized or fullscreen window is the one it returns
+    /// to when restored.
+    pub fn of(window: &Window) -> Self {
+        let (position, size) = window.sys.windowed_frame();
+        WindowState {
+            position,
+            size,
+            maximized: window.sys.is_zoomed(),
+            fullscreen: window.is_fullscreen(),
+            monitor: window.sys.monitor(),
+        }
+    }
+
+    /// Moves and shrinks `self` to fit within the currently visible monitors.
+    ///
+    /// The window is kept on its saved monitor if that is still connected
+    /// and the window overlaps it. Otherwise it is placed on whichever
+    /// monitor it overlaps, falling back to the primary monitor.
+    pub fn clamp_to_monitors(&mut self) {
+        let monitors = sys::monitors();
+        let monitor = monitors.iter()
+            .find(|m| Some(&m.id) == self.monitor.as_ref() && self.overlaps(m))
+            .or_else(|| monitors.iter().find(|m| self.overlaps(m)))
+            .or_else(|| monitors.first());
+
+        let monitor = match monitor {
+            Some(monitor) => monitor,
+            None => return,
+        };
+
+        let area = monitor.rect;
+        self.size.width = self.size.width.min(area.size.width);
+        self.size.height = self.size.height.min(area.size.height);
+
+        let max_x = area.origin.x + area.size.width - self.size.width;
+        let max_y = area.origin.y + area.size.height - self.size.height;
+        self.position.x = self.position.x.max(area.origin.x).min(max_x);
+        self.position.y = self.position.y.max(area.origin.y).min(max_y);
+
+        self.monitor = Some(monitor.id.clone());
+    }
+
+    fn overlaps(&self, monitor: &sys::Monitor) -> bool {
+        let rect = LogicalRect::new(self.position, self.size);
+        rect.intersection(&monitor.rect).is_some()
+    }
+
+    fn to_text(&self) -> String {
+        let mut text = format!(
+            "x = {}\ny = {}\nwidth = {}\nheight = {}\nmaximized = {}\nfullscreen = {}\n",
+            self.position.x,
+            self.position.y,
+            self.size.width,
+            self.size.height,
+            self.maximized,
+            self.fullscreen,
+        );
+        if let Some(monitor) = &self.monitor {
+            text.push_str(&format!("monitor = \"{}\"\n", escape(monitor)));
+        }
+        text
+    }
+
+    /// Parses the output of `to_text`, which must have every key but
+    /// `monitor`.
+    fn from_text(text: &str) -> io::Result<Self> {
+        fn invalid(msg: String) -> io::Error {
+            io::Error::new(io::ErrorKind::InvalidData, msg)
+        }
+
+        let mut x = None;
+        let mut y = None;
+        let mut width = None;
+        let mut height = None;
+        let mut maximized = None;
+        let mut fullscreen = None;
+        let mut monitor = None;
+
+        for line in text.lines().map(str::trim).filter(|line| !line.is_empty()) {
+            let bad_line = || invalid(format!("invalid window state line: {:?}", line));
+
+            let mut parts = line.splitn(2, '=').map(str::trim);
+            let (key, value) = match (parts.next(), parts.next()) {
+                (Some(key), Some(value)) => (key, value),
+                _ => return Err(bad_line()),
+            };
+
+            let coordinate = || match value.parse::<f64>() {
+                Ok(n) if n.is_finite() => Ok(Some(n)),
+                _ => Err(bad_line()),
+            };
+            let length = || match value.parse::<f64>() {
+                Ok(n) if n.is_finite() && n > 0.0 => Ok(Some(n)),
+                _ => Err(bad_line()),
+            };
+            let boolean = || value.parse::<bool>().map(Some).map_err(|_| bad_line());
+
+            match key {
+                "x" => x = coordinate()?,
+                "y" => y = coordinate()?,
+                "width" => width = length()?,
+                "height" => height = length()?,
+                "maximized" => maximized = boolean()?,
+                "fullscreen" => fullscreen = boolean()?,
+                "monitor" => monitor = Some(unescape(value).ok_or_else(bad_line)?),
+                // Ignore keys written by newer versions.
+                _ => {},
+            }
+        }
+
+        let missing = |key: &str| invalid(format!("missing window state key: {:?}", key));
+        Ok(WindowState {
+            position: LogicalPosition::new(
+                x.ok_or_else(|| missing("x"))?,
+                y.ok_or_else(|| missing("y"))?,
+            ),
+            size: LogicalSize::new(
+                width.ok_or_else(|| missing("width"))?,
+                height.ok_or_else(|| missing("height"))?,
+            ),
+            maximized: maximized.ok_or_else(|| missing("maximized"))?,
+            fullscreen: fullscreen.ok_or_else(|| missing("fullscreen"))?,
+            monitor,
+        })
+    }
+}
+
+/// Escapes `s` for writing between double quotes on a single line.
+fn escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Reverses `escape` on a double-quoted `value`, returning `None` if it is
+/// malformed.
+fn unescape(value: &str) -> Option<String> {
+    if value.len() < 2 || !value.starts_with('"') || !value.ends_with('"') {
+        return None;
+    }
+
+    let mut unescaped = String::with_capacity(value.len());
+    let mut chars = value[1..value.len() - 1].chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => unescaped.push(match chars.next()? {
+                '"' => '"',
+                '\\' => '\\',
+                'n' => '\n',
+                'r' => '\r',
+                _ => return None,
+            }),
+            '"' => return None,
+            c => unescaped.push(c),
+        }
+    }
+    Some(unescaped)
+}
+
+/// Saves and loads [`WindowState`](struct.WindowState.html) by window name,
+/// in a directory within the user's configuration directory.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WindowStateStore {
+    dir: PathBuf,
+}
+
+impl WindowStateStore {
+    /// Creates a store for the application named `app` within the user's
+    /// configuration directory.
+    ///
+    /// Returns `None` if the configuration directory can't be determined.
+    pub fn new<S: AsRef<str>>(app: S) -> Option<Self> {
+        let dir = sys::config_dir()?.join(app.as_ref()).join("windows");
+        Some(Self::at(dir))
+    }
+
+    /// Creates a store named after the current executable.
+    ///
+    /// Returns `None` if the executable name or the configuration directory
+    /// can't be determined.
+    pub fn current() -> Option<Self> {
+        let exe = env::current_exe().ok()?;
+        let app = exe.file_stem()?.to_str()?.to_owned();
+        Self::new(app)
+    }
+
+    /// Creates a store within `dir`.
+    #[inline]
+    pub fn at<P: Into<PathBuf>>(dir: P) -> Self {
+        WindowStateStore { dir: dir.into() }
+    }
+
+    /// Returns the directory where states are stored.
+    #[inline]
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Saves the current state of `window` under `name`.
+    #[inline]
+    pub fn save(&self, name: &str, window: &Window) -> io::Result<()> {
+        self.save_state(name, &WindowState::of(window))
+    }
+
+    /// Saves `state` under `name`, replacing any previous state.
+    ///
+    /// The state is written to a temporary file that then replaces the
+    /// previous one, so that a crash mid-write leaves the previous state
+    /// intact.
+    pub fn save_state(&self, name: &str, state: &WindowState) -> io::Result<()> {
+        let path = self.path(name)?;
+        fs::create_dir_all(&self.dir)?;
+
+        // Names can't start with a dot, so this doesn't clash with states.
+        let temp = self.dir.join(format!(".{}.{}.tmp", name, EXTENSION));
+        fs::write(&temp, state.to_text())?;
+        fs::rename(&temp, path).inspect_err(|_| {
+            let _ = fs::remove_file(&temp);
+        })
+    }
+
+    /// Loads the state saved under `name`, or `None` if there is none.
+    pub fn load(&self, name: &str) -> io::Result<Option<WindowState>> {
+        match fs::read_to_string(self.path(name)?) {
+            Ok(text) => WindowState::from_text(&text).map(Some),
+            Err(ref error) if error.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(error) => Err(error),
+        }
+    }
+
+    /// Removes the state saved under `name`, if any.
+    pub fn remove(&self, name: &str) -> io::Result<()> {
+        match fs::remove_file(self.path(name)?) {
+            Err(ref error) if error.kind() == io::ErrorKind::NotFound => Ok(()),
+            result => result,
+        }
+    }
+
+    fn path(&self, name: &str) -> io::Result<PathBuf> {
+        let is_valid = !name.is_empty()
+            && !name.starts_with('.')
+            && !name.contains(&['/', '\\'][..]);
+
+        if is_valid {
+            Ok(self.dir.join(format!("{}.{}", name, EXTENSION)))
+        } else {
+            let msg = format!("invalid window name: {:?}", name);
+            Err(io::Error::new(io::ErrorKind::InvalidInput, msg))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state(monitor: Option<&str>) -> WindowState {
+        WindowState {
+            position: LogicalPosition::new(-120.5, 40.0),
+            size: LogicalSize::new(800.0, 600.25),
+            maximized: true,
+            fullscreen: false,
+            monitor: monitor.map(Into::into),
+        }
+    }
+
+    #[test]
+    fn store_round_trips() {
+        let dir = env::temp_dir().join(format!("zui-window-state-{}", std::process::id()));
+        let store = WindowStateStore::at(&dir);
+        assert_eq!(store.load("main").unwrap(), None);
+
+        store.save_state("main", &state(None)).unwrap();
+        store.save_state("main", &state(Some("DP-1"))).unwrap();
+        assert_eq!(store.load("main").unwrap(), Some(state(Some("DP-1"))));
+
+        // Only the state itself is left behind.
+        let files: Vec<_> = fs::read_dir(&dir).unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+        assert_eq!(files, ["main.window"]);
+
+        store.remove("main").unwrap();
+        store.remove("main").unwrap();
+        assert_eq!(store.load("main").unwrap(), None);
+        assert!(store.save_state(".hidden", &state(None)).is_err());
+        assert!(store.save_state("a/b", &state(None)).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn text_round_trips() {
+        for &monitor in &[None, Some("69733382"), Some("a \"quoted\" \\ name\n")] {
+            let state = state(monitor);
+            assert_eq!(WindowState::from_text(&state.to_text()).unwrap(), state);
+        }
+    }
+
+    #[test]
+    fn text_ignores_unknown_keys() {
+        let text = format!("{}opacity = 1\n", state(None).to_text());
+        assert_eq!(WindowState::from_text(&text).unwrap(), state(None));
+    }
+
+    #[test]
+    fn text_rejects_incomplete_records() {
+        let text = state(None).to_text();
+        for key in &["x", "y", "width", "height", "maximized", "fullscreen"] {
+            let incomplete: String = text.lines()
+                .filter(|line| !line.starts_with(&format!("{} ", key)))
+                .map(|line| format!("{}\n", line))
+                .collect();
+            assert!(WindowState::from_text(&incomplete).is_err(), "missing {}", key);
+        }
+        assert!(WindowState::from_text("").is_err());
+    }
+
+    #[test]
+    fn text_rejects_invalid_values() {
+        let text = state(None).to_text();
+        for (line, replacement) in &[
+            ("width = 800", "width = 0"),
+            ("height = 600.25", "height = -1"),
+            ("x = -120.5", "x = NaN"),
+            ("maximized = true", "maximized = yes"),
+        ] {
+            let invalid = text.replace(line, replacement);
+            assert!(WindowState::from_text(&invalid).is_err(), "{}", replacement);
+        }
+
+        let unquoted = format!("{}monitor = 1\n", text);
+        assert!(WindowState::from_text(&unquoted).is_err());
+    }
+}
@@ -0,0 +1,83 @@
+//! Conversions between the text positions of input method protocols and the
+//! byte ranges of `Preedit` events.
+
+use std::{convert::TryFrom, env, ops::Range};
+
+/// Returns the byte offset of the character at `index` within `text`,
+/// clamped to its length.
+fn byte_offset(text: &str, index: usize) -> usize {
+    text.char_indices().nth(index).map_or(text.len(), |(i, _)| i)
+}
+
+/// Replaces `len` characters at `first` within the text being composed, as
+/// requested by XIM preedit draw callbacks.
+pub fn replace_chars(preedit: &mut String, first: usize, len: usize, text: &str) {
+    let start = byte_offset(preedit, first);
+    let end = start + byte_offset(&preedit[start..], len);
+    preedit.replace_range(start..end, text);
+}
+
+/// Returns the cursor at character `caret` within `text`, or `None` if the
+/// caret is outside of it.
+pub fn caret_cursor(text: &str, caret: i32) -> Option<Range<usize>> {
+    let caret = usize::try_from(caret).ok()?;
+    if caret > text.chars().count() {
+        return None;
+    }
+    let offset = byte_offset(text, caret);
+    Some(offset..offset)
+}
+
+/// Returns the cursor between byte offsets `begin` and `end` within `text`,
+/// as reported by text-input-v3, which uses -1 for a hidden cursor.
+pub fn byte_cursor(text: &str, begin: i32, end: i32) -> Option<Range<usize>> {
+    let begin = usize::try_from(begin).ok()?;
+    let end = usize::try_from(end).ok()?;
+    let (begin, end) = (begin.min(end), begin.max(end));
+    if end > text.len() || !text.is_char_boundary(begin) || !text.is_char_boundary(end) {
+        return None;
+    }
+    Some(begin..end)
+}
+
+/// Returns the locale to open input methods with, without its encoding.
+pub fn locale() -> String {
+    ["LC_ALL", "LC_CTYPE", "LANG"].iter()
+        .filter_map(|name| env::var(name).ok())
+        .find(|value| !value.is_empty())
+        .map(|value| value.split('.').next().unwrap_or_default().to_owned())
+        .filter(|locale| !locale.is_empty())
+        .unwrap_or_else(|| "C".to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replace_preedit_chars() {
+        let mut preedit = String::new();
+        replace_chars(&mut preedit, 0, 0, "にほ");
+        assert_eq!(preedit, "にほ");
+        replace_chars(&mut preedit, 2, 0, "ん");
+        assert_eq!(preedit, "にほん");
+        replace_chars(&mut preedit, 0, 3, "日本");
+        assert_eq!(preedit, "日本");
+        replace_chars(&mut preedit, 1, 5, "");
+        assert_eq!(preedit, "日");
+    }
+
+    #[test]
+    fn cursors() {
+        assert_eq!(caret_cursor("日本", 1), Some(3..3));
+        assert_eq!(caret_cursor("日本", 2), Some(6..6));
+        assert_eq!(caret_cursor("日本", 3), None);
+        assert_eq!(caret_cursor("日本", -1), None);
+
+        assert_eq!(byte_cursor("日本", 3, 6), Some(3..6));
+        assert_eq!(byte_cursor("日本", 6, 3), Some(3..6));
+        assert_eq!(byte_cursor("日本", 1, 3), None);
+        assert_eq!(byte_cursor("日本", -1, -1), None);
+        assert_eq!(byte_cursor("日本", 0, 7), None);
+    }
+}
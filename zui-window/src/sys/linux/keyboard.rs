@@ -0,0 +1,190 @@
+//! Translation of key presses into text with libxkbcommon, which is loaded
+//! when first needed.
+
+use std::{
+    ffi::{CStr, CString},
+    os::raw::c_char,
+    ptr,
+};
+use xkbcommon_dl::{
+    xkb_context,
+    xkb_context_flags,
+    xkb_keymap,
+    xkb_keymap_compile_flags,
+    xkb_keymap_format,
+    xkb_rule_names,
+    xkb_state,
+    xkb_state_component,
+    xkbcommon_option,
+    XkbCommon,
+    XKB_MOD_NAME_ALT,
+    XKB_MOD_NAME_CTRL,
+    XKB_MOD_NAME_LOGO,
+};
+
+/// A keymap along with the state of its modifiers.
+pub struct Keymap {
+    xkb: &'static XkbCommon,
+    context: *mut xkb_context,
+    keymap: *mut xkb_keymap,
+    state: *mut xkb_state,
+}
+
+impl Drop for Keymap {
+    fn drop(&mut self) {
+        unsafe {
+            (self.xkb.xkb_state_unref)(self.state);
+            (self.xkb.xkb_keymap_unref)(self.keymap);
+            (self.xkb.xkb_context_unref)(self.context);
+        }
+    }
+}
+
+impl Keymap {
+    fn new(create: impl FnOnce(&XkbCommon, *mut xkb_context) -> *mut xkb_keymap) -> Option<Self> {
+        let xkb = xkbcommon_option()?;
+        unsafe {
+            let context = (xkb.xkb_context_new)(xkb_context_flags::XKB_CONTEXT_NO_FLAGS);
+            if context.is_null() {
+                return None;
+            }
+            let keymap = create(xkb, context);
+            if keymap.is_null() {
+                (xkb.xkb_context_unref)(context);
+                return None;
+            }
+            let state = (xkb.xkb_state_new)(keymap);
+            if state.is_null() {
+                (xkb.xkb_keymap_unref)(keymap);
+                (xkb.xkb_context_unref)(context);
+                return None;
+            }
+            Some(Keymap { xkb, context, keymap, state })
+        }
+    }
+
+    /// Compiles a keymap in the text format sent by Wayland compositors.
+    pub fn from_string(keymap: &[u8]) -> Option<Self> {
+        // The keymap may be terminated by a null byte.
+        let end = keymap.iter().position(|&b| b == 0).unwrap_or(keymap.len());
+        let keymap = CString::new(&keymap[..end]).ok()?;
+        Self::new(|xkb, context| unsafe {
+            (xkb.xkb_keymap_new_from_string)(
+                context,
+                keymap.as_ptr(),
+                xkb_keymap_format::XKB_KEYMAP_FORMAT_TEXT_V1,
+                xkb_keymap_compile_flags::XKB_KEYMAP_COMPILE_NO_FLAGS,
+            )
+        })
+    }
+
+    /// Compiles a keymap from the null-separated rules, model, layout,
+    /// variant and options of the `_XKB_RULES_NAMES` X11 property, using
+    /// the system defaults for those that are missing.
+    pub fn from_names(names: &[u8]) -> Option<Self> {
+        let names: Vec<CString> = names.split(|&b| b == 0)
+            .take(5)
+            .filter_map(|name| CString::new(name).ok())
+            .collect();
+        let name = |i: usize| -> *const c_char {
+            names.get(i).filter(|name| !name.as_bytes().is_empty()).map_or(ptr::null(), |name| name.as_ptr())
+        };
+        let names = xkb_rule_names {
+            rules: name(0),
+            model: name(1),
+            layout: name(2),
+            variant: name(3),
+            options: name(4),
+        };
+        Self::new(|xkb, context| unsafe {
+            (xkb.xkb_keymap_new_from_names)(
+                context,
+                &names,
+                xkb_keymap_compile_flags::XKB_KEYMAP_COMPILE_NO_FLAGS,
+            )
+        })
+    }
+
+    /// Sets the modifiers and layout group in effect.
+    pub fn update_mask(&mut self, depressed: u32, latched: u32, locked: u32, group: u32) {
+        unsafe {
+            (self.xkb.xkb_state_update_mask)(self.state, depressed, latched, locked, 0, 0, group);
+        }
+    }
+
+    /// Sets the modifiers and group of the `state` field of a core X11 key
+    /// event.
+    pub fn update_x11_state(&mut self, state: u16) {
+        // Caps Lock and Num Lock, which are Lock and Mod2 in X11.
+        const LOCKS: u32 = 1 << 1 | 1 << 4;
+        let mods = state as u32 & 0xff;
+        let group = (state as u32 >> 13) & 0b11;
+        self.update_mask(mods & !LOCKS, 0, mods & LOCKS, group);
+    }
+
+    fn mod_active(&self, name: &[u8]) -> bool {
+        unsafe {
+            (self.xkb.xkb_state_mod_name_is_active)(
+                self.state,
+                name.as_ptr() as *const c_char,
+                xkb_state_component::XKB_STATE_MODS_EFFECTIVE,
+            ) > 0
+        }
+    }
+
+    /// Returns the keysym of the key with the XKB `keycode` in the current
+    /// state, or zero if it has none or several.
+    pub fn key_sym(&self, keycode: u32) -> u32 {
+        unsafe { (self.xkb.xkb_state_key_get_one_sym)(self.state, keycode) }
+    }
+
+    /// Returns the text typed by pressing the key with the XKB `keycode`, or
+    /// `None` if it types no text or is part of a shortcut.
+    pub fn key_text(&self, keycode: u32) -> Option<String> {
+        if [XKB_MOD_NAME_CTRL, XKB_MOD_NAME_ALT, XKB_MOD_NAME_LOGO].iter().any(|name| self.mod_active(name)) {
+            return None;
+        }
+        let mut buffer = [0 as c_char; 64];
+        let len = unsafe {
+            (self.xkb.xkb_state_key_get_utf8)(self.state, keycode, buffer.as_mut_ptr(), buffer.len())
+        };
+        if len <= 0 || len as usize >= buffer.len() {
+            return None;
+        }
+        let text = unsafe { CStr::from_ptr(buffer.as_ptr()) }.to_str().ok()?;
+        if text.chars().any(char::is_control) {
+            None
+        } else {
+            Some(text.to_owned())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Keycodes of the evdev rules, which X servers and compositors use.
+    const KEY_A: u32 = 38;
+    const KEY_RETURN: u32 = 36;
+    const KEYSYM_RETURN: u32 = 0xff0d;
+
+    #[test]
+    fn translate_keys() {
+        let mut keymap = Keymap::from_names(b"evdev\0pc105\0us\0\0").expect("libxkbcommon is not available");
+        assert_eq!(keymap.key_text(KEY_A).as_deref(), Some("a"));
+        assert_eq!(keymap.key_text(KEY_RETURN), None);
+        assert_eq!(keymap.key_sym(KEY_RETURN), KEYSYM_RETURN);
+
+        // Shift
+        keymap.update_x11_state(1);
+        assert_eq!(keymap.key_text(KEY_A).as_deref(), Some("A"));
+
+        // Control
+        keymap.update_x11_state(1 << 2);
+        assert_eq!(keymap.key_text(KEY_A), None);
+
+        keymap.update_x11_state(0);
+        assert_eq!(keymap.key_text(KEY_A).as_deref(), Some("a"));
+    }
+}
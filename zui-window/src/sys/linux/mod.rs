@@ -0,0 +1,762 @@
+//! Windows on an X11 display or a Wayland compositor.
+//!
+//! Each backend keeps one connection per thread, which is registered with the
+//! thread's main loop so that events are dispatched while it runs. Windows
+//! stay open until the user closes them, even if every handle is dropped.
+
+use std::{
+    cell::{Cell, RefCell},
+    collections::{BTreeMap, VecDeque},
+    env,
+    fmt,
+    hash::{Hash, Hasher},
+    ops::Range,
+    path::PathBuf,
+    rc::{Rc, Weak},
+    sync::Arc,
+    time::Duration,
+};
+use menu::Menu;
+use shared::{
+    mime,
+    os::linux::{self, main_loop, DisplayBackend, ZedStringExt},
+    Icon,
+    ZedString,
+};
+use crate::{
+    dpi::{LogicalInsets, LogicalPosition, LogicalRect, LogicalSize, ScaleFactor},
+    drag::DragData,
+    event::WindowEvent,
+    state::WindowStateStore,
+    CoordinateSpace,
+    UserAttentionType,
+    WindowId,
+    WindowKind,
+};
+
+mod ime;
+mod keyboard;
+mod popup;
+mod wayland;
+mod x11;
+mod xdnd;
+mod xim;
+
+pub type EventHandler = Box<dyn FnMut(&crate::Window, WindowEvent)>;
+
+/// The MIME type of dragged file lists.
+const URI_LIST: &str = "text/uri-list";
+
+/// The size of the content area of new windows.
+const DEFAULT_SIZE: LogicalSize = LogicalSize::new(800.0, 600.0);
+
+/// How long the window must stay still before a move or resize is saved.
+const AUTOSAVE_DELAY: Duration = Duration::from_millis(500);
+
+thread_local! {
+    // Open windows, which are not retained so that they may still close.
+    static WINDOWS: RefCell<BTreeMap<WindowId, Weak<Inner>>> = const { RefCell::new(BTreeMap::new()) };
+}
+
+#[inline]
+pub fn is_main_thread() -> bool {
+    linux::is_main_thread()
+}
+
+/// Returns `$XDG_CONFIG_HOME`, or `~/.config` if it is unset or relative.
+pub fn config_dir() -> Option<PathBuf> {
+    env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_absolute())
+        .or_else(|| Some(PathBuf::from(env::var_os("HOME")?).join(".config")))
+}
+
+/// Windows are drawn directly onto their surfaces, without native views, so
+/// no view handles exist.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ViewHandle {}
+
+/// The area of a monitor on the desktop.
+pub struct Monitor {
+    pub id: String,
+    pub rect: LogicalRect,
+}
+
+/// Returns the area of every connected monitor, with the primary monitor
+/// first.
+///
+/// Wayland compositors don't tell clients where their windows or monitors
+/// are, so there are none there.
+pub fn monitors() -> Vec<Monitor> {
+    match DisplayBackend::current() {
+        Some(DisplayBackend::X11) => x11::monitors(),
+        _ => Vec::new(),
+    }
+}
+
+/// Returns the open window identified by `id`, if any.
+pub fn window(id: WindowId) -> Option<Window> {
+    let inner = WINDOWS.with(|windows| windows.borrow().get(&id)?.upgrade())?;
+    Some(Window { inner })
+}
+
+/// Returns all open windows, ordered by ID.
+pub fn windows() -> Vec<Window> {
+    WINDOWS.with(|windows| {
+        windows.borrow()
+            .values()
+            .filter_map(Weak::upgrade)
+            .map(|inner| Window { inner })
+            .collect()
+    })
+}
+
+fn unregister(id: WindowId) {
+    // The registry may already be destroyed if this thread is exiting.
+    let _ = WINDOWS.try_with(|windows| windows.borrow_mut().remove(&id));
+}
+
+#[derive(Clone)]
+pub struct Window {
+    inner: Rc<Inner>,
+}
+
+pub struct Inner {
+    id: WindowId,
+    backend: Backend,
+    state: WindowState,
+}
+
+impl Drop for Inner {
+    fn drop(&mut self) {
+        unregister(self.id);
+    }
+}
+
+// Backends are only ever stored in an `Rc`, so boxing the larger one would
+// only add an indirection.
+#[allow(clippy::large_enum_variant)]
+enum Backend {
+    X11(x11::Window),
+    Wayland(wayland::Window),
+}
+
+impl fmt::Debug for Window {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.inner.backend {
+            Backend::X11(window) => f.debug_struct("Window").field("x11", &window.id()).finish(),
+            Backend::Wayland(window) => f.debug_struct("Window").field("wayland", &window.id()).finish(),
+        }
+    }
+}
+
+impl PartialEq for Window {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.inner, &other.inner)
+    }
+}
+
+impl Eq for Window {}
+
+impl Hash for Window {
+    #[inline]
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.inner.id.hash(state);
+    }
+}
+
+impl Window {
+    fn new(backend: Backend) -> Self {
+        let id = WindowId::next();
+        let inner = Rc::new(Inner {
+            id,
+            backend,
+            state: WindowState::default(),
+        });
+        WINDOWS.with(|windows| windows.borrow_mut().insert(id, Rc::downgrade(&inner)));
+        Window { inner }
+    }
+
+    #[inline]
+    pub fn id(&self) -> WindowId {
+        self.inner.id
+    }
+
+    #[inline]
+    pub fn display_backend(&self) -> DisplayBackend {
+        match self.inner.backend {
+            Backend::X11(_) => DisplayBackend::X11,
+            Backend::Wayland(_) => DisplayBackend::Wayland,
+        }
+    }
+
+    #[inline]
+    pub fn xid(&self) -> Option<u32> {
+        match &self.inner.backend {
+            Backend::X11(window) => Some(window.id()),
+            Backend::Wayland(_) => None,
+        }
+    }
+
+    pub fn set_title(&self, title: ZedString) {
+        match &self.inner.backend {
+            Backend::X11(window) => window.set_title(title.as_str()),
+            Backend::Wayland(window) => window.set_title(title.as_str()),
+        }
+    }
+
+    /// Returns zero insets, since window decorations are drawn outside of
+    /// the content area.
+    #[inline]
+    pub fn safe_area_insets(&self) -> LogicalInsets {
+        LogicalInsets::default()
+    }
+
+    pub fn titlebar_height(&self) -> f64 {
+        match &self.inner.backend {
+            Backend::X11(window) => window.titlebar_height(),
+            Backend::Wayland(window) => window.titlebar_height(),
+        }
+    }
+
+    pub fn scale_factor(&self) -> ScaleFactor {
+        match &self.inner.backend {
+            Backend::X11(window) => window.scale_factor(),
+            Backend::Wayland(window) => window.scale_factor(),
+        }
+    }
+
+    pub fn inner_size(&self) -> LogicalSize {
+        match &self.inner.backend {
+            Backend::X11(window) => window.inner_size(),
+            Backend::Wayland(window) => window.inner_size(),
+        }
+    }
+
+    pub fn set_inner_size(&self, size: LogicalSize) {
+        match &self.inner.backend {
+            Backend::X11(window) => window.set_inner_size(size),
+            Backend::Wayland(window) => window.set_inner_size(size),
+        }
+    }
+
+    pub fn outer_position(&self) -> LogicalPosition {
+        match &self.inner.backend {
+            Backend::X11(window) => window.outer_position(),
+            Backend::Wayland(_) => LogicalPosition::new(0.0, 0.0),
+        }
+    }
+
+    /// Returns the top-left corner of `space` in screen coordinates.
+    ///
+    /// Wayland windows have no known position, so every space starts at the
+    /// origin there.
+    pub fn origin_of(&self, space: CoordinateSpace) -> LogicalPosition {
+        match (space, &self.inner.backend) {
+            (CoordinateSpace::View(view), _) => match view.sys {},
+            (CoordinateSpace::Screen, _) | (_, Backend::Wayland(_)) => LogicalPosition::new(0.0, 0.0),
+            (CoordinateSpace::WindowOuter, Backend::X11(window)) => window.outer_position(),
+            (CoordinateSpace::WindowContent, Backend::X11(window)) => window.content_position(),
+        }
+    }
+
+    pub fn set_outer_position(&self, position: LogicalPosition) {
+        if let Backend::X11(window) = &self.inner.backend {
+            window.set_outer_position(position);
+        }
+    }
+
+    /// Returns the position and size of the frame that `self` has when it is
+    /// neither maximized nor fullscreen.
+    pub fn windowed_frame(&self) -> (LogicalPosition, LogicalSize) {
+        match &self.inner.backend {
+            Backend::X11(window) => window.windowed_frame(),
+            Backend::Wayland(window) => (LogicalPosition::new(0.0, 0.0), window.windowed_size()),
+        }
+    }
+
+    pub fn set_outer_frame(&self, position: LogicalPosition, size: LogicalSize) {
+        match &self.inner.backend {
+            Backend::X11(window) => window.set_outer_frame(position, size),
+            // Decorations are drawn by the compositor around the surface.
+            Backend::Wayland(window) => window.set_inner_size(size),
+        }
+    }
+
+    pub fn is_minimized(&self) -> bool {
+        match &self.inner.backend {
+            Backend::X11(window) => window.is_minimized(),
+            Backend::Wayland(window) => window.is_minimized(),
+        }
+    }
+
+    pub fn set_minimized(&self, minimized: bool) {
+        match &self.inner.backend {
+            Backend::X11(window) => window.set_minimized(minimized),
+            Backend::Wayland(window) => window.set_minimized(self, minimized),
+        }
+    }
+
+    pub fn focus(&self) {
+        match &self.inner.backend {
+            Backend::X11(window) => window.focus(),
+            Backend::Wayland(window) => window.focus(),
+        }
+    }
+
+    pub fn request_user_attention(&self, ty: UserAttentionType) {
+        match &self.inner.backend {
+            Backend::X11(window) => window.request_user_attention(ty),
+            Backend::Wayland(window) => window.request_user_attention(ty),
+        }
+    }
+
+    pub fn is_zoomed(&self) -> bool {
+        match &self.inner.backend {
+            Backend::X11(window) => window.is_maximized(),
+            Backend::Wayland(window) => window.is_maximized(),
+        }
+    }
+
+    pub fn set_zoomed(&self, zoomed: bool) {
+        match &self.inner.backend {
+            Backend::X11(window) => window.set_maximized(zoomed),
+            Backend::Wayland(window) => window.set_maximized(zoomed),
+        }
+    }
+
+    pub fn set_opacity(&self, opacity: f64) {
+        let opacity = opacity.clamp(0.0, 1.0);
+        match &self.inner.backend {
+            Backend::X11(window) => window.set_opacity(opacity),
+            Backend::Wayland(window) => window.set_opacity(opacity),
+        }
+    }
+
+    pub fn set_cursor_hittest(&self, hittest: bool) {
+        self.state().hittest.set(hittest);
+        self.update_input_region();
+    }
+
+    pub fn set_input_region(&self, region: Option<Vec<LogicalRect>>) {
+        *self.state().input_region.borrow_mut() = region;
+        self.update_input_region();
+    }
+
+    /// Applies the input region, which is empty if hit testing is disabled.
+    fn update_input_region(&self) {
+        let state = self.state();
+        let region = state.input_region.borrow();
+        let region = match &*region {
+            _ if !state.hittest.get() => Some(&[][..]),
+            region => region.as_deref(),
+        };
+        match &self.inner.backend {
+            Backend::X11(window) => window.set_input_region(region),
+            Backend::Wayland(window) => window.set_input_region(region),
+        }
+    }
+
+    pub fn is_fullscreen(&self) -> bool {
+        match &self.inner.backend {
+            Backend::X11(window) => window.is_fullscreen(),
+            Backend::Wayland(window) => window.is_fullscreen(),
+        }
+    }
+
+    pub fn set_fullscreen(&self, fullscreen: bool) {
+        match &self.inner.backend {
+            Backend::X11(window) => window.set_fullscreen(fullscreen),
+            Backend::Wayland(window) => window.set_fullscreen(fullscreen),
+        }
+    }
+
+    pub fn monitor(&self) -> Option<String> {
+        match &self.inner.backend {
+            Backend::X11(window) => window.monitor(),
+            Backend::Wayland(_) => None,
+        }
+    }
+
+    /// Moves `self` so that it is centered on `parent`.
+    ///
+    /// Wayland compositors place child windows themselves.
+    pub fn center_on(&self, parent: &Window) {
+        if let (Backend::X11(window), Backend::X11(parent)) = (&self.inner.backend, &parent.inner.backend) {
+            window.center_on(parent);
+        }
+    }
+
+    /// Attaches `self` to `parent`, blocking the input that reaches `parent`
+    /// through us while `self` is open if `modal`.
+    pub fn set_parent(&self, parent: &Window, modal: bool) {
+        match (&self.inner.backend, &parent.inner.backend) {
+            (Backend::X11(window), Backend::X11(parent)) => window.set_parent(parent, modal),
+            (Backend::Wayland(window), Backend::Wayland(parent)) => window.set_parent(parent, modal),
+            _ => {},
+        }
+        self.inner.state.parent.set(Some(parent.id()));
+        if modal {
+            parent.inner.state.modal_children.borrow_mut().push(self.id());
+        }
+    }
+
+    /// Returns whether a modal child of `self` is open.
+    #[inline]
+    fn is_blocked(&self) -> bool {
+        !self.inner.state.modal_children.borrow().is_empty()
+    }
+
+    #[inline]
+    pub fn state(&self) -> &WindowState {
+        &self.inner.state
+    }
+
+    /// Saves the window's state once it has been still for
+    /// `AUTOSAVE_DELAY`, rather than on every move or resize.
+    fn schedule_autosave(&self) {
+        let state = &self.inner.state;
+        if state.autosave.borrow().is_none() {
+            return;
+        }
+        if let Some(timeout) = state.autosave_timeout.take() {
+            main_loop::remove_source(timeout);
+        }
+        let window = Rc::downgrade(&self.inner);
+        let timeout = main_loop::add_timeout(AUTOSAVE_DELAY, move || {
+            if let Some(inner) = Weak::upgrade(&window) {
+                inner.state.autosave_timeout.set(None);
+                Window { inner }.autosave();
+            }
+        });
+        state.autosave_timeout.set(Some(timeout));
+    }
+
+    /// Saves the window's state now, in place of any scheduled save.
+    fn autosave(&self) {
+        if let Some(timeout) = self.inner.state.autosave_timeout.take() {
+            main_loop::remove_source(timeout);
+        }
+        let autosave = self.inner.state.autosave.borrow().clone();
+        if let Some((store, name)) = autosave {
+            // There's no one to report a failure to from an event.
+            let _ = store.save(&name, &crate::Window::from(self.clone()));
+        }
+    }
+
+    /// Saves the window's state one last time before it closes, since it
+    /// may not be reopened before the application exits.
+    fn autosave_on_close(&self) {
+        self.autosave();
+        self.inner.state.autosave.borrow_mut().take();
+    }
+
+    /// Removes the window from the registry once it is closed, even if
+    /// handles to it remain.
+    fn closed(&self) {
+        unregister(self.inner.id);
+        if let Some(parent) = self.inner.state.parent.take().and_then(window) {
+            parent.inner.state.modal_children.borrow_mut().retain(|&id| id != self.inner.id);
+        }
+    }
+
+    pub fn start_drag(&self, data: &DragData, icon: &Icon) -> Result<(), ()> {
+        let formats = drag_formats(data);
+        if formats.is_empty() {
+            return Err(());
+        }
+        match &self.inner.backend {
+            Backend::X11(window) => window.start_drag(self, formats, data.operations, icon),
+            Backend::Wayland(window) => window.start_drag(self, formats, data.operations, icon),
+        }
+    }
+
+    pub fn set_ime_allowed(&self, allowed: bool) {
+        if self.inner.state.ime_allowed.replace(allowed) == allowed {
+            return;
+        }
+        match &self.inner.backend {
+            Backend::X11(window) => window.update_ime(self),
+            Backend::Wayland(window) => window.update_ime(self),
+        }
+        if !allowed {
+            self.set_preedit(String::new(), None);
+        }
+    }
+
+    pub fn set_ime_cursor_area(&self, position: LogicalPosition, size: LogicalSize) {
+        self.inner.state.ime_cursor_area.set((position, size));
+        match &self.inner.backend {
+            Backend::X11(window) => window.update_ime_cursor_area(self),
+            Backend::Wayland(window) => window.update_ime_cursor_area(self),
+        }
+    }
+
+    /// Pops up `menu`, which is drawn by us. Unlike on macOS, this returns
+    /// right away and the menu event handler is called once an item is
+    /// activated.
+    pub fn show_context_menu(&self, menu: &Menu, position: LogicalPosition) {
+        self.open_context_menu(&popup::Model::new(menu), position);
+    }
+
+    pub fn set_context_menu(&self, menu: Option<&Menu>) {
+        *self.inner.state.context_menu.borrow_mut() = menu.map(popup::Model::new);
+    }
+
+    fn open_context_menu(&self, model: &popup::Model, position: LogicalPosition) {
+        match &self.inner.backend {
+            Backend::X11(window) => window.show_context_menu(self, model, position),
+            Backend::Wayland(window) => window.show_context_menu(self, model, position),
+        }
+    }
+
+    /// Handles a right-click at `position`, showing the context menu if one
+    /// is set, or emitting a `ContextMenuRequested` event otherwise.
+    fn context_click(&self, position: LogicalPosition) {
+        if self.is_blocked() {
+            return;
+        }
+        let model = self.inner.state.context_menu.borrow().clone();
+        match model {
+            Some(model) => self.open_context_menu(&model, position),
+            None => self.emit(WindowEvent::ContextMenuRequested { position }),
+        }
+    }
+
+    #[inline]
+    fn ime_allowed(&self) -> bool {
+        self.inner.state.ime_allowed.get()
+    }
+
+    #[inline]
+    fn ime_cursor_area(&self) -> (LogicalPosition, LogicalSize) {
+        self.inner.state.ime_cursor_area.get()
+    }
+
+    /// Replaces the text being composed, emitting a `Preedit` event if it
+    /// changed.
+    fn set_preedit(&self, text: String, cursor: Option<Range<usize>>) {
+        let mut preedit = self.inner.state.preedit.borrow_mut();
+        if *preedit == (text.clone(), cursor.clone()) {
+            return;
+        }
+        *preedit = (text.clone(), cursor.clone());
+        drop(preedit);
+        self.emit(WindowEvent::Preedit { text, cursor });
+    }
+
+    /// Emits a `Commit` event for `text` if IME is allowed.
+    fn commit(&self, text: String) {
+        if self.ime_allowed() && !text.is_empty() && !self.is_blocked() {
+            self.emit(WindowEvent::Commit { text });
+        }
+    }
+
+    #[inline]
+    pub fn set_event_handler(&self, handler: EventHandler) {
+        *self.inner.state.event_handler.borrow_mut() = Some(handler);
+    }
+
+    /// Passes `event` to the event handler, if any.
+    ///
+    /// The handler is taken out for the duration of the call so that it may
+    /// replace itself. Events emitted by the handler itself are queued until
+    /// it returns.
+    pub fn emit(&self, event: WindowEvent) {
+        let state = &self.inner.state;
+        state.pending_events.borrow_mut().push_back(event);
+        if state.emitting.replace(true) {
+            return;
+        }
+
+        let window = crate::Window::from(self.clone());
+        loop {
+            let event = match state.pending_events.borrow_mut().pop_front() {
+                Some(event) => event,
+                None => break,
+            };
+            let handler = state.event_handler.borrow_mut().take();
+            if let Some(mut handler) = handler {
+                handler(&window, event);
+                let mut slot = state.event_handler.borrow_mut();
+                if slot.is_none() {
+                    *slot = Some(handler);
+                }
+            }
+        }
+        state.emitting.set(false);
+    }
+}
+
+pub struct WindowState {
+    event_handler: RefCell<Option<EventHandler>>,
+    // Events emitted while the handler runs, which are passed to it once it
+    // returns.
+    pending_events: RefCell<VecDeque<WindowEvent>>,
+    emitting: Cell<bool>,
+    ime_allowed: Cell<bool>,
+    ime_cursor_area: Cell<(LogicalPosition, LogicalSize)>,
+    // The text being composed and its cursor, as last reported.
+    preedit: RefCell<(String, Option<Range<usize>>)>,
+    context_menu: RefCell<Option<popup::Model>>,
+    autosave: RefCell<Option<(WindowStateStore, String)>>,
+    autosave_timeout: Cell<Option<main_loop::SourceId>>,
+    parent: Cell<Option<WindowId>>,
+    // The open modal children, which block input to the window.
+    modal_children: RefCell<Vec<WindowId>>,
+    hittest: Cell<bool>,
+    input_region: RefCell<Option<Vec<LogicalRect>>>,
+}
+
+impl WindowState {
+    /// Saves the window's state in `store` under `name` once it stops moving
+    /// or resizing, and when it closes.
+    #[inline]
+    pub fn set_autosave(&self, store: WindowStateStore, name: String) {
+        *self.autosave.borrow_mut() = Some((store, name));
+    }
+}
+
+impl Default for WindowState {
+    fn default() -> Self {
+        WindowState {
+            event_handler: RefCell::new(None),
+            pending_events: RefCell::new(VecDeque::new()),
+            emitting: Cell::new(false),
+            ime_allowed: Cell::new(false),
+            ime_cursor_area: Cell::new((LogicalPosition::new(0.0, 0.0), LogicalSize::new(0.0, 0.0))),
+            preedit: RefCell::new((String::new(), None)),
+            context_menu: RefCell::new(None),
+            autosave: RefCell::new(None),
+            autosave_timeout: Cell::new(None),
+            parent: Cell::new(None),
+            modal_children: RefCell::new(Vec::new()),
+            hittest: Cell::new(true),
+            input_region: RefCell::new(None),
+        }
+    }
+}
+
+pub struct WindowBuilder {
+    pub app_id: Option<String>,
+}
+
+impl Default for WindowBuilder {
+    #[inline]
+    fn default() -> Self {
+        WindowBuilder { app_id: None }
+    }
+}
+
+impl crate::WindowBuilder {
+    pub(crate) fn sys_build(&self) -> Result<crate::Window, ()> {
+        if !is_main_thread() {
+            return Err(());
+        }
+
+        // Compositors without the XDG shell may still run XWayland.
+        let window = match DisplayBackend::current().ok_or(())? {
+            DisplayBackend::X11 => x11::Window::build(self)?,
+            DisplayBackend::Wayland => wayland::Window::build(self)
+                .or_else(|()| x11::Window::build(self))?,
+        };
+        if self.kind == WindowKind::Tooltip {
+            window.set_cursor_hittest(false);
+        }
+        Ok(window.into())
+    }
+
+    #[inline]
+    fn title_str(&self) -> &str {
+        self.title.as_ref().map_or("", |t| t.as_str())
+    }
+
+    /// Returns the size of the content area, scaling physical sizes by
+    /// `scale_factor`.
+    #[inline]
+    fn inner_size(&self, scale_factor: ScaleFactor) -> LogicalSize {
+        self.size.map_or(DEFAULT_SIZE, |size| size.to_logical_with(scale_factor))
+    }
+}
+
+/// Returns the contents of a drag under each type it is offered as.
+///
+/// Text is also offered under the names used by X11 and older toolkits.
+fn drag_formats(data: &DragData) -> Vec<(String, Arc<[u8]>)> {
+    let mut formats: Vec<(String, Arc<[u8]>)> = Vec::new();
+    if let Some(text) = &data.text {
+        let text: Arc<[u8]> = text.as_bytes().into();
+        for &ty in &[mime::TEXT_PLAIN, "text/plain", "UTF8_STRING"] {
+            formats.push((ty.into(), text.clone()));
+        }
+    }
+    if !data.uris.is_empty() {
+        let mut list = String::new();
+        for uri in &data.uris {
+            list.push_str(uri);
+            list.push_str("\r\n");
+        }
+        formats.push((URI_LIST.into(), list.into_bytes().into()));
+    }
+    for (ty, bytes) in &data.data {
+        if !formats.iter().any(|(t, _)| t == ty) {
+            formats.push((ty.clone(), bytes.as_slice().into()));
+        }
+    }
+    formats
+}
+
+/// Returns the local paths in a `text/uri-list`, skipping comments and URIs
+/// of other schemes.
+fn parse_uri_list(list: &[u8]) -> Vec<PathBuf> {
+    list.split(|&b| b == b'\n')
+        .map(|line| line.strip_suffix(b"\r").unwrap_or(line))
+        .filter(|line| !line.is_empty() && !line.starts_with(b"#"))
+        .filter_map(linux::path_from_file_uri)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn uri_list() {
+        let list = b"# comment\r\n\
+                     file:///home/user/a%20b.txt\r\n\
+                     file://localhost/tmp/%C3%A9\r\n\
+                     https://example.com/file\r\n\
+                     file:/etc/hosts\n\
+                     file:///100%\n";
+        assert_eq!(parse_uri_list(list), vec![
+            PathBuf::from("/home/user/a b.txt"),
+            PathBuf::from("/tmp/é"),
+            PathBuf::from("/etc/hosts"),
+            PathBuf::from("/100%"),
+        ]);
+        assert!(parse_uri_list(b"").is_empty());
+    }
+
+    #[test]
+    fn drag_data_formats() {
+        let mut data = DragData::new();
+        data.text("hi")
+            .uri("file:///a")
+            .uri("https://example.com")
+            .data("application/x-zui", b"\x01")
+            .data("text/plain", b"ignored");
+        let formats = drag_formats(&data);
+        let formats: Vec<(&str, &[u8])> = formats.iter()
+            .map(|(ty, bytes)| (ty.as_str(), &bytes[..]))
+            .collect();
+        assert_eq!(formats, [
+            (mime::TEXT_PLAIN, &b"hi"[..]),
+            ("text/plain", b"hi"),
+            ("UTF8_STRING", b"hi"),
+            (URI_LIST, b"file:///a\r\nhttps://example.com\r\n"),
+            ("application/x-zui", b"\x01"),
+        ]);
+        assert!(drag_formats(&DragData::new()).is_empty());
+    }
+}
@@ -0,0 +1,560 @@
+//! Context menus, which are drawn by us since neither X11 nor Wayland has
+//! native menus.
+//!
+//! A menu and its open submenus form a stack of popups, each drawn into a
+//! buffer that the backend shows in a popup window or surface. The backend
+//! passes pointer events to the stack and then reconciles its popups with
+//! it: opening and closing them to match its length, and redrawing those
+//! that are dirty.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+    rc::Rc,
+};
+use ab_glyph::{point, Font as _, FontVec, PxScale, ScaleFont as _};
+use menu::{Menu, MenuEntry, MenuEvent, MenuItemId};
+use shared::os::linux::ZedStringExt;
+
+/// Sizes in logical pixels.
+const FONT_SIZE: f64 = 14.0;
+const ROW_HEIGHT: f64 = 24.0;
+const SEPARATOR_HEIGHT: f64 = 9.0;
+const PADDING: f64 = 12.0;
+const CHECK_WIDTH: f64 = 20.0;
+const ARROW_WIDTH: f64 = 16.0;
+const ACCELERATOR_GAP: f64 = 24.0;
+const MIN_WIDTH: f64 = 120.0;
+
+const BACKGROUND: u32 = 0xff_fa_fa_fa;
+const BORDER: u32 = 0xff_b8_b8_b8;
+const SEPARATOR: u32 = 0xff_dc_dc_dc;
+const HIGHLIGHT: u32 = 0xff_35_84_e4;
+const TEXT: u32 = 0xff_20_20_20;
+const HIGHLIGHTED_TEXT: u32 = 0xff_ff_ff_ff;
+const DISABLED_TEXT: u32 = 0xff_a0_a0_a0;
+
+/// A snapshot of a [`Menu`](../../../../zui_menu/struct.Menu.html) that keeps
+/// the identifiers of its items.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Model {
+    rows: Vec<RowModel>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct RowModel {
+    kind: Kind,
+    label: String,
+    accelerator: String,
+    enabled: bool,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Kind {
+    Item { id: MenuItemId, checked: Option<bool> },
+    Submenu(Rc<Model>),
+    Separator,
+}
+
+impl Model {
+    pub fn new(menu: &Menu) -> Self {
+        let rows = menu.entries().iter().map(|entry| match entry {
+            MenuEntry::Item(item) => RowModel {
+                kind: Kind::Item { id: item.id(), checked: item.checked() },
+                label: item.label().as_str().to_owned(),
+                accelerator: item.accelerator().map_or_else(String::new, |a| a.to_string()),
+                enabled: item.is_enabled(),
+            },
+            MenuEntry::Submenu(submenu) => RowModel {
+                kind: Kind::Submenu(Rc::new(Model::new(submenu.menu()))),
+                label: submenu.label().as_str().to_owned(),
+                accelerator: String::new(),
+                enabled: submenu.is_enabled(),
+            },
+            MenuEntry::Separator => RowModel {
+                kind: Kind::Separator,
+                label: String::new(),
+                accelerator: String::new(),
+                enabled: false,
+            },
+        }).collect();
+        Model { rows }
+    }
+}
+
+/// The font menus are drawn with.
+pub struct Font {
+    font: FontVec,
+}
+
+impl Font {
+    /// Returns the sans-serif font of the system, loading it on first use,
+    /// or `None` if no font could be found.
+    pub fn get() -> Option<Rc<Font>> {
+        thread_local! {
+            static FONT: Option<Rc<Font>> = Font::load().map(Rc::new);
+        }
+        FONT.with(Clone::clone)
+    }
+
+    fn load() -> Option<Font> {
+        // Fontconfig knows the preferred font, but may not be installed.
+        let matched = Command::new("fc-match")
+            .args(["--format=%{file}", "sans-serif"])
+            .output()
+            .ok()
+            .filter(|output| output.status.success())
+            .and_then(|output| String::from_utf8(output.stdout).ok())
+            .map(PathBuf::from);
+        let fallbacks = [
+            "/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf",
+            "/usr/share/fonts/TTF/DejaVuSans.ttf",
+            "/usr/share/fonts/dejavu/DejaVuSans.ttf",
+            "/usr/share/fonts/noto/NotoSans-Regular.ttf",
+            "/usr/share/fonts/truetype/noto/NotoSans-Regular.ttf",
+            "/usr/share/fonts/liberation/LiberationSans-Regular.ttf",
+            "/usr/share/fonts/truetype/liberation/LiberationSans-Regular.ttf",
+        ];
+        matched.into_iter()
+            .chain(fallbacks.iter().map(PathBuf::from))
+            .chain(find_font(Path::new("/usr/share/fonts"), 4))
+            .find_map(|path| FontVec::try_from_vec(fs::read(path).ok()?).ok())
+            .map(|font| Font { font })
+    }
+
+    /// Returns the width of `text` at `size` pixels.
+    fn width(&self, text: &str, size: f32) -> f32 {
+        let font = self.font.as_scaled(PxScale::from(size));
+        let mut width = 0.0;
+        let mut previous = None;
+        for c in text.chars() {
+            let id = font.glyph_id(c);
+            if let Some(previous) = previous {
+                width += font.kern(previous, id);
+            }
+            width += font.h_advance(id);
+            previous = Some(id);
+        }
+        width
+    }
+
+    /// Draws `text` at `size` pixels into `canvas`, with the left end of its
+    /// baseline at `(x, y)`.
+    fn draw(&self, canvas: &mut Canvas, text: &str, size: f32, (x, y): (f32, f32), color: u32) {
+        let font = self.font.as_scaled(PxScale::from(size));
+        let mut x = x;
+        let mut previous = None;
+        for c in text.chars() {
+            let id = font.glyph_id(c);
+            if let Some(previous) = previous {
+                x += font.kern(previous, id);
+            }
+            let glyph = id.with_scale_and_position(size, point(x, y));
+            if let Some(outline) = self.font.outline_glyph(glyph) {
+                let bounds = outline.px_bounds();
+                outline.draw(|gx, gy, coverage| {
+                    let px = bounds.min.x as i64 + gx as i64;
+                    let py = bounds.min.y as i64 + gy as i64;
+                    canvas.blend(px, py, color, coverage);
+                });
+            }
+            x += font.h_advance(id);
+            previous = Some(id);
+        }
+    }
+
+    /// Returns `c` if the font has a glyph for it, or `fallback` otherwise.
+    fn symbol(&self, c: char, fallback: char) -> char {
+        if self.font.glyph_id(c).0 != 0 { c } else { fallback }
+    }
+}
+
+/// Returns the first TrueType font within `dir`, searching `depth` levels of
+/// subdirectories.
+fn find_font(dir: &Path, depth: usize) -> Option<PathBuf> {
+    let mut entries: Vec<PathBuf> = fs::read_dir(dir).ok()?.filter_map(|e| Some(e.ok()?.path())).collect();
+    entries.sort();
+    let font = entries.iter()
+        .find(|path| path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("ttf")))
+        .cloned();
+    font.or_else(|| {
+        if depth == 0 {
+            return None;
+        }
+        entries.iter().filter(|path| path.is_dir()).find_map(|path| find_font(path, depth - 1))
+    })
+}
+
+/// Opaque pixels in the native-endian ARGB format of X11 and `wl_shm`.
+pub struct Canvas {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u32>,
+}
+
+impl Canvas {
+    fn new(width: u32, height: u32, color: u32) -> Self {
+        Canvas { width, height, pixels: vec![color; width as usize * height as usize] }
+    }
+
+    fn fill(&mut self, x: i64, y: i64, width: i64, height: i64, color: u32) {
+        let (x0, y0) = (x.max(0), y.max(0));
+        let x1 = (x + width).min(self.width as i64);
+        let y1 = (y + height).min(self.height as i64);
+        for py in y0..y1 {
+            let row = py as usize * self.width as usize;
+            for px in x0..x1 {
+                self.pixels[row + px as usize] = color;
+            }
+        }
+    }
+
+    fn blend(&mut self, x: i64, y: i64, color: u32, coverage: f32) {
+        if x < 0 || y < 0 || x >= self.width as i64 || y >= self.height as i64 {
+            return;
+        }
+        let pixel = &mut self.pixels[y as usize * self.width as usize + x as usize];
+        let alpha = coverage.clamp(0.0, 1.0);
+        let channel = |shift: u32| {
+            let fg = ((color >> shift) & 0xff) as f32;
+            let bg = ((*pixel >> shift) & 0xff) as f32;
+            ((fg * alpha + bg * (1.0 - alpha)).round() as u32) << shift
+        };
+        *pixel = 0xff00_0000 | channel(16) | channel(8) | channel(0);
+    }
+
+    /// Returns the pixels as bytes in native order.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.pixels.iter().flat_map(|pixel| pixel.to_ne_bytes()).collect()
+    }
+}
+
+/// A menu shown in a popup.
+pub struct Popup {
+    model: Rc<Model>,
+    /// The offset and height of each row, in physical pixels.
+    rows: Vec<(u32, u32)>,
+    pub width: u32,
+    pub height: u32,
+    /// Where the popup goes, in physical pixels relative to the popup it
+    /// opened from, or to the window for the first one.
+    pub origin: (i32, i32),
+    hovered: Option<usize>,
+    /// Whether the popup must be drawn again.
+    pub dirty: bool,
+    scale: f64,
+}
+
+impl Popup {
+    fn new(model: Rc<Model>, font: &Font, scale: f64, origin: (i32, i32)) -> Self {
+        let physical = |v: f64| (v * scale).round() as u32;
+        let size = (FONT_SIZE * scale) as f32;
+        let checks = model.rows.iter().any(|r| matches!(r.kind, Kind::Item { checked: Some(_), .. }));
+        let labels = model.rows.iter().map(|r| font.width(&r.label, size)).fold(0.0, f32::max) as f64;
+        let accelerators = model.rows.iter().map(|r| font.width(&r.accelerator, size)).fold(0.0, f32::max) as f64;
+
+        let mut width = labels + PADDING * scale * 2.0 + ARROW_WIDTH * scale;
+        if checks {
+            width += CHECK_WIDTH * scale;
+        }
+        if accelerators > 0.0 {
+            width += ACCELERATOR_GAP * scale + accelerators;
+        }
+        let width = (width.ceil() as u32).max(physical(MIN_WIDTH));
+
+        let border = physical(1.0).max(1);
+        let mut y = border;
+        let rows = model.rows.iter().map(|row| {
+            let height = match row.kind {
+                Kind::Separator => physical(SEPARATOR_HEIGHT),
+                _ => physical(ROW_HEIGHT),
+            };
+            y += height;
+            (y - height, height)
+        }).collect();
+
+        Popup {
+            model,
+            rows,
+            width,
+            height: y + border,
+            origin,
+            hovered: None,
+            dirty: true,
+            scale,
+        }
+    }
+
+    /// Returns the row at `(x, y)` within `self`, if any.
+    pub fn row_at(&self, x: f64, y: f64) -> Option<usize> {
+        if x < 0.0 || x >= self.width as f64 {
+            return None;
+        }
+        self.rows.iter().position(|&(top, height)| y >= top as f64 && y < (top + height) as f64)
+    }
+
+    fn contains(&self, x: f64, y: f64) -> bool {
+        x >= 0.0 && y >= 0.0 && x < self.width as f64 && y < self.height as f64
+    }
+
+    fn set_hovered(&mut self, hovered: Option<usize>) {
+        if self.hovered != hovered {
+            self.hovered = hovered;
+            self.dirty = true;
+        }
+    }
+
+    pub fn draw(&self, font: &Font) -> Canvas {
+        let scale = self.scale;
+        let physical = |v: f64| (v * scale).round() as i64;
+        let size = (FONT_SIZE * scale) as f32;
+        let border = physical(1.0).max(1);
+        let mut canvas = Canvas::new(self.width, self.height, BORDER);
+        canvas.fill(border, border, self.width as i64 - border * 2, self.height as i64 - border * 2, BACKGROUND);
+
+        let checks = self.model.rows.iter().any(|r| matches!(r.kind, Kind::Item { checked: Some(_), .. }));
+        let label_x = physical(PADDING) + if checks { physical(CHECK_WIDTH) } else { 0 };
+        let scaled = font.font.as_scaled(PxScale::from(size));
+        let text_height = scaled.ascent() - scaled.descent();
+
+        for (i, (row, &(top, height))) in self.model.rows.iter().zip(&self.rows).enumerate() {
+            let (top, height) = (top as i64, height as i64);
+            if let Kind::Separator = row.kind {
+                canvas.fill(border, top + height / 2, self.width as i64 - border * 2, border, SEPARATOR);
+                continue;
+            }
+            let highlighted = row.enabled && self.hovered == Some(i);
+            if highlighted {
+                canvas.fill(border, top, self.width as i64 - border * 2, height, HIGHLIGHT);
+            }
+            let color = match (row.enabled, highlighted) {
+                (false, _) => DISABLED_TEXT,
+                (true, true) => HIGHLIGHTED_TEXT,
+                (true, false) => TEXT,
+            };
+            let baseline = top as f32 + (height as f32 - text_height) / 2.0 + scaled.ascent();
+
+            font.draw(&mut canvas, &row.label, size, (label_x as f32, baseline), color);
+            match &row.kind {
+                Kind::Item { checked: Some(true), .. } => {
+                    let check = font.symbol('✓', '*').to_string();
+                    font.draw(&mut canvas, &check, size, (physical(PADDING) as f32, baseline), color);
+                },
+                Kind::Submenu(_) => {
+                    let arrow = font.symbol('▸', '>').to_string();
+                    let x = self.width as f32 - physical(PADDING) as f32 - font.width(&arrow, size);
+                    font.draw(&mut canvas, &arrow, size, (x, baseline), color);
+                },
+                _ => {},
+            }
+            if !row.accelerator.is_empty() {
+                let x = self.width as f32
+                    - physical(PADDING + ARROW_WIDTH) as f32
+                    - font.width(&row.accelerator, size);
+                let color = if highlighted { color } else { DISABLED_TEXT };
+                font.draw(&mut canvas, &row.accelerator, size, (x, baseline), color);
+            }
+        }
+        canvas
+    }
+}
+
+/// What became of a context menu after an event.
+#[derive(Debug, PartialEq)]
+pub enum Outcome {
+    /// The menu stays open.
+    Open,
+    /// The menu was dismissed, having activated an item if any.
+    Closed(Option<MenuEvent>),
+}
+
+/// A context menu and its open submenus.
+pub struct ContextMenu {
+    pub popups: Vec<Popup>,
+    font: Rc<Font>,
+}
+
+impl ContextMenu {
+    /// Creates a menu whose top-left corner is at `origin` within the window.
+    pub fn new(model: &Model, font: Rc<Font>, scale: f64, origin: (i32, i32)) -> Self {
+        let popup = Popup::new(Rc::new(model.clone()), &font, scale, origin);
+        ContextMenu { popups: vec![popup], font }
+    }
+
+    #[inline]
+    pub fn font(&self) -> &Rc<Font> {
+        &self.font
+    }
+
+    /// Handles the pointer moving to `(x, y)` within the popup at `level`,
+    /// or leaving every popup if `level` is `None`.
+    pub fn motion(&mut self, level: Option<usize>, x: f64, y: f64) {
+        let level = match level {
+            Some(level) if level < self.popups.len() => level,
+            // Leaving keeps open submenus, but nothing is hovered anymore.
+            _ => {
+                if let Some(popup) = self.popups.last_mut() {
+                    popup.set_hovered(None);
+                }
+                return;
+            },
+        };
+        let popup = &mut self.popups[level];
+        let row = popup.row_at(x, y).filter(|&row| popup.model.rows[row].enabled);
+        popup.set_hovered(row);
+
+        // Hovering a submenu opens it in place of any deeper popups.
+        let submenu = row.and_then(|row| match &popup.model.rows[row].kind {
+            Kind::Submenu(model) => Some((row, model.clone())),
+            _ => None,
+        });
+        let open = self.popups.get(level + 1).map(|child| child.model.clone());
+        match submenu {
+            Some((_, model)) if open.as_ref().is_some_and(|open| Rc::ptr_eq(open, &model)) => {},
+            Some((row, model)) => {
+                self.popups.truncate(level + 1);
+                let parent = &self.popups[level];
+                let border = (parent.scale.round() as i32).max(1);
+                let origin = (parent.width as i32 - border, parent.rows[row].0 as i32 - border);
+                let popup = Popup::new(model, &self.font, parent.scale, origin);
+                self.popups.push(popup);
+            },
+            None if row.is_some() => self.popups.truncate(level + 1),
+            None => {},
+        }
+    }
+
+    /// Handles a button press at `(x, y)` within the popup at `level`, or
+    /// outside of every popup if `level` is `None`.
+    pub fn press(&mut self, level: Option<usize>, x: f64, y: f64) -> Outcome {
+        match level.and_then(|level| self.popups.get(level)) {
+            Some(popup) if popup.contains(x, y) => Outcome::Open,
+            _ => Outcome::Closed(None),
+        }
+    }
+
+    /// Handles a button release within the popup at `level`, activating the
+    /// hovered item.
+    ///
+    /// Releasing the button that opened the menu activates nothing, since
+    /// the pointer has not yet moved onto an item.
+    pub fn release(&mut self, level: Option<usize>, x: f64, y: f64) -> Outcome {
+        let popup = match level.and_then(|level| self.popups.get(level)) {
+            Some(popup) => popup,
+            None => return Outcome::Open,
+        };
+        let row = match popup.row_at(x, y) {
+            Some(row) if popup.hovered == Some(row) => &popup.model.rows[row],
+            _ => return Outcome::Open,
+        };
+        match row.kind {
+            Kind::Item { id, checked } if row.enabled => {
+                Outcome::Closed(Some(MenuEvent { id, checked: checked.map(|checked| !checked) }))
+            },
+            _ => Outcome::Open,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use menu::{MenuItem, Submenu};
+
+    fn menu() -> (Menu, MenuItemId, MenuItemId) {
+        let mut copy = MenuItem::new("Copy");
+        copy.set_accelerator("Ctrl+C".parse::<menu::Accelerator>().unwrap());
+        let mut wrap = MenuItem::new("Wrap");
+        wrap.set_checked(true);
+        let ids = (copy.id(), wrap.id());
+
+        let mut view = Menu::new();
+        view.add(wrap);
+        let mut disabled = MenuItem::new("Paste");
+        disabled.set_enabled(false);
+        let mut menu = Menu::new();
+        menu.add(copy).add(disabled).add_separator().add(Submenu::new("View", view));
+        (menu, ids.0, ids.1)
+    }
+
+    fn center(popup: &Popup, row: usize) -> (f64, f64) {
+        let (top, height) = popup.rows[row];
+        (popup.width as f64 / 2.0, top as f64 + height as f64 / 2.0)
+    }
+
+    #[test]
+    fn layout_rows() {
+        let font = Font::get().expect("no font is installed");
+        let (menu, ..) = menu();
+        let popup = Popup::new(Rc::new(Model::new(&menu)), &font, 2.0, (0, 0));
+        assert_eq!(popup.rows, [(2, 48), (50, 48), (98, 18), (116, 48)]);
+        assert_eq!(popup.height, 166);
+        assert!(popup.width >= 240);
+
+        assert_eq!(popup.row_at(10.0, 60.0), Some(1));
+        assert_eq!(popup.row_at(10.0, 0.0), None);
+        assert_eq!(popup.row_at(-1.0, 60.0), None);
+    }
+
+    #[test]
+    fn navigate_and_activate() {
+        let font = Font::get().expect("no font is installed");
+        let (menu, copy, wrap) = menu();
+        let mut context = ContextMenu::new(&Model::new(&menu), font, 1.0, (10, 20));
+
+        // The release of the opening click does nothing.
+        let (x, y) = center(&context.popups[0], 0);
+        assert_eq!(context.release(Some(0), x, y), Outcome::Open);
+
+        // Disabled items and separators are not hovered.
+        let (x, y) = center(&context.popups[0], 1);
+        context.motion(Some(0), x, y);
+        assert_eq!(context.popups[0].hovered, None);
+        assert_eq!(context.release(Some(0), x, y), Outcome::Open);
+
+        // Hovering a submenu opens it next to its row.
+        let (x, y) = center(&context.popups[0], 3);
+        context.motion(Some(0), x, y);
+        assert_eq!(context.popups.len(), 2);
+        let parent = &context.popups[0];
+        assert_eq!(context.popups[1].origin, (parent.width as i32 - 1, parent.rows[3].0 as i32 - 1));
+
+        let (x, y) = center(&context.popups[1], 0);
+        context.motion(Some(1), x, y);
+        assert!(context.popups[1].dirty);
+        assert_eq!(context.release(Some(1), x, y), Outcome::Closed(Some(MenuEvent { id: wrap, checked: Some(false) })));
+
+        // Hovering another item closes the submenu.
+        let (x, y) = center(&context.popups[0], 0);
+        context.motion(Some(0), x, y);
+        assert_eq!(context.popups.len(), 1);
+        assert_eq!(context.release(Some(0), x, y), Outcome::Closed(Some(MenuEvent { id: copy, checked: None })));
+
+        assert_eq!(context.press(Some(0), x, y), Outcome::Open);
+        assert_eq!(context.press(Some(0), -5.0, y), Outcome::Closed(None));
+        assert_eq!(context.press(None, 0.0, 0.0), Outcome::Closed(None));
+    }
+
+    #[test]
+    fn draw_rows() {
+        let font = Font::get().expect("no font is installed");
+        let (menu, ..) = menu();
+        let mut popup = Popup::new(Rc::new(Model::new(&menu)), &font, 1.0, (0, 0));
+        popup.set_hovered(Some(0));
+        let canvas = popup.draw(&font);
+        assert_eq!(canvas.pixels.len(), (popup.width * popup.height) as usize);
+        assert_eq!(canvas.pixels[0], BORDER);
+
+        let row = |i: usize| {
+            let (top, height) = popup.rows[i];
+            &canvas.pixels[(top * popup.width) as usize..((top + height) * popup.width) as usize]
+        };
+        // The hovered row is highlighted behind its text.
+        assert!(row(0).contains(&HIGHLIGHT));
+        assert!(row(0).contains(&HIGHLIGHTED_TEXT));
+        assert!(!row(1).contains(&HIGHLIGHT));
+        assert!(row(1).iter().any(|&p| p != BACKGROUND && p != BORDER));
+        assert!(row(2).contains(&SEPARATOR));
+    }
+}
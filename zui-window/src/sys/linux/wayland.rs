@@ -0,0 +1,1651 @@
+//! Windows on a Wayland compositor, through the XDG shell.
+
+use std::{
+    cell::{Cell, RefCell},
+    collections::HashMap,
+    convert::TryFrom,
+    fs::File,
+    io::{self, Read, Write},
+    mem,
+    os::unix::io::{AsFd, AsRawFd, FromRawFd, OwnedFd},
+    ptr,
+    rc::Rc,
+    slice,
+    sync::Arc,
+    thread,
+};
+use wayland_client::{
+    backend::ObjectId,
+    delegate_noop,
+    event_created_child,
+    globals::{registry_queue_init, GlobalListContents},
+    protocol::{
+        wl_buffer::{self, WlBuffer},
+        wl_compositor::WlCompositor,
+        wl_data_device::{self, WlDataDevice},
+        wl_data_device_manager::{DndAction, WlDataDeviceManager},
+        wl_data_offer::{self, WlDataOffer},
+        wl_data_source::{self, WlDataSource},
+        wl_keyboard::{self, KeyState, KeymapFormat, WlKeyboard},
+        wl_pointer::{self, ButtonState, WlPointer},
+        wl_region::WlRegion,
+        wl_registry::WlRegistry,
+        wl_seat::{self, Capability, WlSeat},
+        wl_shm::{Format, WlShm},
+        wl_shm_pool::WlShmPool,
+        wl_surface::WlSurface,
+    },
+    Connection as WlConnection,
+    Dispatch,
+    EventQueue,
+    Proxy,
+    QueueHandle,
+    WEnum,
+};
+use wayland_protocols::{
+    wp::{
+        alpha_modifier::v1::client::{
+            wp_alpha_modifier_surface_v1::WpAlphaModifierSurfaceV1,
+            wp_alpha_modifier_v1::WpAlphaModifierV1,
+        },
+        text_input::zv3::client::{
+            zwp_text_input_manager_v3::ZwpTextInputManagerV3,
+            zwp_text_input_v3::{self, ContentHint, ContentPurpose, ZwpTextInputV3},
+        },
+    },
+    xdg::{
+        activation::v1::client::{
+            xdg_activation_token_v1::{self, XdgActivationTokenV1},
+            xdg_activation_v1::XdgActivationV1,
+        },
+        decoration::zv1::client::{
+            zxdg_decoration_manager_v1::ZxdgDecorationManagerV1,
+            zxdg_toplevel_decoration_v1::{self, ZxdgToplevelDecorationV1},
+        },
+        dialog::v1::client::{
+            xdg_dialog_v1::XdgDialogV1,
+            xdg_wm_dialog_v1::XdgWmDialogV1,
+        },
+        shell::client::{
+            xdg_popup::{self, XdgPopup},
+            xdg_positioner::{Anchor, ConstraintAdjustment, Gravity, XdgPositioner},
+            xdg_surface::{self, XdgSurface},
+            xdg_toplevel::{self, XdgToplevel},
+            xdg_wm_base::{self, XdgWmBase},
+        },
+    },
+};
+use menu::{os::linux as menu_linux, MenuEvent};
+use shared::{
+    os::linux::{main_loop, IconExt},
+    Icon,
+};
+use crate::{
+    dpi::{LogicalPosition, LogicalRect, LogicalSize, ScaleFactor},
+    drag::{DragOperation, DragOperations},
+    event::WindowEvent,
+    UserAttentionType,
+    WindowKind,
+};
+use super::{
+    ime,
+    keyboard::Keymap,
+    parse_uri_list,
+    popup::{self, ContextMenu, Outcome},
+    Backend,
+    URI_LIST,
+};
+
+/// The evdev code of the pointer button that opens context menus.
+const BTN_RIGHT: u32 = 0x111;
+
+const XKB_KEY_ESCAPE: u32 = 0xff1b;
+
+/// The scale of the buffers attached to surfaces, which makes physical
+/// pixels the same as logical pixels.
+const SCALE_FACTOR: ScaleFactor = ScaleFactor::ONE;
+
+/// Globals bound by the connection.
+#[derive(Clone)]
+struct Globals {
+    compositor: WlCompositor,
+    wm_base: XdgWmBase,
+    shm: WlShm,
+    decorations: Option<ZxdgDecorationManagerV1>,
+    dialogs: Option<XdgWmDialogV1>,
+    activation: Option<XdgActivationV1>,
+    alpha_modifier: Option<WpAlphaModifierV1>,
+    data_devices: Option<WlDataDeviceManager>,
+    text_inputs: Option<ZwpTextInputManagerV3>,
+}
+
+/// The connection to the compositor, shared by the windows of a thread.
+pub struct Connection {
+    conn: WlConnection,
+    queue: RefCell<EventQueue<State>>,
+    handle: QueueHandle<State>,
+    state: RefCell<State>,
+    globals: Globals,
+}
+
+thread_local! {
+    static CONNECTION: RefCell<Option<Rc<Connection>>> = const { RefCell::new(None) };
+}
+
+/// The state passed to event handlers while dispatching.
+struct State {
+    globals: Globals,
+    /// Open windows by surface, which are kept alive until they are closed.
+    windows: HashMap<ObjectId, super::Window>,
+    /// Events to emit once dispatching is over.
+    events: Vec<(super::Window, WindowEvent)>,
+    /// The MIME types of every live data offer.
+    offers: HashMap<ObjectId, Vec<String>>,
+    selection: Option<WlDataOffer>,
+    drag: Option<Drag>,
+    data_device: Option<WlDataDevice>,
+    seat: Option<WlSeat>,
+    /// The serial of the last button or key press, with which popups grab
+    /// the seat.
+    serial: u32,
+    pointer: Option<WlPointer>,
+    /// The surface under the pointer and the position within it.
+    pointer_focus: Option<ObjectId>,
+    pointer_position: (f64, f64),
+    /// The buttons being held, and the serial and surface of the press that
+    /// started the implicit grab, from which drags may be started.
+    buttons: Vec<u32>,
+    grab: Option<(u32, Option<ObjectId>)>,
+    source: Option<Source>,
+    keyboard: Option<WlKeyboard>,
+    keymap: Option<Keymap>,
+    /// The surface with the keyboard focus.
+    keyboard_focus: Option<ObjectId>,
+    text_input: Option<TextInput>,
+    menu: Option<Menu>,
+    /// The event of the menu item activated while dispatching, which is
+    /// passed to the menu event handler once dispatching is over.
+    activated: Option<MenuEvent>,
+}
+
+/// A context menu, each popup of which is an `xdg_popup` grabbing the seat.
+struct Menu {
+    window: super::Window,
+    context: ContextMenu,
+    popups: Vec<MenuPopup>,
+}
+
+struct MenuPopup {
+    surface: WlSurface,
+    xdg_surface: XdgSurface,
+    popup: XdgPopup,
+    configured: bool,
+}
+
+/// The input method of the seat, through text-input-v3.
+struct TextInput {
+    text_input: ZwpTextInputV3,
+    /// The surface the input method is focused on.
+    focus: Option<ObjectId>,
+    /// The text being composed and its cursor, and the text to commit,
+    /// which are applied once the input method is done.
+    preedit: Option<(String, i32, i32)>,
+    commit: Option<String>,
+}
+
+/// A drag started from one of our windows.
+struct Source {
+    source: WlDataSource,
+    window: super::Window,
+    formats: Vec<(String, Arc<[u8]>)>,
+    icon: Option<WlSurface>,
+    /// The action last chosen by the compositor.
+    operation: Option<DragOperation>,
+}
+
+/// A drag over one of our windows.
+struct Drag {
+    offer: WlDataOffer,
+    window: super::Window,
+    accepts: bool,
+    position: LogicalPosition,
+    paths: Option<Vec<std::path::PathBuf>>,
+    dropped: bool,
+    hovered: bool,
+}
+
+impl Connection {
+    /// Returns the connection of the current thread, connecting on first use.
+    pub fn get() -> Result<Rc<Self>, ()> {
+        if let Some(connection) = CONNECTION.with(|c| c.borrow().clone()) {
+            return Ok(connection);
+        }
+        let connection = Rc::new(Self::connect().ok_or(())?);
+        CONNECTION.with(|c| *c.borrow_mut() = Some(connection.clone()));
+
+        let fd = connection.conn.backend().poll_fd().as_raw_fd();
+        main_loop::watch_fd(fd, {
+            let connection = connection.clone();
+            move || connection.dispatch(true)
+        });
+        main_loop::add_prepare({
+            let connection = connection.clone();
+            move || connection.dispatch(false)
+        });
+        Ok(connection)
+    }
+
+    fn connect() -> Option<Self> {
+        let conn = WlConnection::connect_to_env().ok()?;
+        let (list, queue) = registry_queue_init::<State>(&conn).ok()?;
+        let handle = queue.handle();
+
+        let globals = Globals {
+            compositor: list.bind(&handle, 4..=6, ()).ok()?,
+            wm_base: list.bind(&handle, 1..=6, ()).ok()?,
+            shm: list.bind(&handle, 1..=1, ()).ok()?,
+            decorations: list.bind(&handle, 1..=1, ()).ok(),
+            dialogs: list.bind(&handle, 1..=1, ()).ok(),
+            activation: list.bind(&handle, 1..=1, ()).ok(),
+            alpha_modifier: list.bind(&handle, 1..=1, ()).ok(),
+            data_devices: list.bind(&handle, 3..=3, ()).ok(),
+            text_inputs: list.bind(&handle, 1..=1, ()).ok(),
+        };
+
+        // Drag and drop and input methods are optional.
+        let seat = list.bind::<WlSeat, _, _>(&handle, 1..=5, ()).ok();
+        let data_device = match (&seat, &globals.data_devices) {
+            (Some(seat), Some(manager)) => Some(manager.get_data_device(seat, &handle, ())),
+            _ => None,
+        };
+        let text_input = match (&seat, &globals.text_inputs) {
+            (Some(seat), Some(manager)) => Some(TextInput {
+                text_input: manager.get_text_input(seat, &handle, ()),
+                focus: None,
+                preedit: None,
+                commit: None,
+            }),
+            _ => None,
+        };
+
+        let state = State {
+            globals: globals.clone(),
+            windows: HashMap::new(),
+            events: Vec::new(),
+            offers: HashMap::new(),
+            selection: None,
+            drag: None,
+            data_device,
+            seat,
+            serial: 0,
+            pointer: None,
+            pointer_focus: None,
+            pointer_position: (0.0, 0.0),
+            buttons: Vec::new(),
+            grab: None,
+            source: None,
+            keyboard: None,
+            keymap: None,
+            keyboard_focus: None,
+            text_input,
+            menu: None,
+            activated: None,
+        };
+        Some(Connection {
+            conn,
+            queue: RefCell::new(queue),
+            handle,
+            state: RefCell::new(state),
+            globals,
+        })
+    }
+
+    /// Dispatches queued events, reading new ones from the socket first if
+    /// `read` is set.
+    fn dispatch(&self, read: bool) {
+        let (events, activated) = {
+            let mut queue = match self.queue.try_borrow_mut() {
+                Ok(queue) => queue,
+                Err(_) => return,
+            };
+            if read {
+                if let Some(guard) = queue.prepare_read() {
+                    let _ = guard.read();
+                }
+            }
+            let mut state = self.state.borrow_mut();
+            let _ = queue.dispatch_pending(&mut state);
+            let _ = queue.flush();
+            (mem::take(&mut state.events), state.activated.take())
+        };
+        for (window, event) in events {
+            match event {
+                WindowEvent::Preedit { text, cursor } => {
+                    if window.ime_allowed() {
+                        window.set_preedit(text, cursor);
+                    }
+                },
+                WindowEvent::Commit { text } => window.commit(text),
+                WindowEvent::ContextMenuRequested { position } => window.context_click(position),
+                event => window.emit(event),
+            }
+        }
+        if let Some(event) = activated {
+            menu_linux::emit(event);
+        }
+    }
+
+    /// Handles the list of files read from a drag offer.
+    fn receive_files(&self, offer: &WlDataOffer, list: Vec<u8>) {
+        {
+            let mut state = self.state.borrow_mut();
+            let State { drag, events, .. } = &mut *state;
+            let current = match drag.as_mut() {
+                Some(current) if current.offer == *offer && current.paths.is_none() => current,
+                _ => return,
+            };
+            current.paths = Some(parse_uri_list(&list));
+            if current.dropped {
+                finish_drop(drag, events);
+            } else {
+                hover(current, events);
+            }
+        }
+        self.dispatch(false);
+    }
+}
+
+fn wayland(window: &super::Window) -> &Window {
+    match &window.inner.backend {
+        Backend::Wayland(window) => window,
+        _ => unreachable!(),
+    }
+}
+
+/// The role of the surface of a window.
+enum Role {
+    Toplevel {
+        toplevel: XdgToplevel,
+        decoration: Option<ZxdgToplevelDecorationV1>,
+        /// The dialog object through which the window is made modal.
+        dialog: RefCell<Option<XdgDialogV1>>,
+    },
+    /// Popups and tooltips with a parent, which are placed relative to it,
+    /// and the surface of the parent.
+    Popup(XdgPopup, ObjectId),
+}
+
+pub struct Window {
+    connection: Rc<Connection>,
+    surface: WlSurface,
+    xdg_surface: XdgSurface,
+    role: Role,
+    /// The size of the last buffer attached to the surface.
+    size: Cell<(i32, i32)>,
+    /// The size requested by the compositor, applied on the next configure.
+    pending_size: Cell<(i32, i32)>,
+    /// The size that the window has when it is neither maximized nor
+    /// fullscreen.
+    windowed_size: Cell<(i32, i32)>,
+    /// Whether the surface was configured, after which buffers may be
+    /// attached.
+    configured: Cell<bool>,
+    /// Whether the window was minimized by us, which lasts until it is
+    /// activated since compositors don't report it.
+    minimized: Cell<bool>,
+    maximized: Cell<bool>,
+    fullscreen: Cell<bool>,
+    /// Blends the surface, created once an opacity is first set.
+    alpha: RefCell<Option<WpAlphaModifierSurfaceV1>>,
+}
+
+impl Window {
+    pub fn build(builder: &crate::WindowBuilder) -> Result<super::Window, ()> {
+        let connection = Connection::get()?;
+        let globals = &connection.globals;
+        let handle = &connection.handle;
+
+        // Surface coordinates are logical pixels.
+        let size = builder.inner_size(SCALE_FACTOR);
+        let size = (size.width.max(1.0) as i32, size.height.max(1.0) as i32);
+
+        let surface = globals.compositor.create_surface(handle, ());
+        let xdg_surface = globals.wm_base.get_xdg_surface(&surface, handle, surface.id());
+
+        let parent = builder.parent.as_ref().and_then(|parent| match &parent.sys.inner.backend {
+            Backend::Wayland(parent) => Some(parent),
+            Backend::X11(_) => None,
+        });
+        let role = match (builder.kind, parent) {
+            (WindowKind::Popup, Some(parent)) | (WindowKind::Tooltip, Some(parent)) => {
+                let positioner = globals.wm_base.create_positioner(handle, ());
+                positioner.set_size(size.0, size.1);
+                match builder.position {
+                    // Windows have no position on the desktop, so popups are
+                    // placed relative to the content area of their parent.
+                    Some(position) => {
+                        let position = position.to_logical_with(SCALE_FACTOR);
+                        positioner.set_anchor_rect(position.x as i32, position.y as i32, 1, 1);
+                        positioner.set_anchor(Anchor::TopLeft);
+                        positioner.set_gravity(Gravity::BottomRight);
+                    },
+                    None => {
+                        let (width, height) = parent.size.get();
+                        positioner.set_anchor_rect(0, 0, width.max(1), height.max(1));
+                    },
+                }
+                positioner.set_constraint_adjustment(
+                    ConstraintAdjustment::SlideX | ConstraintAdjustment::SlideY | ConstraintAdjustment::FlipY,
+                );
+                let popup = xdg_surface.get_popup(Some(&parent.xdg_surface), &positioner, handle, surface.id());
+                positioner.destroy();
+                Role::Popup(popup, parent.surface.id())
+            },
+            _ => {
+                let toplevel = xdg_surface.get_toplevel(handle, surface.id());
+                let decoration = globals.decorations.as_ref().map(|manager| {
+                    let decoration = manager.get_toplevel_decoration(&toplevel, handle, ());
+                    decoration.set_mode(zxdg_toplevel_decoration_v1::Mode::ServerSide);
+                    decoration
+                });
+                toplevel.set_title(builder.title_str().to_owned());
+                if let Some(app_id) = &builder.sys.app_id {
+                    toplevel.set_app_id(app_id.clone());
+                }
+                Role::Toplevel { toplevel, decoration, dialog: RefCell::new(None) }
+            },
+        };
+
+        let window = super::Window::new(Backend::Wayland(Window {
+            connection: connection.clone(),
+            surface: surface.clone(),
+            xdg_surface,
+            role,
+            size: Cell::new((0, 0)),
+            pending_size: Cell::new(size),
+            windowed_size: Cell::new(size),
+            configured: Cell::new(false),
+            minimized: Cell::new(false),
+            maximized: Cell::new(false),
+            fullscreen: Cell::new(false),
+            alpha: RefCell::new(None),
+        }));
+        connection.state.borrow_mut().windows.insert(surface.id(), window.clone());
+
+        // The first commit without a buffer asks for the initial configure.
+        surface.commit();
+        connection.conn.flush().map_err(drop)?;
+        Ok(window)
+    }
+
+    #[inline]
+    pub fn id(&self) -> ObjectId {
+        self.surface.id()
+    }
+
+    pub fn start_drag(
+        &self,
+        window: &super::Window,
+        formats: Vec<(String, Arc<[u8]>)>,
+        operations: DragOperations,
+        icon: &Icon,
+    ) -> Result<(), ()> {
+        let connection = &self.connection;
+        let globals = &connection.globals;
+        let handle = &connection.handle;
+        let mut state = connection.state.try_borrow_mut().map_err(drop)?;
+        let state = &mut *state;
+
+        let (manager, device) = match (&globals.data_devices, &state.data_device) {
+            (Some(manager), Some(device)) if state.source.is_none() => (manager, device),
+            _ => return Err(()),
+        };
+        // Drags are started from the implicit grab of a button held over
+        // the window.
+        let serial = match &state.grab {
+            Some((serial, Some(surface))) if *surface == self.surface.id() => *serial,
+            _ => return Err(()),
+        };
+        // Wayland has no link action.
+        let mut actions = DndAction::empty();
+        if operations.contains(DragOperation::Copy) {
+            actions |= DndAction::Copy;
+        }
+        if operations.contains(DragOperation::Move) {
+            actions |= DndAction::Move;
+        }
+        if actions.is_empty() {
+            return Err(());
+        }
+
+        let source = manager.create_data_source(handle, ());
+        for (ty, _) in &formats {
+            source.offer(ty.clone());
+        }
+        source.set_actions(actions);
+
+        let icon_surface = globals.compositor.create_surface(handle, ());
+        let mut pixels = icon.argb_premultiplied();
+        if cfg!(target_endian = "big") {
+            pixels.chunks_exact_mut(4).for_each(|pixel| pixel.reverse());
+        }
+        let (width, height) = (icon.width() as i32, icon.height() as i32);
+        let icon_surface = create_buffer(globals, handle, (width, height), Format::Argb8888, &pixels).map(|buffer| {
+            // The pointer is at the origin of the icon, which is centered.
+            let (x, y) = (-width / 2, -height / 2);
+            if icon_surface.version() >= 5 {
+                icon_surface.attach(Some(&buffer), 0, 0);
+                icon_surface.offset(x, y);
+            } else {
+                icon_surface.attach(Some(&buffer), x, y);
+            }
+            icon_surface.damage_buffer(0, 0, width, height);
+            icon_surface.commit();
+            icon_surface
+        });
+
+        device.start_drag(Some(&source), &self.surface, icon_surface.as_ref(), serial);
+        state.source = Some(Source {
+            source,
+            window: window.clone(),
+            formats,
+            icon: icon_surface,
+            operation: None,
+        });
+        connection.conn.flush().map_err(drop)
+    }
+
+    #[inline]
+    fn toplevel(&self) -> Option<&XdgToplevel> {
+        match &self.role {
+            Role::Toplevel { toplevel, .. } => Some(toplevel),
+            Role::Popup(..) => None,
+        }
+    }
+
+    pub fn set_title(&self, title: &str) {
+        if let Some(toplevel) = self.toplevel() {
+            toplevel.set_title(title.to_owned());
+            let _ = self.connection.conn.flush();
+        }
+    }
+
+    /// Attaches the window to `parent`, and asks the compositor to make it
+    /// modal if `modal` and the compositor supports dialogs.
+    pub fn set_parent(&self, parent: &Window, modal: bool) {
+        let (toplevel, dialog) = match &self.role {
+            Role::Toplevel { toplevel, dialog, .. } => (toplevel, dialog),
+            Role::Popup(..) => return,
+        };
+        toplevel.set_parent(parent.toplevel());
+        if modal {
+            if let Some(manager) = &self.connection.globals.dialogs {
+                let mut dialog = dialog.borrow_mut();
+                dialog.get_or_insert_with(|| manager.get_xdg_dialog(toplevel, &self.connection.handle, ()))
+                    .set_modal();
+            }
+        }
+        let _ = self.connection.conn.flush();
+    }
+
+    /// Returns zero, since compositors don't report the size of the
+    /// decorations that they draw.
+    #[inline]
+    pub fn titlebar_height(&self) -> f64 {
+        0.0
+    }
+
+    #[inline]
+    pub fn scale_factor(&self) -> ScaleFactor {
+        SCALE_FACTOR
+    }
+
+    pub fn inner_size(&self) -> LogicalSize {
+        let (width, height) = match self.configured.get() {
+            true => self.size.get(),
+            false => self.pending_size.get(),
+        };
+        LogicalSize::new(width as f64, height as f64)
+    }
+
+    /// Resizes the window, unless it is maximized or fullscreen, in which
+    /// case it takes the new size once restored.
+    pub fn set_inner_size(&self, size: LogicalSize) {
+        let size = (size.width.max(1.0) as i32, size.height.max(1.0) as i32);
+        self.windowed_size.set(size);
+        if self.maximized.get() || self.fullscreen.get() {
+            return;
+        }
+        self.pending_size.set(size);
+        if self.configured.get() {
+            self.configure(&self.connection.globals, &self.connection.handle);
+        }
+        let _ = self.connection.conn.flush();
+    }
+
+    #[inline]
+    pub fn windowed_size(&self) -> LogicalSize {
+        let (width, height) = self.windowed_size.get();
+        LogicalSize::new(width as f64, height as f64)
+    }
+
+    #[inline]
+    pub fn is_minimized(&self) -> bool {
+        self.minimized.get()
+    }
+
+    /// Minimizes the window, or activates it to restore it, since there is
+    /// no request to restore windows.
+    pub fn set_minimized(&self, window: &super::Window, minimized: bool) {
+        if !minimized {
+            self.focus();
+            return;
+        }
+        if let Some(toplevel) = self.toplevel() {
+            toplevel.set_minimized();
+            let _ = self.connection.conn.flush();
+            if !self.minimized.replace(true) {
+                window.emit(WindowEvent::Minimized(true));
+            }
+        }
+    }
+
+    /// Asks the compositor to activate the window, with the serial of the
+    /// last input event as proof that the user is interacting with us.
+    pub fn focus(&self) {
+        let serial = self.connection.state.try_borrow().ok()
+            .and_then(|state| Some((state.serial, state.seat.clone()?)));
+        self.activate(serial);
+    }
+
+    /// Asks the compositor to activate the window without proof of user
+    /// interaction, which compositors treat as a request for attention.
+    pub fn request_user_attention(&self, _: UserAttentionType) {
+        self.activate(None);
+    }
+
+    /// Asks the compositor to blend the window with `opacity`, which needs
+    /// the alpha modifier protocol.
+    pub fn set_opacity(&self, opacity: f64) {
+        let connection = &self.connection;
+        let manager = match &connection.globals.alpha_modifier {
+            Some(manager) => manager,
+            None => return,
+        };
+        let mut alpha = self.alpha.borrow_mut();
+        let alpha = alpha.get_or_insert_with(|| manager.get_surface(&self.surface, &connection.handle, ()));
+        alpha.set_multiplier((opacity * u32::MAX as f64).round() as u32);
+        self.commit();
+    }
+
+    /// Restricts pointer input to `region`, or lets the whole surface
+    /// receive it again if there is none.
+    pub fn set_input_region(&self, region: Option<&[LogicalRect]>) {
+        let connection = &self.connection;
+        match region {
+            // Surface coordinates are logical pixels.
+            Some(region) => {
+                let wl_region = connection.globals.compositor.create_region(&connection.handle, ());
+                for rect in region {
+                    let (x, y) = (rect.origin.x.floor(), rect.origin.y.floor());
+                    let right = (rect.origin.x + rect.size.width).ceil();
+                    let bottom = (rect.origin.y + rect.size.height).ceil();
+                    wl_region.add(x as i32, y as i32, (right - x) as i32, (bottom - y) as i32);
+                }
+                self.surface.set_input_region(Some(&wl_region));
+                wl_region.destroy();
+            },
+            None => self.surface.set_input_region(None),
+        }
+        self.commit();
+    }
+
+    /// Applies pending surface state, which is otherwise left for the first
+    /// configure.
+    fn commit(&self) {
+        if self.configured.get() {
+            self.surface.commit();
+        }
+        let _ = self.connection.conn.flush();
+    }
+
+    fn activate(&self, serial: Option<(u32, WlSeat)>) {
+        let activation = match &self.connection.globals.activation {
+            Some(activation) => activation,
+            None => return,
+        };
+        let token = activation.get_activation_token(&self.connection.handle, self.surface.clone());
+        if let Some((serial, seat)) = serial {
+            token.set_serial(serial, &seat);
+        }
+        token.set_surface(&self.surface);
+        token.commit();
+        let _ = self.connection.conn.flush();
+    }
+
+    #[inline]
+    pub fn is_maximized(&self) -> bool {
+        self.maximized.get()
+    }
+
+    pub fn set_maximized(&self, maximized: bool) {
+        let toplevel = match self.toplevel() {
+            Some(toplevel) => toplevel,
+            None => return,
+        };
+        if maximized {
+            toplevel.set_maximized();
+        } else {
+            toplevel.unset_maximized();
+        }
+        let _ = self.connection.conn.flush();
+    }
+
+    #[inline]
+    pub fn is_fullscreen(&self) -> bool {
+        self.fullscreen.get()
+    }
+
+    /// Makes the window fullscreen on the output chosen by the compositor.
+    pub fn set_fullscreen(&self, fullscreen: bool) {
+        let toplevel = match self.toplevel() {
+            Some(toplevel) => toplevel,
+            None => return,
+        };
+        if fullscreen {
+            toplevel.set_fullscreen(None);
+        } else {
+            toplevel.unset_fullscreen();
+        }
+        let _ = self.connection.conn.flush();
+    }
+
+    /// Enables or disables the input method if it is focused on the window.
+    pub fn update_ime(&self, window: &super::Window) {
+        let state = match self.connection.state.try_borrow() {
+            Ok(state) => state,
+            Err(_) => return,
+        };
+        if let Some(text_input) = state.text_input.as_ref().filter(|t| t.focus == Some(self.surface.id())) {
+            if window.ime_allowed() {
+                enable_text_input(&text_input.text_input, window);
+            } else {
+                text_input.text_input.disable();
+                text_input.text_input.commit();
+            }
+        }
+        let _ = self.connection.conn.flush();
+    }
+
+    pub fn update_ime_cursor_area(&self, window: &super::Window) {
+        let state = match self.connection.state.try_borrow() {
+            Ok(state) => state,
+            Err(_) => return,
+        };
+        if let Some(text_input) = state.text_input.as_ref().filter(|t| t.focus == Some(self.surface.id())) {
+            if window.ime_allowed() {
+                set_cursor_rectangle(&text_input.text_input, window);
+                text_input.text_input.commit();
+            }
+        }
+        let _ = self.connection.conn.flush();
+    }
+
+    /// Opens a context menu at `position` within the window, replacing any
+    /// open one.
+    pub fn show_context_menu(&self, window: &super::Window, model: &popup::Model, position: LogicalPosition) {
+        let connection = &self.connection;
+        let font = match popup::Font::get() {
+            Some(font) => font,
+            None => return,
+        };
+        {
+            let mut state = match connection.state.try_borrow_mut() {
+                Ok(state) => state,
+                Err(_) => return,
+            };
+            close_menu(&mut state, None);
+            if state.seat.is_none() {
+                return;
+            }
+            // Surface coordinates are logical pixels, and the anchor must be
+            // within the window.
+            let origin = (position.x.max(0.0) as i32, position.y.max(0.0) as i32);
+            state.menu = Some(Menu {
+                window: window.clone(),
+                context: ContextMenu::new(model, font, 1.0, origin),
+                popups: Vec::new(),
+            });
+            sync_menu(&mut state, &connection.handle);
+        }
+        let _ = connection.conn.flush();
+    }
+
+    /// Applies the configured size, attaching a buffer to map the surface.
+    fn configure(&self, globals: &Globals, handle: &QueueHandle<State>) {
+        let size = self.pending_size.get();
+        if size != self.size.get() {
+            let white = vec![0xff; size.0.max(0) as usize * size.1.max(0) as usize * 4];
+            if let Some(buffer) = create_buffer(globals, handle, size, Format::Xrgb8888, &white) {
+                self.surface.attach(Some(&buffer), 0, 0);
+                self.surface.damage_buffer(0, 0, size.0, size.1);
+                self.size.set(size);
+            }
+        }
+        self.surface.commit();
+        self.configured.set(true);
+    }
+
+    fn destroy(&self) {
+        match &self.role {
+            Role::Toplevel { toplevel, decoration, dialog } => {
+                if let Some(decoration) = decoration {
+                    decoration.destroy();
+                }
+                if let Some(dialog) = dialog.borrow_mut().take() {
+                    dialog.destroy();
+                }
+                toplevel.destroy();
+            },
+            Role::Popup(popup, _) => popup.destroy(),
+        }
+        if let Some(alpha) = self.alpha.borrow_mut().take() {
+            alpha.destroy();
+        }
+        self.xdg_surface.destroy();
+        self.surface.destroy();
+    }
+}
+
+/// Creates a buffer of `size` holding `pixels` in `format`, which uses four
+/// bytes per pixel.
+fn create_buffer(
+    globals: &Globals,
+    handle: &QueueHandle<State>,
+    (width, height): (i32, i32),
+    format: Format,
+    pixels: &[u8],
+) -> Option<WlBuffer> {
+    let stride = width.checked_mul(4)?;
+    let len = stride.checked_mul(height)?;
+    if len <= 0 || pixels.len() != len as usize {
+        return None;
+    }
+
+    let fd = unsafe { libc::memfd_create(b"zui-buffer\0".as_ptr().cast(), libc::MFD_CLOEXEC) };
+    if fd < 0 {
+        return None;
+    }
+    let mut file = File::from(unsafe { OwnedFd::from_raw_fd(fd) });
+    file.write_all(pixels).ok()?;
+
+    let pool = globals.shm.create_pool(file.as_fd(), len, handle, ());
+    let buffer = pool.create_buffer(0, width, height, stride, format, handle, ());
+    pool.destroy();
+    Some(buffer)
+}
+
+/// Creates and destroys popups to match the open menu, and draws those that
+/// changed once configured.
+fn sync_menu(state: &mut State, handle: &QueueHandle<State>) {
+    let State { globals, seat, serial, menu, .. } = state;
+    let menu = match menu.as_mut() {
+        Some(menu) => menu,
+        None => return,
+    };
+    while menu.popups.len() > menu.context.popups.len() {
+        if let Some(popup) = menu.popups.pop() {
+            popup.destroy();
+        }
+    }
+
+    let opened = menu.popups.len();
+    for (level, popup) in menu.context.popups.iter().enumerate().skip(opened) {
+        let surface = globals.compositor.create_surface(handle, ());
+        let xdg_surface = globals.wm_base.get_xdg_surface(&surface, handle, surface.id());
+
+        // Popups are moved by the compositor to stay on screen.
+        let positioner = globals.wm_base.create_positioner(handle, ());
+        positioner.set_size(popup.width as i32, popup.height as i32);
+        positioner.set_anchor_rect(popup.origin.0, popup.origin.1, 1, 1);
+        positioner.set_anchor(Anchor::TopLeft);
+        positioner.set_gravity(Gravity::BottomRight);
+        positioner.set_constraint_adjustment(
+            ConstraintAdjustment::SlideX
+                | ConstraintAdjustment::SlideY
+                | ConstraintAdjustment::FlipX
+                | ConstraintAdjustment::FlipY,
+        );
+        let parent = match level.checked_sub(1) {
+            Some(parent) => &menu.popups[parent].xdg_surface,
+            None => &wayland(&menu.window).xdg_surface,
+        };
+        let xdg_popup = xdg_surface.get_popup(Some(parent), &positioner, handle, surface.id());
+        positioner.destroy();
+        if let Some(seat) = seat {
+            xdg_popup.grab(seat, *serial);
+        }
+        surface.commit();
+        menu.popups.push(MenuPopup { surface, xdg_surface, popup: xdg_popup, configured: false });
+    }
+
+    let font = menu.context.font().clone();
+    for (surface, popup) in menu.popups.iter().zip(&mut menu.context.popups) {
+        if !surface.configured || !popup.dirty {
+            continue;
+        }
+        popup.dirty = false;
+        let canvas = popup.draw(&font);
+        let mut pixels = canvas.to_bytes();
+        if cfg!(target_endian = "big") {
+            pixels.chunks_exact_mut(4).for_each(|pixel| pixel.reverse());
+        }
+        let size = (canvas.width as i32, canvas.height as i32);
+        if let Some(buffer) = create_buffer(globals, handle, size, Format::Xrgb8888, &pixels) {
+            surface.surface.attach(Some(&buffer), 0, 0);
+            surface.surface.damage_buffer(0, 0, size.0, size.1);
+            surface.surface.commit();
+        }
+    }
+}
+
+/// Closes the open menu, passing `event` to the menu event handler once
+/// dispatching is over if an item was activated.
+fn close_menu(state: &mut State, event: Option<MenuEvent>) {
+    if let Some(menu) = state.menu.take() {
+        // Child popups must be destroyed before their parents.
+        for popup in menu.popups.into_iter().rev() {
+            popup.destroy();
+        }
+        state.activated = event;
+    }
+}
+
+/// Passes a pointer event to the open menu, if any.
+fn menu_pointer(
+    state: &mut State,
+    handle: &QueueHandle<State>,
+    handle_event: impl FnOnce(&mut ContextMenu, Option<usize>, f64, f64) -> Outcome,
+) {
+    let menu = match state.menu.as_mut() {
+        Some(menu) => menu,
+        None => return,
+    };
+    let level = state.pointer_focus.as_ref()
+        .and_then(|focus| menu.popups.iter().position(|popup| popup.surface.id() == *focus));
+    let (x, y) = state.pointer_position;
+    match handle_event(&mut menu.context, level, x, y) {
+        Outcome::Open => sync_menu(state, handle),
+        Outcome::Closed(event) => close_menu(state, event),
+    }
+}
+
+impl MenuPopup {
+    fn destroy(self) {
+        self.popup.destroy();
+        self.xdg_surface.destroy();
+        self.surface.destroy();
+    }
+}
+
+fn enable_text_input(text_input: &ZwpTextInputV3, window: &super::Window) {
+    text_input.enable();
+    text_input.set_content_type(ContentHint::None, ContentPurpose::Normal);
+    set_cursor_rectangle(text_input, window);
+    text_input.commit();
+}
+
+/// Tells the input method where the cursor area is, in surface coordinates,
+/// which are logical pixels.
+fn set_cursor_rectangle(text_input: &ZwpTextInputV3, window: &super::Window) {
+    let (position, size) = window.ime_cursor_area();
+    text_input.set_cursor_rectangle(position.x as i32, position.y as i32, size.width as i32, size.height as i32);
+}
+
+/// Compiles the keymap sent by the compositor, which is mapped into memory
+/// rather than read.
+fn read_keymap(fd: OwnedFd, size: u32) -> Option<Keymap> {
+    let size = size as usize;
+    unsafe {
+        let data = libc::mmap(ptr::null_mut(), size, libc::PROT_READ, libc::MAP_PRIVATE, fd.as_raw_fd(), 0);
+        if data == libc::MAP_FAILED {
+            return None;
+        }
+        let keymap = Keymap::from_string(slice::from_raw_parts(data as *const u8, size));
+        libc::munmap(data, size);
+        keymap
+    }
+}
+
+/// Queues a `HoveredFile` event for each file of `drag`.
+fn hover(drag: &mut Drag, events: &mut Vec<(super::Window, WindowEvent)>) {
+    let paths = drag.paths.as_ref().map_or(&[][..], |p| &p[..]);
+    drag.hovered |= !paths.is_empty();
+    for path in paths {
+        let event = WindowEvent::HoveredFile { path: path.clone(), position: drag.position };
+        events.push((drag.window.clone(), event));
+    }
+}
+
+/// Ends the dropped `drag`, queueing a `DroppedFile` event for each file.
+fn finish_drop(drag: &mut Option<Drag>, events: &mut Vec<(super::Window, WindowEvent)>) {
+    let drag = match drag.take() {
+        Some(drag) => drag,
+        None => return,
+    };
+    drag.offer.finish();
+    drag.offer.destroy();
+    for path in drag.paths.unwrap_or_default() {
+        let event = WindowEvent::DroppedFile { path, position: drag.position };
+        events.push((drag.window.clone(), event));
+    }
+}
+
+/// Reads the list of files offered by `offer` without blocking the thread.
+fn request_files(conn: &WlConnection, offer: &WlDataOffer) {
+    let mut fds = [0; 2];
+    if unsafe { libc::pipe2(fds.as_mut_ptr(), libc::O_CLOEXEC | libc::O_NONBLOCK) } != 0 {
+        return;
+    }
+    let (reader, writer) = unsafe { (OwnedFd::from_raw_fd(fds[0]), OwnedFd::from_raw_fd(fds[1])) };
+    offer.receive(URI_LIST.to_owned(), writer.as_fd());
+    let _ = conn.flush();
+    drop(writer);
+
+    let mut reader = File::from(reader);
+    let mut list = Vec::new();
+    let offer = offer.clone();
+    let source = Rc::new(Cell::new(None));
+    let id = main_loop::watch_fd(reader.as_raw_fd(), {
+        let source = source.clone();
+        move || {
+            let mut buffer = [0; 4096];
+            loop {
+                match reader.read(&mut buffer) {
+                    Ok(0) => break,
+                    Ok(n) => list.extend_from_slice(&buffer[..n]),
+                    Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {},
+                    Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => return,
+                    Err(_) => break,
+                }
+            }
+            if let Some(id) = source.take() {
+                main_loop::remove_source(id);
+            }
+            if let Ok(connection) = Connection::get() {
+                connection.receive_files(&offer, mem::take(&mut list));
+            }
+        }
+    });
+    source.set(Some(id));
+}
+
+delegate_noop!(State: WlCompositor);
+delegate_noop!(State: WlShm);
+delegate_noop!(State: WlShmPool);
+delegate_noop!(State: WlDataDeviceManager);
+delegate_noop!(State: ZxdgDecorationManagerV1);
+delegate_noop!(State: XdgWmDialogV1);
+delegate_noop!(State: XdgDialogV1);
+delegate_noop!(State: WlRegion);
+delegate_noop!(State: XdgActivationV1);
+delegate_noop!(State: WpAlphaModifierV1);
+delegate_noop!(State: WpAlphaModifierSurfaceV1);
+
+impl Dispatch<XdgActivationTokenV1, WlSurface> for State {
+    fn event(
+        state: &mut Self,
+        token: &XdgActivationTokenV1,
+        event: xdg_activation_token_v1::Event,
+        surface: &WlSurface,
+        _: &WlConnection,
+        _: &QueueHandle<Self>,
+    ) {
+        if let xdg_activation_token_v1::Event::Done { token: name } = event {
+            if let Some(activation) = &state.globals.activation {
+                activation.activate(name, surface);
+            }
+            token.destroy();
+        }
+    }
+}
+delegate_noop!(State: ZwpTextInputManagerV3);
+delegate_noop!(State: XdgPositioner);
+delegate_noop!(State: ignore WlSurface);
+delegate_noop!(State: ignore ZxdgToplevelDecorationV1);
+
+impl Dispatch<WlRegistry, GlobalListContents> for State {
+    fn event(
+        _: &mut Self,
+        _: &WlRegistry,
+        _: <WlRegistry as Proxy>::Event,
+        _: &GlobalListContents,
+        _: &WlConnection,
+        _: &QueueHandle<Self>,
+    ) {}
+}
+
+impl Dispatch<WlBuffer, ()> for State {
+    fn event(
+        _: &mut Self,
+        buffer: &WlBuffer,
+        event: wl_buffer::Event,
+        _: &(),
+        _: &WlConnection,
+        _: &QueueHandle<Self>,
+    ) {
+        // Each buffer is attached once and replaced on resize.
+        if let wl_buffer::Event::Release = event {
+            buffer.destroy();
+        }
+    }
+}
+
+impl Dispatch<XdgWmBase, ()> for State {
+    fn event(
+        _: &mut Self,
+        wm_base: &XdgWmBase,
+        event: xdg_wm_base::Event,
+        _: &(),
+        _: &WlConnection,
+        _: &QueueHandle<Self>,
+    ) {
+        if let xdg_wm_base::Event::Ping { serial } = event {
+            wm_base.pong(serial);
+        }
+    }
+}
+
+impl Dispatch<XdgSurface, ObjectId> for State {
+    fn event(
+        state: &mut Self,
+        xdg_surface: &XdgSurface,
+        event: xdg_surface::Event,
+        surface: &ObjectId,
+        _: &WlConnection,
+        handle: &QueueHandle<Self>,
+    ) {
+        if let xdg_surface::Event::Configure { serial } = event {
+            xdg_surface.ack_configure(serial);
+            if let Some(window) = state.windows.get(surface) {
+                wayland(window).configure(&state.globals, handle);
+            } else if let Some(menu) = state.menu.as_mut() {
+                let popups = menu.popups.iter_mut().zip(&mut menu.context.popups);
+                for (popup, context) in popups.filter(|(popup, _)| popup.surface.id() == *surface) {
+                    popup.configured = true;
+                    context.dirty = true;
+                }
+                sync_menu(state, handle);
+            }
+        }
+    }
+}
+
+impl Dispatch<XdgToplevel, ObjectId> for State {
+    fn event(
+        state: &mut Self,
+        _: &XdgToplevel,
+        event: xdg_toplevel::Event,
+        surface: &ObjectId,
+        _: &WlConnection,
+        _: &QueueHandle<Self>,
+    ) {
+        match event {
+            xdg_toplevel::Event::Configure { width, height, states } => {
+                if let Some(window) = state.windows.get(surface) {
+                    let states: Vec<_> = states.chunks_exact(4)
+                        .filter_map(|state| <[u8; 4]>::try_from(state).ok())
+                        .filter_map(|state| xdg_toplevel::State::try_from(u32::from_ne_bytes(state)).ok())
+                        .collect();
+                    let toplevel = wayland(window);
+                    let maximized = states.contains(&xdg_toplevel::State::Maximized);
+                    let fullscreen = states.contains(&xdg_toplevel::State::Fullscreen);
+                    if toplevel.maximized.replace(maximized) != maximized {
+                        state.events.push((window.clone(), WindowEvent::Maximized(maximized)));
+                    }
+                    if toplevel.fullscreen.replace(fullscreen) != fullscreen {
+                        state.events.push((window.clone(), WindowEvent::Fullscreen(fullscreen)));
+                    }
+                    if states.contains(&xdg_toplevel::State::Activated) && toplevel.minimized.replace(false) {
+                        state.events.push((window.clone(), WindowEvent::Minimized(false)));
+                    }
+                    let windowed = !maximized && !fullscreen;
+
+                    // A zero size leaves the choice to us, which is the size
+                    // the window had before it was maximized or fullscreen.
+                    let (old_width, old_height) = match windowed {
+                        true => toplevel.windowed_size.get(),
+                        false => toplevel.pending_size.get(),
+                    };
+                    let width = if width > 0 { width } else { old_width };
+                    let height = if height > 0 { height } else { old_height };
+                    toplevel.pending_size.set((width, height));
+                    if windowed {
+                        toplevel.windowed_size.set((width, height));
+                    }
+                    window.schedule_autosave();
+                }
+            },
+            xdg_toplevel::Event::Close => close_window(state, surface),
+            _ => {},
+        }
+    }
+}
+
+impl Dispatch<XdgPopup, ObjectId> for State {
+    fn event(
+        state: &mut Self,
+        _: &XdgPopup,
+        event: xdg_popup::Event,
+        surface: &ObjectId,
+        _: &WlConnection,
+        _: &QueueHandle<Self>,
+    ) {
+        // Clicking outside of the popups of the client dismisses them.
+        if let xdg_popup::Event::PopupDone = event {
+            if state.windows.contains_key(surface) {
+                close_window(state, surface);
+            } else {
+                close_menu(state, None);
+            }
+        }
+    }
+}
+
+/// Closes the window of `surface`, after the popups opened on it, which must
+/// be destroyed before their parent.
+fn close_window(state: &mut State, surface: &ObjectId) {
+    if state.menu.as_ref().is_some_and(|menu| wayland(&menu.window).surface.id() == *surface) {
+        close_menu(state, None);
+    }
+    let children: Vec<_> = state.windows.iter()
+        .filter(|(_, window)| matches!(&wayland(window).role, Role::Popup(_, parent) if parent == surface))
+        .map(|(id, _)| id.clone())
+        .collect();
+    for child in &children {
+        close_window(state, child);
+    }
+    if let Some(window) = state.windows.remove(surface) {
+        window.autosave_on_close();
+        window.closed();
+        wayland(&window).destroy();
+    }
+}
+
+impl Dispatch<WlSeat, ()> for State {
+    fn event(
+        state: &mut Self,
+        seat: &WlSeat,
+        event: wl_seat::Event,
+        _: &(),
+        _: &WlConnection,
+        handle: &QueueHandle<Self>,
+    ) {
+        if let wl_seat::Event::Capabilities { capabilities: WEnum::Value(capabilities) } = event {
+            let has_pointer = capabilities.contains(Capability::Pointer);
+            if has_pointer && state.pointer.is_none() {
+                state.pointer = Some(seat.get_pointer(handle, ()));
+            } else if !has_pointer {
+                if let Some(pointer) = state.pointer.take() {
+                    if pointer.version() >= 3 {
+                        pointer.release();
+                    }
+                }
+                state.pointer_focus = None;
+                state.buttons.clear();
+                state.grab = None;
+            }
+
+            let has_keyboard = capabilities.contains(Capability::Keyboard);
+            if has_keyboard && state.keyboard.is_none() {
+                state.keyboard = Some(seat.get_keyboard(handle, ()));
+            } else if !has_keyboard {
+                if let Some(keyboard) = state.keyboard.take() {
+                    if keyboard.version() >= 3 {
+                        keyboard.release();
+                    }
+                }
+                state.keyboard_focus = None;
+            }
+        }
+    }
+}
+
+impl Dispatch<WlKeyboard, ()> for State {
+    fn event(
+        state: &mut Self,
+        _: &WlKeyboard,
+        event: wl_keyboard::Event,
+        _: &(),
+        _: &WlConnection,
+        _: &QueueHandle<Self>,
+    ) {
+        match event {
+            wl_keyboard::Event::Keymap { format: WEnum::Value(KeymapFormat::XkbV1), fd, size } => {
+                state.keymap = read_keymap(fd, size);
+            },
+            wl_keyboard::Event::Enter { surface, .. } => {
+                if let Some(window) = state.windows.get(&surface.id()) {
+                    state.events.push((window.clone(), WindowEvent::Focused(true)));
+                }
+                state.keyboard_focus = Some(surface.id());
+            },
+            wl_keyboard::Event::Leave { .. } => {
+                if let Some(window) = state.keyboard_focus.take().and_then(|id| state.windows.get(&id)) {
+                    state.events.push((window.clone(), WindowEvent::Focused(false)));
+                }
+            },
+            wl_keyboard::Event::Modifiers { mods_depressed, mods_latched, mods_locked, group, .. } => {
+                if let Some(keymap) = &mut state.keymap {
+                    keymap.update_mask(mods_depressed, mods_latched, mods_locked, group);
+                }
+            },
+            // Keys handled by an input method are not sent to the window, so
+            // the text of the others is typed directly. Evdev keycodes are
+            // offset by 8 in XKB.
+            wl_keyboard::Event::Key { serial, key, state: WEnum::Value(KeyState::Pressed), .. } => {
+                state.serial = serial;
+                if state.menu.is_some() {
+                    if state.keymap.as_ref().is_some_and(|keymap| keymap.key_sym(key + 8) == XKB_KEY_ESCAPE) {
+                        close_menu(state, None);
+                    }
+                    return;
+                }
+                let window = state.keyboard_focus.as_ref().and_then(|id| state.windows.get(id));
+                let text = state.keymap.as_ref().and_then(|keymap| keymap.key_text(key + 8));
+                if let (Some(window), Some(text)) = (window, text) {
+                    state.events.push((window.clone(), WindowEvent::Commit { text }));
+                }
+            },
+            _ => {},
+        }
+    }
+}
+
+impl Dispatch<ZwpTextInputV3, ()> for State {
+    fn event(
+        state: &mut Self,
+        _: &ZwpTextInputV3,
+        event: zwp_text_input_v3::Event,
+        _: &(),
+        _: &WlConnection,
+        _: &QueueHandle<Self>,
+    ) {
+        let State { windows, events, text_input, .. } = state;
+        let text_input = match text_input {
+            Some(text_input) => text_input,
+            None => return,
+        };
+        match event {
+            zwp_text_input_v3::Event::Enter { surface } => {
+                text_input.focus = Some(surface.id());
+                if let Some(window) = windows.get(&surface.id()).filter(|w| w.ime_allowed()) {
+                    enable_text_input(&text_input.text_input, window);
+                }
+            },
+            zwp_text_input_v3::Event::Leave { surface } => {
+                text_input.focus = None;
+                text_input.preedit = None;
+                text_input.commit = None;
+                text_input.text_input.disable();
+                text_input.text_input.commit();
+                if let Some(window) = windows.get(&surface.id()) {
+                    let event = WindowEvent::Preedit { text: String::new(), cursor: None };
+                    events.push((window.clone(), event));
+                }
+            },
+            zwp_text_input_v3::Event::PreeditString { text, cursor_begin, cursor_end } => {
+                text_input.preedit = Some((text.unwrap_or_default(), cursor_begin, cursor_end));
+            },
+            zwp_text_input_v3::Event::CommitString { text } => {
+                text_input.commit = text;
+            },
+            // The text being composed is replaced first, then the committed
+            // text is inserted before the new text being composed.
+            zwp_text_input_v3::Event::Done { .. } => {
+                let window = match text_input.focus.as_ref().and_then(|id| windows.get(id)) {
+                    Some(window) => window,
+                    None => return,
+                };
+                if let Some(text) = text_input.commit.take() {
+                    let event = WindowEvent::Preedit { text: String::new(), cursor: None };
+                    events.push((window.clone(), event));
+                    events.push((window.clone(), WindowEvent::Commit { text }));
+                }
+                let (text, begin, end) = text_input.preedit.take().unwrap_or_default();
+                let cursor = ime::byte_cursor(&text, begin, end).filter(|_| !text.is_empty());
+                events.push((window.clone(), WindowEvent::Preedit { text, cursor }));
+            },
+            _ => {},
+        }
+    }
+}
+
+impl Dispatch<WlPointer, ()> for State {
+    fn event(
+        state: &mut Self,
+        _: &WlPointer,
+        event: wl_pointer::Event,
+        _: &(),
+        _: &WlConnection,
+        handle: &QueueHandle<Self>,
+    ) {
+        match event {
+            wl_pointer::Event::Enter { surface, surface_x, surface_y, .. } => {
+                state.pointer_focus = Some(surface.id());
+                state.pointer_position = (surface_x, surface_y);
+                menu_pointer(state, handle, |menu, level, x, y| {
+                    menu.motion(level, x, y);
+                    Outcome::Open
+                });
+            },
+            wl_pointer::Event::Motion { surface_x, surface_y, .. } => {
+                state.pointer_position = (surface_x, surface_y);
+                menu_pointer(state, handle, |menu, level, x, y| {
+                    menu.motion(level, x, y);
+                    Outcome::Open
+                });
+            },
+            // Buttons released while the pointer is elsewhere, such as during
+            // a drag, are not reported.
+            wl_pointer::Event::Leave { .. } => {
+                state.pointer_focus = None;
+                state.buttons.clear();
+                state.grab = None;
+                menu_pointer(state, handle, |menu, _, x, y| {
+                    menu.motion(None, x, y);
+                    Outcome::Open
+                });
+            },
+            wl_pointer::Event::Button { serial, button, state: WEnum::Value(button_state), .. } => {
+                match button_state {
+                    ButtonState::Pressed => {
+                        state.serial = serial;
+                        if state.buttons.is_empty() {
+                            state.grab = Some((serial, state.pointer_focus.clone()));
+                        }
+                        state.buttons.push(button);
+                        if state.menu.is_some() {
+                            menu_pointer(state, handle, ContextMenu::press);
+                        } else if button == BTN_RIGHT {
+                            // The context menu, if any, is opened once
+                            // dispatching is over.
+                            let window = state.pointer_focus.as_ref().and_then(|id| state.windows.get(id));
+                            if let Some(window) = window {
+                                let (x, y) = state.pointer_position;
+                                let position = LogicalPosition::new(x, y);
+                                state.events.push((window.clone(), WindowEvent::ContextMenuRequested { position }));
+                            }
+                        }
+                    },
+                    _ => {
+                        state.buttons.retain(|&b| b != button);
+                        if state.buttons.is_empty() {
+                            state.grab = None;
+                        }
+                        menu_pointer(state, handle, ContextMenu::release);
+                    },
+                }
+            },
+            _ => {},
+        }
+    }
+}
+
+impl Dispatch<WlDataSource, ()> for State {
+    fn event(
+        state: &mut Self,
+        data_source: &WlDataSource,
+        event: wl_data_source::Event,
+        _: &(),
+        _: &WlConnection,
+        _: &QueueHandle<Self>,
+    ) {
+        let source = match state.source.as_mut() {
+            Some(source) if source.source == *data_source => source,
+            _ => {
+                data_source.destroy();
+                return;
+            },
+        };
+        let operation = match event {
+            wl_data_source::Event::Send { mime_type, fd } => {
+                if let Some((_, data)) = source.formats.iter().find(|(ty, _)| *ty == mime_type) {
+                    // The destination may be slow to read, so the data is
+                    // written without blocking the main loop.
+                    let data = data.clone();
+                    thread::spawn(move || File::from(fd).write_all(&data));
+                }
+                return;
+            },
+            wl_data_source::Event::Action { dnd_action: WEnum::Value(action) } => {
+                source.operation = if action.contains(DndAction::Move) {
+                    Some(DragOperation::Move)
+                } else if action.contains(DndAction::Copy) {
+                    Some(DragOperation::Copy)
+                } else {
+                    None
+                };
+                return;
+            },
+            wl_data_source::Event::DndFinished => source.operation,
+            wl_data_source::Event::Cancelled => None,
+            _ => return,
+        };
+
+        let source = state.source.take().unwrap();
+        source.source.destroy();
+        if let Some(icon) = source.icon {
+            icon.destroy();
+        }
+        state.events.push((source.window, WindowEvent::DragEnded { operation }));
+    }
+}
+
+impl Dispatch<WlDataOffer, ()> for State {
+    fn event(
+        state: &mut Self,
+        offer: &WlDataOffer,
+        event: wl_data_offer::Event,
+        _: &(),
+        _: &WlConnection,
+        _: &QueueHandle<Self>,
+    ) {
+        if let wl_data_offer::Event::Offer { mime_type } = event {
+            state.offers.entry(offer.id()).or_default().push(mime_type);
+        }
+    }
+}
+
+impl Dispatch<WlDataDevice, ()> for State {
+    fn event(
+        state: &mut Self,
+        _: &WlDataDevice,
+        event: wl_data_device::Event,
+        _: &(),
+        conn: &WlConnection,
+        _: &QueueHandle<Self>,
+    ) {
+        match event {
+            wl_data_device::Event::DataOffer { id } => {
+                state.offers.insert(id.id(), Vec::new());
+            },
+            wl_data_device::Event::Enter { serial, surface, x, y, id } => {
+                let (offer, window) = match (id, state.windows.get(&surface.id())) {
+                    (Some(offer), Some(window)) => (offer, window.clone()),
+                    (offer, _) => {
+                        if let Some(offer) = offer {
+                            offer.accept(serial, None);
+                        }
+                        return;
+                    },
+                };
+                let accepts = state.offers.get(&offer.id())
+                    .is_some_and(|types| types.iter().any(|t| t == URI_LIST));
+                if accepts {
+                    offer.accept(serial, Some(URI_LIST.to_owned()));
+                    offer.set_actions(DndAction::Copy, DndAction::Copy);
+                    request_files(conn, &offer);
+                } else {
+                    offer.accept(serial, None);
+                    offer.set_actions(DndAction::empty(), DndAction::empty());
+                }
+                state.drag = Some(Drag {
+                    offer,
+                    window,
+                    accepts,
+                    position: LogicalPosition { x, y },
+                    paths: None,
+                    dropped: false,
+                    hovered: false,
+                });
+            },
+            wl_data_device::Event::Motion { x, y, .. } => {
+                let State { drag, events, .. } = state;
+                if let Some(drag) = drag.as_mut().filter(|d| !d.dropped) {
+                    drag.position = LogicalPosition { x, y };
+                    if drag.paths.is_some() {
+                        hover(drag, events);
+                    }
+                }
+            },
+            // A drop is followed by a leave, but its files may still be on
+            // their way.
+            wl_data_device::Event::Leave if state.drag.as_ref().is_some_and(|d| !d.dropped) => {
+                let drag = state.drag.take().unwrap();
+                state.offers.remove(&drag.offer.id());
+                drag.offer.destroy();
+                if drag.hovered {
+                    state.events.push((drag.window, WindowEvent::HoveredFileCancelled));
+                }
+            },
+            wl_data_device::Event::Drop => {
+                let State { drag, events, .. } = state;
+                match drag.as_mut() {
+                    Some(current) if current.accepts => {
+                        current.dropped = true;
+                        if current.paths.is_some() {
+                            finish_drop(drag, events);
+                        }
+                    },
+                    Some(_) => {
+                        let drag = drag.take().unwrap();
+                        drag.offer.destroy();
+                    },
+                    None => {},
+                }
+            },
+            wl_data_device::Event::Selection { id } => {
+                if let Some(old) = mem::replace(&mut state.selection, id) {
+                    state.offers.remove(&old.id());
+                    old.destroy();
+                }
+            },
+            _ => {},
+        }
+    }
+
+    event_created_child!(State, WlDataDevice, [
+        wl_data_device::EVT_DATA_OFFER_OPCODE => (WlDataOffer, ()),
+    ]);
+}
@@ -0,0 +1,1627 @@
+//! Windows on an X11 display.
+
+use std::{
+    cell::{Cell, RefCell},
+    collections::HashMap,
+    os::unix::io::AsRawFd,
+    rc::Rc,
+    sync::Arc,
+};
+use x11rb::{
+    connection::{Connection as _, RequestConnection as _},
+    protocol::{
+        randr::ConnectionExt as _,
+        shape::{self, ConnectionExt as _},
+        xproto::{
+            Atom,
+            AtomEnum,
+            ClientMessageEvent,
+            ColormapAlloc,
+            ConfigureWindowAux,
+            ConnectionExt as _,
+            CreateGCAux,
+            Drawable,
+            CreateWindowAux,
+            EventMask,
+            ExposeEvent,
+            GrabMode,
+            KeyPressEvent,
+            GrabStatus,
+            ImageFormat,
+            ImageOrder,
+            KeyButMask,
+            NotifyDetail,
+            PropMode,
+            Rectangle,
+            SelectionNotifyEvent,
+            SelectionRequestEvent,
+            Timestamp,
+            VisualClass,
+            Window as XWindow,
+            WindowClass,
+            SELECTION_NOTIFY_EVENT,
+        },
+        Event,
+    },
+    properties::WmHints,
+    resource_manager,
+    rust_connection::RustConnection,
+    wrapper::ConnectionExt as _,
+};
+use menu::{os::linux as menu_linux, MenuEvent};
+use shared::{
+    os::linux::{main_loop, IconExt},
+    Icon,
+};
+use crate::{
+    dpi::{LogicalPosition, LogicalRect, LogicalSize, ScaleFactor},
+    drag::DragOperations,
+    event::WindowEvent,
+    UserAttentionType,
+    WindowKind,
+};
+use super::{
+    keyboard::Keymap,
+    parse_uri_list,
+    popup::{self, ContextMenu, Outcome},
+    xdnd,
+    xim::{ImeEvent, Xim},
+    Backend,
+    Monitor,
+};
+
+/// The pointer button that opens context menus.
+const RIGHT_BUTTON: u8 = 3;
+
+const XK_ESCAPE: u32 = 0xff1b;
+
+/// The actions of `_NET_WM_STATE` messages.
+const NET_WM_STATE_REMOVE: u32 = 0;
+const NET_WM_STATE_ADD: u32 = 1;
+
+/// The source of `_NET_WM_STATE` and `_NET_ACTIVE_WINDOW` messages sent by
+/// applications.
+const SOURCE_APPLICATION: u32 = 1;
+
+/// The `WM_CHANGE_STATE` state that iconifies a window.
+const ICONIC_STATE: u32 = 3;
+
+struct Atoms {
+    wm_protocols: Atom,
+    wm_delete_window: Atom,
+    net_wm_name: Atom,
+    net_wm_state: Atom,
+    net_wm_state_maximized_vert: Atom,
+    net_wm_state_maximized_horz: Atom,
+    net_wm_state_fullscreen: Atom,
+    net_wm_state_modal: Atom,
+    net_wm_state_hidden: Atom,
+    net_wm_state_demands_attention: Atom,
+    net_active_window: Atom,
+    wm_change_state: Atom,
+    net_wm_state_above: Atom,
+    net_wm_window_type: Atom,
+    /// The window types of each kind of window, in the order of
+    /// `WindowKind`.
+    net_wm_window_types: [Atom; 5],
+    net_frame_extents: Atom,
+    net_wm_window_opacity: Atom,
+    utf8_string: Atom,
+    targets: Atom,
+    xdnd: xdnd::Atoms,
+}
+
+/// The connection to the display, shared by the windows of a thread.
+pub struct Connection {
+    conn: Rc<RustConnection>,
+    screen: usize,
+    atoms: Atoms,
+    scale_factor: f64,
+    /// Open windows, which are kept alive until they are closed.
+    windows: RefCell<HashMap<XWindow, super::Window>>,
+    /// The drag started from one of our windows, if any.
+    source: RefCell<Option<(super::Window, xdnd::Source)>>,
+    /// The window with the keyboard focus, if it is ours.
+    focus: Cell<Option<XWindow>>,
+    /// The keymap used to type text when there is no input method.
+    keymap: RefCell<Option<Keymap>>,
+    /// The input method, which is connected to once a window allows IME.
+    xim: RefCell<Option<Xim>>,
+    xim_started: Cell<bool>,
+    /// The open context menu, if any.
+    menu: RefCell<Option<Menu>>,
+}
+
+/// A context menu, whose popups are override-redirect windows that grab the
+/// pointer and keyboard while it is open.
+struct Menu {
+    window: super::Window,
+    context: ContextMenu,
+    /// The window of each open popup, its position on the root window and
+    /// whether it was exposed.
+    popups: Vec<(XWindow, (i32, i32), bool)>,
+}
+
+thread_local! {
+    static CONNECTION: RefCell<Option<Rc<Connection>>> = const { RefCell::new(None) };
+}
+
+impl Connection {
+    /// Returns the connection of the current thread, connecting on first use.
+    pub fn get() -> Result<Rc<Self>, ()> {
+        if let Some(connection) = CONNECTION.with(|c| c.borrow().clone()) {
+            return Ok(connection);
+        }
+        let connection = Rc::new(Self::connect().ok_or(())?);
+        CONNECTION.with(|c| *c.borrow_mut() = Some(connection.clone()));
+
+        let fd = connection.conn.stream().as_raw_fd();
+        main_loop::watch_fd(fd, {
+            let connection = connection.clone();
+            move || connection.dispatch()
+        });
+        // Replies may have been read along with events, which are then
+        // waiting in the queue without the socket being readable.
+        main_loop::add_prepare({
+            let connection = connection.clone();
+            move || connection.dispatch()
+        });
+        Ok(connection)
+    }
+
+    fn connect() -> Option<Self> {
+        let (conn, screen) = RustConnection::connect(None).ok()?;
+        let conn = Rc::new(conn);
+
+        let intern = |name: &str| -> Option<Atom> {
+            Some(conn.intern_atom(false, name.as_bytes()).ok()?.reply().ok()?.atom)
+        };
+        let mut xdnd = [0; 13];
+        for (atom, name) in xdnd.iter_mut().zip(&xdnd::Atoms::NAMES) {
+            *atom = intern(name)?;
+        }
+        let atoms = Atoms {
+            wm_protocols: intern("WM_PROTOCOLS")?,
+            wm_delete_window: intern("WM_DELETE_WINDOW")?,
+            net_wm_name: intern("_NET_WM_NAME")?,
+            net_wm_state: intern("_NET_WM_STATE")?,
+            net_wm_state_maximized_vert: intern("_NET_WM_STATE_MAXIMIZED_VERT")?,
+            net_wm_state_maximized_horz: intern("_NET_WM_STATE_MAXIMIZED_HORZ")?,
+            net_wm_state_fullscreen: intern("_NET_WM_STATE_FULLSCREEN")?,
+            net_wm_state_modal: intern("_NET_WM_STATE_MODAL")?,
+            net_wm_state_hidden: intern("_NET_WM_STATE_HIDDEN")?,
+            net_wm_state_demands_attention: intern("_NET_WM_STATE_DEMANDS_ATTENTION")?,
+            net_active_window: intern("_NET_ACTIVE_WINDOW")?,
+            wm_change_state: intern("WM_CHANGE_STATE")?,
+            net_wm_state_above: intern("_NET_WM_STATE_ABOVE")?,
+            net_wm_window_type: intern("_NET_WM_WINDOW_TYPE")?,
+            net_wm_window_types: [
+                intern("_NET_WM_WINDOW_TYPE_NORMAL")?,
+                intern("_NET_WM_WINDOW_TYPE_DIALOG")?,
+                intern("_NET_WM_WINDOW_TYPE_UTILITY")?,
+                intern("_NET_WM_WINDOW_TYPE_POPUP_MENU")?,
+                intern("_NET_WM_WINDOW_TYPE_TOOLTIP")?,
+            ],
+            net_frame_extents: intern("_NET_FRAME_EXTENTS")?,
+            net_wm_window_opacity: intern("_NET_WM_WINDOW_OPACITY")?,
+            utf8_string: intern("UTF8_STRING")?,
+            targets: intern("TARGETS")?,
+            xdnd: xdnd::Atoms::new(xdnd),
+        };
+
+        // X11 has no scale factor of its own, so the font DPI set by desktop
+        // environments is used instead.
+        let scale_factor = resource_manager::new_from_default(&*conn).ok()
+            .and_then(|db| db.get_value::<f64>("Xft.dpi", "").ok().flatten())
+            .map_or(1.0, |dpi| dpi / 96.0)
+            .max(1.0);
+
+        // The keyboard layout set by the desktop environment, if any.
+        let root = conn.setup().roots[screen].root;
+        let names = intern("_XKB_RULES_NAMES")
+            .and_then(|atom| conn.get_property(false, root, atom, AtomEnum::STRING, 0, 1024).ok()?.reply().ok())
+            .map_or_else(Vec::new, |reply| reply.value);
+        let keymap = Keymap::from_names(&names);
+
+        Some(Connection {
+            conn,
+            screen,
+            atoms,
+            scale_factor,
+            windows: RefCell::new(HashMap::new()),
+            source: RefCell::new(None),
+            focus: Cell::new(None),
+            keymap: RefCell::new(keymap),
+            xim: RefCell::new(None),
+            xim_started: Cell::new(false),
+            menu: RefCell::new(None),
+        })
+    }
+
+    #[inline]
+    fn root(&self) -> XWindow {
+        self.conn.setup().roots[self.screen].root
+    }
+
+    fn window(&self, id: XWindow) -> Option<super::Window> {
+        self.windows.borrow().get(&id).cloned()
+    }
+
+    fn dispatch(&self) {
+        while let Ok(Some(event)) = self.conn.poll_for_event() {
+            self.handle_event(event);
+        }
+        let _ = self.conn.flush();
+    }
+
+    fn handle_event(&self, event: Event) {
+        if self.filter_ime(&event) || self.filter_menu(&event) {
+            return;
+        }
+        match event {
+            Event::ClientMessage(event) => {
+                let window = match self.window(event.window) {
+                    Some(window) => window,
+                    None => return,
+                };
+                let data = event.data.as_data32();
+                if event.type_ == self.atoms.wm_protocols && data[0] == self.atoms.wm_delete_window {
+                    window.autosave_on_close();
+                    x11(&window).close();
+                } else if let Some(message) = xdnd::Message::decode(&self.atoms.xdnd, event.type_, data) {
+                    x11(&window).handle_xdnd(&window, message);
+                } else if let Some(reply) = xdnd::Reply::decode(&self.atoms.xdnd, event.type_, data) {
+                    self.drag_reply(reply);
+                }
+            },
+            Event::MotionNotify(event) => {
+                self.drag_motion(event.root_x, event.root_y, event.time);
+            },
+            Event::ButtonPress(event) if event.detail == RIGHT_BUTTON => {
+                if let Some(window) = self.window(event.event) {
+                    let scale = self.scale_factor;
+                    let position = LogicalPosition::new(event.event_x as f64 / scale, event.event_y as f64 / scale);
+                    window.context_click(position);
+                }
+            },
+            Event::ButtonRelease(event) => {
+                self.drag_release(event.time);
+            },
+            Event::SelectionRequest(event) if event.selection == self.atoms.xdnd.selection => {
+                self.send_selection(&event);
+            },
+            Event::SelectionNotify(event) if event.selection == self.atoms.xdnd.selection => {
+                if let Some(window) = self.window(event.requestor) {
+                    x11(&window).receive_files(&window, &event);
+                }
+            },
+            Event::KeyPress(event) => {
+                self.handle_key(&event, true);
+            },
+            Event::KeyRelease(event) => {
+                self.handle_key(&event, false);
+            },
+            Event::FocusIn(event) if event.detail != NotifyDetail::POINTER => {
+                self.set_focus(event.event, true);
+            },
+            Event::FocusOut(event) if event.detail != NotifyDetail::POINTER => {
+                self.set_focus(event.event, false);
+            },
+            Event::ConfigureNotify(event) => {
+                if let Some(window) = self.window(event.window) {
+                    x11(&window).update_windowed_frame();
+                    window.schedule_autosave();
+                }
+            },
+            Event::PropertyNotify(event) if event.atom == self.atoms.net_wm_state => {
+                if let Some(window) = self.window(event.window) {
+                    x11(&window).state_changed(&window);
+                    window.schedule_autosave();
+                }
+            },
+            Event::DestroyNotify(event) => {
+                if let Some(window) = self.windows.borrow_mut().remove(&event.window) {
+                    window.closed();
+                }
+            },
+            _ => {},
+        }
+    }
+
+    /// Connects to the input method on first use.
+    fn start_xim(&self) {
+        if !self.xim_started.replace(true) {
+            *self.xim.borrow_mut() = Xim::connect(self.conn.clone(), self.screen);
+        }
+    }
+
+    /// Passes `event` to the input method, returning whether it was part of
+    /// its protocol, and emits what the input method sent.
+    fn filter_ime(&self, event: &Event) -> bool {
+        let mut xim = self.xim.borrow_mut();
+        let (filtered, events) = match xim.as_mut() {
+            Some(xim) => (xim.filter_event(event), xim.take_events()),
+            None => return false,
+        };
+        drop(xim);
+
+        for (id, event) in events {
+            let window = match self.window(id) {
+                Some(window) if window.ime_allowed() => window,
+                _ => continue,
+            };
+            match event {
+                ImeEvent::Preedit(text, cursor) => window.set_preedit(text, cursor),
+                ImeEvent::Commit(text) => window.commit(text),
+                ImeEvent::Key(event) => self.type_key(&window, &event),
+            }
+        }
+        filtered
+    }
+
+    fn handle_key(&self, event: &KeyPressEvent, pressed: bool) {
+        let window = match self.window(event.event) {
+            Some(window) if window.ime_allowed() => window,
+            _ => return,
+        };
+        let forwarded = self.xim.borrow_mut().as_mut().is_some_and(|xim| xim.forward_key(event.event, event));
+        if !forwarded && pressed {
+            self.type_key(&window, event);
+        }
+    }
+
+    /// Commits the text typed by a key press that no input method handled.
+    fn type_key(&self, window: &super::Window, event: &KeyPressEvent) {
+        let text = self.keymap.borrow_mut().as_mut().and_then(|keymap| {
+            keymap.update_x11_state(event.state.into());
+            keymap.key_text(event.detail.into())
+        });
+        if let Some(text) = text {
+            window.commit(text);
+        }
+    }
+
+    fn set_focus(&self, id: XWindow, focused: bool) {
+        if focused {
+            self.focus.set(Some(id));
+        } else if self.focus.get() == Some(id) {
+            self.focus.set(None);
+        }
+        let window = self.window(id);
+        let allowed = window.as_ref().is_some_and(|window| window.ime_allowed());
+        if let Some(xim) = self.xim.borrow_mut().as_mut() {
+            xim.set_focus(id, focused && allowed);
+        }
+        if let Some(window) = window {
+            if focused {
+                x11(&window).set_urgent(false);
+            }
+            window.emit(WindowEvent::Focused(focused));
+        }
+    }
+
+    /// Passes `event` to the open context menu, returning whether it was
+    /// meant for it.
+    fn filter_menu(&self, event: &Event) -> bool {
+        if self.menu.borrow().is_none() {
+            return false;
+        }
+        // Pointer events are reported to the first popup, which holds the
+        // grab, but are located on the root window.
+        let outcome = match event {
+            Event::MotionNotify(event) => {
+                self.with_menu(|menu| {
+                    let (level, x, y) = menu.locate(event.root_x, event.root_y);
+                    menu.context.motion(level, x, y);
+                    Outcome::Open
+                })
+            },
+            Event::ButtonPress(event) => {
+                self.with_menu(|menu| {
+                    let (level, x, y) = menu.locate(event.root_x, event.root_y);
+                    menu.context.press(level, x, y)
+                })
+            },
+            Event::ButtonRelease(event) => {
+                self.with_menu(|menu| {
+                    let (level, x, y) = menu.locate(event.root_x, event.root_y);
+                    menu.context.release(level, x, y)
+                })
+            },
+            Event::KeyPress(event) => {
+                let escape = self.keymap.borrow().as_ref()
+                    .is_some_and(|keymap| keymap.key_sym(event.detail.into()) == XK_ESCAPE);
+                if escape { Outcome::Closed(None) } else { Outcome::Open }
+            },
+            Event::KeyRelease(_) => Outcome::Open,
+            Event::Expose(ExposeEvent { window, count: 0, .. }) => {
+                self.with_menu(|menu| {
+                    let popups = menu.popups.iter_mut().zip(&mut menu.context.popups);
+                    for ((_, _, exposed), popup) in popups.filter(|((id, ..), _)| id == window) {
+                        *exposed = true;
+                        popup.dirty = true;
+                    }
+                    Outcome::Open
+                })
+            },
+            _ => return false,
+        };
+        match outcome {
+            Outcome::Open => self.sync_menu(),
+            Outcome::Closed(event) => self.close_menu(event),
+        }
+        true
+    }
+
+    fn with_menu(&self, f: impl FnOnce(&mut Menu) -> Outcome) -> Outcome {
+        self.menu.borrow_mut().as_mut().map_or(Outcome::Open, f)
+    }
+
+    /// Opens and closes popup windows to match the open menu, and draws
+    /// those that changed.
+    fn sync_menu(&self) {
+        let conn = &self.conn;
+        let mut menu = self.menu.borrow_mut();
+        let menu = match menu.as_mut() {
+            Some(menu) => menu,
+            None => return,
+        };
+        while menu.popups.len() > menu.context.popups.len() {
+            if let Some((id, ..)) = menu.popups.pop() {
+                let _ = conn.destroy_window(id);
+            }
+        }
+
+        let screen = &conn.setup().roots[self.screen];
+        let opened = menu.popups.len();
+        for (level, popup) in menu.context.popups.iter().enumerate().skip(opened) {
+            // The first popup is placed relative to the window, and the
+            // others relative to the popup they opened from.
+            let (parent_x, parent_y) = match level.checked_sub(1) {
+                Some(parent) => menu.popups[parent].1,
+                None => {
+                    let id = x11(&menu.window).id;
+                    match conn.translate_coordinates(id, screen.root, 0, 0).ok().and_then(|c| c.reply().ok()) {
+                        Some(reply) => (reply.dst_x.into(), reply.dst_y.into()),
+                        None => (0, 0),
+                    }
+                },
+            };
+            // Popups are kept on the screen.
+            let clamp = |position: i32, size: u32, screen: u16| {
+                position.min(screen as i32 - size as i32).max(0)
+            };
+            let x = clamp(parent_x + popup.origin.0, popup.width, screen.width_in_pixels);
+            let y = clamp(parent_y + popup.origin.1, popup.height, screen.height_in_pixels);
+
+            let id = match conn.generate_id() {
+                Ok(id) => id,
+                Err(_) => break,
+            };
+            let aux = CreateWindowAux::new()
+                .override_redirect(1)
+                .event_mask(EventMask::EXPOSURE);
+            let _ = conn.create_window(
+                x11rb::COPY_DEPTH_FROM_PARENT,
+                id,
+                screen.root,
+                x as i16,
+                y as i16,
+                popup.width as u16,
+                popup.height as u16,
+                0,
+                WindowClass::INPUT_OUTPUT,
+                x11rb::COPY_FROM_PARENT,
+                &aux,
+            );
+            let _ = conn.map_window(id);
+            menu.popups.push((id, (x, y), false));
+        }
+
+        let font = menu.context.font().clone();
+        for ((id, _, exposed), popup) in menu.popups.iter().zip(&mut menu.context.popups) {
+            if *exposed && popup.dirty {
+                popup.dirty = false;
+                let canvas = popup.draw(&font);
+                if let Ok(gc) = conn.generate_id() {
+                    let _ = conn.create_gc(gc, *id, &CreateGCAux::new());
+                    let size = (canvas.width as u16, canvas.height as u16);
+                    self.put_image(*id, gc, size, screen.root_depth, canvas.to_bytes());
+                    let _ = conn.free_gc(gc);
+                }
+            }
+        }
+        let _ = conn.flush();
+    }
+
+    /// Closes the open context menu, then calls the menu event handler with
+    /// `event` if an item was activated.
+    fn close_menu(&self, event: Option<MenuEvent>) {
+        let menu = match self.menu.borrow_mut().take() {
+            Some(menu) => menu,
+            None => return,
+        };
+        for &(id, ..) in menu.popups.iter().rev() {
+            let _ = self.conn.destroy_window(id);
+        }
+        let _ = self.conn.ungrab_pointer(x11rb::CURRENT_TIME);
+        let _ = self.conn.ungrab_keyboard(x11rb::CURRENT_TIME);
+        let _ = self.conn.flush();
+        if let Some(event) = event {
+            menu_linux::emit(event);
+        }
+    }
+
+    /// Returns the area of every monitor in physical pixels, with the
+    /// primary monitor first, or the whole screen without RandR 1.5.
+    fn monitors(&self) -> Vec<(String, (i32, i32, u32, u32))> {
+        let conn = &self.conn;
+        let supported = conn.randr_query_version(1, 5).ok()
+            .and_then(|cookie| cookie.reply().ok())
+            .is_some_and(|version| (version.major_version, version.minor_version) >= (1, 5));
+        let reply = match supported {
+            true => conn.randr_get_monitors(self.root(), true).ok().and_then(|cookie| cookie.reply().ok()),
+            false => None,
+        };
+        let mut monitors = match reply {
+            Some(reply) => reply.monitors,
+            None => Vec::new(),
+        };
+        if monitors.is_empty() {
+            let screen = &conn.setup().roots[self.screen];
+            let size = (screen.width_in_pixels as u32, screen.height_in_pixels as u32);
+            return vec![(self.screen.to_string(), (0, 0, size.0, size.1))];
+        }
+
+        monitors.sort_by_key(|monitor| !monitor.primary);
+        monitors.iter()
+            .map(|monitor| {
+                let name = conn.get_atom_name(monitor.name).ok()
+                    .and_then(|cookie| cookie.reply().ok())
+                    .map_or_else(|| monitor.name.to_string(), |reply| String::from_utf8_lossy(&reply.name).into_owned());
+                let area = (monitor.x as i32, monitor.y as i32, monitor.width as u32, monitor.height as u32);
+                (name, area)
+            })
+            .collect()
+    }
+
+    fn send_message(&self, target: XWindow, ty: Atom, data: [u32; 5]) {
+        let message = ClientMessageEvent::new(32, target, ty, data);
+        let _ = self.conn.send_event(false, target, EventMask::NO_EVENT, message);
+    }
+
+    fn send_xdnd(&self, target: XWindow, message: &xdnd::Message) {
+        let (ty, data) = message.encode(&self.atoms.xdnd);
+        self.send_message(target, ty, data);
+    }
+
+    /// Returns the XDND version of `window`, if it accepts drops.
+    fn xdnd_version(&self, window: XWindow) -> Option<u32> {
+        self.conn.get_property(false, window, self.atoms.xdnd.aware, AtomEnum::ATOM, 0, 1).ok()?
+            .reply().ok()?
+            .value32()?
+            .next()
+            .filter(|&version| version >= 3)
+    }
+
+    /// Returns the innermost window under the pointer that accepts drops.
+    fn drag_target(&self) -> Option<(XWindow, u32)> {
+        let mut window = self.root();
+        loop {
+            let child = self.conn.query_pointer(window).ok()?.reply().ok()?.child;
+            if child == x11rb::NONE {
+                return None;
+            }
+            if let Some(version) = self.xdnd_version(child) {
+                return Some((child, version));
+            }
+            window = child;
+        }
+    }
+
+    fn drag_motion(&self, x: i16, y: i16, time: Timestamp) {
+        if self.source.borrow().is_none() {
+            return;
+        }
+        let target = self.drag_target();
+
+        let mut source = self.source.borrow_mut();
+        let (window, source) = match source.as_mut() {
+            Some((window, source)) => (x11(window).id, source),
+            None => return,
+        };
+
+        if let Some((icon, width, height)) = source.icon {
+            let aux = ConfigureWindowAux::new()
+                .x(x as i32 - width as i32 / 2)
+                .y(y as i32 - height as i32 / 2);
+            let _ = self.conn.configure_window(icon, &aux);
+        }
+
+        if target.map(|(t, _)| t) != source.target.map(|(t, _)| t) {
+            if let Some((old, _)) = source.target {
+                self.send_xdnd(old, &xdnd::Message::Leave { source: window });
+            }
+            source.target = target;
+            source.status = None;
+            source.waiting = false;
+            source.pending = None;
+            if let Some((new, version)) = target {
+                self.send_xdnd(new, &source.enter(window, version));
+            }
+        }
+
+        if let Some((target, _)) = source.target {
+            if source.waiting {
+                source.pending = Some((x, y, time));
+            } else {
+                source.waiting = true;
+                let message = xdnd::Message::Position { source: window, x, y, time, action: source.action };
+                self.send_xdnd(target, &message);
+            }
+        }
+    }
+
+    fn drag_release(&self, time: Timestamp) {
+        let mut current = self.source.borrow_mut();
+        let (window, source) = match current.as_mut() {
+            Some((window, source)) if !source.dropped => (x11(window).id, source),
+            _ => return,
+        };
+        let _ = self.conn.ungrab_pointer(x11rb::CURRENT_TIME);
+        if let Some((icon, ..)) = source.icon.take() {
+            let _ = self.conn.destroy_window(icon);
+        }
+
+        match source.target {
+            Some((target, _)) if source.status.is_some_and(|(accept, _)| accept) => {
+                source.dropped = true;
+                self.send_xdnd(target, &xdnd::Message::Drop { source: window, time });
+            },
+            target => {
+                if let Some((target, _)) = target {
+                    self.send_xdnd(target, &xdnd::Message::Leave { source: window });
+                }
+                drop(current);
+                self.end_drag(None);
+            },
+        }
+    }
+
+    fn drag_reply(&self, reply: xdnd::Reply) {
+        let mut current = self.source.borrow_mut();
+        let (window, source) = match current.as_mut() {
+            Some((window, source)) if source.target.map(|(t, _)| t) == Some(reply.target()) => {
+                (x11(window).id, source)
+            },
+            _ => return,
+        };
+
+        match reply {
+            xdnd::Reply::Status { target, accept, action } => {
+                source.status = Some((accept, action));
+                source.waiting = false;
+                if let Some((x, y, time)) = source.pending.take() {
+                    source.waiting = true;
+                    let message = xdnd::Message::Position { source: window, x, y, time, action: source.action };
+                    self.send_xdnd(target, &message);
+                }
+            },
+            xdnd::Reply::Finished { accepted, action, .. } if source.dropped => {
+                let atoms = &self.atoms.xdnd;
+                // Older targets only report the action in their status.
+                let action = match (action, source.status) {
+                    (0, Some((_, status))) => status,
+                    _ => action,
+                };
+                let operation = match (source.target, accepted) {
+                    (Some((_, version)), false) if version >= 5 => None,
+                    _ => atoms.operation(action).or_else(|| atoms.operation(source.action)),
+                };
+                drop(current);
+                self.end_drag(operation);
+            },
+            xdnd::Reply::Finished { .. } => {},
+        }
+    }
+
+    fn end_drag(&self, operation: Option<crate::drag::DragOperation>) {
+        let (window, source) = match self.source.borrow_mut().take() {
+            Some(current) => current,
+            None => return,
+        };
+        let _ = self.conn.ungrab_pointer(x11rb::CURRENT_TIME);
+        if let Some((icon, ..)) = source.icon {
+            let _ = self.conn.destroy_window(icon);
+        }
+        let _ = self.conn.delete_property(x11(&window).id, self.atoms.xdnd.type_list);
+        let _ = self.conn.flush();
+        window.emit(WindowEvent::DragEnded { operation });
+    }
+
+    /// Replies to a request for the data of our drag.
+    fn send_selection(&self, event: &SelectionRequestEvent) {
+        // Obsolete clients leave the property unset.
+        let property = if event.property == x11rb::NONE { event.target } else { event.property };
+        let source = self.source.borrow();
+        let formats = source.as_ref().map_or(&[][..], |(_, source)| &source.formats[..]);
+
+        let sent = if event.target == self.atoms.targets {
+            let mut types = vec![self.atoms.targets];
+            types.extend(formats.iter().map(|&(ty, _)| ty));
+            self.conn.change_property32(PropMode::REPLACE, event.requestor, property, AtomEnum::ATOM, &types)
+                .is_ok()
+        } else {
+            // Drags carry little data, so it is sent in one piece.
+            let limit = self.conn.maximum_request_bytes().saturating_sub(64);
+            match formats.iter().find(|&&(ty, _)| ty == event.target) {
+                Some((ty, data)) if data.len() <= limit => self.conn
+                    .change_property8(PropMode::REPLACE, event.requestor, property, *ty, data)
+                    .is_ok(),
+                _ => false,
+            }
+        };
+
+        let reply = SelectionNotifyEvent {
+            response_type: SELECTION_NOTIFY_EVENT,
+            sequence: 0,
+            time: event.time,
+            requestor: event.requestor,
+            selection: event.selection,
+            target: event.target,
+            property: if sent { property } else { x11rb::NONE },
+        };
+        let _ = self.conn.send_event(false, event.requestor, EventMask::NO_EVENT, reply);
+        let _ = self.conn.flush();
+    }
+
+    /// Draws ARGB words in native byte order onto `drawable` of `depth`,
+    /// split into as many requests as needed.
+    fn put_image(&self, drawable: Drawable, gc: u32, (width, height): (u16, u16), depth: u8, mut pixels: Vec<u8>) {
+        let conn = &self.conn;
+        // Only 32 bits per pixel matches the layout of the words.
+        let formats = &conn.setup().pixmap_formats;
+        if !formats.iter().any(|format| format.depth == depth && format.bits_per_pixel == 32) {
+            return;
+        }
+        let big_endian = conn.setup().image_byte_order == ImageOrder::MSB_FIRST;
+        if big_endian != cfg!(target_endian = "big") {
+            pixels.chunks_exact_mut(4).for_each(|pixel| pixel.reverse());
+        }
+        let stride = width as usize * 4;
+        if stride == 0 || pixels.len() < stride * height as usize {
+            return;
+        }
+        let rows = (conn.maximum_request_bytes().saturating_sub(64) / stride).max(1);
+        for (i, chunk) in pixels[..stride * height as usize].chunks(rows * stride).enumerate() {
+            let y = (i * rows) as i16;
+            let chunk_height = (chunk.len() / stride) as u16;
+            let _ = conn.put_image(ImageFormat::Z_PIXMAP, drawable, gc, width, chunk_height, 0, y, 0, depth, chunk);
+        }
+    }
+
+    /// Creates an unmapped window showing `icon` that lets the pointer
+    /// through, or returns `None` if the screen has no visual with alpha.
+    fn create_icon_window(&self, icon: &Icon) -> Option<(XWindow, u16, u16)> {
+        let conn = &self.conn;
+        let screen = &conn.setup().roots[self.screen];
+        let visual = screen.allowed_depths.iter()
+            .filter(|depth| depth.depth == 32)
+            .flat_map(|depth| &depth.visuals)
+            .find(|visual| visual.class == VisualClass::TRUE_COLOR)?
+            .visual_id;
+        let (width, height) = (icon.width().min(u16::MAX as u32) as u16, icon.height().min(u16::MAX as u32) as u16);
+
+        let pixmap = conn.generate_id().ok()?;
+        conn.create_pixmap(32, pixmap, screen.root, width, height).ok()?;
+        let gc = conn.generate_id().ok()?;
+        conn.create_gc(gc, pixmap, &CreateGCAux::new()).ok()?;
+        self.put_image(pixmap, gc, (width, height), 32, icon.argb_premultiplied());
+        let _ = conn.free_gc(gc);
+
+        let colormap = conn.generate_id().ok()?;
+        conn.create_colormap(ColormapAlloc::NONE, colormap, screen.root, visual).ok()?;
+        let window = conn.generate_id().ok()?;
+        let aux = CreateWindowAux::new()
+            .background_pixmap(pixmap)
+            .border_pixel(0)
+            .override_redirect(1)
+            .colormap(colormap);
+        let created = conn.create_window(
+            32, window, screen.root, 0, 0, width, height, 0,
+            WindowClass::INPUT_OUTPUT, visual, &aux,
+        );
+        let _ = conn.free_pixmap(pixmap);
+        let _ = conn.free_colormap(colormap);
+        created.ok()?;
+
+        // An empty input region keeps the icon from hiding the target.
+        self.ignore_input(window);
+        Some((window, width, height))
+    }
+
+    /// Returns the scale factor from the `Xft.dpi` resource, or one if it is
+    /// invalid.
+    #[inline]
+    fn scale_factor(&self) -> ScaleFactor {
+        ScaleFactor::new(self.scale_factor).unwrap_or_default()
+    }
+
+    /// Lets pointer input through `window` to whatever is below it.
+    fn ignore_input(&self, window: XWindow) {
+        self.set_input_shape(window, &[]);
+    }
+
+    /// Restricts pointer input on `window` to `rectangles`.
+    fn set_input_shape(&self, window: XWindow, rectangles: &[Rectangle]) {
+        let _ = self.conn.shape_rectangles(
+            shape::SO::SET,
+            shape::SK::INPUT,
+            x11rb::protocol::xproto::ClipOrdering::UNSORTED,
+            window,
+            0,
+            0,
+            rectangles,
+        );
+    }
+}
+
+impl Menu {
+    /// Returns the deepest popup at `(x, y)` on the root window, and the
+    /// point relative to that popup or to the first one if there is none.
+    fn locate(&self, x: i16, y: i16) -> (Option<usize>, f64, f64) {
+        let (x, y) = (x as i32, y as i32);
+        let popups = self.popups.iter().zip(&self.context.popups).enumerate().rev();
+        for (level, ((_, (px, py), _), popup)) in popups {
+            let (dx, dy) = (x - px, y - py);
+            if dx >= 0 && dy >= 0 && (dx as u32) < popup.width && (dy as u32) < popup.height {
+                return (Some(level), dx as f64, dy as f64);
+            }
+        }
+        let (px, py) = self.popups.first().map_or((0, 0), |&(_, position, _)| position);
+        (None, (x - px) as f64, (y - py) as f64)
+    }
+}
+
+/// Returns the area of every monitor, with the primary monitor first.
+pub fn monitors() -> Vec<Monitor> {
+    let connection = match Connection::get() {
+        Ok(connection) => connection,
+        Err(()) => return Vec::new(),
+    };
+    let scale = connection.scale_factor;
+    connection.monitors()
+        .into_iter()
+        .map(|(id, (x, y, width, height))| Monitor {
+            id,
+            rect: LogicalRect::new(
+                LogicalPosition::new(x as f64 / scale, y as f64 / scale),
+                LogicalSize::new(width as f64 / scale, height as f64 / scale),
+            ),
+        })
+        .collect()
+}
+
+fn x11(window: &super::Window) -> &Window {
+    match &window.inner.backend {
+        Backend::X11(window) => window,
+        _ => unreachable!(),
+    }
+}
+
+pub struct Window {
+    connection: Rc<Connection>,
+    id: XWindow,
+    drag: RefCell<Option<xdnd::Drag>>,
+    /// The frame that the window returns to once it is neither maximized nor
+    /// fullscreen, as last seen.
+    windowed_frame: Cell<Option<(LogicalPosition, LogicalSize)>>,
+    /// Whether the window is minimized, maximized and fullscreen, as last
+    /// reported.
+    states: Cell<(bool, bool, bool)>,
+}
+
+impl Window {
+    pub fn build(builder: &crate::WindowBuilder) -> Result<super::Window, ()> {
+        let connection = Connection::get()?;
+        let conn = &connection.conn;
+        let atoms = &connection.atoms;
+        let screen = &conn.setup().roots[connection.screen];
+        let (title, app_id, size) = (builder.title_str(), builder.sys.app_id.as_deref(), builder.inner_size(connection.scale_factor()));
+
+        // Popups and tooltips are placed by us, without decorations.
+        let kind = builder.kind;
+        let unmanaged = matches!(kind, WindowKind::Popup | WindowKind::Tooltip);
+
+        let id = conn.generate_id().map_err(drop)?;
+        let scale = connection.scale_factor;
+        let aux = CreateWindowAux::new()
+            .background_pixel(screen.white_pixel)
+            .override_redirect(u32::from(unmanaged))
+            .event_mask(
+                EventMask::STRUCTURE_NOTIFY
+                    | EventMask::PROPERTY_CHANGE
+                    | EventMask::BUTTON_PRESS
+                    | EventMask::KEY_PRESS
+                    | EventMask::KEY_RELEASE
+                    | EventMask::FOCUS_CHANGE,
+            );
+        conn.create_window(
+            x11rb::COPY_DEPTH_FROM_PARENT,
+            id,
+            screen.root,
+            0,
+            0,
+            (size.width * scale).max(1.0) as u16,
+            (size.height * scale).max(1.0) as u16,
+            0,
+            WindowClass::INPUT_OUTPUT,
+            x11rb::COPY_FROM_PARENT,
+            &aux,
+        ).map_err(drop)?;
+
+        let property32 = |property: Atom, ty: AtomEnum, data: &[u32]| {
+            conn.change_property32(PropMode::REPLACE, id, property, ty, data).map(drop)
+        };
+        property32(atoms.wm_protocols, AtomEnum::ATOM, &[atoms.wm_delete_window]).map_err(drop)?;
+        property32(atoms.xdnd.aware, AtomEnum::ATOM, &[xdnd::VERSION]).map_err(drop)?;
+        property32(atoms.net_wm_window_type, AtomEnum::ATOM, &[atoms.net_wm_window_types[kind as usize]])
+            .map_err(drop)?;
+        if kind == WindowKind::Utility {
+            // Set before mapping, after which only the window manager may
+            // change the state.
+            property32(atoms.net_wm_state, AtomEnum::ATOM, &[atoms.net_wm_state_above]).map_err(drop)?;
+        }
+
+        if let Some(app_id) = app_id {
+            // The instance and class names, each terminated by a null byte.
+            let class = format!("{0}\0{0}\0", app_id);
+            conn.change_property8(PropMode::REPLACE, id, AtomEnum::WM_CLASS, AtomEnum::STRING, class.as_bytes())
+                .map_err(drop)?;
+        }
+
+        let window = super::Window::new(Backend::X11(Window {
+            connection: connection.clone(),
+            id,
+            drag: RefCell::new(None),
+            windowed_frame: Cell::new(None),
+            states: Cell::new((false, false, false)),
+        }));
+        x11(&window).set_title(title);
+
+        conn.map_window(id).map_err(drop)?;
+        conn.flush().map_err(drop)?;
+        connection.windows.borrow_mut().insert(id, window.clone());
+        Ok(window)
+    }
+
+    #[inline]
+    pub fn id(&self) -> XWindow {
+        self.id
+    }
+
+    pub fn set_title(&self, title: &str) {
+        let conn = &self.connection.conn;
+        let atoms = &self.connection.atoms;
+        let _ = conn.change_property8(PropMode::REPLACE, self.id, atoms.net_wm_name, atoms.utf8_string, title.as_bytes());
+        let _ = conn.change_property8(PropMode::REPLACE, self.id, AtomEnum::WM_NAME, AtomEnum::STRING, title.as_bytes());
+        let _ = conn.flush();
+    }
+
+    /// Returns the scale factor of the display, from the `Xft.dpi`
+    /// resource.
+    #[inline]
+    pub fn scale_factor(&self) -> ScaleFactor {
+        self.connection.scale_factor()
+    }
+
+    pub fn inner_size(&self) -> LogicalSize {
+        let scale = self.connection.scale_factor;
+        match self.connection.conn.get_geometry(self.id).ok().and_then(|cookie| cookie.reply().ok()) {
+            Some(geometry) => LogicalSize::new(geometry.width as f64 / scale, geometry.height as f64 / scale),
+            None => LogicalSize::new(0.0, 0.0),
+        }
+    }
+
+    pub fn set_inner_size(&self, size: LogicalSize) {
+        let scale = self.connection.scale_factor;
+        let aux = ConfigureWindowAux::new()
+            .width((size.width * scale).max(1.0) as u32)
+            .height((size.height * scale).max(1.0) as u32);
+        let _ = self.connection.conn.configure_window(self.id, &aux);
+        let _ = self.connection.conn.flush();
+    }
+
+    /// Returns the widths of the left, right, top and bottom borders that
+    /// the window manager draws around the window, in physical pixels.
+    fn frame_extents(&self) -> [u32; 4] {
+        let conn = &self.connection.conn;
+        let extents = conn.get_property(false, self.id, self.connection.atoms.net_frame_extents, AtomEnum::CARDINAL, 0, 4)
+            .ok()
+            .and_then(|cookie| cookie.reply().ok())
+            .and_then(|reply| Some(reply.value32()?.collect::<Vec<_>>()));
+        match extents.as_deref() {
+            Some(&[left, right, top, bottom]) => [left, right, top, bottom],
+            _ => [0; 4],
+        }
+    }
+
+    /// Returns the height of the top border, which holds the titlebar.
+    pub fn titlebar_height(&self) -> f64 {
+        let [_, _, top, _] = self.frame_extents();
+        f64::from(top) / self.connection.scale_factor
+    }
+
+    pub fn outer_position(&self) -> LogicalPosition {
+        self.outer_frame().0
+    }
+
+    /// Moves the window's frame, since the window manager positions the
+    /// frame rather than the window with the default gravity.
+    pub fn set_outer_position(&self, position: LogicalPosition) {
+        let scale = self.connection.scale_factor;
+        let aux = ConfigureWindowAux::new()
+            .x((position.x * scale).round() as i32)
+            .y((position.y * scale).round() as i32);
+        let _ = self.connection.conn.configure_window(self.id, &aux);
+        let _ = self.connection.conn.flush();
+    }
+
+    /// Returns the position of the content area's top-left corner on the
+    /// root window, in physical pixels.
+    fn content_origin(&self) -> (i32, i32) {
+        let connection = &self.connection;
+        connection.conn.translate_coordinates(self.id, connection.root(), 0, 0).ok()
+            .and_then(|cookie| cookie.reply().ok())
+            .map_or((0, 0), |reply| (reply.dst_x as i32, reply.dst_y as i32))
+    }
+
+    /// Returns the position of the content area's top-left corner on the
+    /// desktop.
+    pub fn content_position(&self) -> LogicalPosition {
+        let scale = self.connection.scale_factor;
+        let (x, y) = self.content_origin();
+        LogicalPosition::new(x as f64 / scale, y as f64 / scale)
+    }
+
+    /// Returns the current position and size of the window's frame.
+    fn outer_frame(&self) -> (LogicalPosition, LogicalSize) {
+        let scale = self.connection.scale_factor;
+        let [left, right, top, bottom] = self.frame_extents();
+        let origin = self.content_origin();
+        let size = self.inner_size();
+        (
+            LogicalPosition::new(
+                (origin.0 - left as i32) as f64 / scale,
+                (origin.1 - top as i32) as f64 / scale,
+            ),
+            LogicalSize::new(
+                size.width + (left + right) as f64 / scale,
+                size.height + (top + bottom) as f64 / scale,
+            ),
+        )
+    }
+
+    /// Returns the position and size of the frame that the window has when
+    /// it is neither maximized nor fullscreen.
+    pub fn windowed_frame(&self) -> (LogicalPosition, LogicalSize) {
+        match self.windowed_frame.get() {
+            Some(frame) if self.is_maximized() || self.is_fullscreen() => frame,
+            _ => self.outer_frame(),
+        }
+    }
+
+    /// Records the frame of the window, unless it is maximized or
+    /// fullscreen.
+    fn update_windowed_frame(&self) {
+        if !self.is_maximized() && !self.is_fullscreen() {
+            self.windowed_frame.set(Some(self.outer_frame()));
+        }
+    }
+
+    pub fn set_outer_frame(&self, position: LogicalPosition, size: LogicalSize) {
+        let scale = self.connection.scale_factor;
+        let [left, right, top, bottom] = self.frame_extents();
+        let width = (size.width * scale).round() as i64 - (left + right) as i64;
+        let height = (size.height * scale).round() as i64 - (top + bottom) as i64;
+        let aux = ConfigureWindowAux::new()
+            .x((position.x * scale).round() as i32)
+            .y((position.y * scale).round() as i32)
+            .width(width.max(1) as u32)
+            .height(height.max(1) as u32);
+        let _ = self.connection.conn.configure_window(self.id, &aux);
+        let _ = self.connection.conn.flush();
+        self.windowed_frame.set(Some((position, size)));
+    }
+
+    /// Returns the atoms in the window's `_NET_WM_STATE`.
+    fn net_wm_state(&self) -> Vec<Atom> {
+        let atoms = &self.connection.atoms;
+        self.connection.conn.get_property(false, self.id, atoms.net_wm_state, AtomEnum::ATOM, 0, 1024).ok()
+            .and_then(|cookie| cookie.reply().ok())
+            .and_then(|reply| Some(reply.value32()?.collect()))
+            .unwrap_or_default()
+    }
+
+    /// Asks the window manager to add or remove `first` and `second` from
+    /// the window's `_NET_WM_STATE`.
+    fn change_net_wm_state(&self, add: bool, first: Atom, second: Atom) {
+        let connection = &self.connection;
+        let action = if add { NET_WM_STATE_ADD } else { NET_WM_STATE_REMOVE };
+        let message = ClientMessageEvent::new(
+            32,
+            self.id,
+            connection.atoms.net_wm_state,
+            [action, first, second, SOURCE_APPLICATION, 0],
+        );
+        let mask = EventMask::SUBSTRUCTURE_REDIRECT | EventMask::SUBSTRUCTURE_NOTIFY;
+        let _ = connection.conn.send_event(false, connection.root(), mask, message);
+        let _ = connection.conn.flush();
+    }
+
+    /// Returns whether the window is minimized, maximized and fullscreen.
+    fn states(&self) -> (bool, bool, bool) {
+        let atoms = &self.connection.atoms;
+        let state = self.net_wm_state();
+        (
+            state.contains(&atoms.net_wm_state_hidden),
+            state.contains(&atoms.net_wm_state_maximized_vert) && state.contains(&atoms.net_wm_state_maximized_horz),
+            state.contains(&atoms.net_wm_state_fullscreen),
+        )
+    }
+
+    /// Emits an event for each state that changed since last reported.
+    fn state_changed(&self, window: &super::Window) {
+        let (minimized, maximized, fullscreen) = self.states();
+        let old = self.states.replace((minimized, maximized, fullscreen));
+        if old.0 != minimized {
+            window.emit(WindowEvent::Minimized(minimized));
+        }
+        if old.1 != maximized {
+            window.emit(WindowEvent::Maximized(maximized));
+        }
+        if old.2 != fullscreen {
+            window.emit(WindowEvent::Fullscreen(fullscreen));
+        }
+    }
+
+    #[inline]
+    pub fn is_minimized(&self) -> bool {
+        self.states().0
+    }
+
+    /// Iconifies the window through the window manager, or maps it again,
+    /// which restores it.
+    pub fn set_minimized(&self, minimized: bool) {
+        let connection = &self.connection;
+        if minimized {
+            let message = ClientMessageEvent::new(32, self.id, connection.atoms.wm_change_state, [ICONIC_STATE, 0, 0, 0, 0]);
+            let mask = EventMask::SUBSTRUCTURE_REDIRECT | EventMask::SUBSTRUCTURE_NOTIFY;
+            let _ = connection.conn.send_event(false, connection.root(), mask, message);
+        } else {
+            let _ = connection.conn.map_window(self.id);
+        }
+        let _ = connection.conn.flush();
+    }
+
+    /// Asks the window manager to activate the window, which also restores
+    /// it if minimized.
+    pub fn focus(&self) {
+        let connection = &self.connection;
+        let data = [SOURCE_APPLICATION, x11rb::CURRENT_TIME, connection.focus.get().unwrap_or(x11rb::NONE), 0, 0];
+        let message = ClientMessageEvent::new(32, self.id, connection.atoms.net_active_window, data);
+        let mask = EventMask::SUBSTRUCTURE_REDIRECT | EventMask::SUBSTRUCTURE_NOTIFY;
+        let _ = connection.conn.map_window(self.id);
+        let _ = connection.conn.send_event(false, connection.root(), mask, message);
+        let _ = connection.conn.flush();
+    }
+
+    /// Marks the window as demanding attention, and critical requests also
+    /// set the urgency hint, until the window is focused.
+    pub fn request_user_attention(&self, ty: UserAttentionType) {
+        if self.connection.focus.get() == Some(self.id) {
+            return;
+        }
+        self.change_net_wm_state(true, self.connection.atoms.net_wm_state_demands_attention, 0);
+        if ty == UserAttentionType::Critical {
+            self.set_urgent(true);
+        }
+    }
+
+    /// Sets or clears the urgency hint of the window.
+    fn set_urgent(&self, urgent: bool) {
+        let conn = &*self.connection.conn;
+        let hints = WmHints::get(conn, self.id).ok().and_then(|cookie| cookie.reply().ok());
+        let mut hints = match hints {
+            Some(Some(hints)) => hints,
+            _ if !urgent => return,
+            _ => WmHints::new(),
+        };
+        if hints.urgent != urgent {
+            hints.urgent = urgent;
+            let _ = hints.set(conn, self.id);
+            let _ = conn.flush();
+        }
+    }
+
+    #[inline]
+    pub fn is_maximized(&self) -> bool {
+        self.states().1
+    }
+
+    pub fn set_maximized(&self, maximized: bool) {
+        if self.is_maximized() != maximized {
+            let atoms = &self.connection.atoms;
+            self.change_net_wm_state(maximized, atoms.net_wm_state_maximized_vert, atoms.net_wm_state_maximized_horz);
+        }
+    }
+
+    #[inline]
+    pub fn is_fullscreen(&self) -> bool {
+        self.states().2
+    }
+
+    pub fn set_fullscreen(&self, fullscreen: bool) {
+        if self.is_fullscreen() != fullscreen {
+            self.change_net_wm_state(fullscreen, self.connection.atoms.net_wm_state_fullscreen, 0);
+        }
+    }
+
+    /// Moves the window so that it is centered on `parent`.
+    pub fn center_on(&self, parent: &Window) {
+        let (position, size) = parent.outer_frame();
+        let (_, own_size) = self.outer_frame();
+        self.set_outer_position(LogicalPosition::new(
+            position.x + (size.width - own_size.width) / 2.0,
+            position.y + (size.height - own_size.height) / 2.0,
+        ));
+    }
+
+    /// Marks the window as transient for `parent`, which keeps it above
+    /// `parent`, and asks the window manager to make it modal if `modal`.
+    pub fn set_parent(&self, parent: &Window, modal: bool) {
+        let conn = &self.connection.conn;
+        let _ = conn.change_property32(PropMode::REPLACE, self.id, AtomEnum::WM_TRANSIENT_FOR, AtomEnum::WINDOW, &[parent.id]);
+        if modal {
+            self.change_net_wm_state(true, self.connection.atoms.net_wm_state_modal, 0);
+        }
+        let _ = conn.flush();
+    }
+
+    /// Asks the compositing manager to blend the window with `opacity`,
+    /// which is fully opaque without the property.
+    pub fn set_opacity(&self, opacity: f64) {
+        let conn = &self.connection.conn;
+        let property = self.connection.atoms.net_wm_window_opacity;
+        let _ = if opacity < 1.0 {
+            let opacity = (opacity * u32::MAX as f64).round() as u32;
+            conn.change_property32(PropMode::REPLACE, self.id, property, AtomEnum::CARDINAL, &[opacity]).map(drop)
+        } else {
+            conn.delete_property(self.id, property).map(drop)
+        };
+        let _ = conn.flush();
+    }
+
+    /// Restricts pointer input to `region`, or lets the whole window receive
+    /// it again if there is none.
+    pub fn set_input_region(&self, region: Option<&[LogicalRect]>) {
+        let connection = &self.connection;
+        match region {
+            Some(region) => {
+                let scale = connection.scale_factor;
+                let rectangles: Vec<_> = region.iter().map(|rect| {
+                    // Round outwards so that the region covers the rectangle.
+                    let (x, y) = ((rect.origin.x * scale).floor(), (rect.origin.y * scale).floor());
+                    let right = ((rect.origin.x + rect.size.width) * scale).ceil();
+                    let bottom = ((rect.origin.y + rect.size.height) * scale).ceil();
+                    Rectangle {
+                        x: x as i16,
+                        y: y as i16,
+                        width: (right - x).max(0.0) as u16,
+                        height: (bottom - y).max(0.0) as u16,
+                    }
+                }).collect();
+                connection.set_input_shape(self.id, &rectangles);
+            },
+            // Resets the input shape to the bounds of the window.
+            None => {
+                let _ = connection.conn.shape_mask(shape::SO::SET, shape::SK::INPUT, self.id, 0, 0, x11rb::NONE);
+            },
+        }
+        let _ = connection.conn.flush();
+    }
+
+    /// Returns the name of the monitor that the center of the window is on.
+    pub fn monitor(&self) -> Option<String> {
+        let center = LogicalRect::from(self.outer_frame()).center();
+        monitors().into_iter()
+            .find(|m| m.rect.contains(center))
+            .map(|m| m.id)
+    }
+
+    pub fn start_drag(
+        &self,
+        window: &super::Window,
+        formats: Vec<(String, Arc<[u8]>)>,
+        operations: DragOperations,
+        icon: &Icon,
+    ) -> Result<(), ()> {
+        let connection = &self.connection;
+        let conn = &connection.conn;
+        let atoms = &connection.atoms;
+        if connection.source.borrow().is_some() {
+            return Err(());
+        }
+        let action = atoms.xdnd.action(operations).ok_or(())?;
+
+        // Drags are started from a button held over the window.
+        let pointer = conn.query_pointer(self.id).map_err(drop)?.reply().map_err(drop)?;
+        let buttons = KeyButMask::BUTTON1 | KeyButMask::BUTTON2 | KeyButMask::BUTTON3;
+        if !pointer.same_screen || u16::from(pointer.mask) & u16::from(buttons) == 0 {
+            return Err(());
+        }
+
+        let mut types = Vec::with_capacity(formats.len());
+        for (ty, data) in formats {
+            let atom = conn.intern_atom(false, ty.as_bytes()).map_err(drop)?.reply().map_err(drop)?.atom;
+            types.push((atom, data));
+        }
+        let mut source = xdnd::Source::new(types, action);
+
+        let grab = conn.grab_pointer(
+            false,
+            self.id,
+            EventMask::POINTER_MOTION | EventMask::BUTTON_RELEASE,
+            GrabMode::ASYNC,
+            GrabMode::ASYNC,
+            x11rb::NONE,
+            x11rb::NONE,
+            x11rb::CURRENT_TIME,
+        ).map_err(drop)?.reply().map_err(drop)?;
+        if grab.status != GrabStatus::SUCCESS {
+            return Err(());
+        }
+
+        conn.set_selection_owner(self.id, atoms.xdnd.selection, x11rb::CURRENT_TIME).map_err(drop)?;
+        let types = source.types();
+        if types.len() > 3 {
+            conn.change_property32(PropMode::REPLACE, self.id, atoms.xdnd.type_list, AtomEnum::ATOM, &types)
+                .map_err(drop)?;
+        }
+
+        source.icon = connection.create_icon_window(icon);
+        if let Some((icon, ..)) = source.icon {
+            let _ = conn.map_window(icon);
+        }
+        *connection.source.borrow_mut() = Some((window.clone(), source));
+        connection.drag_motion(pointer.root_x, pointer.root_y, x11rb::CURRENT_TIME);
+        let _ = conn.flush();
+        Ok(())
+    }
+
+    /// Opens a context menu at `position` within the window, replacing any
+    /// open one.
+    pub fn show_context_menu(&self, window: &super::Window, model: &popup::Model, position: LogicalPosition) {
+        let connection = &self.connection;
+        let conn = &connection.conn;
+        connection.close_menu(None);
+        let font = match popup::Font::get() {
+            Some(font) => font,
+            None => return,
+        };
+        let scale = connection.scale_factor;
+        let origin = ((position.x * scale) as i32, (position.y * scale) as i32);
+        *connection.menu.borrow_mut() = Some(Menu {
+            window: window.clone(),
+            context: ContextMenu::new(model, font, scale, origin),
+            popups: Vec::new(),
+        });
+        connection.sync_menu();
+
+        // Clicks and keys go to the menu until it is closed, wherever they
+        // occur.
+        let popup = match connection.menu.borrow().as_ref().and_then(|menu| menu.popups.first()) {
+            Some(&(popup, ..)) => popup,
+            None => return,
+        };
+        let events = EventMask::BUTTON_PRESS | EventMask::BUTTON_RELEASE | EventMask::POINTER_MOTION;
+        let pointer = conn.grab_pointer(
+            false,
+            popup,
+            events,
+            GrabMode::ASYNC,
+            GrabMode::ASYNC,
+            x11rb::NONE,
+            x11rb::NONE,
+            x11rb::CURRENT_TIME,
+        ).ok().and_then(|cookie| cookie.reply().ok());
+        let keyboard = conn.grab_keyboard(false, popup, x11rb::CURRENT_TIME, GrabMode::ASYNC, GrabMode::ASYNC)
+            .ok()
+            .and_then(|cookie| cookie.reply().ok());
+        let grabbed = |status: Option<GrabStatus>| status == Some(GrabStatus::SUCCESS);
+        if !grabbed(pointer.map(|r| r.status)) || !grabbed(keyboard.map(|r| r.status)) {
+            connection.close_menu(None);
+        }
+    }
+
+    /// Creates an input context for the window once it allows IME, and
+    /// gives it the focus if the window has it.
+    pub fn update_ime(&self, window: &super::Window) {
+        let connection = &self.connection;
+        let allowed = window.ime_allowed();
+        if allowed {
+            connection.start_xim();
+        }
+        if let Some(xim) = connection.xim.borrow_mut().as_mut() {
+            if allowed {
+                xim.add_window(self.id, self.ime_spot(window));
+            }
+            xim.set_focus(self.id, allowed && connection.focus.get() == Some(self.id));
+        }
+        let _ = connection.conn.flush();
+    }
+
+    pub fn update_ime_cursor_area(&self, window: &super::Window) {
+        if let Some(xim) = self.connection.xim.borrow_mut().as_mut() {
+            xim.set_spot(self.id, self.ime_spot(window));
+        }
+        let _ = self.connection.conn.flush();
+    }
+
+    /// Returns where the candidate window goes, which is below the cursor
+    /// area in physical pixels.
+    fn ime_spot(&self, window: &super::Window) -> (i16, i16) {
+        let (position, size) = window.ime_cursor_area();
+        let scale = self.connection.scale_factor;
+        ((position.x * scale) as i16, ((position.y + size.height) * scale) as i16)
+    }
+
+    fn close(&self) {
+        let _ = self.connection.conn.destroy_window(self.id);
+        let _ = self.connection.conn.flush();
+        if let Some(window) = self.connection.windows.borrow_mut().remove(&self.id) {
+            window.closed();
+        }
+    }
+
+    fn handle_xdnd(&self, window: &super::Window, message: xdnd::Message) {
+        let connection = &self.connection;
+        let atoms = &connection.atoms.xdnd;
+
+        if let xdnd::Message::Enter { source, types, .. } = message {
+            let types = match types {
+                Some(types) => types,
+                None => connection.conn
+                    .get_property(false, source, atoms.type_list, AtomEnum::ATOM, 0, u32::MAX)
+                    .ok()
+                    .and_then(|cookie| cookie.reply().ok())
+                    .and_then(|reply| Some(reply.value32()?.collect()))
+                    .unwrap_or_default(),
+            };
+            *self.drag.borrow_mut() = Some(xdnd::Drag::new(atoms, source, &types));
+            return;
+        }
+
+        let mut drag = self.drag.borrow_mut();
+        let current = match drag.as_mut() {
+            Some(current) if current.source == message.source() => current,
+            _ => return,
+        };
+
+        match message {
+            xdnd::Message::Position { source, x, y, time, .. } => {
+                let position = connection.conn
+                    .translate_coordinates(connection.root(), self.id, x, y)
+                    .ok()
+                    .and_then(|cookie| cookie.reply().ok());
+                if let Some(position) = position {
+                    let scale = connection.scale_factor;
+                    current.position = Some((position.dst_x as f64 / scale, position.dst_y as f64 / scale));
+                }
+                connection.send_message(source, atoms.status, xdnd::status(atoms, self.id, current.accepts));
+
+                if !current.accepts {
+                    return;
+                }
+                if current.paths.is_some() {
+                    let events = hover_events(current);
+                    drop(drag);
+                    events.into_iter().for_each(|event| window.emit(event));
+                } else if !current.requested {
+                    current.requested = true;
+                    self.request_files(time);
+                }
+            },
+            xdnd::Message::Leave { .. } => {
+                let hovered = current.hovered;
+                *drag = None;
+                drop(drag);
+                if hovered {
+                    window.emit(WindowEvent::HoveredFileCancelled);
+                }
+            },
+            xdnd::Message::Drop { source, time } => {
+                if !current.accepts {
+                    *drag = None;
+                    connection.send_message(source, atoms.finished, xdnd::finished(atoms, self.id, false));
+                    return;
+                }
+                current.dropped = true;
+                if current.paths.is_some() {
+                    drop(drag);
+                    self.finish_drop(window);
+                } else if !current.requested {
+                    current.requested = true;
+                    self.request_files(time);
+                }
+            },
+            xdnd::Message::Enter { .. } => {},
+        }
+    }
+
+    fn request_files(&self, time: u32) {
+        let atoms = &self.connection.atoms.xdnd;
+        let _ = self.connection.conn
+            .convert_selection(self.id, atoms.selection, atoms.uri_list, atoms.selection, time);
+    }
+
+    fn receive_files(&self, window: &super::Window, event: &SelectionNotifyEvent) {
+        let connection = &self.connection;
+        let paths = if event.property == x11rb::NONE {
+            Vec::new()
+        } else {
+            connection.conn
+                .get_property(true, self.id, event.property, AtomEnum::ANY, 0, u32::MAX)
+                .ok()
+                .and_then(|cookie| cookie.reply().ok())
+                .map(|reply| parse_uri_list(&reply.value))
+                .unwrap_or_default()
+        };
+
+        let mut drag = self.drag.borrow_mut();
+        let current = match drag.as_mut() {
+            Some(current) if current.paths.is_none() => current,
+            _ => return,
+        };
+        current.paths = Some(paths);
+
+        if current.dropped {
+            drop(drag);
+            self.finish_drop(window);
+        } else {
+            let events = hover_events(current);
+            drop(drag);
+            events.into_iter().for_each(|event| window.emit(event));
+        }
+    }
+
+    fn finish_drop(&self, window: &super::Window) {
+        let drag = match self.drag.borrow_mut().take() {
+            Some(drag) => drag,
+            None => return,
+        };
+        let atoms = &self.connection.atoms.xdnd;
+        let paths = drag.paths.unwrap_or_default();
+        self.connection.send_message(drag.source, atoms.finished, xdnd::finished(atoms, self.id, !paths.is_empty()));
+
+        let position = logical_position(drag.position);
+        for path in paths {
+            window.emit(WindowEvent::DroppedFile { path, position });
+        }
+    }
+}
+
+fn logical_position(position: Option<(f64, f64)>) -> LogicalPosition {
+    let (x, y) = position.unwrap_or_default();
+    LogicalPosition { x, y }
+}
+
+/// Returns a `HoveredFile` event for each file of `drag`.
+fn hover_events(drag: &mut xdnd::Drag) -> Vec<WindowEvent> {
+    let position = logical_position(drag.position);
+    let paths = drag.paths.as_ref().map_or(&[][..], |p| &p[..]);
+    drag.hovered |= !paths.is_empty();
+    paths.iter()
+        .map(|path| WindowEvent::HoveredFile { path: path.clone(), position })
+        .collect()
+}
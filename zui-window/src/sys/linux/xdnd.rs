@@ -0,0 +1,403 @@
+//! Messages of the XDND drag-and-drop protocol, version 5.
+//!
+//! See <https://freedesktop.org/wiki/Specifications/XDND/>.
+
+use std::{
+    path::PathBuf,
+    sync::Arc,
+};
+use x11rb::protocol::xproto::{Atom, Timestamp, Window};
+use crate::drag::{DragOperation, DragOperations};
+
+/// The protocol version announced in `XdndAware`.
+pub const VERSION: u32 = 5;
+
+/// The atoms that name XDND messages, properties and types.
+#[derive(Clone, Copy, Debug)]
+pub struct Atoms {
+    pub aware: Atom,
+    pub enter: Atom,
+    pub position: Atom,
+    pub status: Atom,
+    pub leave: Atom,
+    pub drop: Atom,
+    pub finished: Atom,
+    pub selection: Atom,
+    pub type_list: Atom,
+    pub action_copy: Atom,
+    pub action_move: Atom,
+    pub action_link: Atom,
+    pub uri_list: Atom,
+}
+
+impl Atoms {
+    pub const NAMES: [&'static str; 13] = [
+        "XdndAware",
+        "XdndEnter",
+        "XdndPosition",
+        "XdndStatus",
+        "XdndLeave",
+        "XdndDrop",
+        "XdndFinished",
+        "XdndSelection",
+        "XdndTypeList",
+        "XdndActionCopy",
+        "XdndActionMove",
+        "XdndActionLink",
+        super::URI_LIST,
+    ];
+
+    /// Creates an instance from atoms interned in the order of `NAMES`.
+    pub fn new(atoms: [Atom; 13]) -> Self {
+        let [
+            aware, enter, position, status, leave, drop, finished, selection,
+            type_list, action_copy, action_move, action_link, uri_list,
+        ] = atoms;
+        Atoms {
+            aware,
+            enter,
+            position,
+            status,
+            leave,
+            drop,
+            finished,
+            selection,
+            type_list,
+            action_copy,
+            action_move,
+            action_link,
+            uri_list,
+        }
+    }
+
+    /// Returns the action requested by a source that allows `operations`,
+    /// preferring copies.
+    pub fn action(&self, operations: DragOperations) -> Option<Atom> {
+        [DragOperation::Copy, DragOperation::Move, DragOperation::Link].iter()
+            .find(|&&operation| operations.contains(operation))
+            .map(|&operation| match operation {
+                DragOperation::Copy => self.action_copy,
+                DragOperation::Move => self.action_move,
+                DragOperation::Link => self.action_link,
+            })
+    }
+
+    /// Returns the operation named by `action`, if it is one of ours.
+    pub fn operation(&self, action: Atom) -> Option<DragOperation> {
+        if action == self.action_copy {
+            Some(DragOperation::Copy)
+        } else if action == self.action_move {
+            Some(DragOperation::Move)
+        } else if action == self.action_link {
+            Some(DragOperation::Link)
+        } else {
+            None
+        }
+    }
+}
+
+/// A message sent by the source of a drag to a window.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Message {
+    /// The drag entered the window. If the source offers more than three
+    /// types, `types` is `None` and they are listed in the `XdndTypeList`
+    /// property of the source.
+    Enter { source: Window, version: u32, types: Option<Vec<Atom>> },
+    /// The pointer moved to a position relative to the root window, and the
+    /// source requests `action`.
+    Position { source: Window, x: i16, y: i16, time: Timestamp, action: Atom },
+    Leave { source: Window },
+    Drop { source: Window, time: Timestamp },
+}
+
+impl Message {
+    /// Decodes a client message of type `ty`, returning `None` if it is not
+    /// one of the messages handled by targets.
+    pub fn decode(atoms: &Atoms, ty: Atom, data: [u32; 5]) -> Option<Self> {
+        let source = data[0];
+        let message = if ty == atoms.enter {
+            let types = if data[1] & 1 == 0 {
+                Some(data[2..].iter().cloned().filter(|&t| t != 0).collect())
+            } else {
+                None
+            };
+            Message::Enter { source, version: data[1] >> 24, types }
+        } else if ty == atoms.position {
+            let x = (data[2] >> 16) as i16;
+            let y = (data[2] & 0xffff) as i16;
+            Message::Position { source, x, y, time: data[3], action: data[4] }
+        } else if ty == atoms.leave {
+            Message::Leave { source }
+        } else if ty == atoms.drop {
+            Message::Drop { source, time: data[2] }
+        } else {
+            return None;
+        };
+        Some(message)
+    }
+
+    /// Returns the type and data of the client message for `self`.
+    ///
+    /// An enter message without types must be accompanied by the
+    /// `XdndTypeList` property on the source.
+    pub fn encode(&self, atoms: &Atoms) -> (Atom, [u32; 5]) {
+        match *self {
+            Message::Enter { source, version, ref types } => {
+                let mut data = [source, version << 24, 0, 0, 0];
+                match types {
+                    Some(types) => {
+                        for (slot, &ty) in data[2..].iter_mut().zip(types.iter().take(3)) {
+                            *slot = ty;
+                        }
+                    },
+                    None => data[1] |= 1,
+                }
+                (atoms.enter, data)
+            },
+            Message::Position { source, x, y, time, action } => {
+                let position = (x as u16 as u32) << 16 | y as u16 as u32;
+                (atoms.position, [source, 0, position, time, action])
+            },
+            Message::Leave { source } => (atoms.leave, [source, 0, 0, 0, 0]),
+            Message::Drop { source, time } => (atoms.drop, [source, 0, time, 0, 0]),
+        }
+    }
+
+    #[inline]
+    pub fn source(&self) -> Window {
+        match *self {
+            Message::Enter { source, .. } |
+            Message::Position { source, .. } |
+            Message::Leave { source } |
+            Message::Drop { source, .. } => source,
+        }
+    }
+}
+
+/// A reply sent by the target of a drag to its source.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Reply {
+    /// Whether the target would accept a drop at the current position, and
+    /// with which action.
+    Status { target: Window, accept: bool, action: Atom },
+    /// The target is done with a drop. Targets older than version 5 leave
+    /// `action` unset.
+    Finished { target: Window, accepted: bool, action: Atom },
+}
+
+impl Reply {
+    /// Decodes a client message of type `ty`, returning `None` if it is not
+    /// one of the replies handled by sources.
+    pub fn decode(atoms: &Atoms, ty: Atom, data: [u32; 5]) -> Option<Self> {
+        let target = data[0];
+        if ty == atoms.status {
+            Some(Reply::Status { target, accept: data[1] & 1 != 0, action: data[4] })
+        } else if ty == atoms.finished {
+            Some(Reply::Finished { target, accepted: data[1] & 1 != 0, action: data[2] })
+        } else {
+            None
+        }
+    }
+
+    #[inline]
+    pub fn target(&self) -> Window {
+        match *self {
+            Reply::Status { target, .. } |
+            Reply::Finished { target, .. } => target,
+        }
+    }
+}
+
+/// Returns the data of an `XdndStatus` message from `target`, which asks for
+/// a position message whenever the pointer moves.
+pub fn status(atoms: &Atoms, target: Window, accept: bool) -> [u32; 5] {
+    let action = if accept { atoms.action_copy } else { 0 };
+    [target, accept as u32 | 2, 0, 0, action]
+}
+
+/// Returns the data of an `XdndFinished` message from `target`.
+pub fn finished(atoms: &Atoms, target: Window, accepted: bool) -> [u32; 5] {
+    let action = if accepted { atoms.action_copy } else { 0 };
+    [target, accepted as u32, action, 0, 0]
+}
+
+/// A drag over one of our windows.
+#[derive(Debug)]
+pub struct Drag {
+    pub source: Window,
+    /// Whether the source offers a list of files.
+    pub accepts: bool,
+    /// The position of the pointer in logical coordinates of the window.
+    pub position: Option<(f64, f64)>,
+    /// The dragged files, once they were received from the source.
+    pub paths: Option<Vec<PathBuf>>,
+    /// Whether the list of files was requested from the source.
+    pub requested: bool,
+    /// Whether the files were dropped before they were received.
+    pub dropped: bool,
+    /// Whether `HoveredFile` events were emitted for the drag.
+    pub hovered: bool,
+}
+
+impl Drag {
+    pub fn new(atoms: &Atoms, source: Window, types: &[Atom]) -> Self {
+        Drag {
+            source,
+            accepts: types.contains(&atoms.uri_list),
+            position: None,
+            paths: None,
+            requested: false,
+            dropped: false,
+            hovered: false,
+        }
+    }
+}
+
+/// A drag started from one of our windows.
+#[derive(Debug)]
+pub struct Source {
+    /// The data of the drag by type.
+    pub formats: Vec<(Atom, Arc<[u8]>)>,
+    /// The action requested from targets.
+    pub action: Atom,
+    /// The window showing the icon under the pointer, and its size.
+    pub icon: Option<(Window, u16, u16)>,
+    /// The window under the pointer and the protocol version it speaks.
+    pub target: Option<(Window, u32)>,
+    /// The last status received from the target.
+    pub status: Option<(bool, Atom)>,
+    /// Whether a position was sent that the target did not reply to yet.
+    pub waiting: bool,
+    /// A position to send once the target replies.
+    pub pending: Option<(i16, i16, Timestamp)>,
+    /// Whether the data was dropped on the target.
+    pub dropped: bool,
+}
+
+impl Source {
+    pub fn new(formats: Vec<(Atom, Arc<[u8]>)>, action: Atom) -> Self {
+        Source {
+            formats,
+            action,
+            icon: None,
+            target: None,
+            status: None,
+            waiting: false,
+            pending: None,
+            dropped: false,
+        }
+    }
+
+    #[inline]
+    pub fn types(&self) -> Vec<Atom> {
+        self.formats.iter().map(|&(ty, _)| ty).collect()
+    }
+
+    /// Returns the message announcing the drag to a target of `version`.
+    pub fn enter(&self, window: Window, version: u32) -> Message {
+        let types = self.types();
+        Message::Enter {
+            source: window,
+            version: version.min(VERSION),
+            types: if types.len() <= 3 { Some(types) } else { None },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn atoms() -> Atoms {
+        let mut atoms = [0; 13];
+        for (i, atom) in atoms.iter_mut().enumerate() {
+            *atom = 100 + i as Atom;
+        }
+        Atoms::new(atoms)
+    }
+
+    #[test]
+    fn decode_messages() {
+        let atoms = atoms();
+
+        let enter = Message::decode(&atoms, atoms.enter, [7, 5 << 24, atoms.uri_list, 42, 0]);
+        assert_eq!(enter, Some(Message::Enter {
+            source: 7,
+            version: 5,
+            types: Some(vec![atoms.uri_list, 42]),
+        }));
+
+        let enter = Message::decode(&atoms, atoms.enter, [7, 5 << 24 | 1, 1, 2, 3]);
+        assert_eq!(enter, Some(Message::Enter { source: 7, version: 5, types: None }));
+
+        let position = Message::decode(&atoms, atoms.position, [7, 0, 300 << 16 | 40, 99, atoms.action_copy]);
+        assert_eq!(position, Some(Message::Position { source: 7, x: 300, y: 40, time: 99, action: atoms.action_copy }));
+
+        let position = Message::decode(&atoms, atoms.position, [7, 0, 0xfffe_0001, 0, 0]);
+        assert_eq!(position, Some(Message::Position { source: 7, x: -2, y: 1, time: 0, action: 0 }));
+
+        assert_eq!(Message::decode(&atoms, atoms.leave, [7, 0, 0, 0, 0]), Some(Message::Leave { source: 7 }));
+        assert_eq!(Message::decode(&atoms, atoms.drop, [7, 0, 5, 0, 0]), Some(Message::Drop { source: 7, time: 5 }));
+        assert_eq!(Message::decode(&atoms, atoms.status, [7, 0, 0, 0, 0]), None);
+    }
+
+    #[test]
+    fn encode_replies() {
+        let atoms = atoms();
+        assert_eq!(status(&atoms, 9, true), [9, 3, 0, 0, atoms.action_copy]);
+        assert_eq!(status(&atoms, 9, false), [9, 2, 0, 0, 0]);
+        assert_eq!(finished(&atoms, 9, true), [9, 1, atoms.action_copy, 0, 0]);
+        assert_eq!(finished(&atoms, 9, false), [9, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn encode_messages() {
+        let atoms = atoms();
+        let messages = [
+            Message::Enter { source: 7, version: 5, types: Some(vec![atoms.uri_list]) },
+            Message::Enter { source: 7, version: 4, types: None },
+            Message::Position { source: 7, x: -2, y: 300, time: 99, action: atoms.action_move },
+            Message::Leave { source: 7 },
+            Message::Drop { source: 7, time: 5 },
+        ];
+        for message in &messages {
+            let (ty, data) = message.encode(&atoms);
+            assert_eq!(Message::decode(&atoms, ty, data).as_ref(), Some(message));
+        }
+    }
+
+    #[test]
+    fn decode_replies() {
+        let atoms = atoms();
+        let (accept, reject) = (status(&atoms, 9, true), status(&atoms, 9, false));
+        assert_eq!(Reply::decode(&atoms, atoms.status, accept),
+                   Some(Reply::Status { target: 9, accept: true, action: atoms.action_copy }));
+        assert_eq!(Reply::decode(&atoms, atoms.status, reject),
+                   Some(Reply::Status { target: 9, accept: false, action: 0 }));
+        assert_eq!(Reply::decode(&atoms, atoms.finished, finished(&atoms, 9, true)),
+                   Some(Reply::Finished { target: 9, accepted: true, action: atoms.action_copy }));
+        assert_eq!(Reply::decode(&atoms, atoms.enter, accept), None);
+    }
+
+    #[test]
+    fn source_actions() {
+        let atoms = atoms();
+        assert_eq!(atoms.action(DragOperations::ALL), Some(atoms.action_copy));
+        assert_eq!(atoms.action(DragOperations::MOVE | DragOperations::LINK), Some(atoms.action_move));
+        assert_eq!(atoms.action(DragOperations::NONE), None);
+        assert_eq!(atoms.operation(atoms.action_link), Some(DragOperation::Link));
+        assert_eq!(atoms.operation(0), None);
+
+        let source = Source::new(vec![(1, Arc::from(&b""[..])); 4], atoms.action_copy);
+        assert_eq!(source.enter(3, 4), Message::Enter { source: 3, version: 4, types: None });
+        let source = Source::new(vec![(1, Arc::from(&b""[..]))], atoms.action_copy);
+        assert_eq!(source.enter(3, 7), Message::Enter { source: 3, version: 5, types: Some(vec![1]) });
+    }
+
+    #[test]
+    fn accepts_file_lists() {
+        let atoms = atoms();
+        assert!(Drag::new(&atoms, 1, &[5, atoms.uri_list]).accepts);
+        assert!(!Drag::new(&atoms, 1, &[5]).accepts);
+    }
+}
@@ -0,0 +1,311 @@
+//! Input methods on X11 through the XIM protocol, which IBus and Fcitx
+//! serve.
+//!
+//! Each window that allows IME gets an input context using preedit
+//! callbacks, so that the text being composed is reported to the window
+//! instead of being drawn by the input method.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    ops::Range,
+    rc::Rc,
+};
+use x11rb::{
+    protocol::{
+        xproto::{KeyPressEvent, Window as XWindow},
+        Event,
+    },
+    rust_connection::RustConnection,
+};
+use xim::{
+    x11rb::X11rbClient,
+    AHashMap,
+    AttributeName,
+    CaretDirection,
+    CaretStyle,
+    Client,
+    ClientError,
+    ClientHandler,
+    Feedback,
+    ForwardEventFlag,
+    InputStyle,
+    Point,
+    PreeditDrawStatus,
+};
+use super::ime;
+
+/// An input method event for a window.
+#[derive(Debug)]
+pub enum ImeEvent {
+    Preedit(String, Option<Range<usize>>),
+    Commit(String),
+    /// A key event that the input method did not handle.
+    Key(KeyPressEvent),
+}
+
+/// A connection to the input method named by `XMODIFIERS`.
+pub struct Xim {
+    client: X11rbClient<Rc<RustConnection>>,
+    handler: Handler,
+}
+
+impl Xim {
+    pub fn connect(conn: Rc<RustConnection>, screen: usize) -> Option<Self> {
+        let client = X11rbClient::init(conn, screen, None).ok()?;
+        Some(Xim { client, handler: Handler::default() })
+    }
+
+    /// Handles `event` if it belongs to the protocol, returning whether it
+    /// did.
+    pub fn filter_event(&mut self, event: &Event) -> bool {
+        match self.client.filter_event(event, &mut self.handler) {
+            Ok(filtered) => filtered,
+            // The input method is unusable after protocol errors, so key
+            // presses are handled without it.
+            Err(_) => {
+                self.handler.reset();
+                true
+            },
+        }
+    }
+
+    /// Returns the events received since the last call.
+    pub fn take_events(&mut self) -> Vec<(XWindow, ImeEvent)> {
+        std::mem::take(&mut self.handler.events)
+    }
+
+    /// Creates an input context for `window` unless it has one, with the
+    /// candidate window placed at `spot`.
+    pub fn add_window(&mut self, window: XWindow, spot: (i16, i16)) {
+        self.handler.spots.insert(window, spot);
+        if !self.handler.contexts.contains_key(&window) && !self.handler.queue.contains(&window) {
+            self.handler.queue.push_back(window);
+            let _ = self.handler.create_next(&mut self.client);
+        }
+    }
+
+    /// Sets where the candidate window of `window` is placed.
+    pub fn set_spot(&mut self, window: XWindow, spot: (i16, i16)) {
+        self.handler.spots.insert(window, spot);
+        if let (Some(im), Some(&ic)) = (self.handler.im, self.handler.contexts.get(&window)) {
+            let attributes = self.client.build_ic_attributes()
+                .nested_list(AttributeName::PreeditAttributes, |b| {
+                    b.push(AttributeName::SpotLocation, Point { x: spot.0, y: spot.1 });
+                })
+                .build();
+            let _ = self.client.set_ic_values(im, ic, attributes);
+        }
+    }
+
+    /// Sets whether `window` has the keyboard focus and allows IME.
+    pub fn set_focus(&mut self, window: XWindow, focused: bool) {
+        if focused {
+            self.handler.focus = Some(window);
+        } else if self.handler.focus == Some(window) {
+            self.handler.focus = None;
+        } else {
+            return;
+        }
+        if let (Some(im), Some(&ic)) = (self.handler.im, self.handler.contexts.get(&window)) {
+            let _ = if focused {
+                self.client.set_focus(im, ic)
+            } else {
+                self.client.unset_focus(im, ic)
+            };
+        }
+    }
+
+    /// Passes a key event to the input method, returning `false` if
+    /// `window` has no input context to handle it.
+    pub fn forward_key(&mut self, window: XWindow, event: &KeyPressEvent) -> bool {
+        match (self.handler.im, self.handler.contexts.get(&window)) {
+            (Some(im), Some(&ic)) => self.client.forward_event(im, ic, ForwardEventFlag::empty(), event).is_ok(),
+            _ => false,
+        }
+    }
+}
+
+#[derive(Default)]
+struct Handler {
+    /// The opened input method.
+    im: Option<u16>,
+    /// Input contexts by window.
+    contexts: HashMap<XWindow, u16>,
+    /// Windows waiting for an input context. Contexts are created one at a
+    /// time, since replies do not name the window.
+    queue: VecDeque<XWindow>,
+    creating: bool,
+    spots: HashMap<XWindow, (i16, i16)>,
+    focus: Option<XWindow>,
+    /// The text being composed in each input context.
+    preedits: HashMap<u16, String>,
+    events: Vec<(XWindow, ImeEvent)>,
+}
+
+impl Handler {
+    fn reset(&mut self) {
+        let windows: Vec<XWindow> = self.contexts.keys().cloned().collect();
+        for window in windows {
+            self.events.push((window, ImeEvent::Preedit(String::new(), None)));
+        }
+        self.im = None;
+        self.contexts.clear();
+        self.queue.clear();
+        self.creating = false;
+        self.preedits.clear();
+    }
+
+    fn window(&self, ic: u16) -> Option<XWindow> {
+        self.contexts.iter().find(|&(_, &c)| c == ic).map(|(&window, _)| window)
+    }
+
+    fn create_next<C: Client>(&mut self, client: &mut C) -> Result<(), ClientError> {
+        let (im, &window) = match (self.im, self.queue.front()) {
+            (Some(im), Some(window)) if !self.creating => (im, window),
+            _ => return Ok(()),
+        };
+        self.creating = true;
+        let (x, y) = self.spots.get(&window).cloned().unwrap_or_default();
+        let attributes = client.build_ic_attributes()
+            .push(AttributeName::InputStyle, InputStyle::PREEDIT_CALLBACKS | InputStyle::STATUS_NOTHING)
+            .push(AttributeName::ClientWindow, window)
+            .push(AttributeName::FocusWindow, window)
+            .nested_list(AttributeName::PreeditAttributes, |b| {
+                b.push(AttributeName::SpotLocation, Point { x, y });
+            })
+            .build();
+        client.create_ic(im, attributes)
+    }
+
+    fn push_preedit(&mut self, ic: u16, caret: i32) {
+        if let Some(window) = self.window(ic) {
+            let text = self.preedits.get(&ic).cloned().unwrap_or_default();
+            let cursor = ime::caret_cursor(&text, caret);
+            self.events.push((window, ImeEvent::Preedit(text, cursor)));
+        }
+    }
+}
+
+impl<C: Client<XEvent = KeyPressEvent>> ClientHandler<C> for Handler {
+    fn handle_connect(&mut self, client: &mut C) -> Result<(), ClientError> {
+        client.open(&ime::locale())
+    }
+
+    fn handle_open(&mut self, client: &mut C, input_method_id: u16) -> Result<(), ClientError> {
+        self.im = Some(input_method_id);
+        client.get_im_values(input_method_id, &[AttributeName::QueryInputStyle])
+    }
+
+    fn handle_get_im_values(
+        &mut self,
+        client: &mut C,
+        _input_method_id: u16,
+        _attributes: AHashMap<AttributeName, Vec<u8>>,
+    ) -> Result<(), ClientError> {
+        self.create_next(client)
+    }
+
+    fn handle_create_ic(
+        &mut self,
+        client: &mut C,
+        input_method_id: u16,
+        input_context_id: u16,
+    ) -> Result<(), ClientError> {
+        self.creating = false;
+        if let Some(window) = self.queue.pop_front() {
+            self.contexts.insert(window, input_context_id);
+            if self.focus == Some(window) {
+                client.set_focus(input_method_id, input_context_id)?;
+            }
+        }
+        self.create_next(client)
+    }
+
+    fn handle_destroy_ic(
+        &mut self,
+        _client: &mut C,
+        _input_method_id: u16,
+        input_context_id: u16,
+    ) -> Result<(), ClientError> {
+        self.contexts.retain(|_, &mut ic| ic != input_context_id);
+        self.preedits.remove(&input_context_id);
+        Ok(())
+    }
+
+    fn handle_disconnect(&mut self) {
+        self.reset();
+    }
+
+    fn handle_commit(
+        &mut self,
+        _client: &mut C,
+        _input_method_id: u16,
+        input_context_id: u16,
+        text: &str,
+    ) -> Result<(), ClientError> {
+        if let Some(window) = self.window(input_context_id) {
+            self.events.push((window, ImeEvent::Commit(text.to_owned())));
+        }
+        Ok(())
+    }
+
+    fn handle_forward_event(
+        &mut self,
+        _client: &mut C,
+        _input_method_id: u16,
+        input_context_id: u16,
+        _flag: ForwardEventFlag,
+        xev: KeyPressEvent,
+    ) -> Result<(), ClientError> {
+        if let Some(window) = self.window(input_context_id) {
+            self.events.push((window, ImeEvent::Key(xev)));
+        }
+        Ok(())
+    }
+
+    fn handle_preedit_draw(
+        &mut self,
+        _client: &mut C,
+        _input_method_id: u16,
+        input_context_id: u16,
+        caret: i32,
+        chg_first: i32,
+        chg_len: i32,
+        _status: PreeditDrawStatus,
+        preedit_string: &str,
+        _feedbacks: Vec<Feedback>,
+    ) -> Result<(), ClientError> {
+        let preedit = self.preedits.entry(input_context_id).or_default();
+        ime::replace_chars(preedit, chg_first.max(0) as usize, chg_len.max(0) as usize, preedit_string);
+        self.push_preedit(input_context_id, caret);
+        Ok(())
+    }
+
+    fn handle_preedit_caret(
+        &mut self,
+        _client: &mut C,
+        _input_method_id: u16,
+        input_context_id: u16,
+        position: &mut i32,
+        direction: CaretDirection,
+        _style: CaretStyle,
+    ) -> Result<(), ClientError> {
+        if let CaretDirection::AbsolutePosition = direction {
+            self.push_preedit(input_context_id, *position);
+        }
+        Ok(())
+    }
+
+    fn handle_preedit_done(
+        &mut self,
+        _client: &mut C,
+        _input_method_id: u16,
+        input_context_id: u16,
+    ) -> Result<(), ClientError> {
+        self.preedits.remove(&input_context_id);
+        if let Some(window) = self.window(input_context_id) {
+            self.events.push((window, ImeEvent::Preedit(String::new(), None)));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,721 @@
+use std::{
+    cell::{Cell, RefCell},
+    collections::{BTreeMap, VecDeque},
+    ffi::{c_void, CStr, OsStr},
+    os::unix::ffi::OsStrExt,
+    path::PathBuf,
+    sync::Once,
+};
+use cocoa::{
+    base::{id, nil, BOOL, NO, YES},
+    foundation::{
+        NSArray,
+        NSInteger,
+        NSPoint,
+        NSRect,
+        NSString,
+        NSUInteger,
+    },
+};
+use objc::{
+    declare::ClassDecl,
+    rc::StrongPtr,
+    runtime::{Class, Object, Sel},
+};
+use crate::{
+    dpi::LogicalPosition,
+    event::WindowEvent,
+    state::WindowStateStore,
+    WindowId,
+};
+use super::{
+    drag::{
+        self,
+        NS_DRAG_OPERATION_COPY,
+        NS_DRAG_OPERATION_NONE,
+    },
+    input::InputState,
+    view::{self, ImeState},
+};
+
+pub type EventHandler = Box<dyn FnMut(&crate::Window, WindowEvent)>;
+
+const STATE_IVAR: &str = "zuiState";
+
+const NS_FILENAMES_PBOARD_TYPE: &str = "NSFilenamesPboardType";
+
+// How long the window must stay still before a move or resize is saved, in
+// seconds.
+const AUTOSAVE_DELAY: f64 = 0.5;
+
+const NS_APPLICATION_WILL_TERMINATE_NOTIFICATION: &str = "NSApplicationWillTerminateNotification";
+
+const OBJC_ASSOCIATION_RETAIN_NONATOMIC: usize = 1;
+
+// Only the address is used as the key for associating the delegate.
+static DELEGATE_KEY: u8 = 0;
+
+thread_local! {
+    // Open windows, which are not retained so that they may still close.
+    static WINDOWS: RefCell<BTreeMap<WindowId, id>> = RefCell::new(BTreeMap::new());
+}
+
+extern "C" {
+    fn objc_getAssociatedObject(object: id, key: *const c_void) -> id;
+    fn objc_setAssociatedObject(object: id, key: *const c_void, value: id, policy: usize);
+}
+
+/// State shared between a window and its delegate.
+pub struct WindowState {
+    // Not retained, since the window owns its delegate.
+    ns_window: id,
+    // The delegate that the window had before ours, such as one set by the
+    // host application, which messages are forwarded to. Not retained, like
+    // any other window delegate.
+    host_delegate: id,
+    pub id: WindowId,
+    event_handler: RefCell<Option<EventHandler>>,
+    // Events emitted while the handler runs, which are passed to it once it
+    // returns.
+    pending_events: RefCell<VecDeque<WindowEvent>>,
+    emitting: Cell<bool>,
+    drag_operations: Cell<NSUInteger>,
+    // Where files being dragged over the window were last reported, or
+    // `None` if no files are being dragged over it.
+    hover_position: Cell<Option<LogicalPosition>>,
+    autosave: RefCell<Option<(WindowStateStore, String)>>,
+    // `NSWindow` has no notification for zooming, so this is compared
+    // against after each resize.
+    zoomed: Cell<bool>,
+    // The frame that the window returns to once it is neither zoomed nor
+    // fullscreen, since `NSWindow` doesn't expose it.
+    windowed_frame: Cell<NSRect>,
+    pub ime: ImeState,
+    pub input: InputState,
+}
+
+impl WindowState {
+    #[inline]
+    pub fn set_event_handler(&self, handler: EventHandler) {
+        *self.event_handler.borrow_mut() = Some(handler);
+    }
+
+    /// Sets the operations allowed for a drag started from the window.
+    #[inline]
+    pub fn set_drag_operations(&self, mask: NSUInteger) {
+        self.drag_operations.set(mask);
+    }
+
+    /// Saves the window's state in `store` under `name` once it stops moving
+    /// or resizing, and when it closes or the application terminates.
+    #[inline]
+    pub fn set_autosave(&self, store: WindowStateStore, name: String) {
+        *self.autosave.borrow_mut() = Some((store, name));
+    }
+
+    /// Returns the frame that the window has when it is neither zoomed nor
+    /// fullscreen.
+    #[inline]
+    pub fn windowed_frame(&self) -> NSRect {
+        self.windowed_frame.get()
+    }
+
+    /// Records the frame of the window, unless it is zoomed, fullscreen or
+    /// minimized.
+    unsafe fn update_windowed_frame(&self) {
+        let ns_window = self.ns_window;
+        let mask: NSUInteger = msg_send![ns_window, styleMask];
+        let miniaturized: BOOL = msg_send![ns_window, isMiniaturized];
+        let windowed = !is_zoomed(ns_window)
+            && mask & super::NS_FULL_SCREEN_WINDOW_MASK == 0
+            && miniaturized == NO;
+        if windowed {
+            self.windowed_frame.set(msg_send![ns_window, frame]);
+        }
+    }
+
+    /// Saves the window's state, if it has an autosave name.
+    fn autosave(&self) {
+        let autosave = self.autosave.borrow().clone();
+        if let Some((store, name)) = autosave {
+            // There's no one to report a failure to from a notification.
+            let _ = store.save(&name, &self.window());
+        }
+    }
+
+    fn window(&self) -> crate::Window {
+        unsafe {
+            let ns_window = StrongPtr::retain(self.ns_window);
+            crate::Window::from(super::Window { ns_window })
+        }
+    }
+
+    /// Passes `event` to the event handler, if any.
+    ///
+    /// The handler is taken out for the duration of the call so that it may
+    /// replace itself. Events emitted by the handler itself, such as by
+    /// resizing the window, are queued until it returns.
+    pub fn emit(&self, event: WindowEvent) {
+        self.pending_events.borrow_mut().push_back(event);
+        if self.emitting.replace(true) {
+            return;
+        }
+
+        loop {
+            let event = self.pending_events.borrow_mut().pop_front();
+            let event = match event {
+                Some(event) => event,
+                None => break,
+            };
+
+            let handler = self.event_handler.borrow_mut().take();
+            if let Some(mut handler) = handler {
+                handler(&self.window(), event);
+
+                let mut slot = self.event_handler.borrow_mut();
+                if slot.is_none() {
+                    *slot = Some(handler);
+                }
+            }
+        }
+
+        self.emitting.set(false);
+    }
+}
+
+/// Returns the open window identified by `id`, if any.
+pub fn window(id: WindowId) -> Option<super::Window> {
+    WINDOWS.with(|windows| {
+        let ns_window = *windows.borrow().get(&id)?;
+        let ns_window = unsafe { StrongPtr::retain(ns_window) };
+        Some(super::Window { ns_window })
+    })
+}
+
+/// Returns all open windows, ordered by ID.
+pub fn windows() -> Vec<super::Window> {
+    WINDOWS.with(|windows| {
+        windows.borrow()
+            .values()
+            .map(|&ns_window| {
+                let ns_window = unsafe { StrongPtr::retain(ns_window) };
+                super::Window { ns_window }
+            })
+            .collect()
+    })
+}
+
+fn unregister(id: WindowId) {
+    // The registry may already be destroyed if this thread is exiting.
+    let _ = WINDOWS.try_with(|windows| windows.borrow_mut().remove(&id));
+}
+
+/// Returns the state for `ns_window`, installing a delegate if it does not yet
+/// have one of ours.
+///
+/// The returned reference is valid for as long as `ns_window` is.
+#[inline]
+pub unsafe fn window_state<'a>(ns_window: id) -> &'a WindowState {
+    state(&*delegate(ns_window))
+}
+
+/// Returns our delegate for `ns_window`, installing it if needed.
+///
+/// A delegate that the window already has keeps receiving the messages that
+/// ours doesn't handle, along with every notification.
+pub unsafe fn delegate(ns_window: id) -> id {
+    let key = &DELEGATE_KEY as *const u8 as *const c_void;
+    let mut delegate = objc_getAssociatedObject(ns_window, key);
+
+    if delegate == nil {
+        let id = WindowId::next();
+        let state = Box::new(WindowState {
+            ns_window,
+            host_delegate: msg_send![ns_window, delegate],
+            id,
+            event_handler: RefCell::new(None),
+            pending_events: RefCell::new(VecDeque::new()),
+            emitting: Cell::new(false),
+            drag_operations: Cell::new(NS_DRAG_OPERATION_NONE),
+            hover_position: Cell::new(None),
+            autosave: RefCell::new(None),
+            zoomed: Cell::new(is_zoomed(ns_window)),
+            windowed_frame: Cell::new(msg_send![ns_window, frame]),
+            ime: Default::default(),
+            input: Default::default(),
+        });
+
+        delegate = msg_send![delegate_class(), alloc];
+        delegate = msg_send![delegate, init];
+        (*delegate).set_ivar(STATE_IVAR, Box::into_raw(state) as *mut c_void);
+
+        // `NSWindow` does not retain its delegate, so the association keeps
+        // it alive for as long as the window.
+        objc_setAssociatedObject(ns_window, key, delegate, OBJC_ASSOCIATION_RETAIN_NONATOMIC);
+        msg_send![delegate, release];
+        msg_send![ns_window, setDelegate:delegate];
+        WINDOWS.with(|windows| windows.borrow_mut().insert(id, ns_window));
+
+        let ty = StrongPtr::new(NSString::alloc(nil).init_str(NS_FILENAMES_PBOARD_TYPE));
+        let types = NSArray::arrayWithObject(nil, *ty);
+        msg_send![ns_window, registerForDraggedTypes:types];
+
+        // Windows aren't closed when the application terminates, so their
+        // state is also saved then.
+        let name = StrongPtr::new(
+            NSString::alloc(nil).init_str(NS_APPLICATION_WILL_TERMINATE_NOTIFICATION),
+        );
+        let center: id = msg_send![class!(NSNotificationCenter), defaultCenter];
+        msg_send![center, addObserver:delegate
+                             selector:sel!(zuiApplicationWillTerminate:)
+                                 name:*name
+                               object:nil];
+    }
+
+    delegate
+}
+
+unsafe fn state<'a>(delegate: &Object) -> &'a WindowState {
+    let state: *mut c_void = *delegate.get_ivar(STATE_IVAR);
+    &*(state as *const WindowState)
+}
+
+fn delegate_class() -> &'static Class {
+    static REGISTER: Once = Once::new();
+
+    REGISTER.call_once(|| {
+        let superclass = class!(NSObject);
+        let mut decl = ClassDecl::new("ZUIWindowDelegate", superclass).unwrap();
+        decl.add_ivar::<*mut c_void>(STATE_IVAR);
+        unsafe {
+            decl.add_method(
+                sel!(dealloc),
+                dealloc as extern "C" fn(&Object, Sel),
+            );
+            decl.add_method(
+                sel!(respondsToSelector:),
+                responds_to_selector as extern "C" fn(&Object, Sel, Sel) -> BOOL,
+            );
+            decl.add_method(
+                sel!(forwardingTargetForSelector:),
+                forwarding_target as extern "C" fn(&Object, Sel, Sel) -> id,
+            );
+            decl.add_method(
+                sel!(windowWillClose:),
+                window_will_close as extern "C" fn(&Object, Sel, id),
+            );
+            decl.add_method(
+                sel!(windowDidBecomeKey:),
+                window_did_become_key as extern "C" fn(&Object, Sel, id),
+            );
+            decl.add_method(
+                sel!(windowDidResignKey:),
+                window_did_resign_key as extern "C" fn(&Object, Sel, id),
+            );
+            decl.add_method(
+                sel!(windowDidMiniaturize:),
+                window_did_miniaturize as extern "C" fn(&Object, Sel, id),
+            );
+            decl.add_method(
+                sel!(windowDidDeminiaturize:),
+                window_did_deminiaturize as extern "C" fn(&Object, Sel, id),
+            );
+            decl.add_method(
+                sel!(windowDidResize:),
+                window_did_resize as extern "C" fn(&Object, Sel, id),
+            );
+            decl.add_method(
+                sel!(zuiApplicationWillTerminate:),
+                application_will_terminate as extern "C" fn(&Object, Sel, id),
+            );
+            decl.add_method(
+                sel!(zuiAutosave:),
+                autosave as extern "C" fn(&Object, Sel, id),
+            );
+            decl.add_method(
+                sel!(windowDidEndLiveResize:),
+                window_did_end_live_resize as extern "C" fn(&Object, Sel, id),
+            );
+            decl.add_method(
+                sel!(windowDidMove:),
+                window_did_move as extern "C" fn(&Object, Sel, id),
+            );
+            decl.add_method(
+                sel!(windowWillEnterFullScreen:),
+                window_will_enter_full_screen as extern "C" fn(&Object, Sel, id),
+            );
+            decl.add_method(
+                sel!(windowDidEnterFullScreen:),
+                window_did_enter_full_screen as extern "C" fn(&Object, Sel, id),
+            );
+            decl.add_method(
+                sel!(windowDidExitFullScreen:),
+                window_did_exit_full_screen as extern "C" fn(&Object, Sel, id),
+            );
+            decl.add_method(
+                sel!(draggingEntered:),
+                dragging_entered as extern "C" fn(&Object, Sel, id) -> NSUInteger,
+            );
+            decl.add_method(
+                sel!(draggingUpdated:),
+                dragging_updated as extern "C" fn(&Object, Sel, id) -> NSUInteger,
+            );
+            decl.add_method(
+                sel!(draggingExited:),
+                dragging_exited as extern "C" fn(&Object, Sel, id),
+            );
+            decl.add_method(
+                sel!(prepareForDragOperation:),
+                prepare_for_drag_operation as extern "C" fn(&Object, Sel, id) -> BOOL,
+            );
+            decl.add_method(
+                sel!(performDragOperation:),
+                perform_drag_operation as extern "C" fn(&Object, Sel, id) -> BOOL,
+            );
+            decl.add_method(
+                sel!(draggingSession:sourceOperationMaskForDraggingContext:),
+                source_operation_mask as extern "C" fn(&Object, Sel, id, NSInteger) -> NSUInteger,
+            );
+            decl.add_method(
+                sel!(draggingSession:endedAtPoint:operation:),
+                dragging_session_ended as extern "C" fn(&Object, Sel, id, NSPoint, NSUInteger),
+            );
+        }
+        decl.register();
+    });
+
+    class!(ZUIWindowDelegate)
+}
+
+extern "C" fn dealloc(this: &Object, _: Sel) {
+    unsafe {
+        let center: id = msg_send![class!(NSNotificationCenter), defaultCenter];
+        msg_send![center, removeObserver:this];
+
+        let state: *mut c_void = *this.get_ivar(STATE_IVAR);
+        if !state.is_null() {
+            let state = Box::from_raw(state as *mut WindowState);
+            unregister(state.id);
+            state.input.remove_monitors();
+            drop(state);
+        }
+        msg_send![super(this, class!(NSObject)), dealloc];
+    }
+}
+
+/// Returns the delegate that messages are forwarded to, or `nil`.
+unsafe fn host_delegate(this: &Object) -> id {
+    let state: *mut c_void = *this.get_ivar(STATE_IVAR);
+    if state.is_null() {
+        nil
+    } else {
+        (*(state as *const WindowState)).host_delegate
+    }
+}
+
+/// Returns whether the host delegate of `this` implements `selector`.
+unsafe fn host_responds(this: &Object, selector: Sel) -> bool {
+    let host = host_delegate(this);
+    if host == nil {
+        return false;
+    }
+    let responds: BOOL = msg_send![host, respondsToSelector:selector];
+    responds != NO
+}
+
+/// Passes a notification on to the host delegate, if it handles it.
+unsafe fn forward(this: &Object, selector: Sel, notification: id) {
+    if host_responds(this, selector) {
+        let _: id = msg_send![host_delegate(this), performSelector:selector
+                                                       withObject:notification];
+    }
+}
+
+extern "C" fn responds_to_selector(this: &Object, _: Sel, selector: Sel) -> BOOL {
+    unsafe {
+        let responds: BOOL = msg_send![super(this, class!(NSObject)), respondsToSelector:selector];
+        if responds != NO || host_responds(this, selector) { YES } else { NO }
+    }
+}
+
+extern "C" fn forwarding_target(this: &Object, _: Sel, _selector: Sel) -> id {
+    unsafe { host_delegate(this) }
+}
+
+extern "C" fn window_will_close(this: &Object, sel: Sel, notification: id) {
+    let state = unsafe { state(this) };
+    unregister(state.id);
+
+    unsafe {
+        state.input.remove_monitors();
+
+        let ns_window = state.ns_window;
+        let sheet_parent: id = msg_send![ns_window, sheetParent];
+        if sheet_parent != nil {
+            msg_send![sheet_parent, endSheet:ns_window];
+        }
+        let parent: id = msg_send![ns_window, parentWindow];
+        if parent != nil {
+            msg_send![parent, removeChildWindow:ns_window];
+        }
+    }
+
+    // The state is saved one last time, since the window may not be
+    // reopened before the application terminates.
+    unsafe { autosave_now(this) };
+    state.autosave.borrow_mut().take();
+
+    unsafe { forward(this, sel, notification) };
+}
+
+extern "C" fn application_will_terminate(this: &Object, _: Sel, _notification: id) {
+    unsafe { autosave_now(this) };
+}
+
+/// Saves the window's state once it has been still for `AUTOSAVE_DELAY`,
+/// rather than on every notification of a move or resize.
+unsafe fn schedule_autosave(this: &Object) {
+    cancel_autosave(this);
+    msg_send![this, performSelector:sel!(zuiAutosave:)
+                         withObject:nil
+                         afterDelay:AUTOSAVE_DELAY];
+}
+
+unsafe fn cancel_autosave(this: &Object) {
+    msg_send![class!(NSObject), cancelPreviousPerformRequestsWithTarget:this
+                                                               selector:sel!(zuiAutosave:)
+                                                                 object:nil];
+}
+
+/// Saves the window's state now, in place of any scheduled save.
+unsafe fn autosave_now(this: &Object) {
+    cancel_autosave(this);
+    state(this).autosave();
+}
+
+extern "C" fn autosave(this: &Object, _: Sel, _: id) {
+    unsafe { state(this).autosave() };
+}
+
+unsafe fn is_zoomed(ns_window: id) -> bool {
+    let zoomed: BOOL = msg_send![ns_window, isZoomed];
+    zoomed != NO
+}
+
+extern "C" fn window_did_become_key(this: &Object, sel: Sel, notification: id) {
+    unsafe {
+        state(this).emit(WindowEvent::Focused(true));
+        forward(this, sel, notification);
+    }
+}
+
+extern "C" fn window_did_resign_key(this: &Object, sel: Sel, notification: id) {
+    unsafe {
+        state(this).emit(WindowEvent::Focused(false));
+        forward(this, sel, notification);
+    }
+}
+
+extern "C" fn window_did_miniaturize(this: &Object, sel: Sel, notification: id) {
+    unsafe {
+        state(this).emit(WindowEvent::Minimized(true));
+        forward(this, sel, notification);
+    }
+}
+
+extern "C" fn window_did_deminiaturize(this: &Object, sel: Sel, notification: id) {
+    unsafe {
+        state(this).emit(WindowEvent::Minimized(false));
+        forward(this, sel, notification);
+    }
+}
+
+extern "C" fn window_did_resize(this: &Object, sel: Sel, notification: id) {
+    unsafe {
+        let state = state(this);
+        let zoomed = is_zoomed(state.ns_window);
+        if state.zoomed.replace(zoomed) != zoomed {
+            state.emit(WindowEvent::Maximized(zoomed));
+        }
+        state.update_windowed_frame();
+
+        // Live resizes are saved once they end.
+        let live: BOOL = msg_send![state.ns_window, inLiveResize];
+        if live == NO {
+            schedule_autosave(this);
+        }
+        forward(this, sel, notification);
+    }
+}
+
+extern "C" fn window_did_end_live_resize(this: &Object, sel: Sel, notification: id) {
+    unsafe {
+        autosave_now(this);
+        forward(this, sel, notification);
+    }
+}
+
+extern "C" fn window_did_move(this: &Object, sel: Sel, notification: id) {
+    unsafe {
+        let state = state(this);
+        state.update_windowed_frame();
+        schedule_autosave(this);
+        forward(this, sel, notification);
+    }
+}
+
+extern "C" fn window_will_enter_full_screen(this: &Object, sel: Sel, notification: id) {
+    unsafe {
+        // The style mask may already be fullscreen by the time the window is
+        // first resized.
+        state(this).update_windowed_frame();
+        forward(this, sel, notification);
+    }
+}
+
+extern "C" fn window_did_enter_full_screen(this: &Object, sel: Sel, notification: id) {
+    unsafe {
+        state(this).emit(WindowEvent::Fullscreen(true));
+        forward(this, sel, notification);
+    }
+}
+
+extern "C" fn window_did_exit_full_screen(this: &Object, sel: Sel, notification: id) {
+    unsafe {
+        state(this).emit(WindowEvent::Fullscreen(false));
+        forward(this, sel, notification);
+    }
+}
+
+/// Returns the paths of the files being dragged by `dragging_info`.
+unsafe fn dragged_files(dragging_info: id) -> Vec<PathBuf> {
+    let pasteboard: id = msg_send![dragging_info, draggingPasteboard];
+    let ty = StrongPtr::new(NSString::alloc(nil).init_str(NS_FILENAMES_PBOARD_TYPE));
+    let filenames: id = msg_send![pasteboard, propertyListForType:*ty];
+    if filenames == nil {
+        return Vec::new();
+    }
+
+    let count: NSUInteger = msg_send![filenames, count];
+    (0..count)
+        .map(|i| {
+            let filename: id = msg_send![filenames, objectAtIndex:i];
+            let filename = CStr::from_ptr(filename.UTF8String());
+            PathBuf::from(OsStr::from_bytes(filename.to_bytes()))
+        })
+        .collect()
+}
+
+/// Returns the location of `dragging_info` relative to the top-left corner of
+/// the content view.
+unsafe fn dragging_position(ns_window: id, dragging_info: id) -> LogicalPosition {
+    let location: NSPoint = msg_send![dragging_info, draggingLocation];
+    let view: id = msg_send![ns_window, contentView];
+    let point: NSPoint = msg_send![view, convertPoint:location fromView:nil];
+    view::to_top_left(view, point)
+}
+
+extern "C" fn dragging_entered(this: &Object, sel: Sel, sender: id) -> NSUInteger {
+    unsafe {
+        let state = state(this);
+        let files = dragged_files(sender);
+        if files.is_empty() {
+            state.hover_position.set(None);
+            return if host_responds(this, sel) {
+                msg_send![state.host_delegate, draggingEntered:sender]
+            } else {
+                NS_DRAG_OPERATION_NONE
+            };
+        }
+
+        let position = dragging_position(state.ns_window, sender);
+        state.hover_position.set(Some(position));
+        for path in files {
+            state.emit(WindowEvent::HoveredFile { path, position });
+        }
+        NS_DRAG_OPERATION_COPY
+    }
+}
+
+extern "C" fn dragging_updated(this: &Object, sel: Sel, sender: id) -> NSUInteger {
+    unsafe {
+        let state = state(this);
+        let last = match state.hover_position.get() {
+            Some(last) => last,
+            None => {
+                return if host_responds(this, sel) {
+                    msg_send![state.host_delegate, draggingUpdated:sender]
+                } else {
+                    NS_DRAG_OPERATION_NONE
+                };
+            },
+        };
+
+        let position = dragging_position(state.ns_window, sender);
+        if position != last {
+            state.hover_position.set(Some(position));
+            for path in dragged_files(sender) {
+                state.emit(WindowEvent::HoveredFile { path, position });
+            }
+        }
+        NS_DRAG_OPERATION_COPY
+    }
+}
+
+extern "C" fn dragging_exited(this: &Object, sel: Sel, sender: id) {
+    unsafe {
+        let state = state(this);
+        if state.hover_position.replace(None).is_some() {
+            state.emit(WindowEvent::HoveredFileCancelled);
+        } else {
+            forward(this, sel, sender);
+        }
+    }
+}
+
+extern "C" fn prepare_for_drag_operation(this: &Object, sel: Sel, sender: id) -> BOOL {
+    unsafe {
+        let state = state(this);
+        if state.hover_position.get().is_some() {
+            YES
+        } else if host_responds(this, sel) {
+            msg_send![state.host_delegate, prepareForDragOperation:sender]
+        } else {
+            NO
+        }
+    }
+}
+
+extern "C" fn perform_drag_operation(this: &Object, sel: Sel, sender: id) -> BOOL {
+    unsafe {
+        let state = state(this);
+        if state.hover_position.replace(None).is_none() {
+            return if host_responds(this, sel) {
+                msg_send![state.host_delegate, performDragOperation:sender]
+            } else {
+                NO
+            };
+        }
+
+        let position = dragging_position(state.ns_window, sender);
+        for path in dragged_files(sender) {
+            state.emit(WindowEvent::DroppedFile { path, position });
+        }
+        YES
+    }
+}
+
+extern "C" fn source_operation_mask(this: &Object, _: Sel, _session: id, _context: NSInteger) -> NSUInteger {
+    unsafe { state(this).drag_operations.get() }
+}
+
+extern "C" fn dragging_session_ended(
+    this: &Object,
+    _: Sel,
+    _session: id,
+    _point: NSPoint,
+    operation: NSUInteger,
+) {
+    let operation = drag::drag_operation(operation);
+    unsafe { state(this).emit(WindowEvent::DragEnded { operation }) };
+}
@@ -0,0 +1,168 @@
+use std::ffi::c_void;
+use cocoa::{
+    appkit::NSWindow,
+    base::{id, nil, BOOL},
+    foundation::{
+        NSArray,
+        NSPoint,
+        NSRect,
+        NSSize,
+        NSString,
+        NSUInteger,
+    },
+};
+use objc::rc::StrongPtr;
+use shared::{
+    mime,
+    os::macos::{pasteboard_type, IconExt},
+    Icon,
+};
+use crate::drag::{DragData, DragOperation, DragOperations};
+use super::{delegate, Window};
+
+pub const NS_DRAG_OPERATION_NONE: NSUInteger = 0;
+pub const NS_DRAG_OPERATION_COPY: NSUInteger = 1;
+pub const NS_DRAG_OPERATION_LINK: NSUInteger = 2;
+pub const NS_DRAG_OPERATION_GENERIC: NSUInteger = 4;
+pub const NS_DRAG_OPERATION_MOVE: NSUInteger = 16;
+pub const NS_DRAG_OPERATION_DELETE: NSUInteger = 32;
+
+// `NSEventType` values from which a drag may be started.
+const MOUSE_EVENT_TYPES: &[NSUInteger] = &[
+    1,  // NSEventTypeLeftMouseDown
+    3,  // NSEventTypeRightMouseDown
+    6,  // NSEventTypeLeftMouseDragged
+    7,  // NSEventTypeRightMouseDragged
+    25, // NSEventTypeOtherMouseDown
+    27, // NSEventTypeOtherMouseDragged
+];
+
+pub fn ns_drag_operation_mask(operations: DragOperations) -> NSUInteger {
+    let mut mask = NS_DRAG_OPERATION_NONE;
+    if operations.contains(DragOperation::Copy) {
+        mask |= NS_DRAG_OPERATION_COPY | NS_DRAG_OPERATION_GENERIC;
+    }
+    if operations.contains(DragOperation::Move) {
+        mask |= NS_DRAG_OPERATION_MOVE | NS_DRAG_OPERATION_DELETE;
+    }
+    if operations.contains(DragOperation::Link) {
+        mask |= NS_DRAG_OPERATION_LINK;
+    }
+    mask
+}
+
+pub fn drag_operation(operation: NSUInteger) -> Option<DragOperation> {
+    if operation & (NS_DRAG_OPERATION_MOVE | NS_DRAG_OPERATION_DELETE) != 0 {
+        Some(DragOperation::Move)
+    } else if operation & (NS_DRAG_OPERATION_COPY | NS_DRAG_OPERATION_GENERIC) != 0 {
+        Some(DragOperation::Copy)
+    } else if operation & NS_DRAG_OPERATION_LINK != 0 {
+        Some(DragOperation::Link)
+    } else {
+        None
+    }
+}
+
+unsafe fn ns_string(s: &str) -> StrongPtr {
+    StrongPtr::new(NSString::alloc(nil).init_str(s))
+}
+
+unsafe fn set_string(item: id, string: &str, ty: &str) {
+    let string = ns_string(string);
+    let ty = ns_string(ty);
+    let _: BOOL = msg_send![item, setString:*string forType:*ty];
+}
+
+unsafe fn set_uri(item: id, uri: &str) {
+    let ty = if uri.starts_with("file://") {
+        "public.file-url"
+    } else {
+        "public.url"
+    };
+    set_string(item, uri, ty);
+}
+
+impl Window {
+    pub fn start_drag(&self, data: &DragData, icon: &Icon) -> Result<(), ()> {
+        unsafe {
+            let ns_app: id = msg_send![class!(NSApplication), sharedApplication];
+            let event: id = msg_send![ns_app, currentEvent];
+            if event == nil {
+                return Err(());
+            }
+            let event_type: NSUInteger = msg_send![event, type];
+            if !MOUSE_EVENT_TYPES.contains(&event_type) {
+                return Err(());
+            }
+
+            let mut writers = Vec::new();
+
+            // Text and custom data are carried by the first item, and each
+            // additional URI gets its own item so that file managers receive
+            // every file.
+            let first = StrongPtr::new(msg_send![class!(NSPasteboardItem), new]);
+            if let Some(text) = &data.text {
+                set_string(*first, text, pasteboard_type(mime::TEXT_PLAIN));
+            }
+            for (mime, bytes) in &data.data {
+                let ns_data: id = msg_send![
+                    class!(NSData),
+                    dataWithBytes:bytes.as_ptr() as *const c_void
+                           length:bytes.len() as NSUInteger
+                ];
+                let ty = ns_string(pasteboard_type(mime));
+                let _: BOOL = msg_send![*first, setData:ns_data forType:*ty];
+            }
+
+            let mut uris = data.uris.iter();
+            if let Some(uri) = uris.next() {
+                set_uri(*first, uri);
+            }
+            if data.text.is_some() || !data.data.is_empty() || !data.uris.is_empty() {
+                writers.push(first);
+            }
+            for uri in uris {
+                let item = StrongPtr::new(msg_send![class!(NSPasteboardItem), new]);
+                set_uri(*item, uri);
+                writers.push(item);
+            }
+
+            if writers.is_empty() {
+                return Err(());
+            }
+
+            let view = self.ns_window.contentView();
+            let location: NSPoint = msg_send![event, locationInWindow];
+            let location: NSPoint = msg_send![view, convertPoint:location fromView:nil];
+
+            let ns_image = **icon.as_ns_image();
+            let size: NSSize = msg_send![ns_image, size];
+            let frame = NSRect::new(
+                NSPoint::new(
+                    location.x - size.width / 2.0,
+                    location.y - size.height / 2.0,
+                ),
+                size,
+            );
+
+            let items: Vec<StrongPtr> = writers.iter()
+                .map(|writer| {
+                    let item: id = msg_send![class!(NSDraggingItem), alloc];
+                    let item: id = msg_send![item, initWithPasteboardWriter:**writer];
+                    msg_send![item, setDraggingFrame:frame contents:ns_image];
+                    StrongPtr::new(item)
+                })
+                .collect();
+            let items: Vec<id> = items.iter().map(|item| **item).collect();
+            let items = NSArray::arrayWithObjects(nil, &items);
+
+            self.state().set_drag_operations(ns_drag_operation_mask(data.operations));
+
+            let source = delegate::delegate(*self.ns_window);
+            let _: id = msg_send![view, beginDraggingSessionWithItems:items
+                                                                event:event
+                                                               source:source];
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,128 @@
+use std::cell::{Cell, RefCell};
+use block::ConcreteBlock;
+use cocoa::{
+    base::{id, nil, BOOL, YES},
+    foundation::{NSPoint, NSRect, NSSize, NSUInteger},
+};
+use objc::rc::StrongPtr;
+use crate::dpi::LogicalRect;
+use super::{delegate, view, Window};
+
+const NS_MOUSE_MOVED_MASK: NSUInteger = 1 << 5;
+const NS_LEFT_MOUSE_DRAGGED_MASK: NSUInteger = 1 << 6;
+const NS_RIGHT_MOUSE_DRAGGED_MASK: NSUInteger = 1 << 7;
+const NS_OTHER_MOUSE_DRAGGED_MASK: NSUInteger = 1 << 27;
+
+/// Which parts of a window receive pointer input.
+pub struct InputState {
+    hittest: Cell<bool>,
+    region: RefCell<Option<Vec<LogicalRect>>>,
+    // Event monitors that track the pointer while a region is set.
+    monitors: RefCell<Vec<StrongPtr>>,
+}
+
+impl Default for InputState {
+    #[inline]
+    fn default() -> Self {
+        InputState {
+            hittest: Cell::new(true),
+            region: RefCell::new(None),
+            monitors: RefCell::new(Vec::new()),
+        }
+    }
+}
+
+impl InputState {
+    /// Stops tracking the pointer.
+    pub unsafe fn remove_monitors(&self) {
+        for monitor in self.monitors.borrow_mut().drain(..) {
+            msg_send![class!(NSEvent), removeMonitor:*monitor];
+        }
+    }
+}
+
+impl Window {
+    pub fn set_opacity(&self, opacity: f64) {
+        let opacity = opacity.max(0.0).min(1.0);
+        unsafe { msg_send![*self.ns_window, setAlphaValue:opacity] };
+    }
+
+    pub fn set_cursor_hittest(&self, hittest: bool) {
+        self.state().input.hittest.set(hittest);
+        unsafe { update_ignores_mouse_events(*self.ns_window) };
+    }
+
+    pub fn set_input_region(&self, region: Option<Vec<LogicalRect>>) {
+        let input = &self.state().input;
+        let tracking = region.is_some();
+        *input.region.borrow_mut() = region;
+
+        unsafe {
+            if !tracking {
+                input.remove_monitors();
+            } else if input.monitors.borrow().is_empty() {
+                let monitors = add_monitors(*self.ns_window);
+                *input.monitors.borrow_mut() = monitors;
+            }
+            update_ignores_mouse_events(*self.ns_window);
+        }
+    }
+}
+
+/// Watches pointer movement both within and outside of the application,
+/// since the window no longer receives events while ignoring them.
+unsafe fn add_monitors(ns_window: id) -> Vec<StrongPtr> {
+    let mask = NS_MOUSE_MOVED_MASK
+        | NS_LEFT_MOUSE_DRAGGED_MASK
+        | NS_RIGHT_MOUSE_DRAGGED_MASK
+        | NS_OTHER_MOUSE_DRAGGED_MASK;
+
+    // Local monitors only see moves if the window asks for them.
+    msg_send![ns_window, setAcceptsMouseMovedEvents:YES];
+
+    // The monitors are removed before the window is deallocated, so it is
+    // safe for the handlers to refer to it without retaining it.
+    let local = ConcreteBlock::new(move |event: id| -> id {
+        update_ignores_mouse_events(ns_window);
+        event
+    }).copy();
+    let local: *const _ = &*local;
+
+    let global = ConcreteBlock::new(move |_event: id| {
+        update_ignores_mouse_events(ns_window);
+    }).copy();
+    let global: *const _ = &*global;
+
+    let local: id = msg_send![class!(NSEvent), addLocalMonitorForEventsMatchingMask:mask
+                                                                            handler:local];
+    let global: id = msg_send![class!(NSEvent), addGlobalMonitorForEventsMatchingMask:mask
+                                                                             handler:global];
+
+    vec![StrongPtr::retain(local), StrongPtr::retain(global)]
+}
+
+/// Makes the window ignore the pointer if hit testing is disabled or if the
+/// pointer is outside of the input region.
+unsafe fn update_ignores_mouse_events(ns_window: id) {
+    let input = &delegate::window_state(ns_window).input;
+
+    let ignores = !input.hittest.get() || match &*input.region.borrow() {
+        None => false,
+        Some(region) => {
+            let location: NSPoint = msg_send![class!(NSEvent), mouseLocation];
+            let rect = NSRect::new(location, NSSize::new(0.0, 0.0));
+            let rect: NSRect = msg_send![ns_window, convertRectFromScreen:rect];
+
+            let view: id = msg_send![ns_window, contentView];
+            if view == nil {
+                return;
+            }
+            let point: NSPoint = msg_send![view, convertPoint:rect.origin fromView:nil];
+            let point = view::to_top_left(view, point);
+
+            !region.iter().any(|rect| rect.contains(point))
+        },
+    };
+
+    msg_send![ns_window, setIgnoresMouseEvents:ignores as BOOL];
+}
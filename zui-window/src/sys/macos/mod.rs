@@ -0,0 +1,569 @@
+use std::{
+    env,
+    ffi::CStr,
+    fmt,
+    hash::{Hash, Hasher},
+    path::PathBuf,
+};
+use cocoa::{
+    appkit::{
+        self,
+        NSWindow,
+        NSWindowStyleMask,
+    },
+    base::{id, nil, BOOL, NO, YES},
+    foundation::{
+        NSInteger,
+        NSPoint,
+        NSRect,
+        NSSize,
+        NSString,
+        NSUInteger,
+    },
+};
+use menu::{
+    os::macos::MenuExt,
+    Menu,
+};
+use objc::rc::StrongPtr;
+use shared::{
+    os::macos::ZedStringExt,
+    ZedString,
+};
+use crate::{
+    dpi::{LogicalInsets, LogicalPosition, LogicalRect, LogicalSize, ScaleFactor},
+    os::macos::WindowExt,
+    CoordinateSpace,
+    UserAttentionType,
+    WindowKind,
+};
+
+mod delegate;
+mod drag;
+mod input;
+mod view;
+
+pub use delegate::{window, windows, EventHandler};
+
+const NS_UTILITY_WINDOW_MASK: NSUInteger = 1 << 4;
+const NS_NONACTIVATING_PANEL_MASK: NSUInteger = 1 << 7;
+const NS_FULL_SCREEN_WINDOW_MASK: NSUInteger = 1 << 14;
+
+const NS_FLOATING_WINDOW_LEVEL: NSInteger = 3;
+const NS_POP_UP_MENU_WINDOW_LEVEL: NSInteger = 101;
+// `kCGHelpWindowLevel`, used by AppKit for tooltips.
+const NS_HELP_WINDOW_LEVEL: NSInteger = 200;
+
+const NS_WINDOW_ABOVE: NSInteger = 1;
+
+const NS_CRITICAL_REQUEST: NSUInteger = 0;
+const NS_INFORMATIONAL_REQUEST: NSUInteger = 10;
+
+pub fn is_main_thread() -> bool {
+    unsafe { msg_send![class!(NSThread), isMainThread] }
+}
+
+/// Returns `~/Library/Application Support`.
+pub fn config_dir() -> Option<PathBuf> {
+    let home = env::var_os("HOME")?;
+    Some(PathBuf::from(home).join("Library").join("Application Support"))
+}
+
+/// The visible area of a screen, excluding the menu bar and Dock.
+pub struct Monitor {
+    pub id: String,
+    pub rect: LogicalRect,
+}
+
+/// Returns the visible area of every connected screen, with the primary
+/// screen first.
+pub fn monitors() -> Vec<Monitor> {
+    unsafe {
+        let screens: id = msg_send![class!(NSScreen), screens];
+        let count: NSUInteger = msg_send![screens, count];
+        (0..count)
+            .map(|i| {
+                let screen: id = msg_send![screens, objectAtIndex:i];
+                let frame: NSRect = msg_send![screen, visibleFrame];
+                Monitor {
+                    id: screen_id(screen),
+                    rect: LogicalRect::new(screen_top_left(frame), frame.size.into()),
+                }
+            })
+            .collect()
+    }
+}
+
+/// Returns the display ID of `screen` as a string.
+unsafe fn screen_id(screen: id) -> String {
+    let key = StrongPtr::new(NSString::alloc(nil).init_str("NSScreenNumber"));
+    let description: id = msg_send![screen, deviceDescription];
+    let number: id = msg_send![description, objectForKey:*key];
+    let number: id = msg_send![number, stringValue];
+    CStr::from_ptr(number.UTF8String()).to_string_lossy().into_owned()
+}
+
+/// Returns the height of the primary screen, which screen coordinates are
+/// flipped against.
+unsafe fn primary_screen_height() -> f64 {
+    let screens: id = msg_send![class!(NSScreen), screens];
+    let count: NSUInteger = msg_send![screens, count];
+    if count == 0 {
+        return 0.0;
+    }
+    let screen: id = msg_send![screens, objectAtIndex:0 as NSUInteger];
+    let frame: NSRect = msg_send![screen, frame];
+    frame.size.height
+}
+
+/// Returns the scale factor of the screen with the key window, which new
+/// windows are placed on.
+unsafe fn main_screen_scale_factor() -> ScaleFactor {
+    let screen: id = msg_send![class!(NSScreen), mainScreen];
+    if screen == nil {
+        return ScaleFactor::ONE;
+    }
+    let scale_factor: f64 = msg_send![screen, backingScaleFactor];
+    ScaleFactor::new(scale_factor).unwrap_or_default()
+}
+
+/// Converts the bottom-left origin of `rect` in screen coordinates to the
+/// top-left corner relative to the top of the primary screen.
+unsafe fn screen_top_left(rect: NSRect) -> LogicalPosition {
+    let y = primary_screen_height() - (rect.origin.y + rect.size.height);
+    LogicalPosition::new(rect.origin.x, y)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ViewHandle {
+    pub ns_view: id,
+}
+
+#[derive(Clone)]
+pub struct Window {
+    pub ns_window: StrongPtr,
+}
+
+impl fmt::Debug for Window {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Window")
+            .field("ns_window", &*self.ns_window) // No `Debug` for `StrongPtr`
+            .finish()
+    }
+}
+
+// Windows are compared by identity, which unlike `id` doesn't need a
+// delegate to be installed.
+impl PartialEq for Window {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        *self.ns_window == *other.ns_window
+    }
+}
+
+impl Eq for Window {}
+
+impl Hash for Window {
+    #[inline]
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        (*self.ns_window as usize).hash(state);
+    }
+}
+
+impl Window {
+    #[inline]
+    pub fn id(&self) -> crate::WindowId {
+        self.state().id
+    }
+
+    #[inline]
+    pub fn set_title(&self, title: ZedString) {
+        unsafe { self.ns_window.setTitle_(**title.as_ns_string()) };
+    }
+
+    #[inline]
+    pub fn scale_factor(&self) -> ScaleFactor {
+        let scale_factor: f64 = unsafe { msg_send![*self.ns_window, backingScaleFactor] };
+        ScaleFactor::new(scale_factor).unwrap_or_default()
+    }
+
+    pub fn inner_size(&self) -> LogicalSize {
+        unsafe {
+            let frame = NSWindow::frame(*self.ns_window);
+            let rect: NSRect = msg_send![*self.ns_window, contentRectForFrameRect:frame];
+            rect.size.into()
+        }
+    }
+
+    #[inline]
+    pub fn set_inner_size(&self, size: LogicalSize) {
+        let size = NSSize::from(size);
+        unsafe { msg_send![*self.ns_window, setContentSize:size] };
+    }
+
+    #[inline]
+    pub fn outer_position(&self) -> LogicalPosition {
+        unsafe { screen_top_left(NSWindow::frame(*self.ns_window)) }
+    }
+
+    pub fn set_outer_position(&self, position: LogicalPosition) {
+        unsafe {
+            let point = NSPoint::new(position.x, primary_screen_height() - position.y);
+            msg_send![*self.ns_window, setFrameTopLeftPoint:point];
+        }
+    }
+
+    /// Returns the position and size of the frame that `self` has when it is
+    /// neither maximized nor fullscreen.
+    pub fn windowed_frame(&self) -> (LogicalPosition, LogicalSize) {
+        let frame = self.state().windowed_frame();
+        unsafe { (screen_top_left(frame), frame.size.into()) }
+    }
+
+    pub fn set_outer_frame(&self, position: LogicalPosition, size: LogicalSize) {
+        unsafe {
+            let y = primary_screen_height() - (position.y + size.height);
+            let rect = NSRect::new(NSPoint::new(position.x, y), size.into());
+            msg_send![*self.ns_window, setFrame:rect display:YES];
+        }
+    }
+
+    pub fn safe_area_insets(&self) -> LogicalInsets {
+        unsafe {
+            let view = self.ns_window.contentView();
+            let content: NSRect = msg_send![view, frame];
+            let layout: NSRect = msg_send![*self.ns_window, contentLayoutRect];
+
+            // Both are in window coordinates, which have a bottom-left origin.
+            let top = (content.origin.y + content.size.height)
+                - (layout.origin.y + layout.size.height);
+            let right = (content.origin.x + content.size.width)
+                - (layout.origin.x + layout.size.width);
+            LogicalInsets::new(
+                top.max(0.0),
+                (layout.origin.x - content.origin.x).max(0.0),
+                (layout.origin.y - content.origin.y).max(0.0),
+                right.max(0.0),
+            )
+        }
+    }
+
+    pub fn titlebar_height(&self) -> f64 {
+        unsafe {
+            let frame = NSWindow::frame(*self.ns_window);
+            let layout: NSRect = msg_send![*self.ns_window, contentLayoutRect];
+            (frame.size.height - (layout.origin.y + layout.size.height)).max(0.0)
+        }
+    }
+
+    /// Returns the top-left corner of `space` in screen coordinates.
+    pub fn origin_of(&self, space: CoordinateSpace) -> LogicalPosition {
+        unsafe {
+            let frame = NSWindow::frame(*self.ns_window);
+            match space {
+                CoordinateSpace::Screen => LogicalPosition::new(0.0, 0.0),
+                CoordinateSpace::WindowOuter => screen_top_left(frame),
+                CoordinateSpace::WindowContent => {
+                    let rect: NSRect = msg_send![*self.ns_window, contentRectForFrameRect:frame];
+                    screen_top_left(rect)
+                },
+                CoordinateSpace::View(view) => {
+                    let view = view.sys.ns_view;
+                    let origin = view::from_top_left(view, LogicalPosition::new(0.0, 0.0));
+                    let origin: NSPoint = msg_send![view, convertPoint:origin toView:nil];
+                    let rect = NSRect::new(origin, NSSize::new(0.0, 0.0));
+                    let rect: NSRect = msg_send![*self.ns_window, convertRectToScreen:rect];
+                    screen_top_left(rect)
+                },
+            }
+        }
+    }
+
+    pub unsafe fn view_to_content(&self, view: id, point: NSPoint) -> LogicalPosition {
+        let content_view = self.ns_window.contentView();
+        let point: NSPoint = msg_send![content_view, convertPoint:point fromView:view];
+        view::to_top_left(content_view, point)
+    }
+
+    pub unsafe fn content_to_view(&self, view: id, position: LogicalPosition) -> NSPoint {
+        let content_view = self.ns_window.contentView();
+        let point = view::from_top_left(content_view, position);
+        msg_send![content_view, convertPoint:point toView:view]
+    }
+
+    #[inline]
+    pub fn is_minimized(&self) -> bool {
+        let minimized: BOOL = unsafe { msg_send![*self.ns_window, isMiniaturized] };
+        minimized != NO
+    }
+
+    pub fn set_minimized(&self, minimized: bool) {
+        unsafe {
+            if minimized {
+                msg_send![*self.ns_window, miniaturize:nil];
+            } else {
+                msg_send![*self.ns_window, deminiaturize:nil];
+            }
+        }
+    }
+
+    pub fn focus(&self) {
+        unsafe {
+            let app: id = msg_send![class!(NSApplication), sharedApplication];
+            msg_send![app, activateIgnoringOtherApps:YES];
+            if self.is_minimized() {
+                msg_send![*self.ns_window, deminiaturize:nil];
+            }
+            msg_send![*self.ns_window, makeKeyAndOrderFront:nil];
+        }
+    }
+
+    pub fn request_user_attention(&self, ty: UserAttentionType) {
+        let request = match ty {
+            UserAttentionType::Critical => NS_CRITICAL_REQUEST,
+            UserAttentionType::Informational => NS_INFORMATIONAL_REQUEST,
+        };
+        unsafe {
+            let app: id = msg_send![class!(NSApplication), sharedApplication];
+            let _: NSInteger = msg_send![app, requestUserAttention:request];
+        }
+    }
+
+    #[inline]
+    pub fn is_zoomed(&self) -> bool {
+        let zoomed: BOOL = unsafe { msg_send![*self.ns_window, isZoomed] };
+        zoomed != NO
+    }
+
+    pub fn set_zoomed(&self, zoomed: bool) {
+        if self.is_zoomed() != zoomed {
+            unsafe { msg_send![*self.ns_window, zoom:nil] };
+        }
+    }
+
+    #[inline]
+    pub fn is_fullscreen(&self) -> bool {
+        let mask: NSUInteger = unsafe { msg_send![*self.ns_window, styleMask] };
+        mask & NS_FULL_SCREEN_WINDOW_MASK != 0
+    }
+
+    pub fn set_fullscreen(&self, fullscreen: bool) {
+        if self.is_fullscreen() != fullscreen {
+            unsafe { msg_send![*self.ns_window, toggleFullScreen:nil] };
+        }
+    }
+
+    pub fn monitor(&self) -> Option<String> {
+        unsafe {
+            let screen: id = msg_send![*self.ns_window, screen];
+            if screen == nil {
+                None
+            } else {
+                Some(screen_id(screen))
+            }
+        }
+    }
+
+    /// Moves `self` so that it is centered on `parent`.
+    pub fn center_on(&self, parent: &Window) {
+        unsafe {
+            let parent = NSWindow::frame(*parent.ns_window);
+            let frame = NSWindow::frame(*self.ns_window);
+            let origin = NSPoint::new(
+                parent.origin.x + (parent.size.width - frame.size.width) / 2.0,
+                parent.origin.y + (parent.size.height - frame.size.height) / 2.0,
+            );
+            msg_send![*self.ns_window, setFrameOrigin:origin];
+        }
+    }
+
+    /// Attaches `self` to `parent`, as a sheet if `modal`.
+    pub fn set_parent(&self, parent: &Window, modal: bool) {
+        unsafe {
+            if modal {
+                msg_send![*parent.ns_window, beginSheet:*self.ns_window
+                                      completionHandler:nil];
+            } else {
+                msg_send![*parent.ns_window, addChildWindow:*self.ns_window
+                                                    ordered:NS_WINDOW_ABOVE];
+            }
+        }
+    }
+
+    #[inline]
+    pub fn state(&self) -> &delegate::WindowState {
+        unsafe { delegate::window_state(*self.ns_window) }
+    }
+
+    #[inline]
+    pub fn set_event_handler(&self, handler: EventHandler) {
+        self.state().set_event_handler(handler);
+    }
+
+    pub fn set_ime_allowed(&self, allowed: bool) {
+        let ime = &self.state().ime;
+        if ime.allowed.replace(allowed) && !allowed {
+            unsafe {
+                let view = self.ns_window.contentView();
+                if view::is_content_view(view) {
+                    view::discard_marked_text(view);
+                }
+            }
+        }
+    }
+
+    #[inline]
+    pub fn set_ime_cursor_area(&self, position: LogicalPosition, size: LogicalSize) {
+        self.state().ime.cursor_area.set((position, size));
+    }
+
+    pub fn show_context_menu(&self, menu: &Menu, position: LogicalPosition) {
+        unsafe {
+            let ns_menu = menu.to_ns_menu();
+            let view = self.ns_window.contentView();
+            let point = view::from_top_left(view, position);
+            let _: BOOL = msg_send![*ns_menu, popUpMenuPositioningItem:nil
+                                                            atLocation:point
+                                                                inView:view];
+        }
+    }
+
+    pub fn set_context_menu(&self, menu: Option<&Menu>) {
+        let ns_menu = menu.map(MenuExt::to_ns_menu);
+        let ns_menu = ns_menu.as_ref().map_or(nil, |ns_menu| **ns_menu);
+        unsafe {
+            let view = self.ns_window.contentView();
+            msg_send![view, setMenu:ns_menu];
+        }
+    }
+}
+
+pub struct WindowBuilder {
+    pub style_mask: Option<NSUInteger>,
+    pub full_size_content_view: bool,
+    pub titlebar_hidden: bool,
+}
+
+impl Default for WindowBuilder {
+    #[inline]
+    fn default() -> Self {
+        WindowBuilder {
+            style_mask: None,
+            full_size_content_view: false,
+            titlebar_hidden: false,
+        }
+    }
+}
+
+impl WindowBuilder {
+    fn ns_window_style_mask(&self, kind: WindowKind) -> NSUInteger {
+        use NSWindowStyleMask as M;
+
+        let mut mask = self.style_mask.unwrap_or_else(|| match kind {
+            WindowKind::Normal => (
+                M::NSClosableWindowMask |
+                M::NSMiniaturizableWindowMask |
+                M::NSResizableWindowMask |
+                M::NSTitledWindowMask
+            ).bits(),
+            WindowKind::Dialog => (
+                M::NSClosableWindowMask |
+                M::NSTitledWindowMask
+            ).bits(),
+            WindowKind::Utility => (
+                M::NSClosableWindowMask |
+                M::NSResizableWindowMask |
+                M::NSTitledWindowMask
+            ).bits() | NS_UTILITY_WINDOW_MASK,
+            WindowKind::Popup |
+            WindowKind::Tooltip => NS_NONACTIVATING_PANEL_MASK,
+        });
+
+        if self.titlebar_hidden {
+            mask |= M::NSFullSizeContentViewWindowMask.bits();
+        }
+
+        mask
+    }
+}
+
+impl crate::WindowBuilder {
+    fn content_rect(&self) -> NSRect {
+        let size = match self.size {
+            Some(size) => size.to_logical_with(unsafe { main_screen_scale_factor() }),
+            None => LogicalSize::new(800.0, 600.0),
+        };
+        LogicalRect::from(size).into()
+    }
+
+    pub(crate) fn sys_build(&self) -> Result<crate::Window, ()> {
+        if !is_main_thread() {
+            return Err(());
+        }
+
+        let content_rect = self.content_rect();
+        let style_mask = self.sys.ns_window_style_mask(self.kind);
+        let backing = appkit::NSBackingStoreBuffered as NSUInteger;
+
+        // Panels don't activate the application or appear in the window menu.
+        let class = match self.kind {
+            WindowKind::Normal | WindowKind::Dialog => class!(NSWindow),
+            WindowKind::Utility | WindowKind::Popup | WindowKind::Tooltip => class!(NSPanel),
+        };
+
+        let window = unsafe {
+            let ns_window: id = msg_send![class, alloc];
+            let ns_window: id = msg_send![
+                ns_window,
+                initWithContentRect:content_rect
+                          styleMask:style_mask
+                            backing:backing
+                              defer:NO
+            ];
+
+            let content_view = view::new_content_view(content_rect);
+            ns_window.setContentView_(content_view);
+            let _: BOOL = msg_send![ns_window, makeFirstResponder:content_view];
+            msg_send![content_view, release];
+
+            let ns_window = StrongPtr::new(ns_window);
+
+            crate::Window::from(Window { ns_window })
+        };
+
+        // Installs the delegate so that no events are missed.
+        window.sys.state();
+
+        if let Some(title) = &self.title {
+            unsafe { WindowExt::set_title(&window, **title.as_ns_string()) };
+        }
+
+        if self.sys.titlebar_hidden {
+            window.set_titlebar_appears_transparent(true);
+        }
+
+        unsafe {
+            let ns_window = *window.sys.ns_window;
+            match self.kind {
+                WindowKind::Normal | WindowKind::Dialog => {},
+                WindowKind::Utility => {
+                    msg_send![ns_window, setLevel:NS_FLOATING_WINDOW_LEVEL];
+                },
+                WindowKind::Popup => {
+                    msg_send![ns_window, setLevel:NS_POP_UP_MENU_WINDOW_LEVEL];
+                    msg_send![ns_window, setHasShadow:YES];
+                },
+                WindowKind::Tooltip => {
+                    msg_send![ns_window, setLevel:NS_HELP_WINDOW_LEVEL];
+                    msg_send![ns_window, setHasShadow:YES];
+                },
+            }
+        }
+
+        if self.kind == WindowKind::Tooltip {
+            window.sys.set_cursor_hittest(false);
+        }
+
+        Ok(window)
+    }
+}
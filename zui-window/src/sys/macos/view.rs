@@ -0,0 +1,387 @@
+use std::{
+    cell::{Cell, RefCell},
+    ffi::{c_void, CStr},
+    ops::Range,
+    sync::Once,
+};
+use cocoa::{
+    base::{id, nil, BOOL, NO, YES},
+    foundation::{
+        NSArray,
+        NSInteger,
+        NSPoint,
+        NSRect,
+        NSSize,
+        NSString,
+        NSUInteger,
+    },
+};
+use objc::{
+    declare::ClassDecl,
+    runtime::{Class, Object, Protocol, Sel},
+    Encode,
+    Encoding,
+};
+use crate::{
+    dpi::{LogicalPosition, LogicalSize},
+    event::WindowEvent,
+};
+use super::delegate;
+
+const NS_NOT_FOUND: NSUInteger = NSInteger::max_value() as NSUInteger;
+
+/// `NSRange`, which is passed by value to the text input methods.
+///
+/// This is declared here since cocoa's `NSRange` does not implement `Encode`,
+/// which is required to register methods that take or return it.
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+struct NSRange {
+    location: NSUInteger,
+    length: NSUInteger,
+}
+
+impl NSRange {
+    #[inline]
+    fn new(location: NSUInteger, length: NSUInteger) -> Self {
+        NSRange { location, length }
+    }
+}
+
+unsafe impl Encode for NSRange {
+    fn encode() -> Encoding {
+        let encoding = format!(
+            "{{_NSRange={}{}}}",
+            NSUInteger::encode().as_str(),
+            NSUInteger::encode().as_str(),
+        );
+        unsafe { Encoding::from_str(&encoding) }
+    }
+}
+
+/// Input method state of a window whose content view is ours.
+pub struct ImeState {
+    pub allowed: Cell<bool>,
+    pub cursor_area: Cell<(LogicalPosition, LogicalSize)>,
+    marked_text: RefCell<String>,
+}
+
+impl Default for ImeState {
+    #[inline]
+    fn default() -> Self {
+        ImeState {
+            allowed: Cell::new(false),
+            cursor_area: Cell::new((
+                LogicalPosition::new(0.0, 0.0),
+                LogicalSize::new(0.0, 0.0),
+            )),
+            marked_text: RefCell::new(String::new()),
+        }
+    }
+}
+
+/// Converts `position`, relative to the top-left corner of `view`, to the
+/// coordinate system of `view`.
+pub unsafe fn from_top_left(view: id, position: LogicalPosition) -> NSPoint {
+    let flipped: BOOL = msg_send![view, isFlipped];
+    if flipped != NO {
+        position.into()
+    } else {
+        let bounds: NSRect = msg_send![view, bounds];
+        NSPoint::new(position.x, bounds.size.height - position.y)
+    }
+}
+
+/// Converts `point`, in the coordinate system of `view`, to a position
+/// relative to the top-left corner of `view`.
+pub unsafe fn to_top_left(view: id, point: NSPoint) -> LogicalPosition {
+    let flipped: BOOL = msg_send![view, isFlipped];
+    if flipped != NO {
+        point.into()
+    } else {
+        let bounds: NSRect = msg_send![view, bounds];
+        LogicalPosition::new(point.x, bounds.size.height - point.y)
+    }
+}
+
+/// Creates a content view that handles text input and context menus for its
+/// window.
+pub unsafe fn new_content_view(frame: NSRect) -> id {
+    let view: id = msg_send![content_view_class(), alloc];
+    msg_send![view, initWithFrame:frame]
+}
+
+/// Returns whether `view` is one created by `new_content_view`.
+pub unsafe fn is_content_view(view: id) -> bool {
+    let is_kind: BOOL = msg_send![view, isKindOfClass:content_view_class()];
+    is_kind != NO
+}
+
+/// Discards any text being composed within `view`.
+pub unsafe fn discard_marked_text(view: id) {
+    let input_context: id = msg_send![view, inputContext];
+    msg_send![input_context, discardMarkedText];
+    unmark_text(&*view, sel!(unmarkText));
+}
+
+fn content_view_class() -> &'static Class {
+    static REGISTER: Once = Once::new();
+
+    REGISTER.call_once(|| {
+        let superclass = class!(NSView);
+        let mut decl = ClassDecl::new("ZUIContentView", superclass).unwrap();
+        // Input methods check for conformance before sending text input
+        // messages.
+        decl.add_protocol(Protocol::get("NSTextInputClient").unwrap());
+        unsafe {
+            decl.add_method(
+                sel!(acceptsFirstResponder),
+                accepts_first_responder as extern "C" fn(&Object, Sel) -> BOOL,
+            );
+            decl.add_method(
+                sel!(menuForEvent:),
+                menu_for_event as extern "C" fn(&Object, Sel, id) -> id,
+            );
+            decl.add_method(
+                sel!(keyDown:),
+                key_down as extern "C" fn(&Object, Sel, id),
+            );
+            decl.add_method(
+                sel!(hasMarkedText),
+                has_marked_text as extern "C" fn(&Object, Sel) -> BOOL,
+            );
+            decl.add_method(
+                sel!(markedRange),
+                marked_range as extern "C" fn(&Object, Sel) -> NSRange,
+            );
+            decl.add_method(
+                sel!(selectedRange),
+                selected_range as extern "C" fn(&Object, Sel) -> NSRange,
+            );
+            decl.add_method(
+                sel!(setMarkedText:selectedRange:replacementRange:),
+                set_marked_text as extern "C" fn(&Object, Sel, id, NSRange, NSRange),
+            );
+            decl.add_method(
+                sel!(unmarkText),
+                unmark_text as extern "C" fn(&Object, Sel),
+            );
+            decl.add_method(
+                sel!(validAttributesForMarkedText),
+                valid_attributes_for_marked_text as extern "C" fn(&Object, Sel) -> id,
+            );
+            decl.add_method(
+                sel!(attributedSubstringForProposedRange:actualRange:),
+                attributed_substring as extern "C" fn(&Object, Sel, NSRange, *mut c_void) -> id,
+            );
+            decl.add_method(
+                sel!(insertText:replacementRange:),
+                insert_text as extern "C" fn(&Object, Sel, id, NSRange),
+            );
+            decl.add_method(
+                sel!(characterIndexForPoint:),
+                character_index_for_point as extern "C" fn(&Object, Sel, NSPoint) -> NSUInteger,
+            );
+            decl.add_method(
+                sel!(firstRectForCharacterRange:actualRange:),
+                first_rect_for_character_range as extern "C" fn(&Object, Sel, NSRange, *mut c_void) -> NSRect,
+            );
+            decl.add_method(
+                sel!(doCommandBySelector:),
+                do_command_by_selector as extern "C" fn(&Object, Sel, Sel),
+            );
+        }
+        decl.register();
+    });
+
+    class!(ZUIContentView)
+}
+
+unsafe fn window_state<'a>(view: &Object) -> Option<&'a delegate::WindowState> {
+    let ns_window: id = msg_send![view, window];
+    if ns_window == nil {
+        None
+    } else {
+        Some(delegate::window_state(ns_window))
+    }
+}
+
+/// Returns the UTF-8 contents of an `NSString` or `NSAttributedString`.
+unsafe fn string_contents(string: id) -> String {
+    let is_attributed: BOOL = msg_send![string, isKindOfClass:class!(NSAttributedString)];
+    let string: id = if is_attributed != NO {
+        msg_send![string, string]
+    } else {
+        string
+    };
+    CStr::from_ptr(string.UTF8String()).to_string_lossy().into_owned()
+}
+
+/// Converts an offset in UTF-16 code units to a byte offset within `s`.
+fn utf8_offset(s: &str, utf16_offset: usize) -> usize {
+    let mut units = 0;
+    for (i, c) in s.char_indices() {
+        if units >= utf16_offset {
+            return i;
+        }
+        units += c.len_utf16();
+    }
+    s.len()
+}
+
+fn utf8_range(s: &str, range: NSRange) -> Option<Range<usize>> {
+    if range.location == NS_NOT_FOUND {
+        return None;
+    }
+    let start = range.location as usize;
+    let end = start + range.length as usize;
+    Some(utf8_offset(s, start)..utf8_offset(s, end))
+}
+
+extern "C" fn accepts_first_responder(_this: &Object, _: Sel) -> BOOL {
+    YES
+}
+
+extern "C" fn menu_for_event(this: &Object, _: Sel, event: id) -> id {
+    unsafe {
+        let menu: id = msg_send![this, menu];
+        if menu != nil {
+            return menu;
+        }
+        if let Some(state) = window_state(this) {
+            let location: NSPoint = msg_send![event, locationInWindow];
+            let point: NSPoint = msg_send![this, convertPoint:location fromView:nil];
+            let position = to_top_left(this as *const Object as id, point);
+            state.emit(WindowEvent::ContextMenuRequested { position });
+        }
+        nil
+    }
+}
+
+extern "C" fn key_down(this: &Object, _: Sel, event: id) {
+    unsafe {
+        let allowed = window_state(this).map_or(false, |s| s.ime.allowed.get());
+        if allowed {
+            let events = NSArray::arrayWithObject(nil, event);
+            msg_send![this, interpretKeyEvents:events];
+        } else {
+            msg_send![super(this, class!(NSView)), keyDown:event];
+        }
+    }
+}
+
+extern "C" fn has_marked_text(this: &Object, _: Sel) -> BOOL {
+    unsafe {
+        match window_state(this) {
+            Some(state) if !state.ime.marked_text.borrow().is_empty() => YES,
+            _ => NO,
+        }
+    }
+}
+
+extern "C" fn marked_range(this: &Object, _: Sel) -> NSRange {
+    unsafe {
+        let len = window_state(this)
+            .map_or(0, |s| s.ime.marked_text.borrow().encode_utf16().count());
+        if len == 0 {
+            NSRange::new(NS_NOT_FOUND, 0)
+        } else {
+            NSRange::new(0, len as NSUInteger)
+        }
+    }
+}
+
+extern "C" fn selected_range(_this: &Object, _: Sel) -> NSRange {
+    NSRange::new(NS_NOT_FOUND, 0)
+}
+
+extern "C" fn set_marked_text(
+    this: &Object,
+    _: Sel,
+    string: id,
+    selected_range: NSRange,
+    _replacement_range: NSRange,
+) {
+    unsafe {
+        let state = match window_state(this) {
+            Some(state) => state,
+            None => return,
+        };
+        let text = string_contents(string);
+        let cursor = if text.is_empty() {
+            None
+        } else {
+            utf8_range(&text, selected_range)
+        };
+        *state.ime.marked_text.borrow_mut() = text.clone();
+        state.emit(WindowEvent::Preedit { text, cursor });
+    }
+}
+
+extern "C" fn unmark_text(this: &Object, _: Sel) {
+    unsafe {
+        let state = match window_state(this) {
+            Some(state) => state,
+            None => return,
+        };
+        let had_marked_text = !state.ime.marked_text.borrow().is_empty();
+        state.ime.marked_text.borrow_mut().clear();
+        if had_marked_text {
+            state.emit(WindowEvent::Preedit { text: String::new(), cursor: None });
+        }
+    }
+}
+
+extern "C" fn valid_attributes_for_marked_text(_this: &Object, _: Sel) -> id {
+    unsafe { msg_send![class!(NSArray), array] }
+}
+
+extern "C" fn attributed_substring(_this: &Object, _: Sel, _range: NSRange, _actual: *mut c_void) -> id {
+    nil
+}
+
+extern "C" fn insert_text(this: &Object, sel: Sel, string: id, _replacement_range: NSRange) {
+    unsafe {
+        unmark_text(this, sel);
+        if let Some(state) = window_state(this) {
+            let text = string_contents(string);
+            if !text.is_empty() {
+                state.emit(WindowEvent::Commit { text });
+            }
+        }
+    }
+}
+
+extern "C" fn character_index_for_point(_this: &Object, _: Sel, _point: NSPoint) -> NSUInteger {
+    NS_NOT_FOUND
+}
+
+extern "C" fn first_rect_for_character_range(
+    this: &Object,
+    _: Sel,
+    _range: NSRange,
+    _actual: *mut c_void,
+) -> NSRect {
+    unsafe {
+        let (position, size) = match window_state(this) {
+            Some(state) => state.ime.cursor_area.get(),
+            None => return NSRect::new(NSPoint::new(0.0, 0.0), NSSize::new(0.0, 0.0)),
+        };
+
+        // The cursor area has a top-left origin within the content view,
+        // whereas AppKit expects a bottom-left origin in screen coordinates.
+        let bounds: NSRect = msg_send![this, bounds];
+        let rect = NSRect::new(
+            NSPoint::new(position.x, bounds.size.height - position.y - size.height),
+            size.into(),
+        );
+        let rect: NSRect = msg_send![this, convertRect:rect toView:nil];
+        let ns_window: id = msg_send![this, window];
+        msg_send![ns_window, convertRectToScreen:rect]
+    }
+}
+
+extern "C" fn do_command_by_selector(_this: &Object, _: Sel, _command: Sel) {
+    // Prevents the system beep for commands, such as moving the cursor, that
+    // are handled by the application through key events instead.
+}
@@ -3,6 +3,11 @@
 #![deny(missing_docs)]
 #![doc(html_logo_url = "https://zed-ui.dev/static/logos/zed-ui.svg")]
 
+pub extern crate zui_clipboard as clipboard;
+pub extern crate zui_dialog as dialog;
+pub extern crate zui_menu as menu;
+pub extern crate zui_notification as notification;
+pub extern crate zui_tray as tray;
 pub extern crate zui_window as window;
 pub extern crate zui_web_view as web_view;
 
@@ -10,6 +15,11 @@ pub mod os;
 
 #[doc(inline)]
 pub use self::{
+    clipboard::Clipboard,
+    dialog::{FileDialog, MessageDialog},
+    menu::Menu,
+    notification::Notification,
+    tray::TrayIcon,
     web_view::WebView,
-    window::{Window, dpi},
+    window::{Window, dpi, event},
 };
@@ -4,6 +4,12 @@ cfg_if::cfg_if! {
     if #[cfg(target_os = "macos")] {
         /// macOS-specific extensions.
         pub mod macos {
+            #[doc(inline)]
+            pub use clipboard::os::macos::*;
+            #[doc(inline)]
+            pub use menu::os::macos::*;
+            #[doc(inline)]
+            pub use tray::os::macos::*;
             #[doc(inline)]
             pub use window::os::macos::*;
         }